@@ -0,0 +1,187 @@
+//! CoworkAny Desktop - Command History
+//!
+//! Records the launcher/tray commands the user runs so the launcher can offer
+//! "recent commands". History is kept per workspace, capped, and persisted as
+//! JSON in the shared app data directory.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::warn;
+
+/// Commands kept per workspace; the oldest are dropped first
+pub const MAX_COMMAND_HISTORY: usize = 50;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// One executed command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandHistoryEntry {
+    /// Command id as carried by the `command-executed` event (e.g. `new-task`)
+    pub id: String,
+    pub executed_at: String,
+}
+
+pub struct CommandHistory {
+    /// Where history is persisted
+    path: PathBuf,
+    cap: usize,
+    /// Newest first, keyed by workspace root
+    workspaces: HashMap<String, VecDeque<CommandHistoryEntry>>,
+}
+
+impl CommandHistory {
+    /// Persist history at `path`, starting from what is saved there. An
+    /// unreadable file starts an empty history rather than failing startup.
+    pub fn load(path: PathBuf, cap: usize) -> Self {
+        let workspaces = match fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable command history {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let mut history = Self {
+            path,
+            cap,
+            workspaces,
+        };
+        for entries in history.workspaces.values_mut() {
+            entries.truncate(cap);
+        }
+        history
+    }
+
+    /// Record that `id` ran in `workspace`, dropping the oldest entries
+    /// beyond the cap.
+    pub fn record(&mut self, workspace: &str, id: &str) -> Result<(), String> {
+        let entries = self.workspaces.entry(workspace.to_string()).or_default();
+        entries.push_front(CommandHistoryEntry {
+            id: id.to_string(),
+            executed_at: chrono::Utc::now().to_rfc3339(),
+        });
+        entries.truncate(self.cap);
+        self.save()
+    }
+
+    /// Commands run in `workspace`, newest first
+    pub fn entries(&self, workspace: &str) -> Vec<CommandHistoryEntry> {
+        self.workspaces
+            .get(workspace)
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&mut self, workspace: &str) -> Result<(), String> {
+        self.workspaces.remove(workspace);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let raw = serde_json::to_string_pretty(&self.workspaces).map_err(|e| e.to_string())?;
+        fs::write(&self.path, raw).map_err(|e| e.to_string())
+    }
+}
+
+pub struct CommandHistoryState(pub Mutex<CommandHistory>);
+
+impl CommandHistoryState {
+    /// Load and save history at `path`.
+    pub fn with_path(path: PathBuf) -> Self {
+        Self(Mutex::new(CommandHistory::load(path, MAX_COMMAND_HISTORY)))
+    }
+}
+
+/// History is grouped by the workspace the app runs in, the same root
+/// `get_workspace_root` reports.
+fn current_workspace() -> String {
+    std::env::current_dir()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Emit `command-executed` for `id` and add it to the current workspace's
+/// history.
+pub fn emit_command_executed(app: &AppHandle, id: &str) {
+    if let Some(state) = app.try_state::<CommandHistoryState>() {
+        if let Ok(mut history) = state.0.lock() {
+            if let Err(e) = history.record(&current_workspace(), id) {
+                warn!("Failed to save command history: {}", e);
+            }
+        }
+    }
+    let _ = app.emit("command-executed", serde_json::json!({ "id": id }));
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Recent commands for `workspace` (the current workspace when omitted),
+/// newest first
+#[tauri::command]
+pub async fn get_command_history(
+    state: State<'_, CommandHistoryState>,
+    workspace: Option<String>,
+) -> Result<Vec<CommandHistoryEntry>, String> {
+    let history = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(history.entries(&workspace.unwrap_or_else(current_workspace)))
+}
+
+#[tauri::command]
+pub async fn clear_command_history(
+    state: State<'_, CommandHistoryState>,
+    workspace: Option<String>,
+) -> Result<(), String> {
+    let mut history = state.0.lock().map_err(|e| e.to_string())?;
+    history.clear(&workspace.unwrap_or_else(current_workspace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_capped_per_workspace_and_survives_reload() {
+        let path = std::env::temp_dir().join(format!(
+            "coworkany-command-history-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let mut history = CommandHistory::load(path.clone(), 3);
+        for id in ["one", "two", "three", "four"] {
+            history.record("/ws/a", id).unwrap();
+        }
+        history.record("/ws/b", "settings").unwrap();
+
+        let ids = |history: &CommandHistory, workspace: &str| -> Vec<String> {
+            history
+                .entries(workspace)
+                .into_iter()
+                .map(|entry| entry.id)
+                .collect()
+        };
+        assert_eq!(ids(&history, "/ws/a"), ["four", "three", "two"]);
+
+        let mut reloaded = CommandHistory::load(path.clone(), 3);
+        assert_eq!(ids(&reloaded, "/ws/a"), ["four", "three", "two"]);
+        assert_eq!(ids(&reloaded, "/ws/b"), ["settings"]);
+
+        reloaded.clear("/ws/a").unwrap();
+        let cleared = CommandHistory::load(path.clone(), 3);
+        assert!(cleared.entries("/ws/a").is_empty());
+        assert_eq!(ids(&cleared, "/ws/b"), ["settings"]);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -4,9 +4,24 @@
 //! Used by Shadow FS to compute file changes for user review.
 
 use serde::{Deserialize, Serialize};
-use similar::{ChangeTag, TextDiff};
+use similar::{Algorithm, ChangeTag, TextDiff};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// Largest file the review flow will read and diff (10 MiB)
+pub const MAX_DIFF_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Patches with at least this many hunks report per-hunk progress
+pub const HUNK_PROGRESS_MIN_HUNKS: usize = 16;
+
+/// How many lines a hunk may have drifted from its recorded position
+pub const DEFAULT_HUNK_FUZZ: usize = 3;
+
+/// Marker following a diff line that has no trailing newline
+pub const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
 // ============================================================================
 // Types
 // ============================================================================
@@ -28,6 +43,35 @@ pub struct DiffHunk {
     pub header: String,
     /// Optional context (function/class name)
     pub context: Option<String>,
+    /// The hunk ends the new file and its last line has no trailing newline
+    #[serde(default)]
+    pub no_newline_at_eof: bool,
+    /// Word-level changes within modified lines, when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_segments: Vec<LineSegments>,
+}
+
+/// Word-level breakdown of one changed line of a hunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineSegments {
+    /// Index of the line within the hunk's `content` (0-indexed)
+    pub line: usize,
+    pub segments: Vec<DiffSegment>,
+}
+
+/// A run of a changed line that is kept, inserted or deleted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub tag: SegmentTag,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentTag {
+    Equal,
+    Insert,
+    Delete,
 }
 
 /// A complete file patch
@@ -43,6 +87,91 @@ pub struct FilePatch {
     pub additions: usize,
     pub deletions: usize,
     pub description: Option<String>,
+    /// Line ending `apply_patch` writes; hunks themselves are always LF
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// Hunks carry fewer context lines than requested because the file was
+    /// over `LargeFileLimits`; they apply with less fuzz tolerance
+    #[serde(default)]
+    pub truncated_context: bool,
+}
+
+/// Outcome of `three_way_merge`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum MergeResult {
+    /// Every change merged without overlap
+    Clean { content: String },
+    /// `content` holds `<<<<<<<`/`=======`/`>>>>>>>` markers for each region
+    /// both sides changed differently
+    Conflicted {
+        content: String,
+        conflicts: Vec<ConflictRegion>,
+    },
+}
+
+/// Lines both sides of a merge changed, in different ways
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictRegion {
+    /// Line of the `<<<<<<<` marker in the merged content (0-indexed)
+    pub start_line: usize,
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+/// Totals over one or more patches, for "+42 −17 across 3 files" summaries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffStats {
+    pub files_changed: usize,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: usize,
+}
+
+impl std::ops::AddAssign for DiffStats {
+    fn add_assign(&mut self, other: Self) {
+        self.files_changed += other.files_changed;
+        self.additions += other.additions;
+        self.deletions += other.deletions;
+        self.hunks += other.hunks;
+    }
+}
+
+impl std::iter::Sum for DiffStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |mut total, stats| {
+            total += stats;
+            total
+        })
+    }
+}
+
+impl FilePatch {
+    /// This patch's line counts (byte counts for binary patches). A modify
+    /// with no hunks changes no file.
+    pub fn stats(&self) -> DiffStats {
+        let changed = self.operation != PatchOperation::Modify || !self.hunks.is_empty();
+        DiffStats {
+            files_changed: usize::from(changed),
+            additions: self.additions,
+            deletions: self.deletions,
+            hunks: self.hunks.len(),
+        }
+    }
+}
+
+/// Totals across `patches`
+pub fn summarize(patches: &[FilePatch]) -> DiffStats {
+    patches.iter().map(FilePatch::stats).sum()
+}
+
+/// Progress of a hunk-by-hunk apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkProgress {
+    pub applied: usize,
+    pub total: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -52,6 +181,162 @@ pub enum PatchOperation {
     Modify,
     Delete,
     Rename,
+    /// Whole-file replacement of binary content; `additions` and `deletions`
+    /// count bytes and there are no hunks
+    Binary,
+}
+
+/// How lines are matched up between the two versions. Patience keeps moved
+/// blocks of code together where Myers may interleave them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    Patience,
+    Lcs,
+}
+
+impl From<DiffAlgorithm> for Algorithm {
+    fn from(algorithm: DiffAlgorithm) -> Self {
+        match algorithm {
+            DiffAlgorithm::Myers => Algorithm::Myers,
+            DiffAlgorithm::Patience => Algorithm::Patience,
+            DiffAlgorithm::Lcs => Algorithm::Lcs,
+        }
+    }
+}
+
+/// Line ending of patched content. Both sides are compared with LF endings,
+/// so a CRLF file diffed against LF output only shows the real changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// CRLF on Windows, LF elsewhere
+    Native,
+    /// Whichever ending dominates the original
+    #[default]
+    Preserve,
+}
+
+impl LineEnding {
+    /// The ending to write for content replacing `original`
+    fn resolve(self, original: &str) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Native if cfg!(windows) => "\r\n",
+            LineEnding::Native => "\n",
+            LineEnding::Preserve => {
+                let crlf = original.matches("\r\n").count();
+                if crlf > original.matches('\n').count() - crlf {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
+}
+
+fn normalize_line_endings(content: &str) -> std::borrow::Cow<'_, str> {
+    if content.contains("\r\n") {
+        content.replace("\r\n", "\n").into()
+    } else {
+        content.into()
+    }
+}
+
+/// `content` with LF endings replaced by `eol`
+fn with_line_ending(content: &str, eol: &str) -> String {
+    if eol == "\n" {
+        content.to_string()
+    } else {
+        content.replace('\n', eol)
+    }
+}
+
+/// Line openings that mark a function, class or impl block in most languages
+pub const DEFAULT_CONTEXT_SIGNATURES: &[&str] = &["fn ", "def ", "class ", "function ", "impl "];
+
+/// How files past a size threshold are diffed. Fewer context lines keep hunks
+/// small, and the fast configuration trades minimal hunks for a bounded run
+/// time: Myers with a deadline and no word-level segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LargeFileLimits {
+    /// Either side having more lines than this counts as large
+    pub line_threshold: usize,
+    /// Or more bytes than this
+    pub byte_threshold: usize,
+    pub max_context_lines: usize,
+    pub fast_diff: bool,
+}
+
+/// After this long the fast configuration settles for a coarser diff
+pub const LARGE_FILE_DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+impl Default for LargeFileLimits {
+    fn default() -> Self {
+        Self {
+            line_threshold: 20_000,
+            byte_threshold: 2 * 1024 * 1024,
+            max_context_lines: 1,
+            fast_diff: true,
+        }
+    }
+}
+
+impl LargeFileLimits {
+    fn applies_to(&self, content: &str) -> bool {
+        content.len() > self.byte_threshold || content.lines().count() > self.line_threshold
+    }
+}
+
+/// Optional behaviour of `compute_unified_diff_with`; the default matches
+/// `compute_unified_diff`
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    pub algorithm: DiffAlgorithm,
+    /// Break each modified line into word-level segments
+    pub word_diff: bool,
+    pub line_ending: LineEnding,
+    /// A hunk's context is the nearest line above it containing one of these
+    /// as a word; empty disables context detection
+    pub context_signatures: Vec<String>,
+    /// `None` diffs every file the same way regardless of size
+    pub large_file: Option<LargeFileLimits>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: DiffAlgorithm::default(),
+            word_diff: false,
+            line_ending: LineEnding::default(),
+            context_signatures: DEFAULT_CONTEXT_SIGNATURES
+                .iter()
+                .map(|signature| signature.to_string())
+                .collect(),
+            large_file: Some(LargeFileLimits::default()),
+        }
+    }
+}
+
+/// The nearest line before `start` that opens a function, class or the like,
+/// trimmed, for the `@@` header of a hunk starting there
+fn hunk_context(lines: &[&str], start: usize, signatures: &[String]) -> Option<String> {
+    lines[..start.min(lines.len())]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .find(|line| {
+            signatures.iter().any(|signature| {
+                line.starts_with(signature.as_str()) || line.contains(&format!(" {}", signature))
+            })
+        })
+        .map(str::to_string)
 }
 
 /// Error types for diff operations
@@ -61,7 +346,6 @@ pub enum DiffError {
     IoError(#[from] std::io::Error),
 
     #[error("Hunk does not apply cleanly at line {line}: expected '{expected}', found '{found}'")]
-    #[allow(dead_code)]
     HunkMismatch {
         line: usize,
         expected: String,
@@ -71,6 +355,15 @@ pub enum DiffError {
     #[error("Line {0} is out of bounds")]
     #[allow(dead_code)]
     OutOfBounds(usize),
+
+    #[error("Cannot diff binary or non-UTF-8 file: {0}")]
+    NotText(String),
+
+    #[error("File too large to diff: {path} ({size} bytes, limit {limit})")]
+    TooLarge { path: String, size: u64, limit: u64 },
+
+    #[error("Malformed patch at line {line}: {reason}")]
+    MalformedPatch { line: usize, reason: String },
 }
 
 // ============================================================================
@@ -84,7 +377,50 @@ pub fn compute_unified_diff(
     file_path: &str,
     context_lines: usize,
 ) -> FilePatch {
-    let diff = TextDiff::from_lines(original, modified);
+    compute_unified_diff_with(
+        original,
+        modified,
+        file_path,
+        context_lines,
+        &DiffOptions::default(),
+    )
+}
+
+/// Compute a unified diff with a chosen line-matching algorithm and,
+/// optionally, word-level segments for modified lines
+pub fn compute_unified_diff_with(
+    original: &str,
+    modified: &str,
+    file_path: &str,
+    context_lines: usize,
+    options: &DiffOptions,
+) -> FilePatch {
+    let original_lf = normalize_line_endings(original);
+    let modified_lf = normalize_line_endings(modified);
+    let large = options
+        .large_file
+        .filter(|limits| limits.applies_to(&original_lf) || limits.applies_to(&modified_lf));
+    let truncated_context = large.is_some_and(|limits| context_lines > limits.max_context_lines);
+    let context_lines = match large {
+        Some(limits) => context_lines.min(limits.max_context_lines),
+        None => context_lines,
+    };
+    let fast_diff = large.is_some_and(|limits| limits.fast_diff);
+    let mut config = TextDiff::configure();
+    if fast_diff {
+        config
+            .algorithm(Algorithm::Myers)
+            .timeout(LARGE_FILE_DIFF_TIMEOUT);
+    } else {
+        config.algorithm(options.algorithm.into());
+    }
+    let diff = config.diff_lines(original_lf.as_ref(), modified_lf.as_ref());
+    let word_diff = options.word_diff && !fast_diff;
+    let original_lines: Vec<&str> = if options.context_signatures.is_empty() {
+        Vec::new()
+    } else {
+        original_lf.lines().collect()
+    };
 
     let mut hunks = Vec::new();
     let mut additions = 0;
@@ -97,9 +433,15 @@ pub fn compute_unified_diff(
         let mut old_lines = 0;
         let mut new_start = 0;
         let mut new_lines = 0;
+        let mut no_newline_at_eof = false;
+        let mut line_segments = Vec::new();
+        let mut content_lines = 0;
         let mut first = true;
 
         for op in &group {
+            // Lines removed and added by this op, paired up for word diffs
+            let mut deleted = Vec::new();
+            let mut inserted = Vec::new();
             for change in diff.iter_changes(op) {
                 if first {
                     old_start = change.old_index().unwrap_or(0);
@@ -125,22 +467,55 @@ pub fn compute_unified_diff(
                     }
                 };
 
+                if word_diff {
+                    match change.tag() {
+                        ChangeTag::Delete => deleted.push((content_lines, change.value())),
+                        ChangeTag::Insert => inserted.push((content_lines, change.value())),
+                        ChangeTag::Equal => {}
+                    }
+                }
+
                 hunk_content.push_str(prefix);
                 hunk_content.push_str(change.value());
-                if !hunk_content.ends_with('\n') {
+                content_lines += 1;
+                if change.missing_newline() {
+                    hunk_content.push('\n');
+                    hunk_content.push_str(NO_NEWLINE_MARKER);
                     hunk_content.push('\n');
+                    content_lines += 1;
+                    if change.tag() != ChangeTag::Delete {
+                        no_newline_at_eof = true;
+                    }
                 }
             }
+
+            for (&(old_line, old), &(new_line, new)) in deleted.iter().zip(&inserted) {
+                let (old_segments, new_segments) = word_segments(old, new);
+                line_segments.push(LineSegments {
+                    line: old_line,
+                    segments: old_segments,
+                });
+                line_segments.push(LineSegments {
+                    line: new_line,
+                    segments: new_segments,
+                });
+            }
         }
+        line_segments.sort_by_key(|segments| segments.line);
 
         if !hunk_content.is_empty() {
-            let header = format!(
+            let context = hunk_context(&original_lines, old_start, &options.context_signatures);
+            let mut header = format!(
                 "@@ -{},{} +{},{} @@",
                 old_start + 1,
                 old_lines,
                 new_start + 1,
                 new_lines
             );
+            if let Some(context) = &context {
+                header.push(' ');
+                header.push_str(context);
+            }
 
             hunks.push(DiffHunk {
                 old_start,
@@ -149,7 +524,9 @@ pub fn compute_unified_diff(
                 new_lines,
                 content: hunk_content,
                 header,
-                context: None,
+                context,
+                no_newline_at_eof,
+                line_segments,
             });
         }
     }
@@ -169,14 +546,79 @@ pub fn compute_unified_diff(
         operation,
         new_file_path: None,
         hunks,
+        // A new file has no original to take an ending from, so Preserve
+        // keeps the one it was written with.
         full_content: if operation == PatchOperation::Create {
-            Some(modified.to_string())
+            let basis = if original.is_empty() {
+                modified
+            } else {
+                original
+            };
+            Some(with_line_ending(
+                &modified_lf,
+                options.line_ending.resolve(basis),
+            ))
         } else {
             None
         },
         additions,
         deletions,
         description: None,
+        line_ending: options.line_ending,
+        truncated_context,
+    }
+}
+
+/// Word-level segments of a deleted line and the line that replaced it
+fn word_segments(old: &str, new: &str) -> (Vec<DiffSegment>, Vec<DiffSegment>) {
+    fn push(segments: &mut Vec<DiffSegment>, tag: SegmentTag, value: &str) {
+        match segments.last_mut() {
+            Some(last) if last.tag == tag => last.value.push_str(value),
+            _ => segments.push(DiffSegment {
+                tag,
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    let old = old.trim_end_matches('\n');
+    let new = new.trim_end_matches('\n');
+    let mut old_segments = Vec::new();
+    let mut new_segments = Vec::new();
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Equal => {
+                push(&mut old_segments, SegmentTag::Equal, change.value());
+                push(&mut new_segments, SegmentTag::Equal, change.value());
+            }
+            ChangeTag::Delete => push(&mut old_segments, SegmentTag::Delete, change.value()),
+            ChangeTag::Insert => push(&mut new_segments, SegmentTag::Insert, change.value()),
+        }
+    }
+    (old_segments, new_segments)
+}
+
+/// Whether `content` should be treated as binary rather than diffed as
+/// lines: it contains NUL or is not valid UTF-8.
+pub fn is_binary(content: &[u8]) -> bool {
+    content.contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+/// Describe a change to binary content as a full-content replacement.
+pub fn compute_binary_diff(original: &[u8], modified: &[u8], file_path: &str) -> FilePatch {
+    FilePatch {
+        id: Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        file_path: file_path.to_string(),
+        operation: PatchOperation::Binary,
+        new_file_path: None,
+        hunks: Vec::new(),
+        full_content: String::from_utf8(modified.to_vec()).ok(),
+        additions: modified.len(),
+        deletions: original.len(),
+        description: None,
+        line_ending: LineEnding::Preserve,
+        truncated_context: false,
     }
 }
 
@@ -186,6 +628,105 @@ pub fn compute_unified_diff(
 
 /// Apply a patch to the original content
 pub fn apply_patch(original: &str, patch: &FilePatch) -> Result<String, DiffError> {
+    apply_patch_with_progress(original, patch, DEFAULT_HUNK_FUZZ, |_| {})
+}
+
+/// Lines a hunk expects in the original: its context and deleted lines
+fn hunk_old_lines(hunk: &DiffHunk) -> Vec<&str> {
+    hunk.content
+        .lines()
+        .filter(|line| line.starts_with(' ') || (line.starts_with('-') && !line.starts_with("---")))
+        .map(|line| &line[1..])
+        .collect()
+}
+
+/// Where `old_lines` occur within `fuzz` lines of `expected`, nearest first,
+/// never before `floor` (the end of the previous hunk).
+fn locate_hunk(
+    lines: &[&str],
+    old_lines: &[&str],
+    expected: usize,
+    floor: usize,
+    fuzz: usize,
+) -> Option<usize> {
+    let matches_at = |start: usize| {
+        start >= floor
+            && start + old_lines.len() <= lines.len()
+            && lines[start..start + old_lines.len()] == *old_lines
+    };
+    (0..=fuzz).find_map(|offset| {
+        if matches_at(expected + offset) {
+            Some(expected + offset)
+        } else {
+            expected
+                .checked_sub(offset)
+                .filter(|start| offset > 0 && matches_at(*start))
+        }
+    })
+}
+
+/// Where each hunk of `patch` starts in `lines`. Each may be found up to
+/// `fuzz` lines from where it was recorded, adjusted by how far the previous
+/// hunk drifted.
+fn locate_hunks(lines: &[&str], patch: &FilePatch, fuzz: usize) -> Result<Vec<usize>, DiffError> {
+    let mut starts = Vec::with_capacity(patch.hunks.len());
+    // End of the previous hunk; hunks never overlap
+    let mut floor = 0;
+    // How far the file has shifted relative to the recorded hunk positions
+    let mut drift: isize = 0;
+
+    for hunk in &patch.hunks {
+        let old_lines = hunk_old_lines(hunk);
+        let expected = hunk.old_start.saturating_add_signed(drift).max(floor);
+        let start = if old_lines.is_empty() {
+            expected.min(lines.len())
+        } else {
+            locate_hunk(lines, &old_lines, expected, floor, fuzz).ok_or_else(|| {
+                DiffError::HunkMismatch {
+                    line: expected + 1,
+                    expected: old_lines[0].to_string(),
+                    found: lines
+                        .get(expected)
+                        .map(|line| line.to_string())
+                        .unwrap_or_else(|| "<end of file>".to_string()),
+                }
+            })?
+        };
+        drift = start as isize - hunk.old_start as isize;
+        floor = start + old_lines.len();
+        starts.push(start);
+    }
+    Ok(starts)
+}
+
+/// How many lines each hunk of `patch` has moved in `original` since it was
+/// recorded, or `HunkMismatch` if one no longer matches within `fuzz`.
+pub fn hunk_offsets(
+    original: &str,
+    patch: &FilePatch,
+    fuzz: usize,
+) -> Result<Vec<isize>, DiffError> {
+    let lines: Vec<&str> = original.lines().collect();
+    let starts = locate_hunks(&lines, patch, fuzz)?;
+    Ok(starts
+        .iter()
+        .zip(&patch.hunks)
+        .map(|(start, hunk)| *start as isize - hunk.old_start as isize)
+        .collect())
+}
+
+/// Apply a patch, calling `on_hunk` after each hunk. The result is built in
+/// memory, so callers still write it out in one step.
+///
+/// Each hunk may be found up to `fuzz` lines away from where it was recorded.
+/// A hunk whose old lines match nowhere in that window fails with
+/// `HunkMismatch` instead of being spliced in blind.
+pub fn apply_patch_with_progress(
+    original: &str,
+    patch: &FilePatch,
+    fuzz: usize,
+    mut on_hunk: impl FnMut(HunkProgress),
+) -> Result<String, DiffError> {
     // For create operations, return full content
     if patch.operation == PatchOperation::Create {
         return Ok(patch.full_content.clone().unwrap_or_default());
@@ -196,14 +737,27 @@ pub fn apply_patch(original: &str, patch: &FilePatch) -> Result<String, DiffErro
         return Ok(String::new());
     }
 
+    // Binary patches replace the whole file; bytes that aren't text can't
+    // come back through a string
+    if patch.operation == PatchOperation::Binary {
+        return patch
+            .full_content
+            .clone()
+            .ok_or_else(|| DiffError::NotText(patch.file_path.clone()));
+    }
+
     // Apply hunks
     let lines: Vec<&str> = original.lines().collect();
+    // Untouched, the file keeps the original's trailing newline (or lack of one)
+    let mut trailing_newline = original.ends_with('\n');
     let mut result: Vec<String> = Vec::new();
     let mut current_line = 0;
+    let starts = locate_hunks(&lines, patch, fuzz)?;
 
-    for hunk in &patch.hunks {
+    let total = patch.hunks.len();
+    for (index, (hunk, &start)) in patch.hunks.iter().zip(&starts).enumerate() {
         // Copy unchanged lines before this hunk
-        while current_line < hunk.old_start {
+        while current_line < start {
             if current_line < lines.len() {
                 result.push(lines[current_line].to_string());
             }
@@ -224,6 +778,14 @@ pub fn apply_patch(original: &str, patch: &FilePatch) -> Result<String, DiffErro
                 current_line += 1;
             }
         }
+        if current_line >= lines.len() {
+            // The hunk runs to the end of the file, so it decides how it ends.
+            trailing_newline = !hunk.no_newline_at_eof;
+        }
+        on_hunk(HunkProgress {
+            applied: index + 1,
+            total,
+        });
     }
 
     // Copy remaining lines after last hunk
@@ -232,7 +794,335 @@ pub fn apply_patch(original: &str, patch: &FilePatch) -> Result<String, DiffErro
         current_line += 1;
     }
 
-    Ok(result.join("\n"))
+    let eol = patch.line_ending.resolve(original);
+    let mut output = result.join(eol);
+    if trailing_newline && !output.is_empty() {
+        output.push_str(eol);
+    }
+    Ok(output)
+}
+
+// ============================================================================
+// Patch Reversal
+// ============================================================================
+
+/// The patch that undoes `patch`: hunks run from the new content back to the
+/// old, creates become deletes (and vice versa) and renames point back at the
+/// old path. Reversing the result gives `patch` back, apart from its id and
+/// timestamp. A reversed binary patch has no content to restore.
+pub fn reverse_patch(patch: &FilePatch) -> FilePatch {
+    let operation = match patch.operation {
+        PatchOperation::Create => PatchOperation::Delete,
+        PatchOperation::Delete => PatchOperation::Create,
+        other => other,
+    };
+    let (file_path, new_file_path) = match (patch.operation, &patch.new_file_path) {
+        (PatchOperation::Rename, Some(new_path)) => {
+            (new_path.clone(), Some(patch.file_path.clone()))
+        }
+        _ => (patch.file_path.clone(), patch.new_file_path.clone()),
+    };
+    let hunks: Vec<DiffHunk> = patch.hunks.iter().map(reverse_hunk).collect();
+    let full_content = (operation == PatchOperation::Create).then(|| new_side_content(&hunks));
+
+    FilePatch {
+        id: Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        file_path,
+        operation,
+        new_file_path,
+        hunks,
+        full_content,
+        additions: patch.deletions,
+        deletions: patch.additions,
+        description: patch.description.clone(),
+        line_ending: patch.line_ending,
+        truncated_context: patch.truncated_context,
+    }
+}
+
+/// One line of hunk content with its "no newline" marker and word segments
+struct HunkLine<'a> {
+    prefix: char,
+    text: &'a str,
+    no_newline: bool,
+    segments: Option<Vec<DiffSegment>>,
+}
+
+fn reverse_hunk(hunk: &DiffHunk) -> DiffHunk {
+    let mut segments_by_line: HashMap<usize, &Vec<DiffSegment>> = hunk
+        .line_segments
+        .iter()
+        .map(|line| (line.line, &line.segments))
+        .collect();
+
+    let mut lines: Vec<HunkLine> = Vec::new();
+    for (index, line) in hunk.content.lines().enumerate() {
+        if line.starts_with('\\') {
+            if let Some(last) = lines.last_mut() {
+                last.no_newline = true;
+            }
+            continue;
+        }
+        let mut chars = line.chars();
+        let prefix = match chars.next() {
+            Some('+') => '-',
+            Some('-') => '+',
+            _ => ' ',
+        };
+        let segments = segments_by_line.remove(&index).map(|segments| {
+            segments
+                .iter()
+                .map(|segment| DiffSegment {
+                    tag: match segment.tag {
+                        SegmentTag::Insert => SegmentTag::Delete,
+                        SegmentTag::Delete => SegmentTag::Insert,
+                        SegmentTag::Equal => SegmentTag::Equal,
+                    },
+                    value: segment.value.clone(),
+                })
+                .collect()
+        });
+        lines.push(HunkLine {
+            prefix,
+            text: chars.as_str(),
+            no_newline: false,
+            segments,
+        });
+    }
+
+    // Keep removals ahead of additions within each run of changed lines.
+    let mut ordered: Vec<HunkLine> = Vec::with_capacity(lines.len());
+    let mut run: Vec<HunkLine> = Vec::new();
+    for line in lines {
+        if line.prefix == ' ' {
+            flush_changed_run(&mut ordered, &mut run);
+            ordered.push(line);
+        } else {
+            run.push(line);
+        }
+    }
+    flush_changed_run(&mut ordered, &mut run);
+
+    let mut content = String::with_capacity(hunk.content.len());
+    let mut line_segments = Vec::new();
+    let mut line_index = 0;
+    let mut no_newline_at_eof = false;
+    for line in ordered {
+        content.push(line.prefix);
+        content.push_str(line.text);
+        content.push('\n');
+        if let Some(segments) = line.segments {
+            line_segments.push(LineSegments {
+                line: line_index,
+                segments,
+            });
+        }
+        line_index += 1;
+        if line.no_newline {
+            content.push_str(NO_NEWLINE_MARKER);
+            content.push('\n');
+            line_index += 1;
+            no_newline_at_eof |= line.prefix != '-';
+        }
+    }
+
+    let mut header = format!(
+        "@@ -{},{} +{},{} @@",
+        hunk.new_start + 1,
+        hunk.new_lines,
+        hunk.old_start + 1,
+        hunk.old_lines
+    );
+    if let Some(context) = &hunk.context {
+        header.push(' ');
+        header.push_str(context);
+    }
+
+    DiffHunk {
+        old_start: hunk.new_start,
+        old_lines: hunk.new_lines,
+        new_start: hunk.old_start,
+        new_lines: hunk.old_lines,
+        content,
+        header,
+        context: hunk.context.clone(),
+        no_newline_at_eof,
+        line_segments,
+    }
+}
+
+fn flush_changed_run<'a>(ordered: &mut Vec<HunkLine<'a>>, run: &mut Vec<HunkLine<'a>>) {
+    let (removed, added): (Vec<HunkLine>, Vec<HunkLine>) =
+        run.drain(..).partition(|line| line.prefix == '-');
+    ordered.extend(removed);
+    ordered.extend(added);
+}
+
+/// Content on the new side of `hunks`, for a patch that creates the file
+fn new_side_content(hunks: &[DiffHunk]) -> String {
+    let mut content = String::new();
+    let mut last_kept = false;
+    for hunk in hunks {
+        for line in hunk.content.lines() {
+            if line.starts_with('\\') {
+                if last_kept {
+                    content.pop();
+                }
+                continue;
+            }
+            last_kept = line.starts_with('+') || line.starts_with(' ');
+            if last_kept {
+                content.push_str(&line[1..]);
+                content.push('\n');
+            }
+        }
+    }
+    content
+}
+
+// ============================================================================
+// Three-Way Merge
+// ============================================================================
+
+const MERGE_OURS_MARKER: &str = "<<<<<<< ours\n";
+const MERGE_SEPARATOR: &str = "=======\n";
+const MERGE_THEIRS_MARKER: &str = ">>>>>>> theirs\n";
+
+/// A change one side made: `base` lines replaced by `side` lines
+#[derive(Debug, Clone)]
+struct MergeChunk {
+    base: std::ops::Range<usize>,
+    side: std::ops::Range<usize>,
+}
+
+/// Merge the changes `ours` and `theirs` each made to `base`. Changes to
+/// separate lines combine cleanly; where both sides touched the same or
+/// adjacent lines differently, both versions are kept between conflict
+/// markers.
+pub fn three_way_merge(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let ours_chunks = merge_chunks(&base_lines, &ours_lines);
+    let theirs_chunks = merge_chunks(&base_lines, &theirs_lines);
+
+    let mut content = String::with_capacity(base.len().max(ours.len()));
+    let mut conflicts = Vec::new();
+    let mut base_pos = 0;
+    let (mut next_ours, mut next_theirs) = (0, 0);
+
+    while next_ours < ours_chunks.len() || next_theirs < theirs_chunks.len() {
+        // Start a region at whichever change comes first, then pull in every
+        // change from either side that overlaps or touches it.
+        let first = match (ours_chunks.get(next_ours), theirs_chunks.get(next_theirs)) {
+            (Some(o), Some(t)) => o.base.start.min(t.base.start),
+            (Some(o), None) => o.base.start,
+            (None, Some(t)) => t.base.start,
+            (None, None) => break,
+        };
+        let mut region = first..first;
+        let (ours_from, theirs_from) = (next_ours, next_theirs);
+        loop {
+            let mut grew = false;
+            while let Some(chunk) = ours_chunks.get(next_ours) {
+                if chunk.base.start > region.end {
+                    break;
+                }
+                region.end = region.end.max(chunk.base.end);
+                next_ours += 1;
+                grew = true;
+            }
+            while let Some(chunk) = theirs_chunks.get(next_theirs) {
+                if chunk.base.start > region.end {
+                    break;
+                }
+                region.end = region.end.max(chunk.base.end);
+                next_theirs += 1;
+                grew = true;
+            }
+            if !grew {
+                break;
+            }
+        }
+
+        content.push_str(&base_lines[base_pos..region.start].concat());
+        let ours_text = merge_side_text(
+            &base_lines,
+            &ours_lines,
+            &ours_chunks[ours_from..next_ours],
+            region.clone(),
+        );
+        let theirs_text = merge_side_text(
+            &base_lines,
+            &theirs_lines,
+            &theirs_chunks[theirs_from..next_theirs],
+            region.clone(),
+        );
+        if next_theirs == theirs_from || ours_text == theirs_text {
+            content.push_str(&ours_text);
+        } else if next_ours == ours_from {
+            content.push_str(&theirs_text);
+        } else {
+            conflicts.push(ConflictRegion {
+                start_line: content.matches('\n').count(),
+                base: base_lines[region.clone()].concat(),
+                ours: ours_text.clone(),
+                theirs: theirs_text.clone(),
+            });
+            push_merge_line(&mut content, MERGE_OURS_MARKER);
+            push_merge_line(&mut content, &ours_text);
+            push_merge_line(&mut content, MERGE_SEPARATOR);
+            push_merge_line(&mut content, &theirs_text);
+            push_merge_line(&mut content, MERGE_THEIRS_MARKER);
+        }
+        base_pos = region.end;
+    }
+    content.push_str(&base_lines[base_pos..].concat());
+
+    if conflicts.is_empty() {
+        MergeResult::Clean { content }
+    } else {
+        MergeResult::Conflicted { content, conflicts }
+    }
+}
+
+/// The changes that turn `base` into `side`, in base order
+fn merge_chunks(base: &[&str], side: &[&str]) -> Vec<MergeChunk> {
+    similar::capture_diff_slices(Algorithm::Myers, base, side)
+        .into_iter()
+        .filter(|op| op.tag() != similar::DiffTag::Equal)
+        .map(|op| MergeChunk {
+            base: op.old_range(),
+            side: op.new_range(),
+        })
+        .collect()
+}
+
+/// What one side has in place of `base[region]`, given its changes there
+fn merge_side_text(
+    base: &[&str],
+    side: &[&str],
+    chunks: &[MergeChunk],
+    region: std::ops::Range<usize>,
+) -> String {
+    let mut text = String::new();
+    let mut pos = region.start;
+    for chunk in chunks {
+        text.push_str(&base[pos..chunk.base.start].concat());
+        text.push_str(&side[chunk.side.clone()].concat());
+        pos = chunk.base.end;
+    }
+    text.push_str(&base[pos..region.end].concat());
+    text
+}
+
+/// Append `text`, making sure a conflict marker after it starts a new line
+fn push_merge_line(content: &mut String, text: &str) {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(text);
 }
 
 // ============================================================================
@@ -240,8 +1130,15 @@ pub fn apply_patch(original: &str, patch: &FilePatch) -> Result<String, DiffErro
 // ============================================================================
 
 /// Generate a unified diff string for display
-#[allow(dead_code)]
 pub fn generate_unified_diff_string(patch: &FilePatch) -> String {
+    if patch.operation == PatchOperation::Binary {
+        let new_path = patch.new_file_path.as_deref().unwrap_or(&patch.file_path);
+        return format!(
+            "Binary files a/{} and b/{} differ\n",
+            patch.file_path, new_path
+        );
+    }
+
     let mut output = String::new();
 
     // Header
@@ -272,6 +1169,302 @@ pub fn generate_unified_diff_string(patch: &FilePatch) -> String {
     output
 }
 
+/// Generate a git-style `.patch` file: a `diff --git` line with extended
+/// headers for created, deleted and renamed files, then the unified diff.
+pub fn generate_git_patch(patch: &FilePatch) -> String {
+    let old_path = &patch.file_path;
+    let new_path = patch.new_file_path.as_deref().unwrap_or(old_path);
+    let mut output = format!("diff --git a/{} b/{}\n", old_path, new_path);
+
+    match patch.operation {
+        PatchOperation::Create => output.push_str("new file mode 100644\n"),
+        PatchOperation::Delete => output.push_str("deleted file mode 100644\n"),
+        PatchOperation::Rename if new_path != old_path => {
+            output.push_str(&format!("rename from {}\n", old_path));
+            output.push_str(&format!("rename to {}\n", new_path));
+        }
+        _ => {}
+    }
+
+    // A pure rename has no content change and no ---/+++ lines.
+    if !patch.hunks.is_empty() || patch.operation == PatchOperation::Binary {
+        output.push_str(&generate_unified_diff_string(patch));
+    }
+    output
+}
+
+// ============================================================================
+// Unified Diff Parsing
+// ============================================================================
+
+/// Parse `diff -u` / `git diff` text for a single file into a `FilePatch`.
+/// Leading `diff --git`/`index` lines are skipped; `/dev/null` on either side
+/// makes it a create or delete, and differing paths a rename.
+pub fn parse_unified_diff(text: &str) -> Result<FilePatch, DiffError> {
+    let malformed = |line: usize, reason: String| DiffError::MalformedPatch { line, reason };
+    let lines: Vec<&str> = text.lines().collect();
+
+    let Some(header_index) = lines.iter().position(|line| line.starts_with("--- ")) else {
+        return Err(malformed(1, "missing '---' file header".to_string()));
+    };
+    let old_path = parse_file_header(lines[header_index], "--- ");
+    let new_path = match lines.get(header_index + 1) {
+        Some(line) if line.starts_with("+++ ") => parse_file_header(line, "+++ "),
+        _ => {
+            return Err(malformed(
+                header_index + 2,
+                "expected '+++' file header after '---'".to_string(),
+            ))
+        }
+    };
+
+    let mut hunks = Vec::new();
+    let mut additions = 0;
+    let mut deletions = 0;
+    let mut index = header_index + 2;
+    while index < lines.len() {
+        let header = lines[index];
+        if header.is_empty() {
+            index += 1;
+            continue;
+        }
+        let Some((old_start, old_lines, new_start, new_lines, context)) = parse_hunk_header(header)
+        else {
+            return Err(malformed(
+                index + 1,
+                format!("expected '@@ -a,b +c,d @@' hunk header, found '{}'", header),
+            ));
+        };
+        index += 1;
+
+        let mut content = String::new();
+        let mut remaining_old = old_lines;
+        let mut remaining_new = new_lines;
+        let mut no_newline_at_eof = false;
+        let mut last_prefix = ' ';
+        while let Some(&line) = lines.get(index) {
+            let exhausted = remaining_old == 0 && remaining_new == 0;
+            // Some tools strip the space from empty context lines.
+            let prefix = line.chars().next().unwrap_or(' ');
+            if prefix == '\\' {
+                content.push_str(line);
+                content.push('\n');
+                no_newline_at_eof |= last_prefix != '-';
+                index += 1;
+                continue;
+            }
+            if exhausted {
+                break;
+            }
+            let counted = match prefix {
+                ' ' if remaining_old > 0 && remaining_new > 0 => {
+                    remaining_old -= 1;
+                    remaining_new -= 1;
+                    true
+                }
+                '-' if remaining_old > 0 => {
+                    remaining_old -= 1;
+                    deletions += 1;
+                    true
+                }
+                '+' if remaining_new > 0 => {
+                    remaining_new -= 1;
+                    additions += 1;
+                    true
+                }
+                _ => false,
+            };
+            if !counted {
+                return Err(malformed(
+                    index + 1,
+                    format!("unexpected line in hunk '{}': '{}'", header, line),
+                ));
+            }
+            content.push(prefix);
+            content.push_str(line.get(1..).unwrap_or_default());
+            content.push('\n');
+            last_prefix = prefix;
+            index += 1;
+        }
+        if remaining_old > 0 || remaining_new > 0 {
+            return Err(malformed(
+                index + 1,
+                format!("hunk '{}' ends before all of its lines", header),
+            ));
+        }
+
+        hunks.push(DiffHunk {
+            // A side with no lines names the line it follows; otherwise the
+            // header is 1-indexed.
+            old_start: if old_lines == 0 {
+                old_start
+            } else {
+                old_start.saturating_sub(1)
+            },
+            old_lines,
+            new_start: if new_lines == 0 {
+                new_start
+            } else {
+                new_start.saturating_sub(1)
+            },
+            new_lines,
+            content,
+            header: header.to_string(),
+            context,
+            no_newline_at_eof,
+            line_segments: Vec::new(),
+        });
+    }
+
+    let (operation, file_path, new_file_path) = match (old_path, new_path) {
+        (None, None) => {
+            return Err(malformed(
+                header_index + 1,
+                "both sides of the patch are /dev/null".to_string(),
+            ))
+        }
+        (None, Some(new_path)) => (PatchOperation::Create, new_path, None),
+        (Some(old_path), None) => (PatchOperation::Delete, old_path, None),
+        (Some(old_path), Some(new_path)) if old_path != new_path => {
+            (PatchOperation::Rename, old_path, Some(new_path))
+        }
+        (Some(old_path), Some(_)) => (PatchOperation::Modify, old_path, None),
+    };
+    let full_content = (operation == PatchOperation::Create).then(|| new_side_content(&hunks));
+
+    Ok(FilePatch {
+        id: Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        file_path,
+        operation,
+        new_file_path,
+        hunks,
+        full_content,
+        additions,
+        deletions,
+        description: None,
+        line_ending: LineEnding::Preserve,
+        truncated_context: false,
+    })
+}
+
+/// Path named by a `---`/`+++` line, without its `a/`/`b/` prefix or
+/// trailing timestamp; `None` for `/dev/null`
+fn parse_file_header(line: &str, marker: &str) -> Option<String> {
+    let path = line[marker.len()..]
+        .split('\t')
+        .next()
+        .unwrap_or_default()
+        .trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// `(old_start, old_lines, new_start, new_lines, context)` from an
+/// `@@ -a,b +c,d @@ context` line, with the header's own 1-indexed starts.
+/// An omitted count means one line.
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize, usize, Option<String>)> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, context) = rest.split_once(" @@")?;
+    let (old_range, new_range) = ranges.split_once(" +")?;
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = parse_range(old_range)?;
+    let (new_start, new_lines) = parse_range(new_range)?;
+    let context = context.trim();
+    let context = (!context.is_empty()).then(|| context.to_string());
+    Some((old_start, old_lines, new_start, new_lines, context))
+}
+
+// ============================================================================
+// File Comparison
+// ============================================================================
+
+/// Decode file bytes as text. Returns `None` for content that looks binary
+/// (contains NUL) or is not valid UTF-8.
+pub fn decode_text(bytes: Vec<u8>) -> Option<String> {
+    if is_binary(&bytes) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn read_diffable_file(path: &Path) -> Result<String, DiffError> {
+    let display = path.to_string_lossy().to_string();
+    let size = std::fs::metadata(path)?.len();
+    if size > MAX_DIFF_FILE_BYTES {
+        return Err(DiffError::TooLarge {
+            path: display,
+            size,
+            limit: MAX_DIFF_FILE_BYTES,
+        });
+    }
+    decode_text(std::fs::read(path)?).ok_or(DiffError::NotText(display))
+}
+
+/// Diff two files on disk, `path_a` being the "before" side.
+pub fn diff_file_pair(
+    path_a: &Path,
+    path_b: &Path,
+    context_lines: usize,
+    options: &DiffOptions,
+) -> Result<FilePatch, DiffError> {
+    let original = read_diffable_file(path_a)?;
+    let modified = read_diffable_file(path_b)?;
+
+    let mut patch = compute_unified_diff_with(
+        &original,
+        &modified,
+        &path_b.to_string_lossy(),
+        context_lines,
+        options,
+    );
+    patch.description = Some(format!(
+        "{} → {}",
+        path_a.to_string_lossy(),
+        path_b.to_string_lossy()
+    ));
+    Ok(patch)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Compare two files on disk, e.g. a backup against the current file.
+#[tauri::command]
+pub async fn diff_files(
+    path_a: String,
+    path_b: String,
+    context_lines: Option<usize>,
+    algorithm: Option<DiffAlgorithm>,
+    word_diff: Option<bool>,
+    line_ending: Option<LineEnding>,
+) -> Result<FilePatch, String> {
+    diff_file_pair(
+        Path::new(&path_a),
+        Path::new(&path_b),
+        context_lines.unwrap_or(3),
+        &DiffOptions {
+            algorithm: algorithm.unwrap_or_default(),
+            word_diff: word_diff.unwrap_or(false),
+            line_ending: line_ending.unwrap_or_default(),
+            ..Default::default()
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -314,4 +1507,426 @@ mod tests {
 
         assert_eq!(result.trim(), modified.trim());
     }
+
+    #[test]
+    fn test_missing_newline_at_eof_round_trips() {
+        let original = "line1\nline2\nline3";
+
+        let unchanged = compute_unified_diff(original, original, "eof.txt", 3);
+        assert_eq!(apply_patch(original, &unchanged).unwrap(), original);
+
+        let modified = "line1\nchanged\nline3";
+        let patch = compute_unified_diff(original, modified, "eof.txt", 3);
+        assert!(patch.hunks[0].no_newline_at_eof);
+        assert!(patch.hunks[0]
+            .content
+            .ends_with("\\ No newline at end of file\n"));
+        assert_eq!(apply_patch(original, &patch).unwrap(), modified);
+
+        // Adding or dropping the final newline is a real change and survives apply.
+        let terminated = "line1\nline2\nline3\n";
+        let patch = compute_unified_diff(original, terminated, "eof.txt", 3);
+        assert!(!patch.hunks[0].no_newline_at_eof);
+        assert_eq!(apply_patch(original, &patch).unwrap(), terminated);
+        let patch = compute_unified_diff(terminated, original, "eof.txt", 3);
+        assert_eq!(apply_patch(terminated, &patch).unwrap(), original);
+    }
+
+    #[test]
+    fn test_patience_keeps_moved_block_together() {
+        let original =
+            "fn a() {\n    one();\n}\n\nfn b() {\n    two();\n}\n\nfn c() {\n    three();\n}\n";
+        let modified =
+            "fn c() {\n    three();\n}\n\nfn a() {\n    one();\n}\n\nfn b() {\n    two();\n}\n";
+
+        let myers = compute_unified_diff(original, modified, "m.rs", 3);
+        let patience = compute_unified_diff_with(
+            original,
+            modified,
+            "m.rs",
+            3,
+            &DiffOptions {
+                algorithm: DiffAlgorithm::Patience,
+                ..Default::default()
+            },
+        );
+
+        // Myers splits `fn c` across the closing braces of `fn b`; patience
+        // removes it whole and leaves `fn b` untouched.
+        assert!(myers.hunks[0].content.contains("     two();\n-}\n"));
+        assert!(patience
+            .hunks
+            .iter()
+            .any(|hunk| hunk.content.ends_with("-fn c() {\n-    three();\n-}\n")));
+        assert_ne!(
+            generate_unified_diff_string(&myers),
+            generate_unified_diff_string(&patience)
+        );
+        assert_eq!(apply_patch(original, &myers).unwrap(), modified);
+        assert_eq!(apply_patch(original, &patience).unwrap(), modified);
+    }
+
+    #[test]
+    fn test_word_diff_marks_changed_tokens() {
+        let original = "let total = count + 1;\nunchanged\n";
+        let modified = "let total = amount + 1;\nunchanged\n";
+        let options = DiffOptions {
+            word_diff: true,
+            ..Default::default()
+        };
+
+        let patch = compute_unified_diff_with(original, modified, "w.rs", 3, &options);
+        let hunk = &patch.hunks[0];
+        assert_eq!(
+            hunk.content,
+            compute_unified_diff(original, modified, "w.rs", 3).hunks[0].content
+        );
+        let segment = |tag, value: &str| DiffSegment {
+            tag,
+            value: value.to_string(),
+        };
+        assert_eq!(
+            hunk.line_segments,
+            vec![
+                LineSegments {
+                    line: 0,
+                    segments: vec![
+                        segment(SegmentTag::Equal, "let total = "),
+                        segment(SegmentTag::Delete, "count"),
+                        segment(SegmentTag::Equal, " + 1;"),
+                    ],
+                },
+                LineSegments {
+                    line: 1,
+                    segments: vec![
+                        segment(SegmentTag::Equal, "let total = "),
+                        segment(SegmentTag::Insert, "amount"),
+                        segment(SegmentTag::Equal, " + 1;"),
+                    ],
+                },
+            ]
+        );
+        assert!(compute_unified_diff(original, modified, "w.rs", 3).hunks[0]
+            .line_segments
+            .is_empty());
+    }
+
+    #[test]
+    fn test_hunk_context_names_enclosing_function() {
+        let original = "impl ShadowFs {\n    pub fn apply(&self) -> usize {\n        let a = 1;\n        let b = 2;\n        let c = 3;\n        let d = 4;\n        a + b + c + d\n    }\n}\n";
+        let modified = original.replace("let d = 4;", "let d = 5;");
+
+        let patch = compute_unified_diff(original, &modified, "src/shadow.rs", 1);
+        assert_eq!(patch.hunks.len(), 1);
+        let hunk = &patch.hunks[0];
+        assert_eq!(
+            hunk.context.as_deref(),
+            Some("pub fn apply(&self) -> usize {")
+        );
+        assert_eq!(
+            hunk.header,
+            "@@ -5,3 +5,3 @@ pub fn apply(&self) -> usize {"
+        );
+
+        let no_context = DiffOptions {
+            context_signatures: Vec::new(),
+            ..Default::default()
+        };
+        let patch = compute_unified_diff_with(original, &modified, "src/shadow.rs", 1, &no_context);
+        assert_eq!(patch.hunks[0].context, None);
+    }
+
+    #[test]
+    fn test_crlf_original_against_lf_output_diffs_minimally() {
+        let original = "alpha\r\nbeta\r\ngamma\r\n";
+        let modified = "alpha\nBETA\ngamma\n";
+
+        let patch = compute_unified_diff(original, modified, "w.txt", 3);
+        assert_eq!((patch.additions, patch.deletions), (1, 1));
+        assert_eq!(patch.hunks[0].content, " alpha\n-beta\n+BETA\n gamma\n");
+        assert_eq!(
+            apply_patch(original, &patch).unwrap(),
+            "alpha\r\nBETA\r\ngamma\r\n"
+        );
+
+        let lf = DiffOptions {
+            line_ending: LineEnding::Lf,
+            ..Default::default()
+        };
+        let patch = compute_unified_diff_with(original, modified, "w.txt", 3, &lf);
+        assert_eq!(apply_patch(original, &patch).unwrap(), modified);
+    }
+
+    #[test]
+    fn test_summarize_totals_patches() {
+        let modify = compute_unified_diff("a\nb\n", "a\nB\nc\n", "m.txt", 3);
+        let create = compute_unified_diff("", "new\n", "n.txt", 3);
+        let unchanged = compute_unified_diff("same\n", "same\n", "s.txt", 3);
+        assert_eq!(
+            modify.stats(),
+            DiffStats {
+                files_changed: 1,
+                additions: 2,
+                deletions: 1,
+                hunks: 1
+            }
+        );
+        assert_eq!(unchanged.stats().files_changed, 0);
+        assert_eq!(
+            summarize(&[modify, create, unchanged]),
+            DiffStats {
+                files_changed: 2,
+                additions: 3,
+                deletions: 1,
+                hunks: 2
+            }
+        );
+        assert_eq!(summarize(&[]), DiffStats::default());
+    }
+
+    #[test]
+    fn test_three_way_merge() {
+        let base = "a\nb\nc\nd\ne\n";
+        let ours = "A\nb\nc\nd\ne\n";
+        let theirs = "a\nb\nc\nd\nE\nf\n";
+        assert_eq!(
+            three_way_merge(base, ours, theirs),
+            MergeResult::Clean {
+                content: "A\nb\nc\nd\nE\nf\n".to_string()
+            }
+        );
+        // Both sides making the same change is not a conflict.
+        assert_eq!(
+            three_way_merge(base, ours, ours),
+            MergeResult::Clean {
+                content: ours.to_string()
+            }
+        );
+
+        let theirs = "a\nb\nC\nd\ne\n";
+        let ours = "a\nb\nc-ours\nd\ne\n";
+        let MergeResult::Conflicted { content, conflicts } = three_way_merge(base, ours, theirs)
+        else {
+            panic!("overlapping edits should conflict");
+        };
+        assert_eq!(
+            content,
+            "a\nb\n<<<<<<< ours\nc-ours\n=======\nC\n>>>>>>> theirs\nd\ne\n"
+        );
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].start_line, 2);
+        assert_eq!(conflicts[0].base, "c\n");
+    }
+
+    #[test]
+    fn test_parse_unified_diff() {
+        let original = "one\ntwo\nthree\nfour\nfive\nsix\n";
+        let modified = "one\n2\nthree\nfour\nfive\nsix\nseven";
+        let text = format!(
+            "diff --git a/src/n.txt b/src/n.txt\nindex 1a2b3c..4d5e6f 100644\n{}",
+            generate_unified_diff_string(&compute_unified_diff(original, modified, "src/n.txt", 1))
+        );
+        let patch = parse_unified_diff(&text).unwrap();
+        assert_eq!(patch.operation, PatchOperation::Modify);
+        assert_eq!(patch.file_path, "src/n.txt");
+        assert_eq!((patch.additions, patch.deletions), (2, 1));
+        assert_eq!(patch.hunks.len(), 2);
+        assert_eq!((patch.hunks[0].old_start, patch.hunks[0].old_lines), (0, 3));
+        assert!(patch.hunks[1].no_newline_at_eof);
+        assert_eq!(apply_patch(original, &patch).unwrap(), modified);
+
+        let create =
+            parse_unified_diff("--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+a\n+b\n").unwrap();
+        assert_eq!(create.operation, PatchOperation::Create);
+        assert_eq!(create.full_content.as_deref(), Some("a\nb\n"));
+
+        let bad_header = parse_unified_diff("--- a/x\n+++ b/x\n@@ -1,x +1 @@\n-a\n").unwrap_err();
+        assert!(matches!(
+            bad_header,
+            DiffError::MalformedPatch { line: 3, .. }
+        ));
+        let short_hunk = parse_unified_diff("--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n a\n").unwrap_err();
+        assert!(matches!(short_hunk, DiffError::MalformedPatch { .. }));
+        assert!(parse_unified_diff("not a patch").is_err());
+    }
+
+    #[test]
+    fn test_reverse_patch_round_trips() {
+        let original = "keep\nold line\nlast";
+        let modified = "keep\nnew line\nlast\n";
+        let word_diff = DiffOptions {
+            word_diff: true,
+            ..Default::default()
+        };
+        let modify = compute_unified_diff_with(original, modified, "r.txt", 3, &word_diff);
+        let create = compute_unified_diff("", "fresh\ncontent", "new.txt", 3);
+        let mut rename = compute_unified_diff(original, modified, "old.txt", 3);
+        rename.operation = PatchOperation::Rename;
+        rename.new_file_path = Some("moved.txt".to_string());
+
+        let comparable = |patch: &FilePatch| {
+            let mut value = serde_json::to_value(patch).unwrap();
+            value["id"] = serde_json::Value::Null;
+            value["timestamp"] = serde_json::Value::Null;
+            value
+        };
+        for patch in [&modify, &create, &rename] {
+            let twice = reverse_patch(&reverse_patch(patch));
+            assert_eq!(comparable(&twice), comparable(patch));
+        }
+
+        let undo = reverse_patch(&modify);
+        assert_eq!(
+            (undo.additions, undo.deletions),
+            (modify.deletions, modify.additions)
+        );
+        assert!(undo.hunks[0].content.starts_with(" keep\n-new line\n"));
+        assert_eq!(apply_patch(modified, &undo).unwrap(), original);
+
+        let undo_create = reverse_patch(&create);
+        assert_eq!(undo_create.operation, PatchOperation::Delete);
+        assert_eq!(
+            reverse_patch(&undo_create).full_content.as_deref(),
+            Some("fresh\ncontent")
+        );
+        let undo_rename = reverse_patch(&rename);
+        assert_eq!(undo_rename.file_path, "moved.txt");
+        assert_eq!(undo_rename.new_file_path.as_deref(), Some("old.txt"));
+    }
+
+    #[test]
+    fn test_apply_patch_tolerates_drift_within_fuzz() {
+        let original: String = (0..20).map(|i| format!("line{}\n", i)).collect();
+        let modified = original.replace("line10\n", "changed10\n");
+        let patch = compute_unified_diff(&original, &modified, "drift.txt", 3);
+
+        // Two lines were added above the hunk after the patch was staged.
+        let drifted = format!("new0\nnew1\n{}", original);
+        let result = apply_patch(&drifted, &patch).unwrap();
+        assert_eq!(result.trim(), format!("new0\nnew1\n{}", modified).trim());
+
+        // Lines removed above the hunk shift it the other way.
+        let shrunk = original.replacen("line0\nline1\n", "", 1);
+        let result = apply_patch(&shrunk, &patch).unwrap();
+        assert_eq!(
+            result.trim(),
+            modified.replacen("line0\nline1\n", "", 1).trim()
+        );
+
+        // Beyond the window, or with the context gone, the hunk is refused.
+        let far = format!("{}{}", "pad\n".repeat(5), original);
+        assert!(matches!(
+            apply_patch_with_progress(&far, &patch, 3, |_| {}),
+            Err(DiffError::HunkMismatch { .. })
+        ));
+        assert!(apply_patch_with_progress(&far, &patch, 5, |_| {}).is_ok());
+        let rewritten = original.replace("line9\n", "other\n");
+        assert!(matches!(
+            apply_patch(&rewritten, &patch),
+            Err(DiffError::HunkMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_apply_patch_reports_progress_per_hunk() {
+        let original: String = (0..200).map(|i| format!("line{}\n", i)).collect();
+        let modified: String = (0..200)
+            .map(|i| {
+                if i % 20 == 10 {
+                    format!("changed{}\n", i)
+                } else {
+                    format!("line{}\n", i)
+                }
+            })
+            .collect();
+
+        let patch = compute_unified_diff(&original, &modified, "big.txt", 3);
+        assert_eq!(patch.hunks.len(), 10);
+
+        let mut progress = Vec::new();
+        let result =
+            apply_patch_with_progress(&original, &patch, DEFAULT_HUNK_FUZZ, |p| progress.push(p))
+                .unwrap();
+
+        assert_eq!(result.trim(), modified.trim());
+        assert_eq!(progress.len(), patch.hunks.len());
+        assert_eq!(
+            progress.last(),
+            Some(&HunkProgress {
+                applied: 10,
+                total: 10
+            })
+        );
+        assert!(progress
+            .windows(2)
+            .all(|w| w[1].applied == w[0].applied + 1));
+    }
+
+    fn unique_temp_dir(name: &str) -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        std::env::temp_dir().join(format!("coworkany-{name}-{}-{nanos}", std::process::id()))
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let dir = unique_temp_dir("diff-files");
+        std::fs::create_dir_all(&dir).unwrap();
+        let backup = dir.join("config.toml.bak");
+        let current = dir.join("config.toml");
+        std::fs::write(&backup, "name = \"app\"\nport = 8080\n").unwrap();
+        std::fs::write(&current, "name = \"app\"\nport = 9090\n").unwrap();
+
+        let patch = diff_file_pair(&backup, &current, 3, &DiffOptions::default()).unwrap();
+
+        assert_eq!(patch.operation, PatchOperation::Modify);
+        assert_eq!(patch.additions, 1);
+        assert_eq!(patch.deletions, 1);
+        assert!(patch.hunks[0].content.contains("+port = 9090"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_diff_files_rejects_binary() {
+        let dir = unique_temp_dir("diff-files-binary");
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.png");
+        let b = dir.join("b.png");
+        std::fs::write(&a, [0x89, b'P', b'N', b'G', 0x00, 0x01]).unwrap();
+        std::fs::write(&b, [0x89, b'P', b'N', b'G', 0x00, 0x02]).unwrap();
+
+        let err = diff_file_pair(&a, &b, 3, &DiffOptions::default()).unwrap_err();
+        assert!(matches!(err, DiffError::NotText(_)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_large_file_caps_context_lines() {
+        let original: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        let modified = original.replace("line 50\n", "line fifty\n");
+        let options = DiffOptions {
+            large_file: Some(LargeFileLimits {
+                line_threshold: 50,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let patch = compute_unified_diff_with(&original, &modified, "big.txt", 3, &options);
+        assert!(patch.truncated_context);
+        assert_eq!(patch.hunks.len(), 1);
+        assert_eq!(patch.hunks[0].old_lines, 3);
+        assert_eq!(
+            apply_patch(&original, &patch).expect("capped patch applies"),
+            modified
+        );
+
+        let small = compute_unified_diff(&original, &modified, "big.txt", 3);
+        assert!(!small.truncated_context);
+        assert_eq!(small.hunks[0].old_lines, 7);
+    }
 }
@@ -1,6 +1,10 @@
+use crate::diff::{decode_text, PatchOperation};
+use crate::shadow_fs::{ExternalChange, ShadowFileEntry, ShadowFsState, ShadowStatus};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use tracing::warn;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GitCommit {
@@ -8,12 +12,48 @@ pub struct GitCommit {
     pub message: String,
     pub author: String,
     pub date: String,
+    /// Files the whole commit touched, even when the log is scoped to one path
+    #[serde(default)]
+    pub files_changed: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GitStatus {
+    /// For display; bytes that aren't UTF-8 show as replacement characters
     pub file: String,
     pub status: String, // "M" | "A" | "D" | "??"
+    /// The path exactly as git reported it, for touching the file
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// One file from `git diff --name-status`, with paths relative to the cwd
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitDiffEntry {
+    pub operation: PatchOperation,
+    pub path: PathBuf,
+    pub new_path: Option<PathBuf>,
+}
+
+/// A path from git's `-z` output. Git writes paths as the bytes on disk,
+/// which needn't be UTF-8 on Unix; elsewhere they are UTF-8.
+fn path_from_git(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// NUL-separated fields of `-z` output, without empty ones
+fn z_fields(output: &[u8]) -> impl Iterator<Item = &[u8]> {
+    output
+        .split(|byte| *byte == 0)
+        .filter(|field| !field.is_empty())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,22 +84,29 @@ impl GitManager {
         Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     }
 
+    /// Like `run_git` but returns stdout bytes untouched (file contents and
+    /// paths, which may not be UTF-8).
+    fn run_git_raw<S: AsRef<OsStr>>(&self, cwd: &Path, args: &[S]) -> Result<Vec<u8>, String> {
+        let output = Command::new("git")
+            .current_dir(cwd)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        Ok(output.stdout)
+    }
+
     pub fn init(&self, path: &Path) -> Result<String, String> {
         self.run_git(path, &["init"])
     }
 
     pub fn status(&self, path: &Path) -> Result<Vec<GitStatus>, String> {
-        let output = self.run_git(path, &["status", "--porcelain"])?;
-        let mut results = Vec::new();
-
-        for line in output.lines() {
-            if line.len() > 3 {
-                let status = line[0..2].trim().to_string();
-                let file = line[3..].to_string();
-                results.push(GitStatus { file, status });
-            }
-        }
-        Ok(results)
+        let output = self.run_git_raw(path, &["status", "--porcelain", "-z"])?;
+        Ok(parse_status_z(&output))
     }
 
     pub fn add(&self, path: &Path, files: Vec<String>) -> Result<(), String> {
@@ -95,32 +142,88 @@ impl GitManager {
         self.run_git(path, &["rev-parse", "--abbrev-ref", "HEAD"])
     }
 
-    pub fn log(&self, path: &Path, limit: usize) -> Result<Vec<GitCommit>, String> {
+    /// Recent commits, newest first. With `file`, only commits touching it.
+    pub fn log(
+        &self,
+        path: &Path,
+        limit: usize,
+        file: Option<&str>,
+    ) -> Result<Vec<GitCommit>, String> {
         let limit_str = format!("-{}", limit);
-        // format: hash|author|date|message
-        let output = self.run_git(
+        // Commits start with a record separator; fields are unit-separated.
+        let mut args = vec![
+            "log",
+            &limit_str,
+            "--pretty=format:%x1e%h%x1f%an%x1f%ad%x1f%s",
+            "--date=iso",
+            "--shortstat",
+        ];
+        if let Some(file) = file {
+            // Count every file in the commit, not just the one filtered on.
+            args.extend(["--full-diff", "--", file]);
+        }
+        let output = self.run_git(path, &args)?;
+        Ok(parse_log_records(&output))
+    }
+
+    /// Resolve `git_ref` to the SHA of the commit it names. Anything that
+    /// git could read as an option is refused before git sees it.
+    pub fn resolve_commit(&self, path: &Path, git_ref: &str) -> Result<String, String> {
+        let git_ref = git_ref.trim();
+        if git_ref.is_empty() || git_ref.starts_with('-') {
+            return Err(format!("invalid_ref: {:?}", git_ref));
+        }
+        self.run_git(
             path,
             &[
-                "log",
-                &limit_str,
-                "--pretty=format:%h|%an|%ad|%s",
-                "--date=iso",
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                "--end-of-options",
+                &format!("{}^{{commit}}", git_ref),
+            ],
+        )
+        .map_err(|_| format!("invalid_ref: {} does not name a commit", git_ref))
+    }
+
+    /// Files that differ between `base_ref` and the working tree, including
+    /// untracked files (reported as created). `base_ref` should come from
+    /// `resolve_commit`.
+    pub fn diff_name_status(
+        &self,
+        path: &Path,
+        base_ref: &str,
+    ) -> Result<Vec<GitDiffEntry>, String> {
+        let output = self.run_git_raw(
+            path,
+            &[
+                "diff",
+                "--name-status",
+                "-z",
+                "-M",
+                "--relative",
+                base_ref,
+                "--",
             ],
         )?;
+        let mut entries = parse_name_status_z(&output);
 
-        let mut commits = Vec::new();
-        for line in output.lines() {
-            let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() >= 4 {
-                commits.push(GitCommit {
-                    hash: parts[0].to_string(),
-                    author: parts[1].to_string(),
-                    date: parts[2].to_string(),
-                    message: parts[3..].join("|"),
-                });
-            }
-        }
-        Ok(commits)
+        let untracked =
+            self.run_git_raw(path, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+        entries.extend(z_fields(&untracked).map(|file| GitDiffEntry {
+            operation: PatchOperation::Create,
+            path: path_from_git(file),
+            new_path: None,
+        }));
+
+        Ok(entries)
+    }
+
+    /// Content of `file` (relative to `path`) at `git_ref`
+    pub fn show_file(&self, path: &Path, git_ref: &str, file: &Path) -> Result<Vec<u8>, String> {
+        let mut spec = OsString::from(format!("{}:./", git_ref));
+        spec.push(file);
+        self.run_git_raw(path, &[OsStr::new("show"), &spec])
     }
 
     pub fn reset_hard(&self, path: &Path) -> Result<(), String> {
@@ -135,6 +238,207 @@ impl GitManager {
     }
 }
 
+/// Parse `git status --porcelain -z` output: `XY path` records, where a
+/// rename or copy record is followed by the path it came from.
+fn parse_status_z(output: &[u8]) -> Vec<GitStatus> {
+    let mut fields = z_fields(output);
+    let mut results = Vec::new();
+
+    while let Some(record) = fields.next() {
+        if record.len() <= 3 {
+            continue;
+        }
+        let status = String::from_utf8_lossy(&record[0..2]).trim().to_string();
+        let path = path_from_git(&record[3..]);
+        if status.starts_with(['R', 'C']) {
+            let _ = fields.next();
+        }
+        results.push(GitStatus {
+            file: path.to_string_lossy().into_owned(),
+            status,
+            path,
+        });
+    }
+    results
+}
+
+/// Parse `log` output: a header per commit, optionally followed by a
+/// `--shortstat` line such as ` 2 files changed, 3 insertions(+)`.
+fn parse_log_records(output: &str) -> Vec<GitCommit> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let header: Vec<&str> = lines.next()?.splitn(4, '\x1f').collect();
+            if header.len() < 4 {
+                return None;
+            }
+            let files_changed = lines
+                .find_map(|line| {
+                    let line = line.trim();
+                    let (count, rest) = line.split_once(' ')?;
+                    rest.starts_with("file")
+                        .then(|| count.parse::<usize>().ok())
+                        .flatten()
+                })
+                .unwrap_or(0);
+            Some(GitCommit {
+                hash: header[0].to_string(),
+                author: header[1].to_string(),
+                date: header[2].to_string(),
+                message: header[3].to_string(),
+                files_changed,
+            })
+        })
+        .collect()
+}
+
+/// Parse `git diff --name-status -z` output: a status token followed by one
+/// path, or two paths for renames/copies.
+fn parse_name_status_z(output: &[u8]) -> Vec<GitDiffEntry> {
+    let mut fields = z_fields(output);
+    let mut entries = Vec::new();
+
+    while let Some(status) = fields.next() {
+        let Some(path) = fields.next().map(path_from_git) else {
+            break;
+        };
+        let entry = match status.first() {
+            Some(b'A') => GitDiffEntry {
+                operation: PatchOperation::Create,
+                path,
+                new_path: None,
+            },
+            Some(b'D') => GitDiffEntry {
+                operation: PatchOperation::Delete,
+                path,
+                new_path: None,
+            },
+            Some(b'R') => {
+                let Some(new_path) = fields.next() else {
+                    break;
+                };
+                GitDiffEntry {
+                    operation: PatchOperation::Rename,
+                    path,
+                    new_path: Some(path_from_git(new_path)),
+                }
+            }
+            // A copy leaves the source untouched; review it as a new file.
+            Some(b'C') => {
+                let Some(new_path) = fields.next() else {
+                    break;
+                };
+                GitDiffEntry {
+                    operation: PatchOperation::Create,
+                    path: path_from_git(new_path),
+                    new_path: None,
+                }
+            }
+            _ => GitDiffEntry {
+                operation: PatchOperation::Modify,
+                path,
+                new_path: None,
+            },
+        };
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Resolve `git diff base_ref` into before/after content for each changed
+/// text file. Binary and non-UTF-8 files are skipped.
+fn collect_git_changes(
+    manager: &GitManager,
+    cwd: &Path,
+    base_ref: &str,
+) -> Result<Vec<ExternalChange>, String> {
+    let base = manager.resolve_commit(cwd, base_ref)?;
+    let mut changes = Vec::new();
+
+    for entry in manager.diff_name_status(cwd, &base)? {
+        let base_content = match entry.operation {
+            PatchOperation::Create => Some(String::new()),
+            _ => decode_text(manager.show_file(cwd, &base, &entry.path)?),
+        };
+        let current_path = cwd.join(entry.new_path.as_deref().unwrap_or(&entry.path));
+        let current_content = match entry.operation {
+            PatchOperation::Delete => Some(String::new()),
+            _ => std::fs::read(&current_path).ok().and_then(decode_text),
+        };
+
+        let (Some(base_content), Some(current_content)) = (base_content, current_content) else {
+            warn!(
+                "Skipping non-text change in git diff: {}",
+                entry.path.display()
+            );
+            continue;
+        };
+
+        changes.push(ExternalChange {
+            operation: entry.operation,
+            path: cwd.join(&entry.path),
+            new_path: entry.new_path.map(|new_path| cwd.join(new_path)),
+            base_content,
+            current_content,
+        });
+    }
+
+    Ok(changes)
+}
+
+/// Changes under `cwd` are only staged into the review list of the
+/// workspace they belong to: `cwd` must be the ShadowFs workspace root.
+fn check_workspace_cwd(cwd: &Path, workspace_root: &Path) -> Result<(), String> {
+    let canonical = |path: &Path| std::fs::canonicalize(path).map_err(|e| e.to_string());
+    if canonical(cwd)? == canonical(workspace_root)? {
+        Ok(())
+    } else {
+        Err(format!(
+            "path_outside_workspace: {} is not the workspace root {}",
+            cwd.display(),
+            workspace_root.display()
+        ))
+    }
+}
+
+/// Whether `entry`'s file is the same at `HEAD` as on disk under `cwd`: its
+/// content matches, or it is gone from both. Files outside `cwd` never are.
+fn committed_at_head(manager: &GitManager, cwd: &Path, entry: &ShadowFileEntry) -> bool {
+    let Ok(relative) = entry.original_path.strip_prefix(cwd) else {
+        return false;
+    };
+    let at_head = manager.show_file(cwd, "HEAD", relative).ok();
+    let on_disk = std::fs::read(&entry.original_path).ok();
+    if at_head != on_disk {
+        return false;
+    }
+    // A committed rename is also gone from its old path.
+    match entry
+        .renamed_from
+        .as_deref()
+        .and_then(|from| from.strip_prefix(cwd).ok())
+    {
+        Some(from) => manager.show_file(cwd, "HEAD", from).is_err(),
+        None => true,
+    }
+}
+
+/// Ids of applied entries whose change `HEAD` now contains
+fn committed_entry_ids(
+    manager: &GitManager,
+    cwd: &Path,
+    entries: &[&ShadowFileEntry],
+) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| entry.status == ShadowStatus::Applied)
+        .filter(|entry| committed_at_head(manager, cwd, entry))
+        .map(|entry| entry.id.clone())
+        .collect()
+}
+
 // ============================================================================
 // Tauri Commands
 // ============================================================================
@@ -192,9 +496,10 @@ pub async fn git_commit(
 pub async fn git_log(
     cwd: String,
     limit: Option<usize>,
+    path: Option<String>,
 ) -> Result<GitResult<Vec<GitCommit>>, String> {
     let manager = GitManager::new();
-    match manager.log(Path::new(&cwd), limit.unwrap_or(10)) {
+    match manager.log(Path::new(&cwd), limit.unwrap_or(10), path.as_deref()) {
         Ok(data) => Ok(GitResult {
             success: true,
             data: Some(data),
@@ -275,3 +580,345 @@ pub async fn git_rollback(cwd: String) -> Result<GitResult<String>, String> {
         error: None,
     })
 }
+
+/// Stage everything that changed since `base_ref` into the shadow review list
+#[tauri::command]
+pub async fn stage_from_git_diff(
+    state: tauri::State<'_, ShadowFsState>,
+    cwd: String,
+    base_ref: String,
+) -> Result<GitResult<Vec<ShadowFileEntry>>, String> {
+    let manager = GitManager::new();
+    let path = PathBuf::from(&cwd);
+
+    let workspace_root = {
+        let guard = state.lock().await;
+        let shadow_fs = guard
+            .as_ref()
+            .ok_or_else(|| crate::shadow_fs::ShadowFsError::NotInitialized.to_string())?;
+        shadow_fs.workspace_root().to_path_buf()
+    };
+    if let Err(e) = check_workspace_cwd(&path, &workspace_root) {
+        return Ok(GitResult {
+            success: false,
+            data: None,
+            error: Some(e),
+        });
+    }
+
+    let changes = match collect_git_changes(&manager, &path, &base_ref) {
+        Ok(changes) => changes,
+        Err(e) => {
+            return Ok(GitResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            })
+        }
+    };
+
+    let mut guard = state.lock().await;
+    let shadow_fs = guard
+        .as_mut()
+        .ok_or_else(|| crate::shadow_fs::ShadowFsError::NotInitialized.to_string())?;
+    if shadow_fs.workspace_root() != workspace_root.as_path() {
+        return Err(format!(
+            "workspace_changed: shadow workspace moved to {} while reading the diff",
+            shadow_fs.workspace_root().display()
+        ));
+    }
+
+    match shadow_fs.stage_external_changes(changes, &format!("git:{}", base_ref)) {
+        Ok(entries) => Ok(GitResult {
+            success: true,
+            data: Some(entries),
+            error: None,
+        }),
+        Err(e) => Ok(GitResult {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+/// Drop applied shadow entries, with their shadow copies and backups, whose
+/// files under `cwd` now match `HEAD`: once committed, git has the history.
+/// Returns the ids pruned.
+#[tauri::command]
+pub async fn prune_committed_entries(
+    state: tauri::State<'_, ShadowFsState>,
+    cwd: String,
+) -> Result<GitResult<Vec<String>>, String> {
+    let manager = GitManager::new();
+    let path = PathBuf::from(&cwd);
+
+    let mut guard = state.lock().await;
+    let shadow_fs = guard
+        .as_mut()
+        .ok_or_else(|| crate::shadow_fs::ShadowFsError::NotInitialized.to_string())?;
+
+    let committed = committed_entry_ids(&manager, &path, &shadow_fs.entries());
+    match shadow_fs.prune_applied(&committed) {
+        Ok(pruned) => Ok(GitResult {
+            success: true,
+            data: Some(pruned),
+            error: None,
+        }),
+        Err(e) => Ok(GitResult {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shadow_fs::ShadowFs;
+    use std::fs;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        std::env::temp_dir().join(format!("coworkany-{name}-{}-{nanos}", std::process::id()))
+    }
+
+    fn git(cwd: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(cwd)
+            .args([
+                "-c",
+                "user.name=CoworkAny Test",
+                "-c",
+                "user.email=test@coworkany.local",
+            ])
+            .args(args)
+            .status()
+            .expect("run git");
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn fixture_repo(name: &str) -> PathBuf {
+        let repo = unique_temp_dir(name);
+        fs::create_dir_all(&repo).expect("create repo");
+        git(&repo, &["init", "-q"]);
+        fs::write(repo.join(".gitignore"), ".coworkany/\n").expect("write gitignore");
+        fs::write(repo.join("keep.txt"), "unchanged\n").expect("write keep");
+        fs::write(repo.join("edit.txt"), "one\ntwo\n").expect("write edit");
+        fs::write(repo.join("remove.txt"), "bye\n").expect("write remove");
+        fs::write(repo.join("move.txt"), "a\nb\nc\nd\n").expect("write move");
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "base"]);
+        repo
+    }
+
+    #[test]
+    fn file_scoped_log_lists_only_commits_touching_the_file() {
+        let repo = fixture_repo("git-log-path");
+        fs::write(repo.join("my notes.txt"), "first\n").expect("write notes");
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "add notes | draft"]);
+        fs::write(repo.join("edit.txt"), "one\ntwo\nthree\n").expect("write edit");
+        git(&repo, &["commit", "-q", "-am", "edit only"]);
+        fs::write(repo.join("my notes.txt"), "second\n").expect("write notes");
+        fs::write(repo.join("keep.txt"), "changed\n").expect("write keep");
+        git(&repo, &["commit", "-q", "-am", "notes and keep"]);
+
+        let manager = GitManager::new();
+        let all = manager.log(&repo, 10, None).expect("log");
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[3].files_changed, 5, "base commit adds five files");
+
+        let scoped = manager
+            .log(&repo, 10, Some("my notes.txt"))
+            .expect("scoped log");
+        assert_eq!(scoped.len(), 2);
+        assert_eq!(scoped[0].message, "notes and keep");
+        assert_eq!(scoped[0].files_changed, 2);
+        assert_eq!(scoped[1].message, "add notes | draft");
+        assert_eq!(scoped[1].files_changed, 1);
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn parses_name_status_output() {
+        let parsed = parse_name_status_z(b"M\0a.txt\0D\0b.txt\0R100\0c.txt\0d.txt\0A\0e.txt\0");
+        assert_eq!(
+            parsed
+                .iter()
+                .map(|entry| (
+                    entry.operation,
+                    entry.path.to_str().unwrap(),
+                    entry.new_path.as_deref().and_then(Path::to_str)
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (PatchOperation::Modify, "a.txt", None),
+                (PatchOperation::Delete, "b.txt", None),
+                (PatchOperation::Rename, "c.txt", Some("d.txt")),
+                (PatchOperation::Create, "e.txt", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn stages_three_changed_files_with_operations() {
+        let repo = fixture_repo("git-diff-stage");
+        fs::write(repo.join("edit.txt"), "one\ntwo\nthree\n").expect("modify");
+        fs::remove_file(repo.join("remove.txt")).expect("delete");
+        fs::write(repo.join("added.txt"), "new\n").expect("add");
+
+        let manager = GitManager::new();
+        let changes = collect_git_changes(&manager, &repo, "HEAD").expect("collect changes");
+        let mut shadow_fs = ShadowFs::new(repo.clone()).expect("init shadow fs");
+        let entries = shadow_fs
+            .stage_external_changes(changes, "git:HEAD")
+            .expect("stage changes");
+
+        let mut staged: Vec<(String, PatchOperation)> = entries
+            .iter()
+            .map(|entry| {
+                let patch = entry.patch.as_ref().expect("patch");
+                (patch.file_path.clone(), patch.operation)
+            })
+            .collect();
+        staged.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            staged,
+            vec![
+                ("added.txt".to_string(), PatchOperation::Create),
+                ("edit.txt".to_string(), PatchOperation::Modify),
+                ("remove.txt".to_string(), PatchOperation::Delete),
+            ]
+        );
+        assert!(entries
+            .iter()
+            .all(|entry| entry.source.as_deref() == Some("git:HEAD")));
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn base_refs_that_look_like_options_are_refused() {
+        let repo = fixture_repo("git-diff-option-ref");
+        let manager = GitManager::new();
+        let target = repo.join("written-by-git.txt");
+
+        for bad in [
+            format!("--output={}", target.display()),
+            "-p".to_string(),
+            String::new(),
+        ] {
+            let err = collect_git_changes(&manager, &repo, &bad).expect_err("refused");
+            assert!(err.starts_with("invalid_ref"), "{err}");
+        }
+        assert!(!target.exists());
+        assert!(collect_git_changes(&manager, &repo, "no-such-branch").is_err());
+
+        let head = manager.resolve_commit(&repo, "HEAD").expect("resolve HEAD");
+        assert_eq!(head.len(), 40);
+
+        let elsewhere = unique_temp_dir("git-diff-other-root");
+        fs::create_dir_all(&elsewhere).expect("create other dir");
+        assert!(check_workspace_cwd(&repo, &repo).is_ok());
+        assert!(check_workspace_cwd(&elsewhere, &repo)
+            .expect_err("other directory")
+            .starts_with("path_outside_workspace"));
+
+        let _ = fs::remove_dir_all(&repo);
+        let _ = fs::remove_dir_all(&elsewhere);
+    }
+
+    #[test]
+    fn stages_renamed_files_as_rename() {
+        let repo = fixture_repo("git-diff-rename");
+        git(&repo, &["mv", "move.txt", "moved.txt"]);
+
+        let manager = GitManager::new();
+        let changes = collect_git_changes(&manager, &repo, "HEAD").expect("collect changes");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].operation, PatchOperation::Rename);
+        assert_eq!(
+            changes[0].new_path.as_deref(),
+            Some(repo.join("moved.txt").as_path())
+        );
+        assert_eq!(changes[0].base_content, changes[0].current_content);
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn status_keeps_non_utf8_file_names_intact() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let repo = fixture_repo("git-status-non-utf8");
+        // "caf\xe9.txt": Latin-1, not valid UTF-8
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        fs::write(repo.join(name), "legacy\n").expect("write legacy-named file");
+        fs::write(repo.join("edit.txt"), "one\ntwo\nthree\n").expect("modify");
+
+        let manager = GitManager::new();
+        let status = manager.status(&repo).expect("status");
+        let legacy = status
+            .iter()
+            .find(|entry| entry.status == "??")
+            .expect("untracked legacy file");
+        assert_eq!(legacy.path, Path::new(name));
+        assert!(repo.join(&legacy.path).exists());
+        assert_eq!(legacy.file, "caf\u{fffd}.txt");
+        assert!(status
+            .iter()
+            .any(|entry| entry.status == "M" && entry.file == "edit.txt"));
+
+        let changes = collect_git_changes(&manager, &repo, "HEAD").expect("collect changes");
+        assert!(changes.iter().any(|change| change.path == repo.join(name)));
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn prunes_applied_entries_only_once_committed() {
+        let repo = fixture_repo("git-prune-committed");
+        let mut shadow_fs = ShadowFs::new(repo.clone()).expect("init shadow fs");
+        let committed = shadow_fs
+            .stage_file(&repo.join("edit.txt"), "one\ntwo\nthree\n")
+            .expect("stage edit");
+        let uncommitted = shadow_fs
+            .stage_file(&repo.join("keep.txt"), "changed\n")
+            .expect("stage keep");
+        for id in [&committed.id, &uncommitted.id] {
+            shadow_fs.approve(id).expect("approve");
+            shadow_fs.apply(id, true).expect("apply");
+        }
+        git(&repo, &["add", "edit.txt"]);
+        git(&repo, &["commit", "-q", "-m", "take the edit"]);
+
+        let manager = GitManager::new();
+        let ids = committed_entry_ids(&manager, &repo, &shadow_fs.entries());
+        assert_eq!(ids, vec![committed.id.clone()]);
+        let backup = shadow_fs.get(&committed.id).unwrap().backup_path.clone();
+        let pruned = shadow_fs.prune_applied(&ids).expect("prune");
+        assert_eq!(pruned, vec![committed.id.clone()]);
+
+        assert!(shadow_fs.get(&committed.id).is_none());
+        assert!(!backup.expect("backup taken on apply").exists());
+        let kept = shadow_fs
+            .get(&uncommitted.id)
+            .expect("uncommitted entry kept");
+        assert_eq!(kept.status, ShadowStatus::Applied);
+        assert!(kept.backup_path.as_ref().is_some_and(|path| path.exists()));
+
+        let reloaded = ShadowFs::new(repo.clone()).expect("reload shadow fs");
+        assert!(reloaded.get(&committed.id).is_none());
+        assert!(reloaded.get(&uncommitted.id).is_some());
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+}
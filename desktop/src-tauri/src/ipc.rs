@@ -6,22 +6,30 @@
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::platform_asr;
 use crate::platform_runtime::{
-    build_platform_runtime_context, build_runtime_snapshot, resolve_skillhub_executable,
+    assemble_capabilities, build_platform_runtime_context, build_runtime_snapshot,
+    resolve_skillhub_executable, CapabilityReport, SystemCapabilityProbe,
+};
+use crate::process_manager::{ProcessManagerState, ServiceConfig, ServiceConfigPatch, ServiceInfo};
+use crate::sidecar::{
+    ensure_writable_dir, recv_streaming, transport_error, IpcCommand, LaunchedRuntime,
+    ProtocolDiagnostics, ProtocolHandshake, RagPathSettings, RagPaths, SidecarManager,
+    SidecarRoute, SidecarState, SidecarStderrLine, TaskConfig, TaskContext, WatchdogState,
+    RAG_PATHS_SETTINGS_KEY, STDERR_BACKLOG_LIMIT,
 };
-use crate::process_manager::{ProcessManagerState, ServiceInfo};
-use crate::sidecar::{IpcCommand, SidecarState, TaskConfig, TaskContext};
 
 static STARTUP_PROCESS_INSTANT: OnceLock<Instant> = OnceLock::new();
 static STARTUP_PROCESS_EPOCH_MS: OnceLock<u128> = OnceLock::new();
@@ -144,6 +152,9 @@ pub struct InstallToolpackInput {
     pub overwrite: Option<bool>,
     #[serde(rename = "approvePermissionExpansion")]
     pub approve_permission_expansion: Option<bool>,
+    /// Reused by the UI when it retries the same install
+    #[serde(rename = "idempotencyKey")]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -407,6 +418,18 @@ pub struct LlmConfigResult {
     pub success: bool,
     pub payload: LlmConfig,
     pub error: Option<String>,
+    /// Field-level problems that prevented a save
+    #[serde(rename = "fieldErrors", skip_serializing_if = "Vec::is_empty")]
+    pub field_errors: Vec<LlmConfigFieldError>,
+}
+
+/// A single validation problem, keyed by the camelCase path of the field in
+/// llm-config.json (e.g. `profiles[0].anthropic.apiKey`).
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmConfigFieldError {
+    pub field: String,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -426,6 +449,16 @@ pub struct SessionsSnapshotResult {
 #[derive(Debug, Clone, Serialize)]
 pub struct SidecarStatusResult {
     pub running: bool,
+    #[serde(rename = "launchedRuntime")]
+    pub launched_runtime: Option<LaunchedRuntime>,
+    #[serde(rename = "protocolHandshake")]
+    pub protocol_handshake: Option<ProtocolHandshake>,
+    /// Workspaces running a dedicated sidecar
+    #[serde(rename = "workspaceSidecars")]
+    pub workspace_sidecars: Vec<String>,
+    /// Paths searched when the sidecar entry could not be found
+    #[serde(rename = "sidecarMissing", skip_serializing_if = "Option::is_none")]
+    pub sidecar_missing: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -434,6 +467,57 @@ pub struct GenericIpcResult {
     pub payload: Value,
 }
 
+/// Result of a `validate_*` command. Problems with the thing being validated
+/// are reported here; `Err` is kept for failures to run the check at all.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationResult {
+    pub valid: bool,
+    pub errors: Vec<ValidationError>,
+    pub warnings: Vec<String>,
+    pub details: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    /// Stable machine-readable reason, e.g. `invalid_input`
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl ValidationError {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+            field: None,
+        }
+    }
+}
+
+impl ValidationResult {
+    pub fn valid(details: Value) -> Self {
+        Self {
+            valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            details,
+        }
+    }
+
+    pub fn invalid(error: ValidationError, details: Value) -> Self {
+        Self {
+            valid: false,
+            errors: vec![error],
+            warnings: Vec::new(),
+            details,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadRemoteFileResult {
@@ -599,13 +683,30 @@ async fn ensure_sidecar_running(
     state: &State<'_, SidecarState>,
     app_handle: &AppHandle,
 ) -> Result<(), String> {
-    let mut manager = state.0.lock().map_err(|e| e.to_string())?;
+    ensure_route_running(&state.default_route(), app_handle).await
+}
+
+/// Spawn the routed sidecar if needed and let its watchdog take over.
+async fn ensure_route_running(route: &SidecarRoute, app_handle: &AppHandle) -> Result<(), String> {
+    let mut manager = route.manager.lock().map_err(|e| e.to_string())?;
     if !manager.is_running() {
-        debug!("Sidecar not running, spawning...");
+        debug!(
+            "Sidecar not running, spawning... (workspace={:?})",
+            route.workspace
+        );
         manager
             .spawn(app_handle.clone())
             .map_err(|e| e.to_string())?;
-        let _ = app_handle.emit("sidecar-reconnected", ());
+        match (&route.watchdog, &route.workspace) {
+            (Some(watchdog), Some(workspace)) => {
+                watchdog.resume();
+                let _ = app_handle.emit("sidecar-reconnected", json!({ "workspace": workspace }));
+            }
+            _ => {
+                app_handle.state::<WatchdogState>().resume();
+                let _ = app_handle.emit("sidecar-reconnected", ());
+            }
+        }
     }
     Ok(())
 }
@@ -623,6 +724,21 @@ async fn send_command_and_wait_with_timeout_policy(
     command: Value,
     timeout_ms: u64,
     invalidate_transport_on_timeout: bool,
+) -> Result<Value, String> {
+    send_to_manager_and_wait(
+        &state.manager,
+        command,
+        timeout_ms,
+        invalidate_transport_on_timeout,
+    )
+    .await
+}
+
+async fn send_to_manager_and_wait(
+    manager: &Arc<Mutex<SidecarManager>>,
+    command: Value,
+    timeout_ms: u64,
+    invalidate_transport_on_timeout: bool,
 ) -> Result<Value, String> {
     let command_id = command
         .get("id")
@@ -630,7 +746,7 @@ async fn send_command_and_wait_with_timeout_policy(
         .ok_or_else(|| "command id missing".to_string())?
         .to_string();
     let rx = {
-        let manager = state.0.lock().map_err(|e| e.to_string())?;
+        let manager = manager.lock().map_err(|e| e.to_string())?;
         manager
             .send_command_async(command)
             .map_err(|e| e.to_string())?
@@ -648,18 +764,9 @@ async fn send_command_and_wait_with_timeout_policy(
 
     match result {
         Ok(response) => {
-            if response.get("type").and_then(Value::as_str) == Some("transport_error_response") {
-                let payload = response
-                    .get("payload")
-                    .cloned()
-                    .unwrap_or_else(|| json!({}));
-                let error_message = payload
-                    .get("details")
-                    .and_then(Value::as_str)
-                    .or_else(|| payload.get("error").and_then(Value::as_str))
-                    .unwrap_or("sidecar transport failed")
-                    .to_string();
-                if let Ok(mut manager) = state.0.lock() {
+            if let Some(error) = transport_error(&response) {
+                let error_message = error.to_string();
+                if let Ok(mut manager) = manager.lock() {
                     manager.invalidate_transport(&error_message);
                 }
                 return Err(error_message);
@@ -668,7 +775,7 @@ async fn send_command_and_wait_with_timeout_policy(
             Ok(response)
         }
         Err(error_message) => {
-            if let Ok(mut manager) = state.0.lock() {
+            if let Ok(mut manager) = manager.lock() {
                 manager.clear_pending_response(&command_id);
                 if invalidate_transport_on_timeout {
                     manager.invalidate_transport(&format!(
@@ -682,6 +789,195 @@ async fn send_command_and_wait_with_timeout_policy(
     }
 }
 
+/// Send a long-running command, emitting each of its `command_progress`
+/// messages as a `command-progress` event until the response arrives.
+/// `idle_timeout_ms` bounds the wait between messages, not the whole call.
+async fn send_command_and_stream(
+    state: &State<'_, SidecarState>,
+    app_handle: &AppHandle,
+    command: Value,
+    idle_timeout_ms: u64,
+) -> Result<Value, String> {
+    let command_id = command
+        .get("id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "command id missing".to_string())?
+        .to_string();
+    let command_type = command.get("type").cloned().unwrap_or(Value::Null);
+    let manager = &state.manager;
+    let rx = {
+        let manager = manager.lock().map_err(|e| e.to_string())?;
+        manager
+            .send_command_streaming(command)
+            .map_err(|e| e.to_string())?
+    };
+
+    let progress_handle = app_handle.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        recv_streaming(
+            &rx,
+            std::time::Duration::from_millis(idle_timeout_ms),
+            |progress| {
+                let _ = progress_handle.emit(
+                    "command-progress",
+                    json!({
+                        "commandId": progress.get("commandId"),
+                        "commandType": command_type,
+                        "payload": progress.get("payload"),
+                    }),
+                );
+            },
+        )
+        .map_err(|err| format!("response timeout: {}", err))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.clear_pending_response(&command_id);
+    match result {
+        Ok(response) => {
+            if let Some(error) = transport_error(&response) {
+                let error_message = error.to_string();
+                manager.invalidate_transport(&error_message);
+                return Err(error_message);
+            }
+            Ok(response)
+        }
+        Err(error_message) => {
+            manager.invalidate_transport(&format!(
+                "command {} stopped reporting progress",
+                command_id
+            ));
+            Err(error_message)
+        }
+    }
+}
+
+/// Send an idempotent read. If it fails because the sidecar died mid-call,
+/// respawn the sidecar once and retry. Never use this for mutating commands.
+async fn send_read_command_and_wait(
+    state: &State<'_, SidecarState>,
+    app_handle: &AppHandle,
+    command: Value,
+    timeout_ms: u64,
+    invalidate_transport_on_timeout: bool,
+) -> Result<Value, String> {
+    retry_read_once(
+        || {
+            send_command_and_wait_with_timeout_policy(
+                state,
+                with_fresh_command_id(command.clone()),
+                timeout_ms,
+                invalidate_transport_on_timeout,
+            )
+        },
+        || respawn_if_sidecar_dead(state, app_handle),
+    )
+    .await
+}
+
+/// Send a mutating command under `idempotency_key` (a new one when `None`).
+/// It is sent once and never retried: a sidecar respawned after a crash has
+/// not seen the key, so a resend could apply the command twice. While the
+/// key is in flight, sending it again is refused as a duplicate.
+async fn send_mutating_command_and_wait(
+    state: &State<'_, SidecarState>,
+    command: Value,
+    idempotency_key: Option<String>,
+    timeout_ms: u64,
+) -> Result<Value, String> {
+    let key = idempotency_key.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let _in_flight = InFlightKey::claim(&key)?;
+    send_command_and_wait(state, with_idempotency_key(command, &key), timeout_ms).await
+}
+
+/// Idempotency keys of mutating commands waiting for a response
+fn in_flight_keys() -> &'static Mutex<HashSet<String>> {
+    static KEYS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    KEYS.get_or_init(Default::default)
+}
+
+/// Holds an idempotency key in `in_flight_keys` until dropped
+struct InFlightKey(String);
+
+impl InFlightKey {
+    fn claim(key: &str) -> Result<Self, String> {
+        let mut keys = in_flight_keys().lock().map_err(|e| e.to_string())?;
+        if !keys.insert(key.to_string()) {
+            return Err(format!(
+                "duplicate: a command with idempotency key {} is already in flight",
+                key
+            ));
+        }
+        Ok(Self(key.to_string()))
+    }
+}
+
+impl Drop for InFlightKey {
+    fn drop(&mut self) {
+        if let Ok(mut keys) = in_flight_keys().lock() {
+            keys.remove(&self.0);
+        }
+    }
+}
+
+fn with_idempotency_key(mut command: Value, key: &str) -> Value {
+    if let Some(command) = command.as_object_mut() {
+        command.insert("idempotencyKey".to_string(), Value::String(key.to_string()));
+    }
+    command
+}
+
+/// Run `attempt`; on failure ask `recover` whether the sidecar was dead and has
+/// been respawned, and if so run `attempt` one more time.
+async fn retry_read_once<A, AFut, R, RFut>(mut attempt: A, recover: R) -> Result<Value, String>
+where
+    A: FnMut() -> AFut,
+    AFut: std::future::Future<Output = Result<Value, String>>,
+    R: FnOnce() -> RFut,
+    RFut: std::future::Future<Output = Result<bool, String>>,
+{
+    let error = match attempt().await {
+        Ok(response) => return Ok(response),
+        Err(error) => error,
+    };
+    match recover().await {
+        Ok(true) => {
+            warn!("Sidecar died during a read ({}); retrying once", error);
+            attempt().await
+        }
+        Ok(false) => Err(error),
+        Err(respawn_error) => Err(format!("{} (respawn failed: {})", error, respawn_error)),
+    }
+}
+
+/// Respawn the default sidecar if it is no longer running. Returns whether it
+/// had to.
+async fn respawn_if_sidecar_dead(
+    state: &State<'_, SidecarState>,
+    app_handle: &AppHandle,
+) -> Result<bool, String> {
+    let running = state
+        .manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_running();
+    if running {
+        return Ok(false);
+    }
+    ensure_sidecar_running(state, app_handle).await?;
+    Ok(true)
+}
+
+/// The same command under a new id, so a retry doesn't match a stale response.
+fn with_fresh_command_id(mut command: Value) -> Value {
+    if let Some(id) = command.get_mut("id") {
+        *id = Value::String(Uuid::new_v4().to_string());
+    }
+    command
+}
+
 fn is_response_timeout_error(error: &str) -> bool {
     error.starts_with("response timeout:")
 }
@@ -961,15 +1257,20 @@ async fn discover_transcription_model(
     }
 
     let payload = response.json::<Value>().await.map_err(|e| e.to_string())?;
-    let model_ids = payload
+    Ok(select_transcription_model_from_catalog(
+        &model_ids_from_catalog(&payload),
+    ))
+}
+
+/// Model ids in an OpenAI- or Anthropic-style `GET /models` reply
+fn model_ids_from_catalog(payload: &Value) -> Vec<String> {
+    payload
         .get("data")
         .and_then(Value::as_array)
         .into_iter()
         .flatten()
         .filter_map(|entry| entry.get("id").and_then(Value::as_str).map(str::to_string))
-        .collect::<Vec<_>>();
-
-    Ok(select_transcription_model_from_catalog(&model_ids))
+        .collect()
 }
 
 fn apply_proxy_to_client_builder(
@@ -1067,8 +1368,10 @@ pub async fn start_task(
     // Generate new task ID
     let task_id = Uuid::new_v4().to_string();
 
-    // Ensure sidecar is running
-    ensure_sidecar_running(&state, &app_handle).await?;
+    // Ensure the sidecar for this workspace is running
+    let route = state.route_workspace(&input.workspace_path)?;
+    let workspace_path = input.workspace_path.clone();
+    ensure_route_running(&route, &app_handle).await?;
 
     // Build context
     let context = TaskContext {
@@ -1080,16 +1383,7 @@ pub async fn start_task(
         environment_context: Some(build_platform_runtime_context(&app_handle, None)),
     };
 
-    let config = input.config.map(|cfg| TaskConfig {
-        model_id: cfg.model_id,
-        execution_path: cfg.execution_path,
-        max_tokens: cfg.max_tokens,
-        max_history_messages: cfg.max_history_messages,
-        enabled_claude_skills: cfg.enabled_claude_skills,
-        enabled_toolpacks: cfg.enabled_toolpacks,
-        enabled_skills: cfg.enabled_skills,
-        voice_provider_mode: cfg.voice_provider_mode,
-    });
+    let config = task_config_from_input(input.config, configured_max_history_messages(&app_handle));
 
     // Send command to sidecar and wait for the immediate ack so the frontend
     // can stay in sync with any workspace metadata updates (e.g. auto-rename).
@@ -1102,7 +1396,7 @@ pub async fn start_task(
     ))
     .map_err(|e| e.to_string())?;
 
-    let response = send_command_and_wait(&state, command, 10000).await?;
+    let response = send_to_manager_and_wait(&route.manager, command, 10000, true).await?;
     let payload = response
         .get("payload")
         .cloned()
@@ -1117,6 +1411,11 @@ pub async fn start_task(
         .and_then(|value| value.as_str())
         .unwrap_or(task_id.as_str())
         .to_string();
+    if route.workspace.is_some() {
+        if let Ok(mut workspaces) = state.workspaces.lock() {
+            workspaces.bind_task(&response_task_id, &workspace_path);
+        }
+    }
     let workspace = payload.get("workspace").cloned();
     let turn_id = payload
         .get("turnId")
@@ -1149,7 +1448,8 @@ pub async fn cancel_task(
 ) -> Result<CancelTaskResult, String> {
     info!("cancel_task command received: {:?}", input);
 
-    let mut manager = state.0.lock().map_err(|e| e.to_string())?;
+    let route = state.route_task(&input.task_id);
+    let mut manager = route.manager.lock().map_err(|e| e.to_string())?;
 
     if !manager.is_running() {
         return Ok(CancelTaskResult {
@@ -1181,12 +1481,13 @@ pub async fn clear_task_history(
 ) -> Result<ClearTaskHistoryResult, String> {
     info!("clear_task_history command received: {:?}", input);
 
-    ensure_sidecar_running(&state, &app_handle).await?;
+    let route = state.route_task(&input.task_id);
+    ensure_route_running(&route, &app_handle).await?;
 
     let command = IpcCommand::clear_task_history(input.task_id.clone());
 
     {
-        let manager = state.0.lock().map_err(|e| e.to_string())?;
+        let manager = route.manager.lock().map_err(|e| e.to_string())?;
         manager.send_command(command).map_err(|e| {
             error!("Failed to send clear_task_history command: {}", e);
             e.to_string()
@@ -1216,7 +1517,7 @@ pub async fn get_tasks(
     let command = build_command("get_tasks", payload);
 
     // Sidecar returns Full Response Object (with type, commandId, payload)
-    let response = send_command_and_wait(&state, command, 3000).await?;
+    let response = send_read_command_and_wait(&state, &app_handle, command, 3000, true).await?;
 
     // meaningful data is in response.payload
     let inner_payload = response.get("payload").cloned().unwrap_or(json!({}));
@@ -1651,17 +1952,344 @@ pub async fn transcribe_audio(
 #[cfg(test)]
 mod tests {
     use super::{
-        apply_proxy_to_client_builder, build_doctor_preflight_payload,
-        build_validation_request_plan, normalize_openai_compatible_model,
-        resolve_openai_compatible_base_url, select_transcription_model_from_catalog,
-        AnthropicProviderSettings, CustomProviderSettings, DoctorPreflightInput,
-        OpenAIProviderSettings, OpenRouterProviderSettings, ProxySettings, StartTaskConfigInput,
-        ValidateLlmInput, ValidationAuthScheme,
+        apply_proxy_to_client_builder, build_command, build_doctor_preflight_payload,
+        build_models_request_plan, build_validation_request_plan, cancel_validation,
+        default_provider_models, llm_validation_result, load_llm_config_cached,
+        migrate_provider_settings, normalize_openai_compatible_model,
+        resolve_openai_compatible_base_url, resolve_provider_models, retry_read_once,
+        run_cancelable_validation, select_transcription_model_from_catalog,
+        sidecar_validation_result, task_config_from_input, validate_llm_config,
+        with_fresh_command_id, with_idempotency_key, AnthropicProviderSettings,
+        CustomProviderSettings, DoctorPreflightInput, InFlightKey, LlmConfig, LlmConfigCache,
+        LlmProfile, OpenAIProviderSettings, OpenRouterProviderSettings, ProxySettings,
+        StartTaskConfigInput, ValidateLlmInput, ValidationAuthScheme, ValidationInterrupted,
     };
     use crate::sidecar::TaskConfig;
     use serde_json::json;
     use std::env;
+    use std::sync::Mutex;
     use std::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn rejects_config_missing_selected_provider_api_key() {
+        let config = LlmConfig {
+            provider: Some("anthropic".to_string()),
+            anthropic: Some(AnthropicProviderSettings {
+                api_key: Some("  ".to_string()),
+                model: Some("claude-sonnet-4-5".to_string()),
+            }),
+            ..Default::default()
+        };
+
+        let errors = validate_llm_config(&config);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "anthropic.apiKey");
+
+        let fixed = LlmConfig {
+            anthropic: Some(AnthropicProviderSettings {
+                api_key: Some("sk-ant-test".to_string()),
+                model: None,
+            }),
+            ..config
+        };
+        assert!(validate_llm_config(&fixed).is_empty());
+    }
+
+    #[test]
+    fn rejects_dangling_active_profile_id_and_bad_base_urls() {
+        let config = LlmConfig {
+            profiles: Some(vec![LlmProfile {
+                id: "work".to_string(),
+                name: "Work".to_string(),
+                provider: "openai".to_string(),
+                openai: Some(OpenAIProviderSettings {
+                    api_key: Some("sk-test".to_string()),
+                    base_url: Some("not a url".to_string()),
+                    model: None,
+                    allow_insecure_tls: None,
+                }),
+                ..Default::default()
+            }]),
+            active_profile_id: Some("deleted-profile".to_string()),
+            ..Default::default()
+        };
+
+        let fields: Vec<String> = validate_llm_config(&config)
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "activeProfileId".to_string(),
+                "profiles[0].openai.baseUrl".to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_validation_resolves_it_promptly() {
+        let started = std::time::Instant::now();
+        let validation = tokio::spawn(run_cancelable_validation(
+            Some("validation-cancel-test"),
+            Duration::from_secs(60),
+            std::future::pending::<()>(),
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(cancel_validation("validation-cancel-test".to_string()));
+        let outcome = tokio::time::timeout(Duration::from_secs(1), validation)
+            .await
+            .expect("cancelled validation resolves")
+            .expect("validation task");
+        assert_eq!(outcome, Err(ValidationInterrupted::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!cancel_validation("validation-cancel-test".to_string()));
+
+        let timed_out = run_cancelable_validation(
+            None,
+            Duration::from_millis(20),
+            std::future::pending::<()>(),
+        )
+        .await;
+        assert_eq!(timed_out, Err(ValidationInterrupted::TimedOut));
+    }
+
+    #[tokio::test]
+    async fn provider_models_fall_back_to_curated_defaults() {
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let models = resolve_provider_models(&client, "anthropic", None).await;
+        assert_eq!(models.models, default_provider_models("anthropic"));
+        assert!(!models.live);
+        assert!(models.error.is_none());
+
+        let no_key = ValidateLlmInput {
+            provider: "openai".to_string(),
+            anthropic: None,
+            openrouter: None,
+            openai: Some(OpenAIProviderSettings {
+                api_key: Some(" ".to_string()),
+                ..Default::default()
+            }),
+            custom: None,
+            proxy: None,
+        };
+        assert!(build_models_request_plan("openai", &no_key).is_none());
+        assert!(resolve_provider_models(&client, "made-up", None)
+            .await
+            .models
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn provider_models_are_augmented_from_the_live_list_and_cached() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answers exactly one request, so a second fetch would fail
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let body = r#"{"data":[{"id":"gpt-4o"},{"id":"gpt-live-preview"}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+
+        let settings = ValidateLlmInput {
+            provider: "openai".to_string(),
+            anthropic: None,
+            openrouter: None,
+            openai: Some(OpenAIProviderSettings {
+                api_key: Some("sk-models-test".to_string()),
+                base_url: Some(format!("http://127.0.0.1:{port}/v1/")),
+                ..Default::default()
+            }),
+            custom: None,
+            proxy: None,
+        };
+        let plan = build_models_request_plan("openai", &settings).expect("plan");
+        assert_eq!(plan.url, format!("http://127.0.0.1:{port}/v1/models"));
+
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let models = resolve_provider_models(&client, "openai", Some(&plan)).await;
+        assert!(models.live, "{:?}", models.error);
+        let defaults = default_provider_models("openai");
+        assert_eq!(&models.models[..defaults.len()], defaults);
+        assert_eq!(
+            models.models.last().map(String::as_str),
+            Some("gpt-live-preview")
+        );
+        assert_eq!(
+            models
+                .models
+                .iter()
+                .filter(|model| *model == "gpt-4o")
+                .count(),
+            1
+        );
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /v1/models"));
+        assert!(request
+            .to_ascii_lowercase()
+            .contains("authorization: bearer sk-models-test"));
+
+        let cached = resolve_provider_models(&client, "openai", Some(&plan)).await;
+        assert_eq!(cached, models);
+    }
+
+    #[test]
+    fn llm_validation_results_have_a_typed_shape() {
+        let ok = llm_validation_result("anthropic", reqwest::StatusCode::OK, "");
+        assert_eq!(
+            serde_json::to_value(&ok).expect("serialize"),
+            json!({
+                "valid": true,
+                "errors": [],
+                "warnings": [],
+                "details": {
+                    "provider": "anthropic",
+                    "status": 200,
+                    "message": "Connection successful",
+                },
+            })
+        );
+
+        let rejected = llm_validation_result(
+            "openrouter",
+            reqwest::StatusCode::UNAUTHORIZED,
+            "invalid key",
+        );
+        assert!(!rejected.valid);
+        assert_eq!(rejected.errors.len(), 1);
+        assert_eq!(rejected.errors[0].code, "provider_rejected");
+        assert!(rejected.errors[0].message.contains("invalid key"));
+        assert_eq!(rejected.details["status"], json!(401));
+        let value = serde_json::to_value(&rejected).expect("serialize");
+        assert!(value["errors"][0].get("field").is_none());
+    }
+
+    #[test]
+    fn sidecar_validation_replies_map_to_validation_results() {
+        let ok = sidecar_validation_result(&json!({
+            "type": "validate_skill_response",
+            "payload": { "success": true, "warnings": ["no README"], "name": "demo" },
+        }));
+        assert!(ok.valid);
+        assert!(ok.errors.is_empty());
+        assert_eq!(ok.warnings, vec!["no README".to_string()]);
+        assert_eq!(ok.details["name"], json!("demo"));
+
+        let rejected = sidecar_validation_result(&json!({
+            "payload": { "success": false, "error": "missing SKILL.md" },
+        }));
+        assert!(!rejected.valid);
+        assert_eq!(rejected.errors.len(), 1);
+        assert_eq!(rejected.errors[0].code, "sidecar_rejected");
+        assert_eq!(rejected.errors[0].message, "missing SKILL.md");
+
+        let bare = sidecar_validation_result(&json!({ "payload": { "valid": false } }));
+        assert!(!bare.valid);
+        assert_eq!(bare.errors.len(), 1);
+    }
+
+    #[test]
+    fn llm_config_cache_rereads_only_after_the_file_changes() {
+        let dir = env::temp_dir().join(format!(
+            "coworkany-llm-config-cache-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("system time")
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("llm-config.json");
+        std::fs::write(&path, r#"{"provider":"anthropic"}"#).expect("write config");
+
+        let cache = Mutex::new(LlmConfigCache::default());
+        let first = load_llm_config_cached(&cache, &path).expect("first read");
+        let second = load_llm_config_cached(&cache, &path).expect("second read");
+        assert_eq!(first.provider.as_deref(), Some("anthropic"));
+        assert_eq!(second.provider.as_deref(), Some("anthropic"));
+        assert_eq!(cache.lock().expect("cache").disk_reads, 1);
+
+        std::fs::write(&path, r#"{"provider":"openrouter"}"#).expect("rewrite config");
+        let changed = load_llm_config_cached(&cache, &path).expect("read after change");
+        assert_eq!(changed.provider.as_deref(), Some("openrouter"));
+        assert_eq!(cache.lock().expect("cache").disk_reads, 2);
+
+        cache.lock().expect("cache").invalidate();
+        load_llm_config_cached(&cache, &path).expect("read after invalidation");
+        assert_eq!(cache.lock().expect("cache").disk_reads, 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn migrates_custom_endpoint_settings_to_openai() {
+        let config = LlmConfig {
+            provider: Some("custom".to_string()),
+            custom: Some(CustomProviderSettings {
+                api_key: Some("sk-custom".to_string()),
+                base_url: Some("https://llm.internal.example/v1".to_string()),
+                model: Some("gpt-4.1".to_string()),
+                api_format: Some("openai".to_string()),
+                allow_insecure_tls: Some(true),
+            }),
+            ..Default::default()
+        };
+
+        let migrated = migrate_provider_settings(config, "custom", "openai").expect("migrate");
+        let openai = migrated.openai.expect("openai settings");
+        assert_eq!(migrated.provider.as_deref(), Some("openai"));
+        assert_eq!(openai.api_key.as_deref(), Some("sk-custom"));
+        assert_eq!(
+            openai.base_url.as_deref(),
+            Some("https://llm.internal.example/v1")
+        );
+        assert_eq!(openai.model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(openai.allow_insecure_tls, Some(true));
+        // The source block is left intact so the user can switch back.
+        assert!(migrated.custom.is_some());
+    }
+
+    #[test]
+    fn migrates_anthropic_model_to_openrouter_namespace_without_api_key() {
+        let config = LlmConfig {
+            provider: Some("anthropic".to_string()),
+            anthropic: Some(AnthropicProviderSettings {
+                api_key: Some("sk-ant-test".to_string()),
+                model: Some("claude-sonnet-4-5".to_string()),
+            }),
+            openrouter: Some(OpenRouterProviderSettings {
+                api_key: Some("sk-or-existing".to_string()),
+                model: None,
+            }),
+            ..Default::default()
+        };
+
+        let migrated =
+            migrate_provider_settings(config, "anthropic", "openrouter").expect("migrate");
+        let openrouter = migrated.openrouter.expect("openrouter settings");
+        assert_eq!(migrated.provider.as_deref(), Some("openrouter"));
+        assert_eq!(
+            openrouter.model.as_deref(),
+            Some("anthropic/claude-sonnet-4-5")
+        );
+        assert_eq!(openrouter.api_key.as_deref(), Some("sk-or-existing"));
+
+        assert!(migrate_provider_settings(LlmConfig::default(), "anthropic", "nope").is_err());
+    }
 
     #[test]
     fn prefers_openai_realtime_transcription_models_over_whisper() {
@@ -1748,6 +2376,78 @@ mod tests {
         assert_eq!(serialized.get("executionPath"), Some(&json!("direct")));
     }
 
+    #[tokio::test]
+    async fn read_command_is_retried_once_after_sidecar_crash() {
+        let attempts = std::cell::Cell::new(0);
+        let response = retry_read_once(
+            || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    if attempt == 1 {
+                        Err("sidecar transport failed".to_string())
+                    } else {
+                        Ok(json!({ "payload": { "workspaces": [] } }))
+                    }
+                }
+            },
+            || async { Ok(true) },
+        )
+        .await
+        .expect("read succeeds on retry");
+        assert_eq!(attempts.get(), 2);
+        assert_eq!(response["payload"]["workspaces"], json!([]));
+
+        // A sidecar that is still alive means the failure was not a crash.
+        attempts.set(0);
+        let error = retry_read_once(
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err("response timeout: timed out".to_string()) }
+            },
+            || async { Ok(false) },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(attempts.get(), 1);
+        assert!(error.starts_with("response timeout:"));
+    }
+
+    #[test]
+    fn resending_an_in_flight_idempotency_key_is_a_duplicate() {
+        let key = Uuid::new_v4().to_string();
+        let first = InFlightKey::claim(&key).expect("first send");
+        let error = InFlightKey::claim(&key).err().expect("duplicate refused");
+        assert!(error.starts_with("duplicate:"));
+        drop(first);
+        let _retry = InFlightKey::claim(&key).expect("free again once answered");
+
+        let command = with_idempotency_key(build_command("create_workspace", json!({})), &key);
+        let retried = with_fresh_command_id(command.clone());
+        assert_ne!(retried["id"], command["id"]);
+        assert_eq!(retried["idempotencyKey"], json!(key));
+    }
+
+    #[test]
+    fn task_config_falls_back_to_configured_max_history_messages() {
+        let unset: StartTaskConfigInput =
+            serde_json::from_value(json!({ "modelId": "openai/gpt-5.3-codex" }))
+                .expect("deserialize StartTaskConfigInput");
+        let resolved = task_config_from_input(Some(unset), Some(40)).expect("task config");
+        assert_eq!(resolved.max_history_messages, Some(40));
+        assert_eq!(resolved.model_id.as_deref(), Some("openai/gpt-5.3-codex"));
+
+        let explicit: StartTaskConfigInput =
+            serde_json::from_value(json!({ "maxHistoryMessages": 8 }))
+                .expect("deserialize StartTaskConfigInput");
+        let resolved = task_config_from_input(Some(explicit), Some(40)).expect("task config");
+        assert_eq!(resolved.max_history_messages, Some(8));
+
+        let defaulted = task_config_from_input(None, Some(40)).expect("task config");
+        assert_eq!(defaulted.max_history_messages, Some(40));
+        assert!(task_config_from_input(None, None).is_none());
+    }
+
     #[test]
     fn build_doctor_preflight_payload_maps_all_fields() {
         let payload = build_doctor_preflight_payload(Some(&DoctorPreflightInput {
@@ -2022,18 +2722,10 @@ pub async fn send_task_message(
         summarize_attachment_content_for_log(&input.content)
     );
 
-    ensure_sidecar_running(&state, &app_handle).await?;
+    let route = state.route_task(&input.task_id);
+    ensure_route_running(&route, &app_handle).await?;
 
-    let config = input.config.map(|cfg| TaskConfig {
-        model_id: cfg.model_id,
-        execution_path: cfg.execution_path,
-        max_tokens: cfg.max_tokens,
-        max_history_messages: cfg.max_history_messages,
-        enabled_claude_skills: cfg.enabled_claude_skills,
-        enabled_toolpacks: cfg.enabled_toolpacks,
-        enabled_skills: cfg.enabled_skills,
-        voice_provider_mode: cfg.voice_provider_mode,
-    });
+    let config = task_config_from_input(input.config, configured_max_history_messages(&app_handle));
 
     let task_id = input.task_id.clone();
     let command = serde_json::to_value(IpcCommand::send_task_message(
@@ -2044,9 +2736,7 @@ pub async fn send_task_message(
     ))
     .map_err(|e| e.to_string())?;
 
-    let response = match send_command_and_wait_with_timeout_policy(&state, command, 30000, false)
-        .await
-    {
+    let response = match send_to_manager_and_wait(&route.manager, command, 30000, false).await {
         Ok(value) => value,
         Err(error_message) => {
             if error_message.starts_with("response timeout:") {
@@ -2136,22 +2826,15 @@ pub async fn resume_interrupted_task(
         input.task_id
     );
 
-    ensure_sidecar_running(&state, &app_handle).await?;
+    let route = state.route_task(&input.task_id);
+    ensure_route_running(&route, &app_handle).await?;
 
-    let config = input.config.map(|cfg| TaskConfig {
-        model_id: cfg.model_id,
-        execution_path: cfg.execution_path,
-        max_tokens: cfg.max_tokens,
-        max_history_messages: cfg.max_history_messages,
-        enabled_claude_skills: cfg.enabled_claude_skills,
-        enabled_toolpacks: cfg.enabled_toolpacks,
-        enabled_skills: cfg.enabled_skills,
-        voice_provider_mode: cfg.voice_provider_mode,
-    });
+    let config = task_config_from_input(input.config, configured_max_history_messages(&app_handle));
 
     let command = IpcCommand::resume_interrupted_task(input.task_id.clone(), config);
     let command_value = serde_json::to_value(command).map_err(|e| e.to_string())?;
-    let response = match send_command_and_wait(&state, command_value, 10000).await {
+    let response = match send_to_manager_and_wait(&route.manager, command_value, 10000, true).await
+    {
         Ok(value) => value,
         Err(error) => {
             return Ok(ResumeInterruptedTaskResult {
@@ -2187,25 +2870,209 @@ pub async fn resume_interrupted_task(
 pub async fn get_sidecar_status(
     state: State<'_, SidecarState>,
 ) -> Result<SidecarStatusResult, String> {
-    let mut manager = state.0.lock().map_err(|e| e.to_string())?;
-
+    let workspace_sidecars = state
+        .workspaces
+        .lock()
+        .map_err(|e| e.to_string())?
+        .workspaces();
+    let mut manager = state.manager.lock().map_err(|e| e.to_string())?;
+
+    let running = manager.is_running();
     Ok(SidecarStatusResult {
-        running: manager.is_running(),
+        running,
+        launched_runtime: manager.launched_runtime().cloned(),
+        protocol_handshake: manager.protocol_handshake().cloned(),
+        workspace_sidecars,
+        sidecar_missing: manager.missing_entry().map(|searched| {
+            searched
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        }),
+    })
+}
+
+/// Counts and recent samples of sidecar stdout lines that could not be
+/// parsed or classified, across the default and workspace sidecars.
+#[tauri::command]
+pub async fn get_protocol_diagnostics(
+    state: State<'_, SidecarState>,
+) -> Result<ProtocolDiagnostics, String> {
+    let workspace_managers = state
+        .workspaces
+        .lock()
+        .map_err(|e| e.to_string())?
+        .managers();
+    let mut diagnostics = state
+        .manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .protocol_diagnostics();
+    for manager in workspace_managers {
+        let manager = manager.lock().map_err(|e| e.to_string())?;
+        diagnostics.merge(manager.protocol_diagnostics());
+    }
+    Ok(diagnostics)
+}
+
+/// Latest sidecar stderr lines, oldest first, across the default and
+/// workspace sidecars, for a log panel opened after they were emitted.
+#[tauri::command]
+pub async fn get_sidecar_stderr(
+    limit: Option<usize>,
+    state: State<'_, SidecarState>,
+) -> Result<Vec<SidecarStderrLine>, String> {
+    let limit = limit.unwrap_or(STDERR_BACKLOG_LIMIT);
+    let workspace_managers = state
+        .workspaces
+        .lock()
+        .map_err(|e| e.to_string())?
+        .managers();
+    let mut lines = state
+        .manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .recent_stderr(limit);
+    for manager in workspace_managers {
+        let manager = manager.lock().map_err(|e| e.to_string())?;
+        lines.extend(manager.recent_stderr(limit));
+    }
+    // RFC 3339 timestamps in UTC sort chronologically as strings.
+    lines.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    let skip = lines.len().saturating_sub(limit);
+    Ok(lines.split_off(skip))
+}
+
+/// Stop a workspace's dedicated sidecar. Returns false when the workspace
+/// has none (it runs on the default sidecar).
+#[tauri::command]
+pub async fn shutdown_workspace_sidecar(
+    workspace_path: String,
+    state: State<'_, SidecarState>,
+) -> Result<bool, String> {
+    info!(
+        "shutdown_workspace_sidecar command received: {}",
+        workspace_path
+    );
+    let mut workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+    Ok(workspaces.shutdown_workspace(&workspace_path))
+}
+
+/// Single-entry cache of the parsed `llm-config.json`. An entry is only
+/// served while the file's mtime and length still match what was read.
+#[derive(Default)]
+struct LlmConfigCache {
+    entry: Option<CachedLlmConfig>,
+    disk_reads: usize,
+}
+
+struct CachedLlmConfig {
+    path: PathBuf,
+    modified: SystemTime,
+    len: u64,
+    config: LlmConfig,
+}
+
+impl LlmConfigCache {
+    fn get(&self, path: &std::path::Path, metadata: &fs::Metadata) -> Option<LlmConfig> {
+        let entry = self.entry.as_ref()?;
+        let unchanged = entry.path == path
+            && metadata.modified().ok() == Some(entry.modified)
+            && metadata.len() == entry.len;
+        unchanged.then(|| entry.config.clone())
+    }
+
+    fn invalidate(&mut self) {
+        self.entry = None;
+    }
+}
+
+fn llm_config_cache() -> &'static Mutex<LlmConfigCache> {
+    static CACHE: OnceLock<Mutex<LlmConfigCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn invalidate_llm_config_cache() {
+    if let Ok(mut cache) = llm_config_cache().lock() {
+        cache.invalidate();
+    }
+}
+
+/// The global `maxHistoryMessages` from `llm-config.json`, if set.
+fn configured_max_history_messages(app_handle: &AppHandle) -> Option<u32> {
+    let path = llm_config_path(app_handle).ok()?;
+    if !path.exists() {
+        return None;
+    }
+    load_llm_config_cached(llm_config_cache(), &path)
+        .ok()?
+        .max_history_messages
+}
+
+/// Map per-call task config to the sidecar's, falling back to the configured
+/// history limit when the call leaves it unset.
+fn task_config_from_input(
+    input: Option<StartTaskConfigInput>,
+    configured_max_history_messages: Option<u32>,
+) -> Option<TaskConfig> {
+    let Some(cfg) = input else {
+        return configured_max_history_messages.map(|limit| TaskConfig {
+            model_id: None,
+            execution_path: None,
+            max_tokens: None,
+            max_history_messages: Some(limit),
+            enabled_claude_skills: None,
+            enabled_toolpacks: None,
+            enabled_skills: None,
+            voice_provider_mode: None,
+        });
+    };
+    Some(TaskConfig {
+        model_id: cfg.model_id,
+        execution_path: cfg.execution_path,
+        max_tokens: cfg.max_tokens,
+        max_history_messages: cfg.max_history_messages.or(configured_max_history_messages),
+        enabled_claude_skills: cfg.enabled_claude_skills,
+        enabled_toolpacks: cfg.enabled_toolpacks,
+        enabled_skills: cfg.enabled_skills,
+        voice_provider_mode: cfg.voice_provider_mode,
     })
 }
 
+/// Parse `path`, reusing the cached config when the file has not changed.
+fn load_llm_config_cached(
+    cache: &Mutex<LlmConfigCache>,
+    path: &std::path::Path,
+) -> Result<LlmConfig, String> {
+    let mut cache = cache.lock().map_err(|e| e.to_string())?;
+    let metadata = fs::metadata(path).map_err(|e| format!("failed to stat file: {}", e))?;
+    if let Some(config) = cache.get(path, &metadata) {
+        debug!("get_llm_settings: served from cache");
+        return Ok(config);
+    }
+
+    let raw = fs::read_to_string(path).map_err(|e| format!("failed to read file: {}", e))?;
+    cache.disk_reads += 1;
+    info!("get_llm_settings: read {} bytes", raw.len());
+    let config: LlmConfig =
+        serde_json::from_str(&raw).map_err(|e| format!("failed to parse JSON: {}", e))?;
+
+    cache.entry = metadata.modified().ok().map(|modified| CachedLlmConfig {
+        path: path.to_path_buf(),
+        modified,
+        len: metadata.len(),
+        config: config.clone(),
+    });
+    Ok(config)
+}
+
 /// Get LLM config from the shared app data directory.
 #[tauri::command]
 pub async fn get_llm_settings(app_handle: AppHandle) -> Result<LlmConfigResult, String> {
     let path = llm_config_path(&app_handle)?;
     info!("get_llm_settings: reading from {:?}", path);
 
-    let raw = if path.exists() {
-        tokio::fs::read_to_string(&path).await.map_err(|e| {
-            error!("get_llm_settings: failed to read file: {}", e);
-            e.to_string()
-        })?
-    } else {
+    if !path.exists() {
         let store_path = settings_store_path(&app_handle)?;
         if store_path.exists() {
             let store_raw = tokio::fs::read_to_string(&store_path).await.map_err(|e| {
@@ -2225,7 +3092,6 @@ pub async fn get_llm_settings(app_handle: AppHandle) -> Result<LlmConfigResult,
                         .await
                         .map_err(|e| e.to_string())?;
                     info!("get_llm_settings: migrated llmConfig from settings.json");
-                    migrated
                 } else {
                     let legacy_path = legacy_llm_config_path()?;
                     if legacy_path.exists() {
@@ -2241,13 +3107,13 @@ pub async fn get_llm_settings(app_handle: AppHandle) -> Result<LlmConfigResult,
                             .await
                             .map_err(|e| e.to_string())?;
                         info!("get_llm_settings: migrated legacy llm-config.json");
-                        legacy_raw
                     } else {
                         info!("get_llm_settings: no config file found, returning default");
                         return Ok(LlmConfigResult {
                             success: true,
                             payload: LlmConfig::default(),
                             error: None,
+                            field_errors: Vec::new(),
                         });
                     }
                 }
@@ -2257,6 +3123,7 @@ pub async fn get_llm_settings(app_handle: AppHandle) -> Result<LlmConfigResult,
                     success: true,
                     payload: LlmConfig::default(),
                     error: None,
+                    field_errors: Vec::new(),
                 });
             }
         } else {
@@ -2274,23 +3141,21 @@ pub async fn get_llm_settings(app_handle: AppHandle) -> Result<LlmConfigResult,
                     .await
                     .map_err(|e| e.to_string())?;
                 info!("get_llm_settings: migrated legacy llm-config.json");
-                legacy_raw
             } else {
                 info!("get_llm_settings: no config file found, returning default");
                 return Ok(LlmConfigResult {
                     success: true,
                     payload: LlmConfig::default(),
                     error: None,
+                    field_errors: Vec::new(),
                 });
             }
         }
-    };
-
-    info!("get_llm_settings: read {} bytes", raw.len());
+    }
 
-    let config: LlmConfig = serde_json::from_str(&raw).map_err(|e| {
-        error!("get_llm_settings: failed to parse JSON: {}", e);
-        e.to_string()
+    let config = load_llm_config_cached(llm_config_cache(), &path).map_err(|e| {
+        error!("get_llm_settings: {}", e);
+        e
     })?;
 
     info!(
@@ -2301,10 +3166,182 @@ pub async fn get_llm_settings(app_handle: AppHandle) -> Result<LlmConfigResult,
         success: true,
         payload: config,
         error: None,
+        field_errors: Vec::new(),
     })
 }
 
+/// Borrowed view over the provider fields shared by `LlmConfig` and
+/// `LlmProfile`, so both are validated by the same rules.
+struct ProviderSettingsView<'a> {
+    provider: &'a str,
+    anthropic: Option<&'a AnthropicProviderSettings>,
+    openrouter: Option<&'a OpenRouterProviderSettings>,
+    openai: Option<&'a OpenAIProviderSettings>,
+    ollama: Option<&'a OllamaProviderSettings>,
+    custom: Option<&'a CustomProviderSettings>,
+}
+
+impl<'a> ProviderSettingsView<'a> {
+    fn from_config(config: &'a LlmConfig) -> Self {
+        Self {
+            provider: config.provider.as_deref().unwrap_or("").trim(),
+            anthropic: config.anthropic.as_ref(),
+            openrouter: config.openrouter.as_ref(),
+            openai: config.openai.as_ref(),
+            ollama: config.ollama.as_ref(),
+            custom: config.custom.as_ref(),
+        }
+    }
+
+    fn from_profile(profile: &'a LlmProfile) -> Self {
+        Self {
+            provider: profile.provider.trim(),
+            anthropic: profile.anthropic.as_ref(),
+            openrouter: profile.openrouter.as_ref(),
+            openai: profile.openai.as_ref(),
+            ollama: profile.ollama.as_ref(),
+            custom: profile.custom.as_ref(),
+        }
+    }
+}
+
+fn is_blank(value: Option<&String>) -> bool {
+    value.map(|v| v.trim().is_empty()).unwrap_or(true)
+}
+
+fn push_field_error(errors: &mut Vec<LlmConfigFieldError>, field: String, message: &str) {
+    errors.push(LlmConfigFieldError {
+        field,
+        message: message.to_string(),
+    });
+}
+
+/// Require the fields the selected provider needs at task time (the same
+/// ones `build_validation_request_plan` insists on).
+fn validate_selected_provider(
+    prefix: &str,
+    settings: &ProviderSettingsView<'_>,
+    errors: &mut Vec<LlmConfigFieldError>,
+) {
+    let field = |name: &str| format!("{prefix}{name}");
+    match settings.provider {
+        "" => push_field_error(errors, field("provider"), "Provider is required"),
+        "anthropic" => {
+            if is_blank(settings.anthropic.and_then(|s| s.api_key.as_ref())) {
+                push_field_error(errors, field("anthropic.apiKey"), "Missing API key");
+            }
+        }
+        "openrouter" => {
+            if is_blank(settings.openrouter.and_then(|s| s.api_key.as_ref())) {
+                push_field_error(errors, field("openrouter.apiKey"), "Missing API key");
+            }
+        }
+        "ollama" => {}
+        "custom" => {
+            let custom = settings.custom;
+            if is_blank(custom.and_then(|s| s.api_key.as_ref())) {
+                push_field_error(errors, field("custom.apiKey"), "Missing API key");
+            }
+            if is_blank(custom.and_then(|s| s.base_url.as_ref())) {
+                push_field_error(errors, field("custom.baseUrl"), "Missing Base URL");
+            }
+            if is_blank(custom.and_then(|s| s.model.as_ref())) {
+                push_field_error(errors, field("custom.model"), "Missing Model ID");
+            }
+        }
+        provider if openai_compatible_default(provider).is_some() => {
+            if is_blank(settings.openai.and_then(|s| s.api_key.as_ref())) {
+                push_field_error(errors, field("openai.apiKey"), "Missing API key");
+            }
+        }
+        provider => push_field_error(
+            errors,
+            field("provider"),
+            &format!("Unknown provider: {}", provider),
+        ),
+    }
+}
+
+/// Every base URL that is filled in must parse as http(s), whether or not
+/// its provider is the selected one.
+fn validate_base_urls(
+    prefix: &str,
+    settings: &ProviderSettingsView<'_>,
+    errors: &mut Vec<LlmConfigFieldError>,
+) {
+    let base_urls = [
+        ("openai", settings.openai.and_then(|s| s.base_url.as_ref())),
+        ("ollama", settings.ollama.and_then(|s| s.base_url.as_ref())),
+        ("custom", settings.custom.and_then(|s| s.base_url.as_ref())),
+    ];
+    for (section, base_url) in base_urls {
+        let Some(raw) = base_url.map(|v| v.trim()).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let valid = url::Url::parse(raw)
+            .map(|parsed| matches!(parsed.scheme(), "http" | "https"))
+            .unwrap_or(false);
+        if !valid {
+            push_field_error(
+                errors,
+                format!("{prefix}{section}.baseUrl"),
+                "Base URL must be a valid http(s) URL",
+            );
+        }
+    }
+}
+
+/// Check a config before it is written. An empty result means it is safe
+/// to save.
+fn validate_llm_config(config: &LlmConfig) -> Vec<LlmConfigFieldError> {
+    let mut errors = Vec::new();
+    let profiles = config.profiles.as_deref().unwrap_or(&[]);
+
+    let active_profile_id = config
+        .active_profile_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty());
+    match active_profile_id {
+        Some(id) => match profiles.iter().position(|profile| profile.id == id) {
+            Some(index) => validate_selected_provider(
+                &format!("profiles[{index}]."),
+                &ProviderSettingsView::from_profile(&profiles[index]),
+                &mut errors,
+            ),
+            None => push_field_error(
+                &mut errors,
+                "activeProfileId".to_string(),
+                &format!("No profile with id '{}'", id),
+            ),
+        },
+        None => {
+            if config.provider.is_some() {
+                validate_selected_provider(
+                    "",
+                    &ProviderSettingsView::from_config(config),
+                    &mut errors,
+                );
+            }
+        }
+    }
+
+    validate_base_urls("", &ProviderSettingsView::from_config(config), &mut errors);
+    for (index, profile) in profiles.iter().enumerate() {
+        validate_base_urls(
+            &format!("profiles[{index}]."),
+            &ProviderSettingsView::from_profile(profile),
+            &mut errors,
+        );
+    }
+
+    errors
+}
+
 /// Save LLM config to the shared app data directory.
+///
+/// The config is validated first; when it has field errors nothing is
+/// written and the errors are returned with `success: false`.
 #[tauri::command]
 pub async fn save_llm_settings(
     mut input: LlmConfig,
@@ -2312,6 +3349,24 @@ pub async fn save_llm_settings(
 ) -> Result<LlmConfigResult, String> {
     let path = llm_config_path(&app)?;
     info!("save_llm_settings: saving to {:?}", path);
+
+    let field_errors = validate_llm_config(&input);
+    if !field_errors.is_empty() {
+        warn!(
+            "save_llm_settings: rejected invalid config ({} field error(s))",
+            field_errors.len()
+        );
+        return Ok(LlmConfigResult {
+            success: false,
+            payload: input,
+            error: Some(format!(
+                "Invalid LLM config: {} field error(s)",
+                field_errors.len()
+            )),
+            field_errors,
+        });
+    }
+
     // Preserve the $schema field
     if input.schema.is_none() {
         input.schema = Some("./llm-config.schema.json".to_string());
@@ -2327,6 +3382,7 @@ pub async fn save_llm_settings(
         error!("save_llm_settings: failed to write file: {}", e);
         e.to_string()
     })?;
+    invalidate_llm_config_cache();
 
     info!(
         "save_llm_settings: saved config, provider={:?}",
@@ -2341,9 +3397,202 @@ pub async fn save_llm_settings(
         success: true,
         payload: input,
         error: None,
+        field_errors: Vec::new(),
     })
 }
 
+/// Which settings block of `LlmConfig` a provider id reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProviderSection {
+    Anthropic,
+    OpenRouter,
+    OpenAI,
+    Ollama,
+    Custom,
+}
+
+impl ProviderSection {
+    fn for_provider(provider: &str) -> Option<Self> {
+        match provider {
+            "anthropic" => Some(Self::Anthropic),
+            "openrouter" => Some(Self::OpenRouter),
+            "ollama" => Some(Self::Ollama),
+            "custom" => Some(Self::Custom),
+            other if openai_compatible_default(other).is_some() => Some(Self::OpenAI),
+            _ => None,
+        }
+    }
+
+    /// Sections that talk to an OpenAI-style endpoint of the user's choosing,
+    /// where an API key legitimately means the same thing on both sides.
+    fn is_openai_style(self) -> bool {
+        matches!(self, Self::OpenAI | Self::Custom)
+    }
+}
+
+/// Provider fields that can be carried from one settings block to another.
+#[derive(Debug, Clone, Default)]
+struct PortableProviderFields {
+    api_key: Option<String>,
+    base_url: Option<String>,
+    model: Option<String>,
+    allow_insecure_tls: Option<bool>,
+}
+
+fn read_provider_section(config: &LlmConfig, section: ProviderSection) -> PortableProviderFields {
+    match section {
+        ProviderSection::Anthropic => config.anthropic.as_ref().map(|s| PortableProviderFields {
+            api_key: s.api_key.clone(),
+            model: s.model.clone(),
+            ..Default::default()
+        }),
+        ProviderSection::OpenRouter => config.openrouter.as_ref().map(|s| PortableProviderFields {
+            api_key: s.api_key.clone(),
+            model: s.model.clone(),
+            ..Default::default()
+        }),
+        ProviderSection::OpenAI => config.openai.as_ref().map(|s| PortableProviderFields {
+            api_key: s.api_key.clone(),
+            base_url: s.base_url.clone(),
+            model: s.model.clone(),
+            allow_insecure_tls: s.allow_insecure_tls,
+        }),
+        ProviderSection::Ollama => config.ollama.as_ref().map(|s| PortableProviderFields {
+            base_url: s.base_url.clone(),
+            model: s.model.clone(),
+            ..Default::default()
+        }),
+        ProviderSection::Custom => config.custom.as_ref().map(|s| PortableProviderFields {
+            api_key: s.api_key.clone(),
+            base_url: s.base_url.clone(),
+            model: s.model.clone(),
+            allow_insecure_tls: s.allow_insecure_tls,
+        }),
+    }
+    .unwrap_or_default()
+}
+
+/// Fill the target block from `fields`, never overwriting a value the user
+/// already set there.
+fn write_provider_section(
+    config: &mut LlmConfig,
+    section: ProviderSection,
+    fields: PortableProviderFields,
+) {
+    fn fill<T>(slot: &mut Option<T>, value: Option<T>) {
+        if slot.is_none() {
+            *slot = value;
+        }
+    }
+
+    match section {
+        ProviderSection::Anthropic => {
+            let target = config.anthropic.get_or_insert_with(Default::default);
+            fill(&mut target.api_key, fields.api_key);
+            fill(&mut target.model, fields.model);
+        }
+        ProviderSection::OpenRouter => {
+            let target = config.openrouter.get_or_insert_with(Default::default);
+            fill(&mut target.api_key, fields.api_key);
+            fill(&mut target.model, fields.model);
+        }
+        ProviderSection::OpenAI => {
+            let target = config.openai.get_or_insert_with(Default::default);
+            fill(&mut target.api_key, fields.api_key);
+            fill(&mut target.base_url, fields.base_url);
+            fill(&mut target.model, fields.model);
+            fill(&mut target.allow_insecure_tls, fields.allow_insecure_tls);
+        }
+        ProviderSection::Ollama => {
+            let target = config.ollama.get_or_insert_with(Default::default);
+            fill(&mut target.base_url, fields.base_url);
+            fill(&mut target.model, fields.model);
+        }
+        ProviderSection::Custom => {
+            let target = config.custom.get_or_insert_with(Default::default);
+            fill(&mut target.api_key, fields.api_key);
+            fill(&mut target.base_url, fields.base_url);
+            fill(&mut target.model, fields.model);
+            fill(&mut target.allow_insecure_tls, fields.allow_insecure_tls);
+        }
+    }
+}
+
+/// OpenRouter namespaces model ids by vendor (`anthropic/claude-...`).
+fn map_model_between_sections(
+    model: Option<String>,
+    from: ProviderSection,
+    to: ProviderSection,
+) -> Option<String> {
+    let model = model?;
+    let mapped = match (from, to) {
+        (ProviderSection::Anthropic, ProviderSection::OpenRouter) => {
+            if model.contains('/') {
+                model
+            } else {
+                format!("anthropic/{}", model)
+            }
+        }
+        (ProviderSection::OpenRouter, ProviderSection::Anthropic) => {
+            let Some(stripped) = model.strip_prefix("anthropic/") else {
+                // A non-Anthropic OpenRouter model has no Anthropic equivalent.
+                return None;
+            };
+            stripped.to_string()
+        }
+        _ => model,
+    };
+    Some(mapped)
+}
+
+/// Switch `config.provider` from `from` to `to`, carrying over the fields the
+/// two providers have in common. Nothing is saved.
+fn migrate_provider_settings(
+    mut config: LlmConfig,
+    from: &str,
+    to: &str,
+) -> Result<LlmConfig, String> {
+    let from_section =
+        ProviderSection::for_provider(from).ok_or_else(|| format!("Unknown provider: {}", from))?;
+    let to_section =
+        ProviderSection::for_provider(to).ok_or_else(|| format!("Unknown provider: {}", to))?;
+
+    if from_section != to_section {
+        let source = read_provider_section(&config, from_section);
+        let carry_base_url =
+            to_section != ProviderSection::Anthropic && to_section != ProviderSection::OpenRouter;
+        let carried = PortableProviderFields {
+            api_key: if from_section.is_openai_style() && to_section.is_openai_style() {
+                source.api_key
+            } else {
+                None
+            },
+            base_url: if carry_base_url {
+                source.base_url
+            } else {
+                None
+            },
+            model: map_model_between_sections(source.model, from_section, to_section),
+            allow_insecure_tls: source.allow_insecure_tls,
+        };
+        write_provider_section(&mut config, to_section, carried);
+    }
+
+    config.provider = Some(to.to_string());
+    Ok(config)
+}
+
+/// Preview switching the top-level provider. Returns the adjusted config for
+/// the user to confirm; call `save_llm_settings` to persist it.
+#[tauri::command]
+pub fn migrate_llm_provider(
+    config: LlmConfig,
+    from: String,
+    to: String,
+) -> Result<LlmConfig, String> {
+    migrate_provider_settings(config, from.trim(), to.trim())
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ValidateLlmInput {
@@ -2478,12 +3727,76 @@ fn build_validation_request_plan(input: ValidateLlmInput) -> Result<ValidationRe
     }
 }
 
-/// Validate LLM connectivity
+/// How long a connectivity probe may take when the caller sets no timeout
+const DEFAULT_VALIDATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a connectivity probe ended without a reply from the provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValidationInterrupted {
+    TimedOut,
+    Cancelled,
+}
+
+/// Cancel senders of in-flight validations, by `validation_id`
+fn validation_cancellers() -> &'static Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>> {
+    static CANCELLERS: OnceLock<Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>> =
+        OnceLock::new();
+    CANCELLERS.get_or_init(Default::default)
+}
+
+/// Run `probe` until it finishes, `timeout` passes, or, when it has an id,
+/// `cancel_validation` is called for it.
+async fn run_cancelable_validation<F: std::future::Future>(
+    validation_id: Option<&str>,
+    timeout: Duration,
+    probe: F,
+) -> Result<F::Output, ValidationInterrupted> {
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    if let Some(id) = validation_id {
+        if let Ok(mut cancellers) = validation_cancellers().lock() {
+            cancellers.insert(id.to_string(), cancel_tx);
+        }
+    }
+
+    let outcome = tokio::select! {
+        output = tokio::time::timeout(timeout, probe) => {
+            output.map_err(|_| ValidationInterrupted::TimedOut)
+        }
+        Ok(()) = cancel_rx => Err(ValidationInterrupted::Cancelled),
+    };
+
+    if let Some(id) = validation_id {
+        if let Ok(mut cancellers) = validation_cancellers().lock() {
+            cancellers.remove(id);
+        }
+    }
+    outcome
+}
+
+/// Abort the in-flight `validate_llm_settings` started with `validation_id`.
+/// Returns whether one was running.
+#[tauri::command]
+pub fn cancel_validation(validation_id: String) -> bool {
+    let canceller = validation_cancellers()
+        .lock()
+        .ok()
+        .and_then(|mut cancellers| cancellers.remove(&validation_id));
+    match canceller {
+        Some(canceller) => canceller.send(()).is_ok(),
+        None => false,
+    }
+}
+
+/// Validate LLM connectivity. The probe gives up after `timeout_ms`
+/// (10 seconds by default) and can be aborted with `cancel_validation` when
+/// started with a `validation_id`.
 #[tauri::command]
 pub async fn validate_llm_settings(
     input: ValidateLlmInput,
     app_handle: AppHandle,
-) -> Result<GenericIpcResult, String> {
+    validation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<ValidationResult, String> {
     info!(
         "validate_llm_settings: validating connectivity for {}",
         input.provider
@@ -2499,10 +3812,21 @@ pub async fn validate_llm_settings(
     };
     let proxy_settings = input.proxy.as_ref().or(configured_proxy.as_ref());
 
-    let mut client_builder = reqwest::Client::builder().timeout(std::time::Duration::from_secs(10));
-    client_builder = apply_proxy_to_client_builder(client_builder, proxy_settings)?;
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_VALIDATION_TIMEOUT);
+    let client_builder = apply_proxy_to_client_builder(reqwest::Client::builder(), proxy_settings)?;
     let client = client_builder.build().map_err(|e| e.to_string())?;
-    let plan = build_validation_request_plan(input)?;
+    let provider = input.provider.clone();
+    let plan = match build_validation_request_plan(input) {
+        Ok(plan) => plan,
+        Err(message) => {
+            return Ok(ValidationResult::invalid(
+                ValidationError::new("invalid_input", message),
+                json!({ "provider": provider }),
+            ))
+        }
+    };
 
     let mut request = client.post(&plan.url);
 
@@ -2517,33 +3841,311 @@ pub async fn validate_llm_settings(
             .header("content-type", "application/json");
     }
 
-    let res = request
-        .json(&plan.body)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+    let probe = async {
+        let res = request.json(&plan.body).send().await?;
+        let status = res.status();
+        let body = if status.is_success() {
+            String::new()
+        } else {
+            res.text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string())
+        };
+        Ok::<_, reqwest::Error>((status, body))
+    };
+
+    let (status, body) =
+        match run_cancelable_validation(validation_id.as_deref(), timeout, probe).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(e)) => {
+                return Ok(ValidationResult::invalid(
+                    ValidationError::new("request_failed", format!("Request failed: {}", e)),
+                    json!({ "provider": plan.provider }),
+                ))
+            }
+            Err(ValidationInterrupted::TimedOut) => {
+                return Ok(ValidationResult::invalid(
+                    ValidationError::new(
+                        "timeout",
+                        format!("No response within {}ms", timeout.as_millis()),
+                    ),
+                    json!({ "provider": plan.provider }),
+                ))
+            }
+            Err(ValidationInterrupted::Cancelled) => {
+                return Ok(ValidationResult::invalid(
+                    ValidationError::new("cancelled", "Validation was cancelled"),
+                    json!({ "provider": plan.provider }),
+                ))
+            }
+        };
 
-    let status = res.status();
     if status.is_success() {
         info!(
             "validate_llm_settings: connectivity verified for {}",
             plan.provider
         );
-        Ok(GenericIpcResult {
-            success: true,
-            payload: json!({ "message": "Connection successful" }),
-        })
     } else {
-        let error_text = res
-            .text()
-            .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        error!("Validation failed with status {}: {}", status, error_text);
-        Ok(GenericIpcResult {
-            success: false,
-            payload: json!({ "error": format!("Provider returned status {}: {}", status, error_text) }),
-        })
+        error!("Validation failed with status {}: {}", status, body);
+    }
+    Ok(llm_validation_result(&plan.provider, status, &body))
+}
+
+/// Map the provider's reply to the connectivity probe onto a `ValidationResult`.
+fn llm_validation_result(
+    provider: &str,
+    status: reqwest::StatusCode,
+    body: &str,
+) -> ValidationResult {
+    let details = json!({ "provider": provider, "status": status.as_u16() });
+    if status.is_success() {
+        let mut details = details;
+        details["message"] = json!("Connection successful");
+        return ValidationResult::valid(details);
+    }
+    ValidationResult::invalid(
+        ValidationError::new(
+            "provider_rejected",
+            format!("Provider returned status {}: {}", status, body),
+        ),
+        details,
+    )
+}
+
+// ============================================================================
+// Provider Model Lists
+// ============================================================================
+
+/// How long a live model list is reused before the provider is asked again
+const PROVIDER_MODELS_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Curated models the settings UI offers for `provider` without asking it
+fn default_provider_models(provider: &str) -> &'static [&'static str] {
+    match provider {
+        "anthropic" => &[
+            "claude-sonnet-4-5",
+            "claude-opus-4-1",
+            "claude-3-5-haiku-latest",
+        ],
+        "openrouter" => &[
+            "anthropic/claude-sonnet-4.5",
+            "openai/gpt-4o",
+            "google/gemini-2.0-flash-001",
+        ],
+        "openai" => &["gpt-4o", "gpt-4.1", "gpt-4o-mini"],
+        "aiberm" => &["gpt-5.3-codex"],
+        "nvidia" => &["meta/llama-3.1-70b-instruct"],
+        "siliconflow" => &["Qwen/Qwen2.5-7B-Instruct", "deepseek-ai/DeepSeek-V3"],
+        "gemini" => &["gemini-2.0-flash", "gemini-2.5-pro"],
+        "qwen" => &["qwen-plus", "qwen-max", "qwen-turbo"],
+        "minimax" => &["MiniMax-M2.7"],
+        "kimi" => &["moonshot-v1-8k", "moonshot-v1-32k"],
+        _ => &[],
+    }
+}
+
+/// Models the settings UI can offer for one provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderModels {
+    pub provider: String,
+    /// Curated defaults first, then any others the provider listed
+    pub models: Vec<String>,
+    /// Whether the provider's own list was merged in
+    pub live: bool,
+    /// Why the live list couldn't be fetched, when it was tried
+    pub error: Option<String>,
+}
+
+/// Where to list a provider's models, from the same settings validation uses
+#[derive(Debug, Clone)]
+struct ModelsRequestPlan {
+    url: String,
+    api_key: String,
+    auth_scheme: ValidationAuthScheme,
+}
+
+/// `None` when `settings` carry no API key for `provider`
+fn build_models_request_plan(
+    provider: &str,
+    settings: &ValidateLlmInput,
+) -> Option<ModelsRequestPlan> {
+    let api_key = |key: Option<&String>| {
+        key.map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+    };
+    match provider {
+        "anthropic" => Some(ModelsRequestPlan {
+            url: "https://api.anthropic.com/v1/models".to_string(),
+            api_key: api_key(settings.anthropic.as_ref()?.api_key.as_ref())?,
+            auth_scheme: ValidationAuthScheme::Anthropic,
+        }),
+        "openrouter" => Some(ModelsRequestPlan {
+            url: "https://openrouter.ai/api/v1/models".to_string(),
+            api_key: api_key(settings.openrouter.as_ref()?.api_key.as_ref())?,
+            auth_scheme: ValidationAuthScheme::Bearer,
+        }),
+        "custom" => {
+            let custom = settings.custom.as_ref()?;
+            let base_url = custom.base_url.as_deref()?.trim().trim_end_matches('/');
+            let base_url = base_url
+                .strip_suffix("/chat/completions")
+                .or_else(|| base_url.strip_suffix("/messages"))
+                .unwrap_or(base_url);
+            let anthropic = custom
+                .api_format
+                .as_deref()
+                .is_some_and(|format| format.eq_ignore_ascii_case("anthropic"));
+            Some(ModelsRequestPlan {
+                url: normalize_openai_compatible_url(base_url, "/models"),
+                api_key: api_key(custom.api_key.as_ref())?,
+                auth_scheme: if anthropic {
+                    ValidationAuthScheme::Anthropic
+                } else {
+                    ValidationAuthScheme::Bearer
+                },
+            })
+        }
+        other => {
+            let openai = settings.openai.as_ref()?;
+            let base_url = openai
+                .base_url
+                .as_deref()
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .or_else(|| openai_compatible_default(other))?;
+            Some(ModelsRequestPlan {
+                url: normalize_openai_compatible_url(base_url, "/models"),
+                api_key: api_key(openai.api_key.as_ref())?,
+                auth_scheme: ValidationAuthScheme::Bearer,
+            })
+        }
+    }
+}
+
+async fn fetch_provider_models(
+    client: &reqwest::Client,
+    plan: &ModelsRequestPlan,
+) -> Result<Vec<String>, String> {
+    let request = client.get(&plan.url).timeout(DEFAULT_VALIDATION_TIMEOUT);
+    let request = match plan.auth_scheme {
+        ValidationAuthScheme::Anthropic => request
+            .header("x-api-key", plan.api_key.clone())
+            .header("anthropic-version", "2023-06-01"),
+        ValidationAuthScheme::Bearer => request.bearer_auth(&plan.api_key),
+    };
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "model listing failed with status {}",
+            response.status()
+        ));
+    }
+    let payload = response.json::<Value>().await.map_err(|e| e.to_string())?;
+    Ok(model_ids_from_catalog(&payload))
+}
+
+/// Curated defaults followed by the live models they don't already name
+fn merge_provider_models(defaults: &[&str], live: Vec<String>) -> Vec<String> {
+    let mut models: Vec<String> = defaults.iter().map(|model| model.to_string()).collect();
+    for model in live {
+        if !models.contains(&model) {
+            models.push(model);
+        }
+    }
+    models
+}
+
+/// Live model lists by provider, endpoint and key, with when they were fetched
+type ProviderModelsCache = HashMap<String, (Instant, Vec<String>)>;
+
+fn provider_models_cache() -> &'static Mutex<ProviderModelsCache> {
+    static CACHE: OnceLock<Mutex<ProviderModelsCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn provider_models_cache_key(provider: &str, plan: &ModelsRequestPlan) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    plan.api_key.hash(&mut hasher);
+    format!("{provider}\n{}\n{:x}", plan.url, hasher.finish())
+}
+
+/// Defaults for `provider`, augmented with its live list when `plan` says
+/// how to fetch one. A failed fetch still returns the defaults.
+async fn resolve_provider_models(
+    client: &reqwest::Client,
+    provider: &str,
+    plan: Option<&ModelsRequestPlan>,
+) -> ProviderModels {
+    let defaults = default_provider_models(provider);
+    let mut result = ProviderModels {
+        provider: provider.to_string(),
+        models: merge_provider_models(defaults, Vec::new()),
+        live: false,
+        error: None,
+    };
+    let Some(plan) = plan else {
+        return result;
+    };
+
+    let key = provider_models_cache_key(provider, plan);
+    let cached = provider_models_cache()
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(&key).cloned())
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < PROVIDER_MODELS_CACHE_TTL)
+        .map(|(_, models)| models);
+    let live = match cached {
+        Some(models) => Ok(models),
+        None => fetch_provider_models(client, plan).await.inspect(|models| {
+            if let Ok(mut cache) = provider_models_cache().lock() {
+                cache.insert(key, (Instant::now(), models.clone()));
+            }
+        }),
+    };
+    match live {
+        Ok(models) => {
+            result.models = merge_provider_models(defaults, models);
+            result.live = true;
+        }
+        Err(error) => {
+            warn!("Listing models for {} failed: {}", provider, error);
+            result.error = Some(error);
+        }
     }
+    result
+}
+
+/// Models to offer for `provider` in settings: a curated list, plus what the
+/// provider itself lists when `settings` carry credentials for it. Live lists
+/// are cached for a few minutes.
+#[tauri::command]
+pub async fn get_provider_models(
+    app_handle: AppHandle,
+    provider: String,
+    settings: Option<ValidateLlmInput>,
+) -> Result<ProviderModels, String> {
+    let provider = provider.trim().to_string();
+    let plan = settings
+        .as_ref()
+        .and_then(|settings| build_models_request_plan(&provider, settings));
+    let configured_proxy = match llm_config_path(&app_handle) {
+        Ok(path) if path.exists() => tokio::fs::read_to_string(path)
+            .await
+            .ok()
+            .and_then(|raw| serde_json::from_str::<LlmConfig>(&raw).ok())
+            .and_then(|config| config.proxy),
+        _ => None,
+    };
+    let proxy_settings = settings
+        .as_ref()
+        .and_then(|settings| settings.proxy.as_ref())
+        .or(configured_proxy.as_ref());
+    let client = apply_proxy_to_client_builder(reqwest::Client::builder(), proxy_settings)?
+        .build()
+        .map_err(|e| e.to_string())?;
+    Ok(resolve_provider_models(&client, &provider, plan.as_ref()).await)
 }
 
 /// Get sessions snapshot from the shared app data directory.
@@ -2892,27 +4494,121 @@ pub async fn fetch_remote_text_preview(
 #[tauri::command]
 pub async fn spawn_sidecar(
     state: State<'_, SidecarState>,
+    watchdog: State<'_, WatchdogState>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
     info!("spawn_sidecar command received");
 
-    let mut manager = state.0.lock().map_err(|e| e.to_string())?;
+    let mut manager = state.manager.lock().map_err(|e| e.to_string())?;
     manager.spawn(app_handle).map_err(|e| e.to_string())?;
+    watchdog.resume();
 
     Ok(())
 }
 
-/// Shutdown the sidecar
+/// Shutdown the sidecar. The watchdog leaves it down until the next spawn.
 #[tauri::command]
-pub async fn shutdown_sidecar(state: State<'_, SidecarState>) -> Result<(), String> {
+pub async fn shutdown_sidecar(
+    state: State<'_, SidecarState>,
+    watchdog: State<'_, WatchdogState>,
+) -> Result<(), String> {
     info!("shutdown_sidecar command received");
 
-    let mut manager = state.0.lock().map_err(|e| e.to_string())?;
+    watchdog.suppress();
+    let mut manager = state.manager.lock().map_err(|e| e.to_string())?;
     manager.shutdown();
 
     Ok(())
 }
 
+/// Vault/vector-store paths the embedded RAG runtime is (or will be) using.
+#[tauri::command]
+pub async fn get_rag_paths(app_handle: AppHandle) -> Result<RagPaths, String> {
+    Ok(SidecarManager::rag_paths(&app_data_dir(&app_handle)?))
+}
+
+/// Persist RAG path overrides to `settings.json` and restart the sidecar so
+/// the embedded RAG runtime picks them up. Blank values clear an override.
+#[tauri::command]
+pub async fn set_rag_paths(
+    input: RagPathSettings,
+    state: State<'_, SidecarState>,
+    app_handle: AppHandle,
+) -> Result<RagPaths, String> {
+    let normalize = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let overrides = RagPathSettings {
+        vault_path: normalize(input.vault_path),
+        chroma_path: normalize(input.chroma_path),
+    };
+    for path in [&overrides.vault_path, &overrides.chroma_path]
+        .into_iter()
+        .flatten()
+    {
+        ensure_writable_dir(std::path::Path::new(path))?;
+    }
+
+    // Go through the store plugin: the webview holds `settings.json` open
+    // there, and a direct write would be lost the next time it saves.
+    let store = app_handle
+        .store(settings_store_path(&app_handle)?)
+        .map_err(|e| e.to_string())?;
+    store.set(
+        RAG_PATHS_SETTINGS_KEY,
+        serde_json::to_value(&overrides).map_err(|e| e.to_string())?,
+    );
+    store.save().map_err(|e| e.to_string())?;
+
+    let paths = SidecarManager::rag_paths(&app_data_dir(&app_handle)?);
+    info!(
+        "set_rag_paths: vault={:?} chroma={:?}",
+        paths.vault_path, paths.chroma_path
+    );
+
+    // The default and every workspace sidecar read these paths at spawn.
+    let mut managers = vec![state.manager.clone()];
+    managers.extend(
+        state
+            .workspaces
+            .lock()
+            .map_err(|e| e.to_string())?
+            .managers(),
+    );
+    tokio::task::spawn_blocking(move || restart_running_sidecars(&managers, &app_handle))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    Ok(paths)
+}
+
+/// Restart each running sidecar in `managers` so it picks up changed
+/// settings. Blocking: each manager stays locked through its own restart.
+fn restart_running_sidecars(
+    managers: &[Arc<Mutex<SidecarManager>>],
+    app_handle: &AppHandle,
+) -> Result<(), String> {
+    let mut failures = Vec::new();
+    for manager in managers {
+        let mut manager = manager.lock().map_err(|e| e.to_string())?;
+        if !manager.is_running() {
+            continue;
+        }
+        manager.shutdown();
+        if let Err(e) = manager.spawn(app_handle.clone()) {
+            warn!("Failed to restart sidecar with new settings: {}", e);
+            failures.push(e.to_string());
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
 // ============================================================================
 // Toolpack & Skill Management Commands
 // ============================================================================
@@ -2928,29 +4624,29 @@ pub async fn list_toolpacks(
         "includeDisabled": input.and_then(|v| v.include_disabled).unwrap_or(true)
     });
     let command = build_command("list_toolpacks", payload);
-    let response =
-        match send_command_and_wait_with_timeout_policy(&state, command, 3000, false).await {
-            Ok(response) => response,
-            Err(error) => {
-                if is_response_timeout_error(&error) {
-                    warn!(
-                        "list_toolpacks timed out without invalidating sidecar transport: {}",
-                        error
-                    );
-                    json!({
-                        "type": "list_toolpacks_response",
-                        "payload": {
-                            "success": true,
-                            "toolpacks": [],
-                            "degraded": true,
-                            "error": error,
-                        }
-                    })
-                } else {
-                    return Err(error);
-                }
+    let response = match send_read_command_and_wait(&state, &app_handle, command, 3000, false).await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            if is_response_timeout_error(&error) {
+                warn!(
+                    "list_toolpacks timed out without invalidating sidecar transport: {}",
+                    error
+                );
+                json!({
+                    "type": "list_toolpacks_response",
+                    "payload": {
+                        "success": true,
+                        "toolpacks": [],
+                        "degraded": true,
+                        "error": error,
+                    }
+                })
+            } else {
+                return Err(error);
             }
-        };
+        }
+    };
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -2968,7 +4664,7 @@ pub async fn get_toolpack(
         "toolpackId": input.toolpack_id
     });
     let command = build_command("get_toolpack", payload);
-    let response = send_command_and_wait(&state, command, 3000).await?;
+    let response = send_read_command_and_wait(&state, &app_handle, command, 3000, true).await?;
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -2991,7 +4687,8 @@ pub async fn install_toolpack(
         "approvePermissionExpansion": input.approve_permission_expansion.unwrap_or(false),
     });
     let command = build_command("install_toolpack", payload);
-    let response = send_command_and_wait(&state, command, 5000).await?;
+    let response =
+        send_mutating_command_and_wait(&state, command, input.idempotency_key, 5000).await?;
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -3047,29 +4744,29 @@ pub async fn list_claude_skills(
         "includeDisabled": input.and_then(|v| v.include_disabled).unwrap_or(true)
     });
     let command = build_command("list_claude_skills", payload);
-    let response =
-        match send_command_and_wait_with_timeout_policy(&state, command, 3000, false).await {
-            Ok(response) => response,
-            Err(error) => {
-                if is_response_timeout_error(&error) {
-                    warn!(
-                        "list_claude_skills timed out without invalidating sidecar transport: {}",
-                        error
-                    );
-                    json!({
-                        "type": "list_claude_skills_response",
-                        "payload": {
-                            "success": true,
-                            "skills": [],
-                            "degraded": true,
-                            "error": error,
-                        }
-                    })
-                } else {
-                    return Err(error);
-                }
+    let response = match send_read_command_and_wait(&state, &app_handle, command, 3000, false).await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            if is_response_timeout_error(&error) {
+                warn!(
+                    "list_claude_skills timed out without invalidating sidecar transport: {}",
+                    error
+                );
+                json!({
+                    "type": "list_claude_skills_response",
+                    "payload": {
+                        "success": true,
+                        "skills": [],
+                        "degraded": true,
+                        "error": error,
+                    }
+                })
+            } else {
+                return Err(error);
             }
-        };
+        }
+    };
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -3237,6 +4934,8 @@ pub async fn remove_claude_skill(
 pub struct CreateWorkspaceInput {
     pub name: String,
     pub path: String,
+    #[serde(rename = "idempotencyKey")]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -3276,29 +4975,29 @@ pub async fn list_workspaces(
 ) -> Result<GenericIpcResult, String> {
     ensure_sidecar_running(&state, &app_handle).await?;
     let command = build_command("list_workspaces", json!({}));
-    let response =
-        match send_command_and_wait_with_timeout_policy(&state, command, 3000, false).await {
-            Ok(response) => response,
-            Err(error) => {
-                if is_response_timeout_error(&error) {
-                    warn!(
-                        "list_workspaces timed out without invalidating sidecar transport: {}",
-                        error
-                    );
-                    json!({
-                        "type": "list_workspaces_response",
-                        "payload": {
-                            "success": true,
-                            "workspaces": [],
-                            "degraded": true,
-                            "error": error,
-                        }
-                    })
-                } else {
-                    return Err(error);
-                }
+    let response = match send_read_command_and_wait(&state, &app_handle, command, 3000, false).await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            if is_response_timeout_error(&error) {
+                warn!(
+                    "list_workspaces timed out without invalidating sidecar transport: {}",
+                    error
+                );
+                json!({
+                    "type": "list_workspaces_response",
+                    "payload": {
+                        "success": true,
+                        "workspaces": [],
+                        "degraded": true,
+                        "error": error,
+                    }
+                })
+            } else {
+                return Err(error);
             }
-        };
+        }
+    };
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -3317,7 +5016,8 @@ pub async fn create_workspace(
         "path": input.path,
     });
     let command = build_command("create_workspace", payload);
-    let response = send_command_and_wait(&state, command, 3000).await?;
+    let response =
+        send_mutating_command_and_wait(&state, command, input.idempotency_key, 3000).await?;
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -3392,8 +5092,8 @@ pub async fn install_from_github(
         "approvePermissionExpansion": input.approve_permission_expansion.unwrap_or(false),
     });
     let command = build_command("install_from_github", payload);
-    // GitHub downloads may take a while
-    let response = send_command_and_wait(&state, command, 30000).await?;
+    // GitHub downloads may take a while; progress keeps the wait alive
+    let response = send_command_and_stream(&state, &app_handle, command, 30000).await?;
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -3531,6 +5231,14 @@ pub fn get_dependency_statuses(
     })
 }
 
+#[tauri::command]
+pub async fn get_capabilities(app_handle: AppHandle) -> Result<CapabilityReport, String> {
+    let probe = SystemCapabilityProbe {
+        app_handle: &app_handle,
+    };
+    Ok(assemble_capabilities(&probe))
+}
+
 const SKILLHUB_KIT_URL: &str =
     "https://skillhub-1388575217.cos.ap-guangzhou.myqcloud.com/install/latest.tar.gz";
 
@@ -3715,8 +5423,8 @@ pub async fn scan_default_repos(
 ) -> Result<GenericIpcResult, String> {
     ensure_sidecar_running(&state, &app_handle).await?;
     let command = build_command("scan_default_repos", json!({}));
-    // Scanning may take a while due to many API calls
-    let response = send_command_and_wait(&state, command, 60000).await?;
+    // Scanning may take a while due to many API calls; progress keeps the wait alive
+    let response = send_command_and_stream(&state, &app_handle, command, 60000).await?;
     Ok(GenericIpcResult {
         success: true,
         payload: response,
@@ -3755,20 +5463,75 @@ pub async fn scan_mcp_servers(
     })
 }
 
+/// Map a sidecar `validate_*` reply onto a `ValidationResult`. The reply's
+/// payload is kept as `details`; its `error`/`errors` become `sidecar_rejected`.
+fn sidecar_validation_result(response: &Value) -> ValidationResult {
+    let details = response
+        .get("payload")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let mut errors: Vec<ValidationError> = match details.get("errors") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(message) => ValidationError::new("sidecar_rejected", message),
+                other => ValidationError::new(
+                    "sidecar_rejected",
+                    other
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| other.to_string()),
+                ),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    if let Some(message) = details.get("error").and_then(Value::as_str) {
+        errors.push(ValidationError::new("sidecar_rejected", message));
+    }
+    let valid = details
+        .get("valid")
+        .or_else(|| details.get("success"))
+        .and_then(Value::as_bool)
+        .unwrap_or(true)
+        && errors.is_empty();
+    if !valid && errors.is_empty() {
+        errors.push(ValidationError::new(
+            "sidecar_rejected",
+            "Sidecar reported the source as invalid",
+        ));
+    }
+    let warnings = details
+        .get("warnings")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    ValidationResult {
+        valid,
+        errors,
+        warnings,
+        details,
+    }
+}
+
 #[tauri::command]
 pub async fn validate_skill(
     input: ScanSourceInput,
     state: State<'_, SidecarState>,
     app_handle: AppHandle,
-) -> Result<GenericIpcResult, String> {
+) -> Result<ValidationResult, String> {
     ensure_sidecar_running(&state, &app_handle).await?;
     let payload = json!({ "source": input.source });
     let command = build_command("validate_skill", payload);
     let response = send_command_and_wait(&state, command, 15000).await?;
-    Ok(GenericIpcResult {
-        success: true,
-        payload: response,
-    })
+    Ok(sidecar_validation_result(&response))
 }
 
 #[tauri::command]
@@ -3776,15 +5539,12 @@ pub async fn validate_mcp(
     input: ScanSourceInput,
     state: State<'_, SidecarState>,
     app_handle: AppHandle,
-) -> Result<GenericIpcResult, String> {
+) -> Result<ValidationResult, String> {
     ensure_sidecar_running(&state, &app_handle).await?;
     let payload = json!({ "source": input.source });
     let command = build_command("validate_mcp", payload);
     let response = send_command_and_wait(&state, command, 15000).await?;
-    Ok(GenericIpcResult {
-        success: true,
-        payload: response,
-    })
+    Ok(sidecar_validation_result(&response))
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -3799,7 +5559,7 @@ pub async fn validate_github_url(
     input: ValidateGitHubUrlInput,
     state: State<'_, SidecarState>,
     app_handle: AppHandle,
-) -> Result<GenericIpcResult, String> {
+) -> Result<ValidationResult, String> {
     ensure_sidecar_running(&state, &app_handle).await?;
     let payload = json!({
         "url": input.url,
@@ -3807,10 +5567,7 @@ pub async fn validate_github_url(
     });
     let command = build_command("validate_github_url", payload);
     let response = send_command_and_wait(&state, command, 15000).await?;
-    Ok(GenericIpcResult {
-        success: true,
-        payload: response,
-    })
+    Ok(sidecar_validation_result(&response))
 }
 
 // ============================================================================
@@ -4082,6 +5839,42 @@ pub fn get_service_status(
     })
 }
 
+/// Configs of all registered services
+#[tauri::command]
+pub fn get_service_configs(
+    state: State<'_, ProcessManagerState>,
+) -> Result<Vec<ServiceConfig>, String> {
+    let manager = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(manager.get_service_configs())
+}
+
+/// Update and save a service's config; takes effect on its next start
+#[tauri::command]
+pub fn update_service_config(
+    name: String,
+    patch: ServiceConfigPatch,
+    state: State<'_, ProcessManagerState>,
+) -> Result<ServiceConfig, String> {
+    let mut manager = state.0.lock().map_err(|e| e.to_string())?;
+    manager
+        .update_service_config(&name, patch)
+        .map_err(|e| e.to_string())
+}
+
+/// Kill whatever holds a CoworkAny service's port so it can be restarted;
+/// requires `confirm`. Returns the killed PIDs.
+#[tauri::command]
+pub fn force_free_port(
+    port: u16,
+    confirm: bool,
+    state: State<'_, ProcessManagerState>,
+) -> Result<Vec<u32>, String> {
+    let manager = state.0.lock().map_err(|e| e.to_string())?;
+    manager
+        .force_free_port(port, confirm)
+        .map_err(|e| e.to_string())
+}
+
 /// Health check for a specific service
 #[tauri::command]
 pub fn health_check_service(
@@ -2,8 +2,10 @@
 //!
 //! Main library crate that re-exports all modules.
 
+pub mod command_history;
 pub mod diff;
 pub mod ipc;
+pub mod locale;
 pub mod platform_asr;
 pub mod platform_runtime;
 pub mod policy;
@@ -11,3 +13,4 @@ pub mod process_manager;
 pub mod shadow_fs;
 pub mod sidecar;
 pub mod tray;
+pub mod workspace_scan;
@@ -0,0 +1,144 @@
+//! CoworkAny Desktop - UI Language
+//!
+//! Which language native UI (the tray menu and its tooltip) is shown in. A
+//! language chosen in the app wins over the system locale and is persisted as
+//! JSON in the shared app data directory.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    Zh,
+}
+
+/// Used when neither a setting nor the system locale names a language we have
+pub const DEFAULT_LANGUAGE: Language = Language::En;
+
+impl Language {
+    /// The language of a locale such as `zh_CN.UTF-8`, if we have strings for it
+    fn from_locale(locale: &str) -> Option<Self> {
+        let locale = locale.to_lowercase();
+        if locale.starts_with("zh") {
+            Some(Language::Zh)
+        } else if locale.starts_with("en") {
+            Some(Language::En)
+        } else {
+            None
+        }
+    }
+}
+
+/// The explicit setting, then the system locale, then `DEFAULT_LANGUAGE`
+pub fn resolve_language(explicit: Option<Language>, system_locale: Option<&str>) -> Language {
+    explicit
+        .or_else(|| system_locale.and_then(Language::from_locale))
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LanguageSettings {
+    language: Option<Language>,
+}
+
+pub struct LanguageState {
+    /// Where the chosen language is persisted
+    path: PathBuf,
+    explicit: Mutex<Option<Language>>,
+}
+
+impl LanguageState {
+    /// Load the language saved at `path`. An unreadable file falls back to the
+    /// system locale rather than failing startup.
+    pub fn with_path(path: PathBuf) -> Self {
+        let settings = match fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable language setting {:?}: {}", path, e);
+                LanguageSettings::default()
+            }),
+            Err(_) => LanguageSettings::default(),
+        };
+        Self {
+            path,
+            explicit: Mutex::new(settings.language),
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        let explicit = self.explicit.lock().map(|lang| *lang).unwrap_or(None);
+        resolve_language(explicit, system_locale().as_deref())
+    }
+
+    /// Persist `language`; `None` goes back to following the system locale.
+    fn set(&self, language: Option<Language>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let raw = serde_json::to_string_pretty(&LanguageSettings { language })
+            .map_err(|e| e.to_string())?;
+        fs::write(&self.path, raw).map_err(|e| e.to_string())?;
+        *self.explicit.lock().map_err(|e| e.to_string())? = language;
+        Ok(())
+    }
+}
+
+/// Language native UI should use right now
+pub fn current_language(app: &AppHandle) -> Language {
+    match app.try_state::<LanguageState>() {
+        Some(state) => state.language(),
+        None => resolve_language(None, system_locale().as_deref()),
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Force the UI language, or follow the system locale again with `None`.
+/// Rebuilds the tray and emits `language-changed` with the language in effect.
+#[tauri::command]
+pub async fn set_language(
+    app: AppHandle,
+    state: State<'_, LanguageState>,
+    lang: Option<Language>,
+) -> Result<Language, String> {
+    state.set(lang)?;
+    let language = state.language();
+    crate::tray::refresh_tray(&app).map_err(|e| e.to_string())?;
+    let _ = app.emit("language-changed", language);
+    Ok(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_language_beats_system_locale_which_beats_default() {
+        assert_eq!(
+            resolve_language(Some(Language::En), Some("zh_CN.UTF-8")),
+            Language::En
+        );
+        assert_eq!(resolve_language(None, Some("zh_CN.UTF-8")), Language::Zh);
+        assert_eq!(resolve_language(None, Some("en_US.UTF-8")), Language::En);
+        assert_eq!(
+            resolve_language(None, Some("de_DE.UTF-8")),
+            DEFAULT_LANGUAGE
+        );
+        assert_eq!(resolve_language(None, None), DEFAULT_LANGUAGE);
+    }
+}
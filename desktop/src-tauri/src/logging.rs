@@ -0,0 +1,231 @@
+//! CoworkAny Desktop - Logging setup
+//!
+//! Wires the tracing subscriber: a daily-rotated file log, an optional
+//! error-only log, and a stderr console layer when there is a console to
+//! write to.
+//!
+//! Configuration (environment):
+//! - `COWORKANY_LOG_RETENTION_DAYS`: delete rotated logs older than this on
+//!   startup (default 14, `0` keeps everything)
+//! - `COWORKANY_LOG_SPLIT_ERRORS`: also write error-level events to `errors.log`
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tracing::info;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, EnvFilter};
+
+const LOG_FILE_PREFIX: &str = "desktop.log";
+const ERROR_LOG_FILE_PREFIX: &str = "errors.log";
+const DEFAULT_RETENTION_DAYS: u32 = 14;
+
+/// Release builds on Windows use the GUI subsystem, so the process starts
+/// without a console and stderr goes nowhere.
+const DETACHED_SUBSYSTEM: bool = cfg!(all(target_os = "windows", not(debug_assertions)));
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Rotated logs older than this many days are deleted on startup
+    pub retention_days: Option<u32>,
+    /// Mirror error-level events into a separate `errors.log`
+    pub split_errors: bool,
+}
+
+impl LogConfig {
+    pub fn from_env() -> Self {
+        let retention_days = std::env::var("COWORKANY_LOG_RETENTION_DAYS")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u32>().ok())
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+        let split_errors = std::env::var("COWORKANY_LOG_SPLIT_ERRORS")
+            .map(|raw| matches!(raw.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        Self {
+            retention_days: (retention_days > 0).then_some(retention_days),
+            split_errors,
+        }
+    }
+}
+
+/// Keeps the non-blocking writers alive; dropping it flushes pending logs.
+pub struct LogGuards {
+    _file: WorkerGuard,
+    _errors: Option<WorkerGuard>,
+}
+
+/// Initialize tracing. The returned guards must be kept alive for the
+/// lifetime of the app.
+pub fn init(log_dir: &Path, config: &LogConfig) -> LogGuards {
+    let pruned = config
+        .retention_days
+        .map(|days| prune_expired_logs(log_dir, days, SystemTime::now()))
+        .unwrap_or(0);
+
+    // Daily-rotated file appender: desktop.log.YYYY-MM-DD
+    let file_appender = tracing_appender::rolling::daily(log_dir, LOG_FILE_PREFIX);
+    let (non_blocking, file_guard) = tracing_appender::non_blocking(file_appender);
+
+    let (errors_layer, errors_guard) = if config.split_errors {
+        let errors_appender = tracing_appender::rolling::daily(log_dir, ERROR_LOG_FILE_PREFIX);
+        let (errors_writer, guard) = tracing_appender::non_blocking(errors_appender);
+        let layer = fmt::layer()
+            .with_ansi(false)
+            .with_writer(errors_writer)
+            .with_filter(LevelFilter::ERROR);
+        (Some(layer), Some(guard))
+    } else {
+        (None, None)
+    };
+
+    let env_filter =
+        EnvFilter::from_default_env().add_directive("coworkany=debug".parse().unwrap());
+
+    let console_layer = should_enable_console_layer(DETACHED_SUBSYSTEM, attach_parent_console)
+        .then(|| fmt::layer().with_writer(std::io::stderr));
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(console_layer) // console
+        .with(fmt::layer().with_ansi(false).with_writer(non_blocking)) // file
+        .with(errors_layer) // errors.log
+        .init();
+
+    if pruned > 0 {
+        info!("Pruned {} expired log file(s)", pruned);
+    }
+
+    LogGuards {
+        _file: file_guard,
+        _errors: errors_guard,
+    }
+}
+
+/// Delete rotated log files older than `retention_days`. Returns the number
+/// of files removed.
+fn prune_expired_logs(log_dir: &Path, retention_days: u32, now: SystemTime) -> usize {
+    let Ok(entries) = fs::read_dir(log_dir) else {
+        return 0;
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok()) else {
+            continue;
+        };
+        if is_expired_log(&file_name, modified, now, retention_days)
+            && fs::remove_file(entry.path()).is_ok()
+        {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Only our own rotated files are candidates, so anything else a user drops
+/// into the log directory is left alone.
+fn is_expired_log(
+    file_name: &str,
+    modified: SystemTime,
+    now: SystemTime,
+    retention_days: u32,
+) -> bool {
+    let ours = [LOG_FILE_PREFIX, ERROR_LOG_FILE_PREFIX]
+        .iter()
+        .any(|prefix| file_name.starts_with(prefix));
+    if !ours {
+        return false;
+    }
+
+    let max_age = Duration::from_secs(u64::from(retention_days) * 24 * 60 * 60);
+    now.duration_since(modified)
+        .map(|age| age > max_age)
+        .unwrap_or(false)
+}
+
+/// The console layer is always on unless the process was started detached,
+/// in which case it is only on if we managed to attach to the parent
+/// terminal's console (e.g. launched from PowerShell).
+fn should_enable_console_layer(
+    detached_subsystem: bool,
+    attach_console: impl FnOnce() -> bool,
+) -> bool {
+    !detached_subsystem || attach_console()
+}
+
+#[cfg(target_os = "windows")]
+fn attach_parent_console() -> bool {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) != 0 }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_parent_console() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_expired_log, should_enable_console_layer};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn console_layer_enabled_when_not_detached() {
+        assert!(should_enable_console_layer(false, || {
+            panic!("should not try to attach a console")
+        }));
+    }
+
+    #[test]
+    fn detached_process_uses_console_only_when_attach_succeeds() {
+        assert!(should_enable_console_layer(true, || true));
+        assert!(!should_enable_console_layer(true, || false));
+    }
+
+    #[test]
+    fn prunes_only_our_logs_past_retention() {
+        let now = SystemTime::now();
+        let days_ago = |days: u64| now - Duration::from_secs(days * 24 * 60 * 60);
+
+        assert!(!is_expired_log(
+            "desktop.log.2026-03-18",
+            days_ago(1),
+            now,
+            7
+        ));
+        assert!(!is_expired_log(
+            "desktop.log.2026-03-12",
+            days_ago(7),
+            now,
+            7
+        ));
+        assert!(is_expired_log(
+            "desktop.log.2026-03-01",
+            days_ago(8),
+            now,
+            7
+        ));
+        assert!(is_expired_log(
+            "errors.log.2026-02-01",
+            days_ago(30),
+            now,
+            7
+        ));
+        assert!(!is_expired_log("notes.txt", days_ago(30), now, 7));
+        // Clock skew: a file "from the future" is never pruned.
+        assert!(!is_expired_log(
+            "desktop.log.2026-03-20",
+            now + Duration::from_secs(60),
+            now,
+            7
+        ));
+    }
+}
@@ -9,33 +9,46 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod command_history;
 mod diff;
 mod git_integration;
 mod ipc;
+mod locale;
+mod logging;
 mod platform_asr;
 mod platform_runtime;
 mod policy;
 mod process_manager;
+mod review_bundle;
 mod screen_capture;
 mod shadow_fs;
 mod sidecar;
 mod tray;
 mod window_manager;
+mod workspace_scan;
 
+use command_history::CommandHistoryState;
+use locale::LanguageState;
+use platform_runtime::{AppReadiness, SubsystemHealth};
 use policy::{ConsoleAuditSink, PolicyEngineState};
 use process_manager::ProcessManagerState;
+use screen_capture::CaptureQueue;
 use shadow_fs::ShadowFsState;
-use sidecar::SidecarState;
+use sidecar::{SidecarError, SidecarState, WatchdogState};
 use std::sync::Arc;
 #[cfg(target_os = "macos")]
 use tauri::TitleBarStyle;
-use tauri::{Emitter, Manager};
+use tauri::{Emitter, Manager, RunEvent};
 use tokio::sync::Mutex;
 use tracing::{error, info, warn};
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use window_manager::CompactModeState;
+use workspace_scan::WorkspaceScanState;
 
 const APP_IDENTIFIER: &str = "com.coworkany.desktop";
 
+/// Stderr lines attached to `sidecar-failed` to say why it kept dying
+const FAILED_STDERR_CONTEXT_LINES: usize = 10;
+
 fn shared_app_data_dir() -> std::path::PathBuf {
     dirs::data_dir()
         .unwrap_or_else(|| {
@@ -44,14 +57,82 @@ fn shared_app_data_dir() -> std::path::PathBuf {
         .join(APP_IDENTIFIER)
 }
 
+/// Whether the environment variable `name` is set to a truthy value
+fn env_flag(name: &str) -> bool {
+    matches!(
+        std::env::var(name)
+            .ok()
+            .map(|value| value.trim().to_ascii_lowercase())
+            .as_deref(),
+        Some("1" | "true" | "yes" | "on")
+    )
+}
+
+/// `COWORKANY_LAZY_SIDECAR_SPAWN`: don't start the sidecar at launch; the
+/// first command that needs it spawns it.
+fn lazy_sidecar_spawn() -> bool {
+    env_flag("COWORKANY_LAZY_SIDECAR_SPAWN")
+}
+
+/// `COWORKANY_AUDIT_HASH_CHAIN`: chain each policy audit log line to the one
+/// before it so `verify_audit_chain` can detect tampering.
+fn audit_hash_chain() -> bool {
+    env_flag("COWORKANY_AUDIT_HASH_CHAIN")
+}
+
+/// `COWORKANY_SHADOW_DIR`: keep Shadow FS state (staged copies, trash and
+/// the apply log) in this directory instead of under the shadow workspace.
+fn shadow_state_dir() -> Option<std::path::PathBuf> {
+    std::env::var("COWORKANY_SHADOW_DIR")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(std::path::PathBuf::from)
+}
+
 fn parse_env_u32(name: &str) -> Option<u32> {
     std::env::var(name)
         .ok()
         .and_then(|raw| raw.trim().parse::<u32>().ok())
 }
 
+/// `COWORKANY_SIDECAR_SHUTDOWN_GRACE_MS`: how long the sidecar gets to exit
+/// on its own after the shutdown command before it is killed.
+/// `COWORKANY_SIDECAR_STDIN_QUEUE`: how many commands may wait for the
+/// sidecar before sends are refused with backpressure.
+fn sidecar_state() -> SidecarState {
+    let state = SidecarState::new();
+    if let Ok(mut manager) = state.manager.lock() {
+        if let Some(ms) = parse_env_u32("COWORKANY_SIDECAR_SHUTDOWN_GRACE_MS") {
+            manager.set_shutdown_grace(std::time::Duration::from_millis(u64::from(ms)));
+        }
+        if let Some(capacity) = parse_env_u32("COWORKANY_SIDECAR_STDIN_QUEUE") {
+            manager.set_stdin_queue_capacity(capacity as usize);
+        }
+    }
+    state
+}
+
+/// Parse an accelerator coming from the UI. Empty or malformed input is
+/// reported with a stable `invalid_shortcut` prefix.
+fn parse_shortcut(raw: &str) -> Result<tauri_plugin_global_shortcut::Shortcut, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("invalid_shortcut: shortcut is empty".to_string());
+    }
+    trimmed
+        .parse::<tauri_plugin_global_shortcut::Shortcut>()
+        .map_err(|e| {
+            format!(
+                "invalid_shortcut: '{}' is not a valid accelerator ({})",
+                trimmed, e
+            )
+        })
+}
+
 /// IPC command to update the global shortcut for toggling the main window.
-/// Unregisters the old shortcut and registers the new one.
+/// Validates the new shortcut before unregistering the old one, so a bad
+/// accelerator leaves the current binding in place.
 #[tauri::command]
 fn update_global_shortcut(
     app: tauri::AppHandle,
@@ -60,13 +141,21 @@ fn update_global_shortcut(
 ) -> Result<(), String> {
     use tauri_plugin_global_shortcut::GlobalShortcutExt;
 
+    let shortcut = parse_shortcut(&new_shortcut)?;
+
     // Unregister the old shortcut (ignore errors — may not be registered)
     let _ = app.global_shortcut().unregister(old_shortcut.as_str());
 
-    // Register the new shortcut
-    app.global_shortcut()
-        .register(new_shortcut.as_str())
-        .map_err(|e| format!("Failed to register shortcut '{}': {}", new_shortcut, e))?;
+    // Register the new shortcut, restoring the old one if the platform refuses
+    if let Err(e) = app.global_shortcut().register(shortcut) {
+        if parse_shortcut(&old_shortcut).is_ok() {
+            let _ = app.global_shortcut().register(old_shortcut.as_str());
+        }
+        return Err(format!(
+            "Failed to register shortcut '{}': {}",
+            new_shortcut, e
+        ));
+    }
 
     info!(
         "Global shortcut updated: '{}' -> '{}'",
@@ -84,30 +173,41 @@ fn main() {
     let log_dir = shared_app_data_dir().join("logs");
     let _ = std::fs::create_dir_all(&log_dir);
 
-    // Daily-rotated file appender: desktop-YYYY-MM-DD.log
-    let file_appender = tracing_appender::rolling::daily(&log_dir, "desktop.log");
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-
-    // Initialize tracing — logs to both stderr (console) and file
-    let env_filter =
-        EnvFilter::from_default_env().add_directive("coworkany=debug".parse().unwrap());
-
-    tracing_subscriber::registry()
-        .with(env_filter)
-        .with(fmt::layer().with_writer(std::io::stderr)) // console
-        .with(fmt::layer().with_ansi(false).with_writer(non_blocking)) // file
-        .init();
+    // Initialize tracing — logs to file, and to stderr when a console is available
+    let _guard = logging::init(&log_dir, &logging::LogConfig::from_env());
 
     info!("CoworkAny Desktop starting...");
     info!("Log directory: {}", log_dir.display());
 
+    let lazy_spawn = lazy_sidecar_spawn();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::new().build())
-        .manage(SidecarState::new())
-        .manage(ProcessManagerState::new())
+        .manage(sidecar_state())
+        .manage(WatchdogState::new(lazy_spawn))
+        .manage(
+            ProcessManagerState::new()
+                .with_config_path(shared_app_data_dir().join("services-config.json")),
+        )
         .manage::<ShadowFsState>(Arc::new(Mutex::new(None)))
-        .manage(PolicyEngineState::new(Box::new(ConsoleAuditSink)))
+        .manage(
+            PolicyEngineState::new(Box::new(ConsoleAuditSink))
+                .with_config_path(shared_app_data_dir().join("policy-config.json"))
+                .with_audit_log(shared_app_data_dir().join("logs").join("policy-audit.jsonl"))
+                .with_audit_hash_chain(audit_hash_chain()),
+        )
+        .manage(WorkspaceScanState::default())
+        .manage(CaptureQueue::default())
+        .manage(CommandHistoryState::with_path(
+            shared_app_data_dir().join("command-history.json"),
+        ))
+        .manage(LanguageState::with_path(
+            shared_app_data_dir().join("ui-language.json"),
+        ))
+        .manage(CompactModeState::with_path(
+            shared_app_data_dir().join("compact-layout.json"),
+        ))
         .invoke_handler(tauri::generate_handler![
             // Sidecar commands
             ipc::start_task,
@@ -126,7 +226,10 @@ fn main() {
             ipc::get_sidecar_status,
             ipc::get_llm_settings,
             ipc::save_llm_settings,
+            ipc::migrate_llm_provider,
             ipc::validate_llm_settings,
+            ipc::get_provider_models,
+            ipc::cancel_validation,
             ipc::get_workspace_root,
             ipc::get_default_workspace_path,
             ipc::open_local_file,
@@ -139,6 +242,11 @@ fn main() {
             ipc::record_startup_metric,
             ipc::spawn_sidecar,
             ipc::shutdown_sidecar,
+            ipc::shutdown_workspace_sidecar,
+            ipc::get_protocol_diagnostics,
+            ipc::get_sidecar_stderr,
+            ipc::get_rag_paths,
+            ipc::set_rag_paths,
             ipc::list_toolpacks,
             ipc::get_toolpack,
             ipc::install_toolpack,
@@ -161,6 +269,7 @@ fn main() {
             ipc::search_skillhub_skills,
             ipc::install_from_skillhub,
             ipc::get_dependency_statuses,
+            ipc::get_capabilities,
             ipc::install_skillhub_cli,
             ipc::prepare_service_runtime,
             // Scanning commands
@@ -170,18 +279,62 @@ fn main() {
             ipc::validate_skill,
             ipc::validate_mcp,
             ipc::validate_github_url,
+            workspace_scan::scan_workspace,
+            workspace_scan::cancel_workspace_scan,
             // Shadow FS commands
+            shadow_fs::init_shadow_fs,
             shadow_fs::stage_file,
+            shadow_fs::stage_files,
+            shadow_fs::verify_shadow_index,
             shadow_fs::list_pending_patches,
+            shadow_fs::get_pending_diff_stats,
             shadow_fs::approve_patch,
             shadow_fs::reject_patch,
+            shadow_fs::restage_rejected,
+            shadow_fs::stage_revert,
+            command_history::get_command_history,
+            command_history::clear_command_history,
+            locale::set_language,
+            shadow_fs::restage_against_current,
+            shadow_fs::resolve_conflict,
+            shadow_fs::restore_from_trash,
+            shadow_fs::watch_shadow_originals,
+            shadow_fs::snapshot_shadow_state,
+            shadow_fs::restore_shadow_snapshot,
+            shadow_fs::list_shadow_snapshots,
+            shadow_fs::delete_shadow_snapshot,
+            shadow_fs::reject_all_conflicts,
             shadow_fs::apply_patch,
+            shadow_fs::apply_all_approved,
+            shadow_fs::apply_patches_atomic,
+            review_bundle::export_review_bundle,
+            shadow_fs::preview_applied_content,
+            shadow_fs::preview_apply,
+            shadow_fs::check_patch_applies,
             shadow_fs::cleanup_trash,
+            shadow_fs::cleanup_shadow_by_size,
+            shadow_fs::get_shadow_store_usage,
+            shadow_fs::set_shadow_quota,
+            shadow_fs::get_apply_history,
+            shadow_fs::purge_shadow_state,
             // Policy commands
             policy::commands::request_effect,
             policy::commands::confirm_effect,
             policy::commands::deny_effect,
             policy::commands::get_pending_confirmations,
+            policy::commands::simulate_effects,
+            policy::commands::get_effective_policy,
+            policy::commands::set_auto_approve_window,
+            policy::commands::get_scope_templates,
+            policy::commands::save_scope_template,
+            policy::commands::list_remembered_denials,
+            policy::commands::clear_remembered_denials,
+            policy::commands::get_audit_event,
+            policy::commands::verify_audit_chain,
+            policy::commands::get_recent_audit_events,
+            policy::commands::add_allowed_command,
+            policy::commands::remove_allowed_command,
+            policy::commands::add_denied_command,
             policy::commands::register_agent_identity,
             policy::commands::record_agent_delegation,
             policy::commands::report_mcp_gateway_decision,
@@ -194,18 +347,27 @@ fn main() {
             ipc::stop_service,
             ipc::get_all_services_status,
             ipc::get_service_status,
+            ipc::get_service_configs,
+            ipc::update_service_config,
+            ipc::force_free_port,
             ipc::health_check_service,
             ipc::prepare_rag_embedding_model,
             // Window commands
             window_manager::quit_app,
+            window_manager::list_monitors,
+            window_manager::toggle_compact_mode,
             // Git commands
             git_integration::git_status,
             git_integration::git_commit,
             git_integration::git_log,
             git_integration::git_checkpoint,
             git_integration::git_rollback,
+            git_integration::stage_from_git_diff,
+            git_integration::prune_committed_entries,
+            diff::diff_files,
             // Screen Capture
             screen_capture::capture_screen,
+            screen_capture::capture_active_window,
             // Shortcut management
             update_global_shortcut,
         ])
@@ -230,10 +392,19 @@ fn main() {
                 })
                 .build(),
         )
-        .setup(|app| {
+        .setup(move |app| {
             info!("Tauri app setup complete");
 
             let app_handle = app.handle().clone();
+            // Enumerating screens can be slow; find out once, off the main thread.
+            std::thread::spawn(|| {
+                if let Err(reason) = screen_capture::capture_support() {
+                    tracing::warn!("Screen capture unavailable: {}", reason);
+                }
+            });
+            app_handle
+                .state::<PolicyEngineState>()
+                .stream_audit_events(app_handle.clone());
 
             if let Some(window) = app.get_webview_window("main") {
                 #[cfg(not(target_os = "macos"))]
@@ -256,14 +427,19 @@ fn main() {
             // errors that occur during dev-mode hot reloads.
             use tauri_plugin_global_shortcut::GlobalShortcutExt;
             let _ = app_handle.global_shortcut().unregister("Alt+Space");
-            if let Err(e) = app_handle.global_shortcut().register("Alt+Space") {
-                tracing::warn!("Failed to register global shortcut 'Alt+Space': {}", e);
-                // Continue application startup even if shortcut fails
-            }
+            let global_shortcut_ready = match app_handle.global_shortcut().register("Alt+Space") {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Failed to register global shortcut 'Alt+Space': {}", e);
+                    // Continue application startup even if shortcut fails
+                    false
+                }
+            };
 
             // Initialize Shadow FS in app data so packaged apps never point
             // at the read-only .app bundle as their workspace root.
             let shadow_state = app.state::<ShadowFsState>();
+            let mut shadow_fs_ready = false;
 
             match app.path().app_data_dir() {
                 Ok(app_data_dir) => {
@@ -275,10 +451,15 @@ fn main() {
                             e
                         );
                     } else {
+                        let config = match shadow_state_dir() {
+                            Some(dir) => shadow_fs::ShadowFsConfig::in_dir(&dir),
+                            None => shadow_fs::ShadowFsConfig::for_workspace(&shadow_workspace),
+                        };
                         let mut guard = shadow_state.blocking_lock();
-                        match shadow_fs::ShadowFs::new(shadow_workspace) {
+                        match shadow_fs::ShadowFs::with_config(shadow_workspace, config) {
                             Ok(fs) => {
                                 *guard = Some(fs);
+                                shadow_fs_ready = true;
                                 info!("Shadow FS initialized");
                             }
                             Err(e) => {
@@ -300,18 +481,43 @@ fn main() {
                 info!("Process Manager initialized");
             }
 
+            // Initialize system tray
+            let tray_ready = match tray::setup_tray(&app_handle) {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Failed to setup system tray: {}", e);
+                    false
+                }
+            };
+
             // Start sidecar and backend services asynchronously so the first window paints faster.
             {
                 let app_for_boot = app_handle.clone();
+                let mut health = SubsystemHealth {
+                    sidecar_deferred: lazy_spawn,
+                    shadow_fs: shadow_fs_ready,
+                    tray: tray_ready,
+                    global_shortcut: global_shortcut_ready,
+                    ..Default::default()
+                };
                 std::thread::spawn(move || {
                     // Auto-spawn sidecar on startup
-                    {
+                    if lazy_spawn {
+                        info!("Lazy sidecar spawn enabled; deferring sidecar until first use");
+                    } else {
                         let state = app_for_boot.state::<SidecarState>();
-                        let mut manager = state.0.lock().unwrap();
+                        let mut manager = state.manager.lock().unwrap();
                         if let Err(e) = manager.spawn(app_for_boot.clone()) {
                             tracing::warn!("Failed to auto-spawn sidecar on startup: {}", e);
+                            if matches!(
+                                e,
+                                SidecarError::EntryMissing { .. } | SidecarError::Incompatible { .. }
+                            ) {
+                                app_for_boot.state::<WatchdogState>().suppress();
+                            }
                             // Non-fatal - sidecar will be spawned on first command
                         } else {
+                            health.sidecar = manager.is_running();
                             let _ = app_for_boot.emit("service-status", serde_json::json!({
                                 "name": "sidecar",
                                 "status": "running"
@@ -319,6 +525,15 @@ fn main() {
                         }
                     }
 
+                    let readiness = AppReadiness::compute(health);
+                    info!(
+                        "App readiness: ready={} failed={:?}",
+                        readiness.ready, readiness.failed
+                    );
+                    for event in readiness.events() {
+                        let _ = app_for_boot.emit(event, &readiness);
+                    }
+
                     // Test-only hook: intentionally call spawn multiple times to verify
                     // sidecar singleton reuse behavior with a real desktop process.
                     let extra_spawn_attempts =
@@ -338,7 +553,7 @@ fn main() {
                                 std::thread::sleep(std::time::Duration::from_millis(delay_ms as u64));
                             }
                             let state = app_for_boot.state::<SidecarState>();
-                            let mut manager = state.0.lock().unwrap();
+                            let mut manager = state.manager.lock().unwrap();
                             match manager.spawn(app_for_boot.clone()) {
                                 Ok(()) => info!(
                                     "Sidecar repeat-spawn attempt {}/{} completed",
@@ -364,15 +579,58 @@ fn main() {
 
             // Sidecar watchdog — auto-restart on crash (max 3 attempts, exponential backoff)
             {
-                let sidecar_state = app.state::<SidecarState>().0.clone();
+                let sidecar_state = app.state::<SidecarState>().manager.clone();
+                let workspace_sidecars = app.state::<SidecarState>().workspaces.clone();
+                let watchdog = app.state::<WatchdogState>().inner().clone();
                 let watchdog_handle = app_handle.clone();
                 std::thread::spawn(move || {
                     let max_restarts = 3u32;
                     let mut restart_count = 0u32;
                     let mut last_restart = std::time::Instant::now();
 
-                    loop {
-                        std::thread::sleep(std::time::Duration::from_secs(5));
+                    while watchdog.sleep(std::time::Duration::from_secs(5)) {
+                        // Workspace sidecars carry their own watchdog flags and restart budgets.
+                        let crashed = workspace_sidecars
+                            .lock()
+                            .map(|mut workspaces| workspaces.crashed())
+                            .unwrap_or_default();
+                        for (workspace, manager) in crashed.failed {
+                            let recent_stderr = manager
+                                .lock()
+                                .map(|mgr| mgr.recent_stderr(FAILED_STDERR_CONTEXT_LINES))
+                                .unwrap_or_default();
+                            let _ = watchdog_handle.emit("sidecar-failed", serde_json::json!({
+                                "message": "Workspace sidecar failed to stay running after multiple restarts",
+                                "workspace": workspace,
+                                "recentStderr": recent_stderr
+                            }));
+                        }
+                        for (workspace, manager, workspace_watchdog) in crashed.restart {
+                            warn!("Sidecar watchdog: workspace {} sidecar not running, restarting", workspace);
+                            if let Ok(mut mgr) = manager.lock() {
+                                if workspace_watchdog.is_stopped() {
+                                    continue;
+                                }
+                                match mgr.spawn(watchdog_handle.clone()) {
+                                    Ok(()) => {
+                                        let _ = watchdog_handle.emit("sidecar-reconnected", serde_json::json!({
+                                            "workspace": workspace
+                                        }));
+                                    }
+                                    Err(SidecarError::EntryMissing { .. } | SidecarError::Incompatible { .. }) => {
+                                        // Nothing to restart until the entry is installed or updated.
+                                        workspace_watchdog.suppress();
+                                    }
+                                    Err(e) => {
+                                        error!("Sidecar watchdog: restart of workspace {} failed: {}", workspace, e);
+                                    }
+                                }
+                            }
+                        }
+
+                        if watchdog.is_suppressed() {
+                            continue;
+                        }
 
                         let running = {
                             if let Ok(mut mgr) = sidecar_state.lock() {
@@ -382,7 +640,7 @@ fn main() {
                             }
                         };
 
-                        if !running {
+                        if watchdog.should_restart(running) {
                             // Reset counter if last restart was > 2 minutes ago
                             if last_restart.elapsed() > std::time::Duration::from_secs(120) {
                                 restart_count = 0;
@@ -390,11 +648,18 @@ fn main() {
 
                             if restart_count >= max_restarts {
                                 error!("Sidecar watchdog: max restarts ({}) exceeded, giving up", max_restarts);
+                                let recent_stderr = sidecar_state
+                                    .lock()
+                                    .map(|mgr| mgr.recent_stderr(FAILED_STDERR_CONTEXT_LINES))
+                                    .unwrap_or_default();
                                 let _ = watchdog_handle.emit("sidecar-failed", serde_json::json!({
-                                    "message": "Sidecar process failed to stay running after multiple restarts"
+                                    "message": "Sidecar process failed to stay running after multiple restarts",
+                                    "recentStderr": recent_stderr
                                 }));
                                 // Wait longer before trying again
-                                std::thread::sleep(std::time::Duration::from_secs(60));
+                                if !watchdog.sleep(std::time::Duration::from_secs(60)) {
+                                    break;
+                                }
                                 restart_count = 0;
                                 continue;
                             }
@@ -411,7 +676,13 @@ fn main() {
                                 "backoffSecs": backoff_secs
                             }));
 
-                            std::thread::sleep(std::time::Duration::from_secs(backoff_secs));
+                            if !watchdog.sleep(std::time::Duration::from_secs(backoff_secs)) {
+                                break;
+                            }
+                            // The user may have shut the sidecar down during the backoff.
+                            if watchdog.is_suppressed() {
+                                continue;
+                            }
 
                             if let Ok(mut mgr) = sidecar_state.lock() {
                                 match mgr.spawn(watchdog_handle.clone()) {
@@ -419,6 +690,10 @@ fn main() {
                                         info!("Sidecar watchdog: restarted successfully (attempt {})", restart_count + 1);
                                         let _ = watchdog_handle.emit("sidecar-reconnected", ());
                                     }
+                                    Err(SidecarError::EntryMissing { .. } | SidecarError::Incompatible { .. }) => {
+                                        // Nothing to restart until the entry is installed or updated.
+                                        watchdog.suppress();
+                                    }
                                     Err(e) => {
                                         error!("Sidecar watchdog: restart failed: {}", e);
                                     }
@@ -429,17 +704,52 @@ fn main() {
                             last_restart = std::time::Instant::now();
                         }
                     }
+                    info!("Sidecar watchdog stopped");
                 });
                 info!("Sidecar watchdog thread started");
             }
 
-            // Initialize system tray
-            if let Err(e) = tray::setup_tray(&app_handle) {
-                tracing::warn!("Failed to setup system tray: {}", e);
-            }
-
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                app_handle.state::<WatchdogState>().stop();
+                let state = app_handle.state::<SidecarState>();
+                if let Ok(mut workspaces) = state.workspaces.lock() {
+                    workspaces.shutdown_all();
+                }
+                if let Ok(mut manager) = state.manager.lock() {
+                    manager.shutdown();
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_shortcut;
+
+    #[test]
+    fn rejects_empty_and_malformed_shortcuts() {
+        for raw in ["", "   "] {
+            let error = parse_shortcut(raw).expect_err("empty shortcut");
+            assert!(error.starts_with("invalid_shortcut:"), "{error}");
+        }
+        for raw in ["Ctrl+", "Ctrl+Shift", "Hyper+Nope"] {
+            let error = parse_shortcut(raw).expect_err("malformed shortcut");
+            assert!(error.starts_with("invalid_shortcut:"), "{error}");
+            assert!(error.contains(raw), "{error}");
+        }
+    }
+
+    #[test]
+    fn accepts_valid_accelerators() {
+        assert!(parse_shortcut("CmdOrCtrl+Shift+Space").is_ok());
+        assert_eq!(
+            parse_shortcut("  Alt+Space ").expect("trimmed"),
+            parse_shortcut("Alt+Space").expect("plain")
+        );
+    }
 }
@@ -65,6 +65,157 @@ pub struct RuntimeSnapshot {
     pub dependencies: Vec<RuntimeDependencyStatus>,
 }
 
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityStatus {
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Optional subsystems the frontend can check up front instead of
+/// discovering failures at call time.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityReport {
+    pub platform: String,
+    pub screen_capture: CapabilityStatus,
+    pub keychain_secrets: CapabilityStatus,
+    pub git: CapabilityStatus,
+    pub python: CapabilityStatus,
+    pub sidecar_runtime: CapabilityStatus,
+}
+
+/// Environment probes behind `assemble_capabilities`, split out so the
+/// assembly logic can be tested without touching the host.
+pub trait CapabilityProbe {
+    fn screen_count(&self) -> Result<usize, String>;
+    fn keychain_backend(&self) -> Option<String>;
+    /// Combined stdout/stderr of a successful run, or `None` if the program
+    /// is missing or exits non-zero.
+    fn command_output(&self, program: &str, args: &[&str]) -> Option<String>;
+    fn sidecar_runtime(&self) -> Option<String>;
+}
+
+pub struct SystemCapabilityProbe<'a> {
+    pub app_handle: &'a AppHandle,
+}
+
+impl CapabilityProbe for SystemCapabilityProbe<'_> {
+    fn screen_count(&self) -> Result<usize, String> {
+        crate::screen_capture::capture_support()
+    }
+
+    fn keychain_backend(&self) -> Option<String> {
+        if cfg!(target_os = "windows") {
+            return Some("windows-credential-manager".to_string());
+        }
+        if cfg!(target_os = "macos") {
+            return find_on_path("security").map(|_| "macos-keychain".to_string());
+        }
+        find_on_path("secret-tool").map(|_| "secret-service".to_string())
+    }
+
+    fn command_output(&self, program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Some(text.trim().to_string())
+    }
+
+    fn sidecar_runtime(&self) -> Option<String> {
+        if crate::sidecar::SidecarManager::packaged_sidecar_available(self.app_handle) {
+            return Some("packaged".to_string());
+        }
+        resolve_sidecar_entry_path()
+            .ok()
+            .map(|path| format!("development:{}", path.to_string_lossy()))
+    }
+}
+
+/// First whitespace-separated token that starts with a digit, e.g.
+/// `git version 2.39.3 (Apple Git-145)` -> `2.39.3`.
+fn parse_version_output(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .find(|token| token.starts_with(|c: char| c.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+fn command_capability(
+    probe: &dyn CapabilityProbe,
+    programs: &[&str],
+    missing_detail: &str,
+) -> CapabilityStatus {
+    for program in programs {
+        if let Some(output) = probe.command_output(program, &["--version"]) {
+            return CapabilityStatus {
+                available: true,
+                version: parse_version_output(&output),
+                detail: Some(program.to_string()),
+            };
+        }
+    }
+
+    CapabilityStatus {
+        available: false,
+        version: None,
+        detail: Some(missing_detail.to_string()),
+    }
+}
+
+pub fn assemble_capabilities(probe: &dyn CapabilityProbe) -> CapabilityReport {
+    let screen_capture = match probe.screen_count() {
+        Ok(0) => CapabilityStatus {
+            available: false,
+            version: None,
+            detail: Some("no displays detected".to_string()),
+        },
+        Ok(count) => CapabilityStatus {
+            available: true,
+            version: None,
+            detail: Some(format!("{} display(s)", count)),
+        },
+        Err(error) => CapabilityStatus {
+            available: false,
+            version: None,
+            detail: Some(error),
+        },
+    };
+
+    let keychain_backend = probe.keychain_backend();
+    let sidecar_runtime = probe.sidecar_runtime();
+
+    CapabilityReport {
+        platform: std::env::consts::OS.to_string(),
+        screen_capture,
+        keychain_secrets: CapabilityStatus {
+            available: keychain_backend.is_some(),
+            version: None,
+            detail: keychain_backend.or_else(|| Some("no system keychain found".to_string())),
+        },
+        git: command_capability(probe, &["git"], "git not found on PATH"),
+        python: command_capability(probe, &["python3", "python"], "python not found on PATH"),
+        sidecar_runtime: CapabilityStatus {
+            available: sidecar_runtime.is_some(),
+            version: None,
+            detail: sidecar_runtime.or_else(|| Some("sidecar runtime not found".to_string())),
+        },
+    }
+}
+
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    let path_env = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_env)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
 pub fn resolve_app_dir() -> String {
     std::env::current_exe()
         .ok()
@@ -84,15 +235,23 @@ pub fn resolve_app_data_dir(app_handle: &AppHandle) -> String {
 }
 
 pub fn resolve_sidecar_entry_path() -> Result<PathBuf, String> {
-    if let Ok(explicit) = std::env::var("COWORKANY_SIDECAR_ENTRY") {
-        let explicit_path = PathBuf::from(explicit);
-        if explicit_path.exists() {
-            return Ok(explicit_path);
-        }
-    }
+    sidecar_entry_candidates()
+        .into_iter()
+        .find(|candidate| candidate.exists())
+        .ok_or_else(|| {
+            "Unable to locate sidecar/src/main.ts from current runtime paths".to_string()
+        })
+}
 
+/// Every path the development sidecar entry is looked for at, in order:
+/// `COWORKANY_SIDECAR_ENTRY`, then paths relative to the cwd and executable.
+pub fn sidecar_entry_candidates() -> Vec<PathBuf> {
     let mut candidates: Vec<PathBuf> = Vec::new();
 
+    if let Ok(explicit) = std::env::var("COWORKANY_SIDECAR_ENTRY") {
+        candidates.push(PathBuf::from(explicit));
+    }
+
     if let Ok(cwd) = std::env::current_dir() {
         candidates.push(cwd.join("../sidecar/src/main.ts"));
         candidates.push(cwd.join("../../sidecar/src/main.ts"));
@@ -108,11 +267,6 @@ pub fn resolve_sidecar_entry_path() -> Result<PathBuf, String> {
     }
 
     candidates
-        .into_iter()
-        .find(|candidate| candidate.exists())
-        .ok_or_else(|| {
-            "Unable to locate sidecar/src/main.ts from current runtime paths".to_string()
-        })
 }
 
 pub fn managed_service_runtime_dir(
@@ -350,12 +504,145 @@ pub fn build_runtime_snapshot(
     }
 }
 
+/// Which subsystems came up during startup.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsystemHealth {
+    /// Sidecar spawned and answered the runtime handshake
+    pub sidecar: bool,
+    /// Lazy-spawn mode: the sidecar starts on first use, so it isn't waited on
+    pub sidecar_deferred: bool,
+    pub shadow_fs: bool,
+    /// Optional
+    pub tray: bool,
+    /// Optional
+    pub global_shortcut: bool,
+}
+
+/// Payload of the `app-ready` / `app-degraded` events.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppReadiness {
+    /// Required subsystems (sidecar, shadow FS) are up
+    pub ready: bool,
+    pub subsystems: SubsystemHealth,
+    /// Subsystems that failed to come up, required ones first
+    pub failed: Vec<String>,
+}
+
+impl AppReadiness {
+    pub fn compute(subsystems: SubsystemHealth) -> Self {
+        let sidecar_ok = subsystems.sidecar || subsystems.sidecar_deferred;
+        let failed: Vec<String> = [
+            ("sidecar", sidecar_ok),
+            ("shadowFs", subsystems.shadow_fs),
+            ("tray", subsystems.tray),
+            ("globalShortcut", subsystems.global_shortcut),
+        ]
+        .into_iter()
+        .filter(|(_, up)| !up)
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+        Self {
+            ready: sidecar_ok && subsystems.shadow_fs,
+            subsystems,
+            failed,
+        }
+    }
+
+    /// `app-ready` once the required subsystems are up, plus `app-degraded`
+    /// whenever anything failed.
+    pub fn events(&self) -> Vec<&'static str> {
+        let mut events = Vec::new();
+        if self.ready {
+            events.push("app-ready");
+        }
+        if !self.failed.is_empty() {
+            events.push("app-degraded");
+        }
+        events
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        ManagedServiceCapability, PlatformRuntimeContext, RuntimeBinaryInfo,
-        RuntimeDependencyStatus,
+        assemble_capabilities, AppReadiness, CapabilityProbe, ManagedServiceCapability,
+        PlatformRuntimeContext, RuntimeBinaryInfo, RuntimeDependencyStatus, SubsystemHealth,
     };
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MockProbe {
+        screens: Option<usize>,
+        keychain: Option<String>,
+        commands: HashMap<String, String>,
+        sidecar: Option<String>,
+    }
+
+    impl CapabilityProbe for MockProbe {
+        fn screen_count(&self) -> Result<usize, String> {
+            self.screens
+                .ok_or_else(|| "capture backend unavailable".to_string())
+        }
+
+        fn keychain_backend(&self) -> Option<String> {
+            self.keychain.clone()
+        }
+
+        fn command_output(&self, program: &str, _args: &[&str]) -> Option<String> {
+            self.commands.get(program).cloned()
+        }
+
+        fn sidecar_runtime(&self) -> Option<String> {
+            self.sidecar.clone()
+        }
+    }
+
+    #[test]
+    fn assembles_capabilities_from_probes() {
+        let probe = MockProbe {
+            screens: Some(2),
+            keychain: Some("macos-keychain".to_string()),
+            commands: HashMap::from([
+                (
+                    "git".to_string(),
+                    "git version 2.39.3 (Apple Git-145)".to_string(),
+                ),
+                ("python".to_string(), "Python 3.11.4".to_string()),
+            ]),
+            sidecar: Some("packaged".to_string()),
+        };
+
+        let report = assemble_capabilities(&probe);
+        assert!(report.screen_capture.available);
+        assert!(report.keychain_secrets.available);
+        assert_eq!(report.git.version.as_deref(), Some("2.39.3"));
+        // python3 is missing, so the probe falls back to `python`.
+        assert_eq!(report.python.version.as_deref(), Some("3.11.4"));
+        assert_eq!(report.python.detail.as_deref(), Some("python"));
+        assert!(report.sidecar_runtime.available);
+    }
+
+    #[test]
+    fn missing_subsystems_are_reported_unavailable() {
+        let report = assemble_capabilities(&MockProbe::default());
+        assert!(!report.screen_capture.available);
+        assert_eq!(
+            report.screen_capture.detail.as_deref(),
+            Some("capture backend unavailable")
+        );
+        assert!(!report.keychain_secrets.available);
+        assert!(!report.git.available);
+        assert!(report.git.version.is_none());
+        assert!(!report.python.available);
+        assert!(!report.sidecar_runtime.available);
+
+        let value = serde_json::to_value(&report).expect("serialize capability report");
+        assert!(value.get("keychainSecrets").is_some());
+        assert!(value["git"].get("version").is_none());
+    }
 
     #[test]
     fn serializing_runtime_context_omits_absent_sidecar_launch_mode() {
@@ -422,4 +709,43 @@ mod tests {
         assert!(value.get("version").is_none());
         assert!(value.get("error").is_none());
     }
+
+    #[test]
+    fn readiness_requires_sidecar_and_shadow_fs() {
+        let all_up = SubsystemHealth {
+            sidecar: true,
+            sidecar_deferred: false,
+            shadow_fs: true,
+            tray: true,
+            global_shortcut: true,
+        };
+        let report = AppReadiness::compute(all_up);
+        assert!(report.ready);
+        assert!(report.failed.is_empty());
+        assert_eq!(report.events(), vec!["app-ready"]);
+
+        let no_tray = AppReadiness::compute(SubsystemHealth {
+            tray: false,
+            ..all_up
+        });
+        assert!(no_tray.ready);
+        assert_eq!(no_tray.failed, vec!["tray"]);
+        assert_eq!(no_tray.events(), vec!["app-ready", "app-degraded"]);
+
+        let no_sidecar = AppReadiness::compute(SubsystemHealth {
+            sidecar: false,
+            ..all_up
+        });
+        assert!(!no_sidecar.ready);
+        assert_eq!(no_sidecar.failed, vec!["sidecar"]);
+        assert_eq!(no_sidecar.events(), vec!["app-degraded"]);
+
+        let deferred = AppReadiness::compute(SubsystemHealth {
+            sidecar: false,
+            sidecar_deferred: true,
+            ..all_up
+        });
+        assert!(deferred.ready);
+        assert_eq!(deferred.events(), vec!["app-ready"]);
+    }
 }
@@ -1,19 +1,69 @@
 use super::engine::PolicyOutcome;
-use super::types::{EffectRequest, EffectResponse};
+use super::secrets::secret_references;
+use super::types::{AutoApproveWindow, EffectRequest, EffectResponse, EffectType};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
 use std::fs::{File, OpenOptions};
-use std::io::{Result as IoResult, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Result as IoResult, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Audit events kept in memory for the UI
+pub const MAX_RECENT_AUDIT_EVENTS: usize = 500;
+
+/// Header or environment variable names whose values are credentials.
+fn is_sensitive_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    matches!(
+        name.as_str(),
+        "authorization" | "proxy-authorization" | "cookie" | "set-cookie"
+    ) || ["token", "secret", "api-key", "apikey", "password"]
+        .iter()
+        .any(|marker| name.contains(marker))
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditEvent {
     pub id: String,
     pub timestamp: String,
     pub event_type: String,
+    #[serde(default)]
+    pub fingerprint: String,
     pub request: EffectRequest,
     pub response: Option<EffectResponse>,
     pub note: Option<String>,
+    /// Outcome of carrying the effect out; only set on `executed` events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution: Option<ExecutionResult>,
+    /// Every request a grouped decision covers, `request` first; empty when
+    /// the event is about `request` alone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request_ids: Vec<String>,
+}
+
+/// How an approved effect turned out once it was carried out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionResult {
+    pub success: bool,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+impl ExecutionResult {
+    /// Outcome of an effect started at `started`; `error` is `None` on success.
+    pub fn finished(started: Instant, error: Option<String>) -> Self {
+        Self {
+            success: error.is_none(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            error,
+        }
+    }
 }
 
 impl AuditEvent {
@@ -23,8 +73,11 @@ impl AuditEvent {
             id: format!("audit-{}", request.id),
             timestamp: Utc::now().to_rfc3339(),
             event_type: "request".to_string(),
+            fingerprint: request.fingerprint(),
             request: request.clone(),
             response: None,
+            execution: None,
+            request_ids: Vec::new(),
             note: Some(format!("Policy decision: {:?}", outcome.decision)),
         }
     }
@@ -35,41 +88,231 @@ impl AuditEvent {
             id: format!("audit-confirm-{}", request.id),
             timestamp: Utc::now().to_rfc3339(),
             event_type: "confirmed".to_string(),
+            fingerprint: request.fingerprint(),
             request: request.clone(),
             response: None,
+            execution: None,
+            request_ids: Vec::new(),
             note: Some(format!("User confirmed (remember: {})", remember)),
         }
     }
 
+    /// Create audit event for a request approved by an open auto-approve window
+    pub fn auto_approved(request: &EffectRequest, window: &AutoApproveWindow) -> Self {
+        Self {
+            id: format!("audit-auto-{}", request.id),
+            timestamp: Utc::now().to_rfc3339(),
+            event_type: "auto_approved".to_string(),
+            fingerprint: request.fingerprint(),
+            request: request.clone(),
+            response: None,
+            execution: None,
+            request_ids: Vec::new(),
+            note: Some(format!(
+                "Auto-approved after manual confirm (window until {})",
+                window.expires_at
+            )),
+        }
+    }
+
     /// Create audit event for user denial
     pub fn denied(request: &EffectRequest, reason: Option<&str>) -> Self {
         Self {
             id: format!("audit-denied-{}", request.id),
             timestamp: Utc::now().to_rfc3339(),
             event_type: "denied".to_string(),
+            fingerprint: request.fingerprint(),
             request: request.clone(),
             response: None,
+            execution: None,
+            request_ids: Vec::new(),
             note: reason.map(String::from),
         }
     }
+
+    /// Create audit event for the outcome of an approved effect
+    pub fn executed(request: &EffectRequest, result: &ExecutionResult) -> Self {
+        let note = match &result.error {
+            None => format!("Executed in {}ms", result.duration_ms),
+            Some(error) => format!("Execution failed after {}ms: {}", result.duration_ms, error),
+        };
+        Self {
+            id: format!("audit-executed-{}", request.id),
+            timestamp: Utc::now().to_rfc3339(),
+            event_type: "executed".to_string(),
+            fingerprint: request.fingerprint(),
+            request: request.clone(),
+            response: None,
+            note: Some(note),
+            execution: Some(result.clone()),
+            request_ids: Vec::new(),
+        }
+    }
+
+    /// Record that the decision also covered `coalesced`, requests for the
+    /// same effect that were waiting on the same confirmation.
+    pub fn with_coalesced(mut self, coalesced: &[EffectRequest]) -> Self {
+        if !coalesced.is_empty() {
+            self.request_ids = std::iter::once(&self.request)
+                .chain(coalesced)
+                .map(|request| request.id.clone())
+                .collect();
+        }
+        self
+    }
+
+    /// Blank out credentials before the event is shown: sensitive header
+    /// values, sensitive env values passed in plaintext rather than as
+    /// `${secret:NAME}` references, and the content of secret reads.
+    /// Returns the redacted fields.
+    pub fn redact(&mut self) -> Vec<String> {
+        let mut redacted = Vec::new();
+        let payload = &mut self.request.payload;
+
+        if let Some(headers) = payload.headers.as_mut() {
+            let mut names: Vec<&String> = headers
+                .keys()
+                .filter(|name| is_sensitive_name(name))
+                .collect();
+            names.sort();
+            let names: Vec<String> = names.into_iter().cloned().collect();
+            for name in names {
+                headers.insert(name.clone(), REDACTED.to_string());
+                redacted.push(format!("request.payload.headers.{}", name));
+            }
+        }
+
+        if let Some(env) = payload.env.as_mut() {
+            let mut names: Vec<String> = env
+                .iter()
+                .filter(|(name, value)| {
+                    is_sensitive_name(name) && secret_references(value).is_empty()
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            names.sort();
+            for name in names {
+                env.insert(name.clone(), REDACTED.to_string());
+                redacted.push(format!("request.payload.env.{}", name));
+            }
+        }
+
+        if self.request.effect_type == EffectType::SecretsRead && payload.content.is_some() {
+            payload.content = Some(REDACTED.to_string());
+            redacted.push("request.payload.content".to_string());
+        }
+
+        redacted
+    }
+}
+
+/// Find the event with `id` in a JSONL audit log. Lines that don't parse are
+/// skipped so one bad write doesn't hide the rest of the log.
+pub fn find_audit_event(path: &Path, id: &str) -> IoResult<Option<AuditEvent>> {
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if !line.contains(id) {
+            continue;
+        }
+        if let Ok(event) = serde_json::from_str::<AuditEvent>(&line) {
+            if event.id == id {
+                return Ok(Some(event));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The last few audit events, so the UI can show recent activity without
+/// reading the log file. Events are redacted as they are added.
+pub struct RecentAuditEvents {
+    cap: usize,
+    /// Oldest first
+    events: VecDeque<AuditEvent>,
+}
+
+impl RecentAuditEvents {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            events: VecDeque::with_capacity(cap),
+        }
+    }
+
+    /// Add `event`, dropping the oldest once the buffer is full. Returns the
+    /// redacted event as stored.
+    pub fn push(&mut self, mut event: AuditEvent) -> AuditEvent {
+        event.redact();
+        if self.events.len() == self.cap {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+        event
+    }
+
+    /// Up to `limit` events, newest first
+    pub fn recent(&self, limit: usize) -> Vec<AuditEvent> {
+        self.events.iter().rev().take(limit).cloned().collect()
+    }
 }
 
 pub trait AuditSink: Send {
     fn log(&mut self, event: AuditEvent) -> IoResult<()>;
 }
 
-#[allow(dead_code)]
+/// Field a hash-chained line carries: the hash of the line before it
+const PREV_HASH_FIELD: &str = "prev_hash";
+
+/// Hex SHA-256 of an audit log line, without its newline
+fn line_hash(line: &str) -> String {
+    hex::encode(Sha256::digest(line.as_bytes()))
+}
+
 pub struct FileAuditSink {
     path: PathBuf,
+    /// Chain each line to the one before it with `prev_hash`
+    hash_chain: bool,
+    /// Hash of the last line written, once known
+    last_hash: Option<String>,
 }
 
-#[allow(dead_code)]
 impl FileAuditSink {
     pub fn new(path: PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            hash_chain: false,
+            last_hash: None,
+        }
+    }
+
+    /// Make the log tamper-evident: every line records the hash of the line
+    /// before it, so `verify_audit_chain` can find edited or removed lines.
+    /// Costs a hash per event and a read of the log's last line on the first.
+    pub fn with_hash_chain(mut self) -> Self {
+        self.hash_chain = true;
+        self
+    }
+
+    /// Hash of the log's current last line; that of an empty line when the
+    /// log is empty or missing
+    fn tail_hash(&self) -> IoResult<String> {
+        let last = match File::open(&self.path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.is_empty())
+                .last(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(line_hash(last.as_deref().unwrap_or("")))
     }
 
     fn open_append(&self) -> IoResult<File> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         OpenOptions::new()
             .create(true)
             .append(true)
@@ -78,14 +321,86 @@ impl FileAuditSink {
 }
 
 impl AuditSink for FileAuditSink {
-    fn log(&mut self, event: AuditEvent) -> IoResult<()> {
+    /// Append `event` redacted, so credentials never reach the disk.
+    fn log(&mut self, mut event: AuditEvent) -> IoResult<()> {
+        event.redact();
+        let line = if self.hash_chain {
+            let prev_hash = match self.last_hash.take() {
+                Some(hash) => hash,
+                None => self.tail_hash()?,
+            };
+            let mut value = serde_json::to_value(&event).unwrap_or_else(|_| json!({}));
+            if let Some(object) = value.as_object_mut() {
+                object.insert(PREV_HASH_FIELD.to_string(), prev_hash.into());
+            }
+            value.to_string()
+        } else {
+            serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string())
+        };
         let mut file = self.open_append()?;
-        let line = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
         writeln!(file, "{}", line)?;
+        if self.hash_chain {
+            self.last_hash = Some(line_hash(&line));
+        }
         Ok(())
     }
 }
 
+/// What `verify_audit_chain` found
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditChainReport {
+    pub lines: usize,
+    /// Lines carrying a `prev_hash`; lines written before chaining was
+    /// turned on have none
+    pub chained: usize,
+    pub valid: bool,
+    /// 1-based line number of the first line that breaks the chain
+    pub first_broken_line: Option<usize>,
+    pub reason: Option<String>,
+}
+
+/// Walk a hash-chained audit log and report the first line whose
+/// `prev_hash` doesn't match the line before it: an edited or removed line
+/// breaks the link after it. Once one line is chained, every later line
+/// must be, so an unchained line appended after that is a break too.
+pub fn verify_audit_chain(path: &Path) -> IoResult<AuditChainReport> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut report = AuditChainReport::default();
+    let mut previous = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        report.lines += 1;
+        let prev_hash = serde_json::from_str::<Value>(&line).ok().and_then(|value| {
+            value
+                .get(PREV_HASH_FIELD)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        });
+        let chained = prev_hash.is_some();
+        let broken = match prev_hash {
+            Some(hash) if hash == line_hash(&previous) => None,
+            Some(_) => Some("prev_hash does not match the line before it"),
+            None if report.chained > 0 => Some("line is missing its prev_hash"),
+            None => None,
+        };
+        if let Some(reason) = broken {
+            report.first_broken_line = Some(report.lines);
+            report.reason = Some(reason.to_string());
+            return Ok(report);
+        }
+        if chained {
+            report.chained += 1;
+        }
+        previous = line;
+    }
+    report.valid = true;
+    Ok(report)
+}
+
 /// Console audit sink for development
 pub struct ConsoleAuditSink;
 
@@ -98,3 +413,159 @@ impl AuditSink for ConsoleAuditSink {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{EffectPayload, EffectSource};
+    use std::collections::HashMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        std::env::temp_dir().join(format!("coworkany-{name}-{}-{nanos}", std::process::id()))
+    }
+
+    fn network_request(id: &str) -> EffectRequest {
+        EffectRequest {
+            id: id.to_string(),
+            timestamp: "2026-03-19T00:00:00Z".to_string(),
+            effect_type: EffectType::NetworkOutbound,
+            source: EffectSource::Agent,
+            source_id: None,
+            payload: EffectPayload {
+                url: Some("https://api.example.com/v1".to_string()),
+                headers: Some(HashMap::from([
+                    ("Authorization".to_string(), "Bearer sk-live".to_string()),
+                    ("Accept".to_string(), "application/json".to_string()),
+                ])),
+                ..Default::default()
+            },
+            context: None,
+            scope: None,
+        }
+    }
+
+    #[test]
+    fn finds_event_by_id_in_log_and_redacts_credentials() {
+        let dir = unique_temp_dir("audit-lookup");
+        let path = dir.join("policy-audit.jsonl");
+        let mut sink = FileAuditSink::new(path.clone());
+        sink.log(AuditEvent::confirmed(&network_request("first"), false))
+            .expect("log first");
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{{not json"))
+            .expect("append garbage");
+        sink.log(AuditEvent::denied(&network_request("second"), Some("nope")))
+            .expect("log second");
+        let raw = std::fs::read_to_string(&path).expect("read raw log");
+        assert!(
+            !raw.contains("sk-live"),
+            "credentials are redacted on write"
+        );
+
+        let mut event = find_audit_event(&path, "audit-denied-second")
+            .expect("read log")
+            .expect("event present");
+        assert_eq!(event.request.id, "second");
+        assert_eq!(event.note.as_deref(), Some("nope"));
+
+        let redactions = event.redact();
+        assert_eq!(redactions, vec!["request.payload.headers.Authorization"]);
+        let headers = event.request.payload.headers.expect("headers");
+        assert_eq!(headers["Authorization"], REDACTED);
+        assert_eq!(headers["Accept"], "application/json");
+
+        assert!(find_audit_event(&path, "audit-missing")
+            .expect("read log")
+            .is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hash_chain_verifies_clean_and_finds_the_tampered_line() {
+        let dir = unique_temp_dir("audit-chain");
+        let path = dir.join("policy-audit.jsonl");
+        let mut sink = FileAuditSink::new(path.clone()).with_hash_chain();
+        for id in ["a", "b", "c"] {
+            sink.log(AuditEvent::confirmed(&network_request(id), false))
+                .expect("log");
+        }
+        // A new sink picks the chain up from the last line on disk.
+        let mut reopened = FileAuditSink::new(path.clone()).with_hash_chain();
+        reopened
+            .log(AuditEvent::denied(&network_request("d"), None))
+            .expect("log after reopen");
+
+        let report = verify_audit_chain(&path).expect("verify");
+        assert!(report.valid);
+        assert_eq!((report.lines, report.chained), (4, 4));
+
+        let content = std::fs::read_to_string(&path).expect("read log");
+        let tampered = content.replacen("\"id\":\"b\"", "\"id\":\"x\"", 1);
+        assert_ne!(tampered, content);
+        std::fs::write(&path, tampered).expect("tamper");
+        let report = verify_audit_chain(&path).expect("verify tampered");
+        assert!(!report.valid);
+        assert_eq!(report.first_broken_line, Some(3));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recent_events_are_capped_and_newest_first() {
+        let mut recent = RecentAuditEvents::new(3);
+        for id in ["a", "b", "c", "d"] {
+            recent.push(AuditEvent::confirmed(&network_request(id), false));
+        }
+
+        let ids: Vec<String> = recent
+            .recent(10)
+            .into_iter()
+            .map(|event| event.request.id)
+            .collect();
+        assert_eq!(ids, ["d", "c", "b"]);
+        assert_eq!(recent.recent(1)[0].request.id, "d");
+
+        let headers = recent.recent(1)[0].request.payload.headers.clone().unwrap();
+        assert_eq!(headers["Authorization"], REDACTED);
+    }
+
+    #[test]
+    fn redacts_plaintext_env_credentials_but_keeps_secret_references() {
+        let request = EffectRequest {
+            id: "shell".to_string(),
+            timestamp: "2026-03-19T00:00:00Z".to_string(),
+            effect_type: EffectType::ShellWrite,
+            source: EffectSource::Agent,
+            source_id: None,
+            payload: EffectPayload {
+                command: Some("gh".to_string()),
+                env: Some(HashMap::from([
+                    (
+                        "GITHUB_TOKEN".to_string(),
+                        "${secret:GITHUB_TOKEN}".to_string(),
+                    ),
+                    ("NPM_TOKEN".to_string(), "npm_plaintext".to_string()),
+                    ("PATH".to_string(), "/usr/bin".to_string()),
+                ])),
+                ..Default::default()
+            },
+            context: None,
+            scope: None,
+        };
+
+        let mut event = AuditEvent::confirmed(&request, false);
+        assert_eq!(event.redact(), vec!["request.payload.env.NPM_TOKEN"]);
+        let env = event.request.payload.env.expect("env");
+        assert_eq!(env["NPM_TOKEN"], REDACTED);
+        assert_eq!(env["GITHUB_TOKEN"], "${secret:GITHUB_TOKEN}");
+        assert_eq!(env["PATH"], "/usr/bin");
+    }
+}
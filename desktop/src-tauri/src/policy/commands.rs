@@ -3,18 +3,24 @@
 //! Exposes PolicyEngine functionality via Tauri invoke commands.
 //! Handles effect requests, user confirmations, and audit logging.
 
-use super::audit::{AuditEvent, AuditSink};
-use super::engine::{PolicyDecision, PolicyEngine, PolicyOutcome};
+use super::audit::{
+    find_audit_event, verify_audit_chain as verify_chain, AuditChainReport, AuditEvent, AuditSink,
+    ExecutionResult, FileAuditSink, RecentAuditEvents, MAX_RECENT_AUDIT_EVENTS,
+};
+use super::engine::{EffectivePolicy, PolicyDecision, PolicyEngine, PolicyOutcome};
 use super::types::{
-    AgentDelegation, AgentIdentity, ConfirmationPolicy, EffectRequest, EffectResponse, EffectType,
-    McpGatewayDecision, RuntimeSecurityAlert,
+    path_rule_matches, AgentDelegation, AgentIdentity, ConfirmationPolicy, EffectRequest,
+    EffectResponse, EffectScope, EffectType, McpGatewayDecision, PolicyConfig, RememberedDenial,
+    RuntimeSecurityAlert,
 };
 use crate::sidecar::{forward_effect_response_to_sidecar, SidecarState};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tauri::State;
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tauri::{AppHandle, Emitter, State};
 use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 
@@ -31,6 +37,36 @@ pub struct PolicyEngineState {
     pub delegations: Arc<Mutex<Vec<AgentDelegation>>>,
     pub mcp_decisions: Arc<Mutex<Vec<McpGatewayDecision>>>,
     pub runtime_alerts: Arc<Mutex<Vec<RuntimeSecurityAlert>>>,
+    /// Where edits to the policy config are persisted; `None` keeps them in memory
+    config_path: Option<PathBuf>,
+    /// JSONL file audit events are written to, when persisted
+    audit_log_path: Option<PathBuf>,
+    /// Recent events, filled in by the audit sink
+    recent_audit_events: Arc<std::sync::Mutex<RecentAuditEvents>>,
+    /// Where appended events are streamed as `audit-event`, once set
+    audit_stream: Arc<OnceLock<AppHandle>>,
+}
+
+/// Redacts each event, then writes it through to the configured sink, keeps a
+/// copy in the recent-events buffer and streams it to the UI.
+struct RecordingAuditSink {
+    inner: Box<dyn AuditSink + Send>,
+    recent: Arc<std::sync::Mutex<RecentAuditEvents>>,
+    stream: Arc<OnceLock<AppHandle>>,
+}
+
+impl AuditSink for RecordingAuditSink {
+    fn log(&mut self, mut event: AuditEvent) -> IoResult<()> {
+        event.redact();
+        let result = self.inner.log(event.clone());
+        if let Ok(mut recent) = self.recent.lock() {
+            let stored = recent.push(event);
+            if let Some(app) = self.stream.get() {
+                let _ = app.emit("audit-event", &stored);
+            }
+        }
+        result
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -38,19 +74,270 @@ pub struct PendingConfirmation {
     pub request: EffectRequest,
     pub outcome: PolicyOutcome,
     pub _requested_at: String,
+    /// Later requests for the identical effect, answered by the same decision
+    pub coalesced: Vec<EffectRequest>,
+}
+
+/// `response` for the confirmed request, then a copy for each coalesced one
+fn group_responses(response: EffectResponse, coalesced: &[EffectRequest]) -> Vec<EffectResponse> {
+    let copies: Vec<_> = coalesced
+        .iter()
+        .map(|request| EffectResponse {
+            request_id: request.id.clone(),
+            ..response.clone()
+        })
+        .collect();
+    std::iter::once(response).chain(copies).collect()
 }
 
 impl PolicyEngineState {
     pub fn new(audit_sink: Box<dyn AuditSink + Send>) -> Self {
         let config = super::types::PolicyConfig::default_config();
+        let recent_audit_events = Arc::new(std::sync::Mutex::new(RecentAuditEvents::new(
+            MAX_RECENT_AUDIT_EVENTS,
+        )));
+        let audit_stream = Arc::new(OnceLock::new());
         Self {
             engine: Arc::new(Mutex::new(PolicyEngine::new(config))),
             pending_confirmations: Arc::new(Mutex::new(HashMap::new())),
-            audit_sink: Arc::new(Mutex::new(audit_sink)),
+            audit_sink: Arc::new(Mutex::new(Box::new(RecordingAuditSink {
+                inner: audit_sink,
+                recent: recent_audit_events.clone(),
+                stream: audit_stream.clone(),
+            }))),
             identities: Arc::new(Mutex::new(HashMap::new())),
             delegations: Arc::new(Mutex::new(Vec::new())),
             mcp_decisions: Arc::new(Mutex::new(Vec::new())),
             runtime_alerts: Arc::new(Mutex::new(Vec::new())),
+            config_path: None,
+            audit_log_path: None,
+            recent_audit_events,
+            audit_stream,
+        }
+    }
+
+    /// Write audit events as JSONL to `path` so they can be looked up later.
+    pub fn with_audit_log(mut self, path: PathBuf) -> Self {
+        self.set_file_audit_sink(FileAuditSink::new(path.clone()));
+        self.audit_log_path = Some(path);
+        self
+    }
+
+    /// Hash-chain the audit log set by `with_audit_log` when `enabled`; off
+    /// unless asked for.
+    pub fn with_audit_hash_chain(mut self, enabled: bool) -> Self {
+        if let (true, Some(path)) = (enabled, self.audit_log_path.clone()) {
+            self.set_file_audit_sink(FileAuditSink::new(path).with_hash_chain());
+        }
+        self
+    }
+
+    fn set_file_audit_sink(&mut self, sink: FileAuditSink) {
+        self.audit_sink = Arc::new(Mutex::new(Box::new(RecordingAuditSink {
+            inner: Box::new(sink),
+            recent: self.recent_audit_events.clone(),
+            stream: self.audit_stream.clone(),
+        })));
+    }
+
+    /// Emit each audit event as `audit-event` from now on.
+    pub fn stream_audit_events(&self, app: AppHandle) {
+        let _ = self.audit_stream.set(app);
+    }
+
+    /// Up to `limit` of the most recent audit events, newest first
+    pub fn recent_audit_events(&self, limit: usize) -> Vec<AuditEvent> {
+        self.recent_audit_events
+            .lock()
+            .map(|recent| recent.recent(limit))
+            .unwrap_or_default()
+    }
+
+    /// JSONL file audit events are written to, if they are persisted.
+    pub fn audit_log_path(&self) -> Option<&Path> {
+        self.audit_log_path.as_deref()
+    }
+
+    /// Log how an approved effect turned out once it was carried out.
+    pub async fn record_execution(&self, request: &EffectRequest, result: &ExecutionResult) {
+        if !result.success {
+            warn!(
+                "Effect {} failed after {}ms: {}",
+                request.id,
+                result.duration_ms,
+                result.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+        let mut audit = self.audit_sink.lock().await;
+        let _ = audit.log(AuditEvent::executed(request, result));
+    }
+
+    /// Persist policy config edits to `path`, starting from the config saved
+    /// there if it can be read.
+    pub fn with_config_path(mut self, path: PathBuf) -> Self {
+        match std::fs::read_to_string(&path).map(|raw| serde_json::from_str::<PolicyConfig>(&raw)) {
+            Ok(Ok(config)) => {
+                info!("Loaded policy config from {}", path.display());
+                self.engine = Arc::new(Mutex::new(PolicyEngine::new(config)));
+            }
+            Ok(Err(e)) => warn!(
+                "Ignoring unreadable policy config {}: {}",
+                path.display(),
+                e
+            ),
+            Err(_) => {}
+        }
+        self.config_path = Some(path);
+        self
+    }
+
+    fn persist_config(&self, config: &PolicyConfig) -> Result<(), String> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Wait for the user's decision on `request`. A request identical to one
+    /// already pending (see `EffectRequest::same_effect`) joins it instead of
+    /// prompting again. Returns whether it was coalesced.
+    pub async fn queue_confirmation(
+        &self,
+        request: &EffectRequest,
+        outcome: &PolicyOutcome,
+    ) -> bool {
+        let mut pending = self.pending_confirmations.lock().await;
+        if let Some(existing) = pending
+            .values_mut()
+            .find(|p| p.request.same_effect(request))
+        {
+            existing.coalesced.push(request.clone());
+            return true;
+        }
+        pending.insert(
+            request.id.clone(),
+            PendingConfirmation {
+                request: request.clone(),
+                outcome: outcome.clone(),
+                _requested_at: Utc::now().to_rfc3339(),
+                coalesced: Vec::new(),
+            },
+        );
+        false
+    }
+
+    /// Approve the pending confirmation `input.request_id` and the requests
+    /// coalesced into it, logging one `confirmed` event for the group.
+    /// Returns a response per request, the confirmed one first.
+    pub async fn resolve_confirmation(
+        &self,
+        input: &ConfirmEffectInput,
+    ) -> Result<Vec<EffectResponse>, String> {
+        let pending = self
+            .pending_confirmations
+            .lock()
+            .await
+            .remove(&input.request_id)
+            .ok_or_else(|| format!("No pending confirmation found for {}", input.request_id))?;
+        let request = pending.request.clone();
+        let coalesced = pending.coalesced.clone();
+
+        let built = {
+            let mut engine = self.engine.lock().await;
+            let built = build_confirmation_response(&engine, pending.clone(), input);
+            if built.is_ok() {
+                // Only the request shown in the dialog had its secrets approved.
+                engine.grant_secrets(&request);
+                if let Some(window) = engine.open_auto_approve_window(&request, Utc::now()) {
+                    debug!(
+                        "Auto-approving requests like {} until {}",
+                        request.id, window.expires_at
+                    );
+                }
+            }
+            built
+        };
+        let response = match built {
+            Ok(response) => response,
+            Err(err) => {
+                // Nothing was decided; leave the dialog answerable.
+                self.pending_confirmations
+                    .lock()
+                    .await
+                    .insert(input.request_id.clone(), pending);
+                return Err(err);
+            }
+        };
+
+        {
+            let mut audit = self.audit_sink.lock().await;
+            let mut event =
+                AuditEvent::confirmed(&request, input.remember).with_coalesced(&coalesced);
+            if input.modified_scope.is_some() {
+                event.response = Some(response.clone());
+            }
+            let _ = audit.log(event);
+        }
+
+        Ok(group_responses(response, &coalesced))
+    }
+
+    /// Deny the pending confirmation `input.request_id` and the requests
+    /// coalesced into it, logging one `denied` event for the group.
+    /// Returns a response per request, the denied one first.
+    pub async fn resolve_denial(
+        &self,
+        input: &DenyEffectInput,
+    ) -> Result<Vec<EffectResponse>, String> {
+        let pending = self
+            .pending_confirmations
+            .lock()
+            .await
+            .remove(&input.request_id)
+            .ok_or_else(|| format!("No pending confirmation found for {}", input.request_id))?;
+
+        let response = EffectResponse {
+            request_id: input.request_id.clone(),
+            timestamp: Utc::now().to_rfc3339(),
+            approved: false,
+            approval_type: None,
+            expires_at: None,
+            denial_reason: input.reason.clone().or(Some("user_denied".to_string())),
+            denial_code: Some("user_denied".to_string()),
+            modified_scope: None,
+        };
+
+        if input.remember {
+            let mut engine = self.engine.lock().await;
+            engine.remember_denial(&pending.request, input.reason.clone());
+            if let Err(e) = self.persist_config(&engine.config) {
+                warn!("Failed to save remembered denial: {}", e);
+            }
+        }
+
+        {
+            let mut audit = self.audit_sink.lock().await;
+            let _ = audit.log(
+                AuditEvent::denied(&pending.request, input.reason.as_deref())
+                    .with_coalesced(&pending.coalesced),
+            );
+        }
+
+        Ok(group_responses(response, &pending.coalesced))
+    }
+
+    /// Why the engine refuses `request` outright (blocked effect type,
+    /// blocklist, or remembered denial), if it does. Confirmation policies are
+    /// not considered, so this suits actions the user started themselves.
+    pub async fn outright_denial(&self, request: &EffectRequest) -> Option<String> {
+        let engine = self.engine.lock().await;
+        match engine.evaluate(request).decision {
+            PolicyDecision::Denied { reason, code } => Some(format!("{}: {}", code, reason)),
+            _ => None,
         }
     }
 }
@@ -66,6 +353,7 @@ pub struct ConfirmationRequest {
     pub effect_type: String,
     pub source: String,
     pub source_id: Option<String>,
+    pub fingerprint: String,
     pub description: String,
     pub details: HashMap<String, serde_json::Value>,
     pub risk_level: u8,
@@ -79,6 +367,9 @@ impl ConfirmationRequest {
         if let Some(ref path) = request.payload.path {
             details.insert("path".to_string(), serde_json::json!(path));
         }
+        if let Some(display_path) = request.display_path() {
+            details.insert("displayPath".to_string(), serde_json::json!(display_path));
+        }
         if let Some(ref command) = request.payload.command {
             details.insert("command".to_string(), serde_json::json!(command));
         }
@@ -100,6 +391,7 @@ impl ConfirmationRequest {
             super::types::EffectSource::Agent => "agent",
             super::types::EffectSource::Toolpack => "toolpack",
             super::types::EffectSource::ClaudeSkill => "claude_skill",
+            super::types::EffectSource::User => "user",
         };
 
         Self {
@@ -107,6 +399,7 @@ impl ConfirmationRequest {
             effect_type: request.effect_type.as_str().to_string(),
             source: source.to_string(),
             source_id: request.source_id.clone(),
+            fingerprint: request.fingerprint(),
             description,
             details,
             risk_level: Self::calculate_risk(&request.effect_type),
@@ -128,7 +421,7 @@ impl ConfirmationRequest {
     }
 }
 
-fn is_host_folder_request(request: &EffectRequest) -> bool {
+fn is_host_folder_request(request: &EffectRequest, root: Option<&str>) -> bool {
     let Some(target_path) = &request.payload.path else {
         return false;
     };
@@ -139,16 +432,15 @@ fn is_host_folder_request(request: &EffectRequest) -> bool {
         return false;
     };
 
-    let target = std::path::PathBuf::from(target_path);
     !workspace_paths
         .iter()
-        .map(std::path::PathBuf::from)
-        .any(|workspace| target.starts_with(&workspace))
+        .any(|rule| path_rule_matches(rule, target_path, root))
 }
 
 fn approval_type_for_confirmation(
     input: &ConfirmEffectInput,
     request: &EffectRequest,
+    root: Option<&str>,
 ) -> Option<ConfirmationPolicy> {
     if !input.remember {
         return None;
@@ -156,7 +448,7 @@ fn approval_type_for_confirmation(
 
     match request.effect_type {
         EffectType::FilesystemRead | EffectType::FilesystemWrite
-            if is_host_folder_request(request) =>
+            if is_host_folder_request(request, root) =>
         {
             Some(ConfirmationPolicy::Permanent)
         }
@@ -164,6 +456,27 @@ fn approval_type_for_confirmation(
     }
 }
 
+fn build_confirmation_response(
+    engine: &PolicyEngine,
+    pending: PendingConfirmation,
+    input: &ConfirmEffectInput,
+) -> Result<EffectResponse, String> {
+    let mut response = engine.to_response(pending.outcome, true);
+    if let Some(approval_type) =
+        approval_type_for_confirmation(input, &pending.request, engine.workspace_root())
+    {
+        response.approval_type = Some(approval_type);
+    }
+    // The sidecar remembers the response as-is, so a narrowed scope also
+    // bounds any remembered approval. It may only narrow what was asked for.
+    if let Some(scope) = &input.modified_scope {
+        let proposed = engine.expand_scope_template(scope.clone());
+        let original = response.modified_scope.clone().unwrap_or_default();
+        response.modified_scope = Some(original.narrowed_by(&proposed, engine.workspace_root())?);
+    }
+    Ok(response)
+}
+
 // ============================================================================
 // Input Types
 // ============================================================================
@@ -173,6 +486,9 @@ fn approval_type_for_confirmation(
 pub struct ConfirmEffectInput {
     pub request_id: String,
     pub remember: bool,
+    /// Narrower scope chosen by the user, replacing the engine's computed scope
+    #[serde(default)]
+    pub modified_scope: Option<EffectScope>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -180,6 +496,8 @@ pub struct ConfirmEffectInput {
 pub struct DenyEffectInput {
     pub request_id: String,
     pub reason: Option<String>,
+    #[serde(default)]
+    pub remember: bool,
 }
 
 // ============================================================================
@@ -189,18 +507,30 @@ pub struct DenyEffectInput {
 /// Request approval for an effect
 #[tauri::command]
 pub async fn request_effect(
-    request: EffectRequest,
+    mut request: EffectRequest,
     state: State<'_, PolicyEngineState>,
 ) -> Result<EffectResponse, String> {
+    request.normalize_paths();
     info!(
         "Effect request received: {:?} from {:?}",
         request.effect_type, request.source
     );
 
     // Evaluate with policy engine
-    let outcome = {
+    let now = Utc::now();
+    let (outcome, auto_approve_window) = {
         let engine = state.engine.lock().await;
-        engine.evaluate(&request)
+        let outcome = engine.evaluate_at(&request, now);
+        let window = match outcome.decision {
+            // `Never` would have been approved anyway.
+            PolicyDecision::Approved {
+                ref approval_type, ..
+            } if *approval_type != ConfirmationPolicy::Never => {
+                engine.auto_approve_window_for(&request, now).cloned()
+            }
+            _ => None,
+        };
+        (outcome, window)
     };
 
     debug!("Policy decision: {:?}", outcome.decision);
@@ -209,6 +539,13 @@ pub async fn request_effect(
     {
         let mut audit = state.audit_sink.lock().await;
         let _ = audit.log(AuditEvent::request(&request, &outcome));
+        if let Some(window) = &auto_approve_window {
+            info!(
+                "Effect auto-approved by trust window until {}: {}",
+                window.expires_at, request.id
+            );
+            let _ = audit.log(AuditEvent::auto_approved(&request, window));
+        }
     }
 
     match &outcome.decision {
@@ -225,15 +562,10 @@ pub async fn request_effect(
             info!("Effect requires confirmation: {}", request.id);
 
             // Store pending confirmation
-            {
-                let mut pending = state.pending_confirmations.lock().await;
-                pending.insert(
-                    request.id.clone(),
-                    PendingConfirmation {
-                        request: request.clone(),
-                        outcome: outcome.clone(),
-                        _requested_at: Utc::now().to_rfc3339(),
-                    },
+            if state.queue_confirmation(&request, &outcome).await {
+                info!(
+                    "Effect {} joined a pending confirmation for the same effect",
+                    request.id
                 );
             }
 
@@ -278,43 +610,21 @@ pub async fn confirm_effect(
         input.request_id, input.remember
     );
 
-    // Get pending confirmation
-    let pending = {
-        let mut pending_map = state.pending_confirmations.lock().await;
-        pending_map.remove(&input.request_id)
-    };
-
-    let Some(pending) = pending else {
-        return Err(format!(
-            "No pending confirmation found for {}",
-            input.request_id
-        ));
-    };
-
-    // Build response
-    let engine = state.engine.lock().await;
-    let mut response = engine.to_response(pending.outcome, true);
-    if let Some(approval_type) = approval_type_for_confirmation(&input, &pending.request) {
-        response.approval_type = Some(approval_type);
-    }
-
-    // Log to audit
-    {
-        let mut audit = state.audit_sink.lock().await;
-        let _ = audit.log(AuditEvent::confirmed(&pending.request, input.remember));
-    }
-
-    if let Err(e) = forward_effect_response_to_sidecar(&sidecar_state, &response) {
-        warn!("Failed to forward effect-confirmed to sidecar: {}", e);
+    let responses = state.resolve_confirmation(&input).await?;
+    for response in &responses {
+        if let Err(e) = forward_effect_response_to_sidecar(&sidecar_state, response) {
+            warn!("Failed to forward effect-confirmed to sidecar: {}", e);
+        }
     }
 
-    Ok(response)
+    Ok(responses.into_iter().next().expect("confirmed response"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::policy::{EffectContext, EffectPayload, EffectScope, EffectSource};
+    use crate::policy::types::PolicyConfig;
+    use crate::policy::{ConsoleAuditSink, EffectContext, EffectPayload, EffectSource};
 
     fn make_request(effect_type: EffectType, path: Option<&str>) -> EffectRequest {
         EffectRequest {
@@ -333,6 +643,7 @@ mod tests {
                 url: None,
                 method: None,
                 headers: None,
+                env: None,
                 description: Some("test".to_string()),
             },
             context: Some(EffectContext {
@@ -353,10 +664,11 @@ mod tests {
         let input = ConfirmEffectInput {
             request_id: "request-1".to_string(),
             remember: true,
+            modified_scope: None,
         };
 
         assert_eq!(
-            approval_type_for_confirmation(&input, &request),
+            approval_type_for_confirmation(&input, &request, Some("/tmp/workspace")),
             Some(ConfirmationPolicy::Permanent)
         );
     }
@@ -367,13 +679,231 @@ mod tests {
         let input = ConfirmEffectInput {
             request_id: "request-1".to_string(),
             remember: true,
+            modified_scope: None,
         };
 
         assert_eq!(
-            approval_type_for_confirmation(&input, &request),
+            approval_type_for_confirmation(&input, &request, Some("/tmp/workspace")),
             Some(ConfirmationPolicy::Session)
         );
     }
+
+    fn pending_for(request: EffectRequest) -> (PolicyEngine, PendingConfirmation) {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+        engine.set_workspace_root(Some("/tmp/workspace".to_string()));
+        let outcome = engine.evaluate(&request);
+        let pending = PendingConfirmation {
+            request,
+            outcome,
+            _requested_at: "2026-03-19T00:00:00Z".to_string(),
+            coalesced: Vec::new(),
+        };
+        (engine, pending)
+    }
+
+    #[tokio::test]
+    async fn confirming_a_coalesced_group_logs_one_decision() {
+        let state = PolicyEngineState::new(Box::new(ConsoleAuditSink));
+        let base = make_request(EffectType::ShellWrite, None);
+        let outcome = state.engine.lock().await.evaluate(&base);
+        assert!(matches!(
+            outcome.decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+
+        let mut coalesced = Vec::new();
+        for id in ["request-1", "request-2", "request-3"] {
+            let request = EffectRequest {
+                id: id.to_string(),
+                ..base.clone()
+            };
+            coalesced.push(state.queue_confirmation(&request, &outcome).await);
+        }
+        assert_eq!(coalesced, [false, true, true]);
+        assert_eq!(state.pending_confirmations.lock().await.len(), 1);
+
+        // Same fingerprint, but a different env or source is its own prompt
+        let mut with_env = EffectRequest {
+            id: "request-env".to_string(),
+            ..base.clone()
+        };
+        with_env.payload.env = Some(HashMap::from([(
+            "TOKEN".to_string(),
+            "${secret:GITHUB_TOKEN}".to_string(),
+        )]));
+        assert_eq!(with_env.fingerprint(), base.fingerprint());
+        assert!(!state.queue_confirmation(&with_env, &outcome).await);
+        let from_toolpack = EffectRequest {
+            id: "request-toolpack".to_string(),
+            source_id: Some("toolpack-x".to_string()),
+            ..base.clone()
+        };
+        assert!(!state.queue_confirmation(&from_toolpack, &outcome).await);
+        assert_eq!(state.pending_confirmations.lock().await.len(), 3);
+
+        let input = ConfirmEffectInput {
+            request_id: "request-1".to_string(),
+            remember: false,
+            modified_scope: None,
+        };
+        let responses = state.resolve_confirmation(&input).await.unwrap();
+        let ids: Vec<_> = responses.iter().map(|r| r.request_id.as_str()).collect();
+        assert_eq!(ids, ["request-1", "request-2", "request-3"]);
+        assert!(responses.iter().all(|r| r.approved));
+
+        let confirmed: Vec<_> = state
+            .recent_audit_events(10)
+            .into_iter()
+            .filter(|event| event.event_type == "confirmed")
+            .collect();
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(
+            confirmed[0].request_ids,
+            ["request-1", "request-2", "request-3"]
+        );
+        assert_eq!(state.pending_confirmations.lock().await.len(), 2);
+    }
+
+    #[test]
+    fn user_narrowed_scope_is_reflected_in_response() {
+        let (engine, pending) = pending_for(make_request(
+            EffectType::FilesystemWrite,
+            Some("/tmp/workspace/src/main.rs"),
+        ));
+        let input = ConfirmEffectInput {
+            request_id: "request-1".to_string(),
+            remember: true,
+            modified_scope: Some(EffectScope {
+                workspace_paths: Some(vec!["/tmp/workspace/src/main.rs".to_string()]),
+                ..Default::default()
+            }),
+        };
+
+        let response = build_confirmation_response(&engine, pending, &input).expect("narrower");
+        assert!(response.approved);
+        assert_eq!(response.approval_type, Some(ConfirmationPolicy::Session));
+        assert_eq!(
+            response
+                .modified_scope
+                .and_then(|scope| scope.workspace_paths),
+            Some(vec!["/tmp/workspace/src/main.rs".to_string()])
+        );
+    }
+
+    #[test]
+    fn user_scope_may_only_narrow_the_requested_one() {
+        let (engine, pending) = pending_for(make_request(
+            EffectType::FilesystemWrite,
+            Some("/tmp/workspace/src/main.rs"),
+        ));
+        let confirm = |scope: EffectScope| ConfirmEffectInput {
+            request_id: "request-1".to_string(),
+            remember: true,
+            modified_scope: Some(scope),
+        };
+
+        let wider = confirm(EffectScope {
+            workspace_paths: Some(vec!["/".to_string()]),
+            ..Default::default()
+        });
+        let error = build_confirmation_response(&engine, pending.clone(), &wider)
+            .expect_err("widening is refused");
+        assert!(error.starts_with("scope_not_narrower"), "{error}");
+
+        let narrower = confirm(EffectScope {
+            workspace_paths: Some(vec!["src".to_string()]),
+            excluded_paths: Some(vec!["src/generated".to_string()]),
+            timeout_ms: Some(1_000),
+            ..Default::default()
+        });
+        let scope = build_confirmation_response(&engine, pending, &narrower)
+            .expect("narrower")
+            .modified_scope
+            .expect("scope");
+        assert_eq!(scope.workspace_paths, Some(vec!["src".to_string()]));
+        assert_eq!(
+            scope.excluded_paths,
+            Some(vec!["src/generated".to_string()])
+        );
+        assert_eq!(scope.timeout_ms, Some(1_000));
+    }
+
+    #[tokio::test]
+    async fn refused_scope_leaves_the_confirmation_pending() {
+        let state = PolicyEngineState::new(Box::new(ConsoleAuditSink));
+        let request = make_request(
+            EffectType::FilesystemWrite,
+            Some("/tmp/workspace/src/main.rs"),
+        );
+        let outcome = state.engine.lock().await.evaluate(&request);
+        state.queue_confirmation(&request, &outcome).await;
+
+        let input = ConfirmEffectInput {
+            request_id: request.id.clone(),
+            remember: false,
+            modified_scope: Some(EffectScope {
+                domain_allowlist: Some(vec!["example.com".to_string()]),
+                workspace_paths: Some(vec!["/etc".to_string()]),
+                ..Default::default()
+            }),
+        };
+        assert!(state.resolve_confirmation(&input).await.is_err());
+        assert!(state
+            .pending_confirmations
+            .lock()
+            .await
+            .contains_key(&request.id));
+    }
+
+    #[tokio::test]
+    async fn remembered_denial_survives_a_reload() {
+        let path = std::env::temp_dir().join(format!(
+            "coworkany-policy-denials-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let state =
+            PolicyEngineState::new(Box::new(ConsoleAuditSink)).with_config_path(path.clone());
+        let request = make_request(EffectType::ShellWrite, None);
+        let outcome = state.engine.lock().await.evaluate(&request);
+        state.queue_confirmation(&request, &outcome).await;
+        state
+            .resolve_denial(&DenyEffectInput {
+                request_id: request.id.clone(),
+                reason: Some("not here".to_string()),
+                remember: true,
+            })
+            .await
+            .expect("deny");
+
+        let reloaded =
+            PolicyEngineState::new(Box::new(ConsoleAuditSink)).with_config_path(path.clone());
+        match reloaded.engine.lock().await.evaluate(&request).decision {
+            PolicyDecision::Denied { code, .. } => assert_eq!(code, "remembered_denial"),
+            other => panic!("expected the remembered denial, got {:?}", other),
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn engine_scope_is_kept_without_user_override() {
+        let (engine, pending) = pending_for(make_request(
+            EffectType::FilesystemWrite,
+            Some("/tmp/workspace/src/main.rs"),
+        ));
+        let input = ConfirmEffectInput {
+            request_id: "request-1".to_string(),
+            remember: false,
+            modified_scope: None,
+        };
+
+        let response = build_confirmation_response(&engine, pending, &input).expect("response");
+        assert_eq!(
+            response
+                .modified_scope
+                .and_then(|scope| scope.workspace_paths),
+            Some(vec!["/tmp/workspace".to_string()])
+        );
+    }
 }
 
 /// User denies an effect
@@ -383,46 +913,19 @@ pub async fn deny_effect(
     state: State<'_, PolicyEngineState>,
     sidecar_state: State<'_, SidecarState>,
 ) -> Result<EffectResponse, String> {
-    info!("Effect denied by user: {}", input.request_id);
-
-    // Get pending confirmation
-    let pending = {
-        let mut pending_map = state.pending_confirmations.lock().await;
-        pending_map.remove(&input.request_id)
-    };
-
-    let Some(pending) = pending else {
-        return Err(format!(
-            "No pending confirmation found for {}",
-            input.request_id
-        ));
-    };
-
-    let response = EffectResponse {
-        request_id: input.request_id.clone(),
-        timestamp: Utc::now().to_rfc3339(),
-        approved: false,
-        approval_type: None,
-        expires_at: None,
-        denial_reason: input.reason.clone().or(Some("user_denied".to_string())),
-        denial_code: Some("user_denied".to_string()),
-        modified_scope: None,
-    };
-
-    // Log to audit
-    {
-        let mut audit = state.audit_sink.lock().await;
-        let _ = audit.log(AuditEvent::denied(
-            &pending.request,
-            input.reason.as_deref(),
-        ));
-    }
+    info!(
+        "Effect denied by user: {} (remember: {})",
+        input.request_id, input.remember
+    );
 
-    if let Err(e) = forward_effect_response_to_sidecar(&sidecar_state, &response) {
-        warn!("Failed to forward effect-denied to sidecar: {}", e);
+    let responses = state.resolve_denial(&input).await?;
+    for response in &responses {
+        if let Err(e) = forward_effect_response_to_sidecar(&sidecar_state, response) {
+            warn!("Failed to forward effect-denied to sidecar: {}", e);
+        }
     }
 
-    Ok(response)
+    Ok(responses.into_iter().next().expect("denied response"))
 }
 
 /// Get pending confirmations (for UI display)
@@ -446,6 +949,245 @@ pub async fn get_pending_confirmations(
     Ok(requests)
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedEffect {
+    pub request_id: String,
+    pub decision: PolicyDecision,
+}
+
+/// Preview how current policy would treat a batch of planned effects.
+/// Nothing is queued, audited, or emitted.
+#[tauri::command]
+pub async fn simulate_effects(
+    state: State<'_, PolicyEngineState>,
+    mut requests: Vec<EffectRequest>,
+) -> Result<Vec<SimulatedEffect>, String> {
+    requests.iter_mut().for_each(EffectRequest::normalize_paths);
+    let engine = state.engine.lock().await;
+    Ok(engine
+        .simulate(&requests)
+        .into_iter()
+        .map(|(request_id, decision)| SimulatedEffect {
+            request_id,
+            decision,
+        })
+        .collect())
+}
+
+/// Resolved policy for one effect type, including any override configured
+/// for `workspace`, for the policy inspector.
+#[tauri::command]
+pub async fn get_effective_policy(
+    state: State<'_, PolicyEngineState>,
+    effect_type: EffectType,
+    workspace: Option<String>,
+) -> Result<EffectivePolicy, String> {
+    let engine = state.engine.lock().await;
+    Ok(engine.effective_policy(&effect_type, workspace.as_deref()))
+}
+
+/// Set how long a manual confirm auto-approves matching requests (0 disables)
+#[tauri::command]
+pub async fn set_auto_approve_window(
+    state: State<'_, PolicyEngineState>,
+    seconds: u64,
+) -> Result<(), String> {
+    let mut engine = state.engine.lock().await;
+    engine.config.auto_approve_window_secs = seconds;
+    state.persist_config(&engine.config)?;
+    info!("Auto-approve window set to {}s", seconds);
+    Ok(())
+}
+
+/// Named scopes that requests and approvals can reference
+#[tauri::command]
+pub async fn get_scope_templates(
+    state: State<'_, PolicyEngineState>,
+) -> Result<HashMap<String, EffectScope>, String> {
+    let engine = state.engine.lock().await;
+    Ok(engine.config.scope_templates.clone())
+}
+
+/// Add or replace the scope template `name`
+#[tauri::command]
+pub async fn save_scope_template(
+    state: State<'_, PolicyEngineState>,
+    name: String,
+    mut scope: EffectScope,
+) -> Result<HashMap<String, EffectScope>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Template name must not be empty".to_string());
+    }
+    // Templates don't chain.
+    scope.template = None;
+
+    let mut engine = state.engine.lock().await;
+    engine
+        .config
+        .scope_templates
+        .insert(name.to_string(), scope);
+    state.persist_config(&engine.config)?;
+    info!("Saved scope template: {}", name);
+    Ok(engine.config.scope_templates.clone())
+}
+
+/// Command prefixes the policy allows and denies, as shown in settings.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandLists {
+    pub allowed: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+impl CommandLists {
+    fn from_config(config: &PolicyConfig) -> Self {
+        Self {
+            allowed: config.allowlists.commands.clone(),
+            denied: config.blocklists.commands.clone(),
+        }
+    }
+}
+
+/// Persist the edited config and tell the UI the command lists changed
+fn commit_command_lists(
+    app_handle: &AppHandle,
+    state: &PolicyEngineState,
+    config: &PolicyConfig,
+) -> Result<CommandLists, String> {
+    state.persist_config(config)?;
+    let lists = CommandLists::from_config(config);
+    let _ = app_handle.emit("policy-changed", &lists);
+    Ok(lists)
+}
+
+/// Allow a shell command prefix without confirmation
+#[tauri::command]
+pub async fn add_allowed_command(
+    app_handle: AppHandle,
+    state: State<'_, PolicyEngineState>,
+    command: String,
+) -> Result<CommandLists, String> {
+    let mut engine = state.engine.lock().await;
+    let entry = engine.add_allowed_command(&command)?;
+    info!("Added allowed command: {}", entry);
+    commit_command_lists(&app_handle, &state, &engine.config)
+}
+
+/// Remove a shell command prefix from the allowlist
+#[tauri::command]
+pub async fn remove_allowed_command(
+    app_handle: AppHandle,
+    state: State<'_, PolicyEngineState>,
+    command: String,
+) -> Result<CommandLists, String> {
+    let mut engine = state.engine.lock().await;
+    if !engine.remove_allowed_command(&command) {
+        return Err(format!("Command not in allowlist: {}", command.trim()));
+    }
+    info!("Removed allowed command: {}", command.trim());
+    commit_command_lists(&app_handle, &state, &engine.config)
+}
+
+/// Deny a shell command prefix outright
+#[tauri::command]
+pub async fn add_denied_command(
+    app_handle: AppHandle,
+    state: State<'_, PolicyEngineState>,
+    command: String,
+) -> Result<CommandLists, String> {
+    let mut engine = state.engine.lock().await;
+    let entry = engine.add_denied_command(&command)?;
+    info!("Added denied command: {}", entry);
+    commit_command_lists(&app_handle, &state, &engine.config)
+}
+
+/// One audit event with credentials blanked out, and which fields were.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventDetail {
+    pub event: AuditEvent,
+    pub redactions: Vec<String>,
+}
+
+/// Look up a single audit event by id for the detail view
+#[tauri::command]
+pub async fn get_audit_event(
+    state: State<'_, PolicyEngineState>,
+    id: String,
+) -> Result<AuditEventDetail, String> {
+    let path = state
+        .audit_log_path
+        .clone()
+        .ok_or_else(|| "Audit events are not persisted".to_string())?;
+    if !path.exists() {
+        return Err(format!("Audit event not found: {}", id));
+    }
+
+    let lookup_id = id.clone();
+    let event = tokio::task::spawn_blocking(move || find_audit_event(&path, &lookup_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let mut event = event.ok_or_else(|| format!("Audit event not found: {}", id))?;
+    let redactions = event.redact();
+    Ok(AuditEventDetail { event, redactions })
+}
+
+/// Check the persisted audit log's hash chain and report the first broken link
+#[tauri::command]
+pub async fn verify_audit_chain(
+    state: State<'_, PolicyEngineState>,
+) -> Result<AuditChainReport, String> {
+    let path = state
+        .audit_log_path
+        .clone()
+        .ok_or_else(|| "Audit events are not persisted".to_string())?;
+    if !path.exists() {
+        return Ok(AuditChainReport {
+            valid: true,
+            ..Default::default()
+        });
+    }
+    tokio::task::spawn_blocking(move || verify_chain(&path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Recent audit events from memory, newest first; all that are kept when
+/// `limit` is omitted
+#[tauri::command]
+pub async fn get_recent_audit_events(
+    state: State<'_, PolicyEngineState>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEvent>, String> {
+    Ok(state.recent_audit_events(limit.unwrap_or(MAX_RECENT_AUDIT_EVENTS)))
+}
+
+/// List denials the user asked to remember
+#[tauri::command]
+pub async fn list_remembered_denials(
+    state: State<'_, PolicyEngineState>,
+) -> Result<Vec<RememberedDenial>, String> {
+    let engine = state.engine.lock().await;
+    Ok(engine.remembered_denials().to_vec())
+}
+
+/// Forget one remembered denial, or all of them when no id is given
+#[tauri::command]
+pub async fn clear_remembered_denials(
+    denial_id: Option<String>,
+    state: State<'_, PolicyEngineState>,
+) -> Result<usize, String> {
+    let mut engine = state.engine.lock().await;
+    let removed = engine.clear_remembered_denials(denial_id.as_deref());
+    state.persist_config(&engine.config)?;
+    info!("Cleared {} remembered denial(s)", removed);
+    Ok(removed)
+}
+
 // ============================================================================
 // Identity and Security Commands
 // ============================================================================
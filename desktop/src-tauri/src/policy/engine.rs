@@ -1,10 +1,19 @@
 use super::types::{
+    is_absolute_path, path_rule_matches, resolve_workspace_path, AutoApproveWindow,
     ConfirmationPolicy, EffectRequest, EffectResponse, EffectScope, EffectType, PolicyConfig,
+    PolicyLists, RememberedDenial,
 };
-use chrono::Utc;
-use std::path::PathBuf;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "outcome",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
 pub enum PolicyDecision {
     Approved {
         approval_type: ConfirmationPolicy,
@@ -20,6 +29,20 @@ pub enum PolicyDecision {
     },
 }
 
+/// How the config resolves for one effect type, optionally inside a workspace.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectivePolicy {
+    pub effect_type: EffectType,
+    /// Root of the workspace override that applied, if any
+    pub workspace_override: Option<String>,
+    pub policy: ConfirmationPolicy,
+    /// Listed in `denied_effects`, globally or by the workspace
+    pub denied: bool,
+    pub allowlists: PolicyLists,
+    pub blocklists: PolicyLists,
+}
+
 #[derive(Debug, Clone)]
 pub struct PolicyOutcome {
     pub request_id: String,
@@ -30,16 +53,232 @@ pub struct PolicyOutcome {
 #[derive(Debug, Clone)]
 pub struct PolicyEngine {
     pub config: PolicyConfig,
+    auto_approve_windows: Vec<AutoApproveWindow>,
+    /// Secrets the user approved by confirming a shell request that
+    /// referenced them, keyed by `EffectRequest::secret_grant_key`
+    granted_secrets: HashMap<String, SecretGrant>,
+    /// Workspace whose override applies to evaluations, as known to the
+    /// desktop; never taken from the request, which the agent controls
+    workspace_root: Option<String>,
+}
+
+/// Secrets approved for one confirmed shell request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretGrant {
+    pub request_id: String,
+    pub names: Vec<String>,
 }
 
 impl PolicyEngine {
     pub fn new(config: PolicyConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            auto_approve_windows: Vec::new(),
+            granted_secrets: HashMap::new(),
+            workspace_root: None,
+        }
+    }
+
+    /// Evaluate requests under the override for `workspace_root` (the
+    /// ShadowFs workspace), or the global config alone with `None`.
+    pub fn set_workspace_root(&mut self, workspace_root: Option<String>) {
+        self.workspace_root = workspace_root;
+    }
+
+    /// Root that relative policy rules resolve against. Set from ShadowFs,
+    /// never from a request.
+    pub fn workspace_root(&self) -> Option<&str> {
+        self.workspace_root.as_deref()
+    }
+
+    /// Record that the user approved the secrets `request` references, for
+    /// one run of exactly that command, arguments and env.
+    pub fn grant_secrets(&mut self, request: &EffectRequest) {
+        let names = request.secret_references();
+        if names.is_empty() {
+            return;
+        }
+        self.granted_secrets.insert(
+            request.secret_grant_key(),
+            SecretGrant {
+                request_id: request.id.clone(),
+                names,
+            },
+        );
+    }
+
+    /// Use up the grant for the shell invocation with `key` (see
+    /// `shell_secret_grant_key`), if one was approved.
+    pub fn take_secret_grant(&mut self, key: &str) -> Option<SecretGrant> {
+        self.granted_secrets.remove(key)
+    }
+
+    /// Whether secret `name` may be injected into a shell effect: it is
+    /// allowlisted or in the grant taken for this run, and not blocked.
+    pub fn secret_injection_allowed(&self, name: &str, grant: Option<&SecretGrant>) -> bool {
+        let name = name.to_string();
+        !self.config.blocklists.secrets.contains(&name)
+            && (self.config.allowlists.secrets.contains(&name)
+                || grant.is_some_and(|grant| grant.names.contains(&name)))
+    }
+
+    /// Secret references in `request` that still need the user's approval.
+    fn unapproved_secrets(&self, request: &EffectRequest, allowlists: &PolicyLists) -> Vec<String> {
+        request
+            .secret_references()
+            .into_iter()
+            .filter(|name| !allowlists.secrets.contains(name))
+            .collect()
+    }
+
+    /// Resolve the confirmation policy, denial and lists for `effect_type`,
+    /// layering the override for `workspace` (matched by normalized root)
+    /// over the global config.
+    pub fn effective_policy(
+        &self,
+        effect_type: &EffectType,
+        workspace: Option<&str>,
+    ) -> EffectivePolicy {
+        let workspace = workspace.map(|path| resolve_workspace_path(path, None));
+        let workspace_override = workspace.as_ref().and_then(|workspace| {
+            self.config
+                .workspace_overrides
+                .iter()
+                .find(|(root, _)| resolve_workspace_path(root, None) == *workspace)
+        });
+
+        let mut effective = EffectivePolicy {
+            effect_type: effect_type.clone(),
+            workspace_override: None,
+            policy: self
+                .config
+                .default_policies
+                .get(effect_type)
+                .cloned()
+                .unwrap_or(ConfirmationPolicy::Always),
+            denied: self.config.denied_effects.contains(effect_type),
+            allowlists: self.config.allowlists.clone(),
+            blocklists: self.config.blocklists.clone(),
+        };
+        if let Some((root, overrides)) = workspace_override {
+            effective.workspace_override = Some(root.clone());
+            if let Some(policy) = overrides.default_policies.get(effect_type) {
+                effective.policy = policy.clone();
+            }
+            effective.denied |= overrides.denied_effects.contains(effect_type);
+            effective.allowlists = effective.allowlists.merged(&overrides.allowlists);
+            effective.blocklists = effective.blocklists.merged(&overrides.blocklists);
+        }
+        effective
+    }
+
+    /// Record a user denial so matching future requests are denied without prompting.
+    pub fn remember_denial(
+        &mut self,
+        request: &EffectRequest,
+        reason: Option<String>,
+    ) -> RememberedDenial {
+        if let Some(existing) = self
+            .config
+            .remembered_denials
+            .iter()
+            .find(|d| d.matches(request))
+        {
+            return existing.clone();
+        }
+
+        let denial = RememberedDenial {
+            id: Uuid::new_v4().to_string(),
+            fingerprint: request.fingerprint(),
+            effect_type: request.effect_type.clone(),
+            target: request.target().map(str::to_string),
+            reason,
+            created_at: Utc::now().to_rfc3339(),
+        };
+        self.config.remembered_denials.push(denial.clone());
+        denial
+    }
+
+    pub fn remembered_denials(&self) -> &[RememberedDenial] {
+        &self.config.remembered_denials
+    }
+
+    /// Remove one remembered denial by id, or all of them when `id` is `None`.
+    /// Returns the number of entries removed.
+    pub fn clear_remembered_denials(&mut self, id: Option<&str>) -> usize {
+        let before = self.config.remembered_denials.len();
+        match id {
+            Some(id) => self.config.remembered_denials.retain(|d| d.id != id),
+            None => self.config.remembered_denials.clear(),
+        }
+        before - self.config.remembered_denials.len()
+    }
+
+    /// Add a command prefix to the allowlist. Returns the stored (trimmed) entry.
+    pub fn add_allowed_command(&mut self, command: &str) -> Result<String, String> {
+        insert_command_entry(&mut self.config.allowlists.commands, command, "allowlist")
+    }
+
+    /// Remove a command prefix from the allowlist. Returns false if it was not listed.
+    pub fn remove_allowed_command(&mut self, command: &str) -> bool {
+        let command = command.trim();
+        let before = self.config.allowlists.commands.len();
+        self.config.allowlists.commands.retain(|c| c != command);
+        before != self.config.allowlists.commands.len()
+    }
+
+    /// Add a command prefix to the denylist. Returns the stored (trimmed) entry.
+    pub fn add_denied_command(&mut self, command: &str) -> Result<String, String> {
+        insert_command_entry(&mut self.config.blocklists.commands, command, "denylist")
+    }
+
+    /// Start (or restart) the auto-approve window for requests matching a
+    /// manually confirmed one. Returns `None` when the window is disabled.
+    pub fn open_auto_approve_window(
+        &mut self,
+        request: &EffectRequest,
+        now: DateTime<Utc>,
+    ) -> Option<AutoApproveWindow> {
+        let secs = self.config.auto_approve_window_secs;
+        if secs == 0 {
+            return None;
+        }
+
+        let fingerprint = request.fingerprint();
+        let shell_key = AutoApproveWindow::shell_key_for(request);
+        self.auto_approve_windows.retain(|w| {
+            (w.fingerprint != fingerprint || w.shell_key != shell_key) && w.is_open(now)
+        });
+        let window = AutoApproveWindow {
+            fingerprint,
+            shell_key,
+            effect_type: request.effect_type.clone(),
+            target: request.target().map(str::to_string),
+            expires_at: (now + Duration::seconds(secs as i64)).to_rfc3339(),
+        };
+        self.auto_approve_windows.push(window.clone());
+        Some(window)
+    }
+
+    /// The open auto-approve window covering `request`, if any.
+    pub fn auto_approve_window_for(
+        &self,
+        request: &EffectRequest,
+        now: DateTime<Utc>,
+    ) -> Option<&AutoApproveWindow> {
+        self.auto_approve_windows
+            .iter()
+            .find(|w| w.covers(request, now))
     }
 
     pub fn evaluate(&self, request: &EffectRequest) -> PolicyOutcome {
-        let now = Utc::now().to_rfc3339();
-        if self.config.denied_effects.contains(&request.effect_type) {
+        self.evaluate_at(request, Utc::now())
+    }
+
+    pub fn evaluate_at(&self, request: &EffectRequest, at: DateTime<Utc>) -> PolicyOutcome {
+        let now = at.to_rfc3339();
+        let effective = self.effective_policy(&request.effect_type, self.workspace_root.as_deref());
+        if effective.denied {
             return PolicyOutcome {
                 request_id: request.id.clone(),
                 timestamp: now,
@@ -50,7 +289,7 @@ impl PolicyEngine {
             };
         }
 
-        if self.is_blocklisted(request) {
+        if Self::is_blocklisted(request, &effective.blocklists, self.workspace_root()) {
             return PolicyOutcome {
                 request_id: request.id.clone(),
                 timestamp: now,
@@ -61,18 +300,69 @@ impl PolicyEngine {
             };
         }
 
-        let mut policy = self
+        if let Some(denial) = self
             .config
-            .default_policies
-            .get(&request.effect_type)
-            .cloned()
-            .unwrap_or(ConfirmationPolicy::Always);
+            .remembered_denials
+            .iter()
+            .find(|d| d.matches(request))
+        {
+            return PolicyOutcome {
+                request_id: request.id.clone(),
+                timestamp: now,
+                decision: PolicyDecision::Denied {
+                    reason: denial
+                        .reason
+                        .clone()
+                        .unwrap_or_else(|| "previously denied by user".to_string()),
+                    code: "remembered_denial".to_string(),
+                },
+            };
+        }
+
+        let template = match self.scope_template_for(request) {
+            Ok(template) => template,
+            Err(name) => {
+                return PolicyOutcome {
+                    request_id: request.id.clone(),
+                    timestamp: now,
+                    decision: PolicyDecision::Denied {
+                        reason: format!("unknown scope template: {}", name),
+                        code: "unknown_scope_template".to_string(),
+                    },
+                };
+            }
+        };
+
+        let mut policy = effective.policy;
 
         if self.requires_host_folder_confirmation(request) {
             policy = ConfirmationPolicy::Once;
         }
 
-        let modified_scope = self.apply_allowlists(request);
+        // A new secret reference is always shown to the user, even for
+        // effects that would otherwise pass silently.
+        let needs_secret_approval = !self
+            .unapproved_secrets(request, &effective.allowlists)
+            .is_empty();
+        if needs_secret_approval && policy == ConfirmationPolicy::Never {
+            policy = ConfirmationPolicy::Once;
+        }
+
+        let modified_scope = Self::apply_allowlists(request, template, &effective.allowlists);
+
+        if policy != ConfirmationPolicy::Always
+            && !needs_secret_approval
+            && self.auto_approve_window_for(request, at).is_some()
+        {
+            return PolicyOutcome {
+                request_id: request.id.clone(),
+                timestamp: now,
+                decision: PolicyDecision::Approved {
+                    approval_type: policy,
+                    modified_scope,
+                },
+            };
+        }
 
         match policy {
             ConfirmationPolicy::Never => PolicyOutcome {
@@ -97,6 +387,15 @@ impl PolicyEngine {
         }
     }
 
+    /// Evaluate a batch of planned effects against the current config. Like
+    /// `evaluate`, this records nothing, so it is safe for previews.
+    pub fn simulate(&self, requests: &[EffectRequest]) -> Vec<(String, PolicyDecision)> {
+        requests
+            .iter()
+            .map(|request| (request.id.clone(), self.evaluate(request).decision))
+            .collect()
+    }
+
     pub fn to_response(&self, outcome: PolicyOutcome, approved: bool) -> EffectResponse {
         let mut response = EffectResponse {
             request_id: outcome.request_id,
@@ -133,8 +432,11 @@ impl PolicyEngine {
         response
     }
 
-    fn is_blocklisted(&self, request: &EffectRequest) -> bool {
-        let blocklists = &self.config.blocklists;
+    fn is_blocklisted(
+        request: &EffectRequest,
+        blocklists: &PolicyLists,
+        root: Option<&str>,
+    ) -> bool {
         if let Some(command) = &request.payload.command {
             if blocklists.commands.iter().any(|c| command.starts_with(c)) {
                 return true;
@@ -145,17 +447,72 @@ impl PolicyEngine {
                 return true;
             }
         }
+        if request
+            .secret_references()
+            .iter()
+            .any(|name| blocklists.secrets.contains(name))
+        {
+            return true;
+        }
         if let Some(path) = &request.payload.path {
-            if blocklists.paths.iter().any(|p| path.starts_with(p)) {
+            if blocklists
+                .paths
+                .iter()
+                .any(|rule| blocklist_rule_matches(rule, path, root))
+            {
                 return true;
             }
         }
         false
     }
 
-    fn apply_allowlists(&self, request: &EffectRequest) -> Option<EffectScope> {
-        let allowlists = &self.config.allowlists;
+    /// The template `request` takes unset scope limits from: the one its
+    /// scope names, else the default for its effect type. `Err` carries a
+    /// name with no template behind it.
+    fn scope_template_for(&self, request: &EffectRequest) -> Result<Option<&EffectScope>, String> {
+        let name = request
+            .scope
+            .as_ref()
+            .and_then(|scope| scope.template.as_ref())
+            .or_else(|| {
+                self.config
+                    .default_scope_templates
+                    .get(&request.effect_type)
+            });
+        match name {
+            Some(name) => self
+                .config
+                .scope_templates
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| name.clone()),
+            None => Ok(None),
+        }
+    }
+
+    /// `scope` with the limits it leaves unset taken from the template it
+    /// names, for scopes chosen outside `evaluate` such as a remembered
+    /// approval. An unknown template name is left unresolved.
+    pub fn expand_scope_template(&self, mut scope: EffectScope) -> EffectScope {
+        if let Some(template) = scope
+            .template
+            .clone()
+            .and_then(|name| self.config.scope_templates.get(&name))
+        {
+            scope.fill_from(template);
+        }
+        scope
+    }
+
+    fn apply_allowlists(
+        request: &EffectRequest,
+        template: Option<&EffectScope>,
+        allowlists: &PolicyLists,
+    ) -> Option<EffectScope> {
         let mut scope = request.scope.clone().unwrap_or_default();
+        if let Some(template) = template {
+            scope.fill_from(template);
+        }
         if !allowlists.commands.is_empty() && scope.command_allowlist.is_none() {
             scope.command_allowlist = Some(allowlists.commands.clone());
         }
@@ -183,17 +540,43 @@ impl PolicyEngine {
             return false;
         };
 
-        let target = PathBuf::from(target_path);
         !workspace_paths
             .iter()
-            .map(PathBuf::from)
-            .any(|workspace| target.starts_with(&workspace))
+            .any(|rule| path_rule_matches(rule, target_path, self.workspace_root()))
     }
 }
 
+/// `path_rule_matches` for a blocklist rule. With no workspace root to pin a
+/// relative rule to, it is matched at any depth rather than not at all.
+fn blocklist_rule_matches(rule: &str, path: &str, root: Option<&str>) -> bool {
+    if root.is_some() || is_absolute_path(rule) {
+        return path_rule_matches(rule, path, root);
+    }
+    let rule = rule.trim().trim_start_matches("./");
+    path_rule_matches(&format!("**/{}", rule), path, None)
+        || (!rule.contains('*') && path_rule_matches(&format!("**/{}/**", rule), path, None))
+}
+
+fn insert_command_entry(
+    list: &mut Vec<String>,
+    command: &str,
+    list_name: &str,
+) -> Result<String, String> {
+    let command = command.trim();
+    if command.is_empty() {
+        return Err("Command must not be empty".to_string());
+    }
+    if list.iter().any(|c| c == command) {
+        return Err(format!("Command already in {}: {}", list_name, command));
+    }
+    list.push(command.to_string());
+    Ok(command.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::policy::types::{shell_secret_grant_key, PolicyOverrides};
     use crate::policy::{EffectContext, EffectPayload, EffectSource};
 
     fn make_request(path: &str, workspace_paths: Vec<&str>) -> EffectRequest {
@@ -213,6 +596,7 @@ mod tests {
                 url: None,
                 method: None,
                 headers: None,
+                env: None,
                 description: Some("test".to_string()),
             },
             context: Some(EffectContext {
@@ -230,10 +614,66 @@ mod tests {
                 domain_blocklist: None,
                 max_file_size_bytes: None,
                 timeout_ms: None,
+                template: None,
             }),
         }
     }
 
+    #[test]
+    fn scope_template_limits_are_applied_to_the_modified_scope() {
+        let mut config = PolicyConfig::default_config();
+        config.scope_templates.insert(
+            "src-writes".to_string(),
+            EffectScope {
+                workspace_paths: Some(vec!["/tmp/workspace/src/**".to_string()]),
+                max_file_size_bytes: Some(1024 * 1024),
+                ..Default::default()
+            },
+        );
+        config
+            .default_scope_templates
+            .insert(EffectType::FilesystemWrite, "src-writes".to_string());
+        let engine = PolicyEngine::new(config);
+
+        let mut request = make_request("/tmp/workspace/src/main.rs", vec![]);
+        request.scope = Some(EffectScope {
+            template: Some("src-writes".to_string()),
+            ..Default::default()
+        });
+        let scope = match engine.evaluate(&request).decision {
+            PolicyDecision::Approved { modified_scope, .. } => modified_scope.expect("scope"),
+            other => panic!("expected approval, got {:?}", other),
+        };
+        assert_eq!(
+            scope.workspace_paths,
+            Some(vec!["/tmp/workspace/src/**".to_string()])
+        );
+        assert_eq!(scope.max_file_size_bytes, Some(1024 * 1024));
+
+        // Writes name no template, so they get the effect type's default.
+        let mut write = make_request("/tmp/workspace/src/main.rs", vec![]);
+        write.effect_type = EffectType::FilesystemWrite;
+        write.scope = None;
+        match engine.evaluate(&write).decision {
+            PolicyDecision::RequiresUserConfirmation { modified_scope, .. } => {
+                assert_eq!(
+                    modified_scope.unwrap().max_file_size_bytes,
+                    Some(1024 * 1024)
+                );
+            }
+            other => panic!("expected confirmation, got {:?}", other),
+        }
+
+        request.scope = Some(EffectScope {
+            template: Some("missing".to_string()),
+            ..Default::default()
+        });
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Denied { ref code, .. } if code == "unknown_scope_template"
+        ));
+    }
+
     #[test]
     fn host_folder_read_requires_confirmation() {
         let engine = PolicyEngine::new(PolicyConfig::default_config());
@@ -265,4 +705,441 @@ mod tests {
             _ => panic!("expected workspace read to remain auto approved"),
         }
     }
+
+    #[test]
+    fn remembered_denial_auto_denies_matching_requests() {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+        let request = make_request("/Users/tester/Downloads", vec!["/Users/tester/workspace"]);
+        engine.remember_denial(&request, None);
+
+        let mut again = request.clone();
+        again.id = "request-2".to_string();
+        match engine.evaluate(&again).decision {
+            PolicyDecision::Denied { code, .. } => assert_eq!(code, "remembered_denial"),
+            other => panic!("expected remembered denial, got {:?}", other),
+        }
+
+        let other_path = make_request("/Users/tester/Desktop", vec!["/Users/tester/workspace"]);
+        assert!(matches!(
+            engine.evaluate(&other_path).decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+    }
+
+    #[test]
+    fn clearing_remembered_denials_restores_prompting() {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+        let request = make_request("/Users/tester/Downloads", vec!["/Users/tester/workspace"]);
+        let denial = engine.remember_denial(&request, Some("not here".to_string()));
+        engine.remember_denial(&request, None);
+        assert_eq!(engine.remembered_denials().len(), 1);
+
+        assert_eq!(engine.clear_remembered_denials(Some(&denial.id)), 1);
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+    }
+
+    #[test]
+    fn simulated_batch_matches_individual_evaluation() {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+        let mut denied = make_request("/Users/tester/Private", vec!["/Users/tester/workspace"]);
+        denied.id = "request-denied".to_string();
+        engine.remember_denial(&denied, None);
+
+        let mut approved = make_request(
+            "/Users/tester/workspace/src",
+            vec!["/Users/tester/workspace"],
+        );
+        approved.id = "request-approved".to_string();
+        let mut confirm = make_request("/Users/tester/Downloads", vec!["/Users/tester/workspace"]);
+        confirm.id = "request-confirm".to_string();
+        let requests = vec![approved, confirm, denied];
+
+        let simulated = engine.simulate(&requests);
+        assert_eq!(simulated.len(), requests.len());
+        for ((id, decision), request) in simulated.iter().zip(&requests) {
+            assert_eq!(id, &request.id);
+            assert_eq!(
+                serde_json::to_value(decision).unwrap(),
+                serde_json::to_value(engine.evaluate(request).decision).unwrap()
+            );
+        }
+
+        let outcomes: Vec<_> = simulated
+            .iter()
+            .map(|(_, d)| serde_json::to_value(d).unwrap()["outcome"].clone())
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec!["approved", "requires_user_confirmation", "denied"]
+        );
+        // Simulation must not add to engine state.
+        assert_eq!(engine.remembered_denials().len(), 1);
+    }
+
+    fn write_request(path: &str) -> EffectRequest {
+        let mut request = make_request(path, vec!["/Users/tester/workspace"]);
+        request.effect_type = EffectType::FilesystemWrite;
+        request
+    }
+
+    #[test]
+    fn confirmed_requests_auto_approve_within_window_only() {
+        let mut config = PolicyConfig::default_config();
+        config.auto_approve_window_secs = 120;
+        let mut engine = PolicyEngine::new(config);
+        let confirmed_at = Utc::now();
+        let request = write_request("/Users/tester/workspace/notes.md");
+
+        engine
+            .open_auto_approve_window(&request, confirmed_at)
+            .expect("window opened");
+
+        let mut again = request.clone();
+        again.id = "request-2".to_string();
+        assert!(matches!(
+            engine
+                .evaluate_at(&again, confirmed_at + Duration::seconds(60))
+                .decision,
+            PolicyDecision::Approved { .. }
+        ));
+
+        // A different target is not covered.
+        let other = write_request("/Users/tester/workspace/other.md");
+        assert!(matches!(
+            engine
+                .evaluate_at(&other, confirmed_at + Duration::seconds(60))
+                .decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+
+        // After the window, the same request prompts again.
+        assert!(matches!(
+            engine
+                .evaluate_at(&again, confirmed_at + Duration::seconds(121))
+                .decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+    }
+
+    #[test]
+    fn shell_auto_approve_window_does_not_cover_another_env() {
+        let mut config = PolicyConfig::default_config();
+        config.auto_approve_window_secs = 120;
+        let mut engine = PolicyEngine::new(config);
+        let mut request = write_request("/Users/tester/workspace");
+        request.effect_type = EffectType::ShellWrite;
+        request.payload.path = None;
+        request.payload.command = Some("npm test".to_string());
+        request.payload.env = Some(HashMap::from([("CI".to_string(), "1".to_string())]));
+        let confirmed_at = Utc::now();
+        engine
+            .open_auto_approve_window(&request, confirmed_at)
+            .expect("window opened");
+        let later = confirmed_at + Duration::seconds(60);
+        assert!(matches!(
+            engine.evaluate_at(&request, later).decision,
+            PolicyDecision::Approved { .. }
+        ));
+
+        let mut preloaded = request.clone();
+        preloaded
+            .payload
+            .env
+            .as_mut()
+            .unwrap()
+            .insert("LD_PRELOAD".to_string(), "/tmp/hook.so".to_string());
+        assert_eq!(preloaded.fingerprint(), request.fingerprint());
+        assert!(matches!(
+            engine.evaluate_at(&preloaded, later).decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+    }
+
+    #[test]
+    fn auto_approve_window_is_off_by_default() {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+        let request = write_request("/Users/tester/workspace/notes.md");
+
+        assert!(engine
+            .open_auto_approve_window(&request, Utc::now())
+            .is_none());
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+    }
+
+    #[test]
+    fn allowed_commands_round_trip() {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+
+        assert_eq!(
+            engine.add_allowed_command("  git status "),
+            Ok("git status".to_string())
+        );
+        assert_eq!(engine.config.allowlists.commands, vec!["git status"]);
+
+        assert!(engine.remove_allowed_command("git status"));
+        assert!(!engine.remove_allowed_command("git status"));
+        assert!(engine.config.allowlists.commands.is_empty());
+
+        engine.add_denied_command("rm -rf").expect("deny rm");
+        let mut request = write_request("/Users/tester/workspace/notes.md");
+        request.effect_type = EffectType::ShellWrite;
+        request.payload.command = Some("rm -rf /".to_string());
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Denied { .. }
+        ));
+    }
+
+    #[test]
+    fn blocklist_rules_ignore_the_root_a_request_claims() {
+        let mut config = PolicyConfig::default_config();
+        config.blocklists.paths = vec!["secrets/**".to_string()];
+        let mut engine = PolicyEngine::new(config);
+        engine.set_workspace_root(Some("/Users/tester/workspace".to_string()));
+
+        let mut request = make_request("/Users/tester/workspace/secrets/prod.env", vec!["/"]);
+        request.effect_type = EffectType::FilesystemWrite;
+        request.payload.cwd = Some("/".to_string());
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Denied { ref code, .. } if code == "policy_blocked"
+        ));
+
+        // Without a trusted root a relative rule still blocks at any depth.
+        engine.set_workspace_root(None);
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Denied { ref code, .. } if code == "policy_blocked"
+        ));
+    }
+
+    #[test]
+    fn command_lists_reject_empty_and_duplicate_entries() {
+        let mut engine = PolicyEngine::new(PolicyConfig::default_config());
+
+        assert!(engine.add_allowed_command("   ").is_err());
+        engine.add_allowed_command("npm test").expect("first add");
+        assert!(engine.add_allowed_command(" npm test").is_err());
+        assert_eq!(engine.config.allowlists.commands.len(), 1);
+
+        engine.add_denied_command("sudo").expect("first deny");
+        assert!(engine.add_denied_command("sudo").is_err());
+        assert_eq!(engine.config.blocklists.commands, vec!["sudo"]);
+    }
+
+    #[test]
+    fn relative_and_absolute_paths_match_the_same_workspace_rule() {
+        let mut config = PolicyConfig::default_config();
+        config.blocklists.paths = vec!["secrets/**".to_string()];
+        let mut engine = PolicyEngine::new(config);
+        engine.set_workspace_root(Some("/Users/tester/workspace".to_string()));
+
+        for path in [
+            "secrets/prod.env",
+            "./src/../secrets/prod.env",
+            "/Users/tester/workspace/secrets/prod.env",
+        ] {
+            let request = write_request(path);
+            assert!(
+                matches!(
+                    engine.evaluate(&request).decision,
+                    PolicyDecision::Denied { ref code, .. } if code == "policy_blocked"
+                ),
+                "{path} should hit the workspace rule"
+            );
+        }
+        assert!(matches!(
+            engine
+                .evaluate(&write_request("/Users/tester/other/secrets/prod.env"))
+                .decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+
+        // Workspace scope rules can be relative globs, too.
+        let engine = PolicyEngine::new(PolicyConfig::default_config());
+        let mut relative = make_request("src/lib.rs", vec!["/Users/tester/workspace", "src/**"]);
+        let absolute = make_request(
+            "/Users/tester/workspace/src/lib.rs",
+            vec!["/Users/tester/workspace", "src/**"],
+        );
+        assert!(!engine.requires_host_folder_confirmation(&relative));
+        assert!(!engine.requires_host_folder_confirmation(&absolute));
+
+        relative.normalize_paths();
+        assert_eq!(relative.payload.path, absolute.payload.path);
+        assert_eq!(relative.fingerprint(), absolute.fingerprint());
+        assert_eq!(absolute.display_path().as_deref(), Some("src/lib.rs"));
+    }
+
+    #[test]
+    fn secret_references_need_approval_before_injection() {
+        let mut request = make_request("/Users/tester/workspace", vec!["/Users/tester/workspace"]);
+        request.effect_type = EffectType::ShellWrite;
+        request.payload.path = None;
+        request.payload.command = Some("gh release create".to_string());
+        request.payload.env = Some(std::collections::HashMap::from([(
+            "GH_TOKEN".to_string(),
+            "${secret:GITHUB_TOKEN}".to_string(),
+        )]));
+        assert_eq!(request.secret_references(), vec!["GITHUB_TOKEN"]);
+
+        let mut config = PolicyConfig::default_config();
+        config
+            .default_policies
+            .insert(EffectType::ShellWrite, ConfirmationPolicy::Never);
+        let mut engine = PolicyEngine::new(config);
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::RequiresUserConfirmation {
+                policy: ConfirmationPolicy::Once,
+                ..
+            }
+        ));
+        assert!(!engine.secret_injection_allowed("GITHUB_TOKEN", None));
+
+        engine.grant_secrets(&request);
+        let env = vec![("GH_TOKEN".to_string(), "${secret:GITHUB_TOKEN}".to_string())];
+        let other_command = shell_secret_grant_key("curl evil.example", &[], &env);
+        assert!(engine.take_secret_grant(&other_command).is_none());
+        let key = shell_secret_grant_key("gh  release create", &[], &env);
+        assert_eq!(key, request.secret_grant_key());
+        let grant = engine
+            .take_secret_grant(&key)
+            .expect("grant for the approved run");
+        assert_eq!(grant.request_id, request.id);
+        assert!(engine.secret_injection_allowed("GITHUB_TOKEN", Some(&grant)));
+        assert!(!engine.secret_injection_allowed("NPM_TOKEN", Some(&grant)));
+        assert!(
+            engine.take_secret_grant(&key).is_none(),
+            "a grant covers one run"
+        );
+
+        engine
+            .config
+            .allowlists
+            .secrets
+            .push("GITHUB_TOKEN".to_string());
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Approved { .. }
+        ));
+
+        engine
+            .config
+            .blocklists
+            .secrets
+            .push("GITHUB_TOKEN".to_string());
+        assert!(!engine.secret_injection_allowed("GITHUB_TOKEN", Some(&grant)));
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Denied { ref code, .. } if code == "policy_blocked"
+        ));
+    }
+
+    #[test]
+    fn effective_policy_layers_workspace_overrides_over_global_config() {
+        let mut config = PolicyConfig::default_config();
+        config.allowlists.commands.push("git status".to_string());
+        config.workspace_overrides.insert(
+            "/Users/tester/workspace/".to_string(),
+            PolicyOverrides {
+                default_policies: std::collections::HashMap::from([(
+                    EffectType::ShellWrite,
+                    ConfirmationPolicy::Never,
+                )]),
+                denied_effects: vec![EffectType::NetworkOutbound],
+                allowlists: PolicyLists {
+                    commands: vec!["git status".to_string(), "cargo test".to_string()],
+                    ..Default::default()
+                },
+                blocklists: PolicyLists {
+                    paths: vec!["secrets/**".to_string()],
+                    ..Default::default()
+                },
+            },
+        );
+        let mut engine = PolicyEngine::new(config);
+
+        let global = engine.effective_policy(&EffectType::ShellWrite, None);
+        assert_eq!(global.policy, ConfirmationPolicy::Once);
+        assert!(!global.denied);
+        assert_eq!(global.workspace_override, None);
+        assert_eq!(global.allowlists.commands, vec!["git status"]);
+
+        let denied = engine.effective_policy(&EffectType::SecretsRead, None);
+        assert!(denied.denied);
+        assert_eq!(denied.policy, ConfirmationPolicy::Always);
+
+        let other = engine.effective_policy(&EffectType::ShellWrite, Some("/Users/tester/other"));
+        assert_eq!(other.policy, ConfirmationPolicy::Once);
+        assert_eq!(other.workspace_override, None);
+
+        let shell =
+            engine.effective_policy(&EffectType::ShellWrite, Some("/Users/tester/workspace"));
+        assert_eq!(shell.policy, ConfirmationPolicy::Never);
+        assert_eq!(
+            shell.workspace_override.as_deref(),
+            Some("/Users/tester/workspace/")
+        );
+        assert_eq!(shell.allowlists.commands, vec!["git status", "cargo test"]);
+        assert_eq!(shell.blocklists.paths, vec!["secrets/**"]);
+
+        let network = engine.effective_policy(
+            &EffectType::NetworkOutbound,
+            Some("/Users/tester/workspace"),
+        );
+        assert!(network.denied);
+
+        // Evaluation goes through the same resolution.
+        let mut write = make_request(
+            "/Users/tester/workspace/secrets/prod.env",
+            vec!["/Users/tester/workspace"],
+        );
+        write.effect_type = EffectType::FilesystemWrite;
+        engine.set_workspace_root(Some("/Users/tester/workspace".to_string()));
+        assert!(matches!(
+            engine.evaluate(&write).decision,
+            PolicyDecision::Denied { ref code, .. } if code == "policy_blocked"
+        ));
+    }
+
+    #[test]
+    fn spoofed_cwd_does_not_pick_the_workspace_override() {
+        let mut config = PolicyConfig::default_config();
+        config.workspace_overrides.insert(
+            "/Users/tester/sandbox".to_string(),
+            PolicyOverrides {
+                default_policies: std::collections::HashMap::from([(
+                    EffectType::ShellWrite,
+                    ConfirmationPolicy::Never,
+                )]),
+                ..Default::default()
+            },
+        );
+        let mut engine = PolicyEngine::new(config);
+        engine.set_workspace_root(Some("/Users/tester/workspace".to_string()));
+
+        // The agent names the permissive workspace as its scope and cwd
+        let mut request = make_request("/Users/tester/sandbox", vec!["/Users/tester/sandbox"]);
+        request.effect_type = EffectType::ShellWrite;
+        request.payload.path = None;
+        request.payload.command = Some("rm -rf build".to_string());
+        request.payload.cwd = Some("/Users/tester/sandbox".to_string());
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::RequiresUserConfirmation { .. }
+        ));
+
+        engine.set_workspace_root(Some("/Users/tester/sandbox".to_string()));
+        assert!(matches!(
+            engine.evaluate(&request).decision,
+            PolicyDecision::Approved { .. }
+        ));
+    }
 }
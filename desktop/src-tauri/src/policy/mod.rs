@@ -4,11 +4,16 @@
 mod audit;
 pub mod commands;
 mod engine;
+mod secrets;
 mod types;
 
-pub use audit::ConsoleAuditSink;
+pub use audit::{AuditEvent, ConsoleAuditSink, ExecutionResult};
 pub use commands::PolicyEngineState;
+pub use engine::SecretGrant;
+pub use secrets::{
+    keychain_secret, redact_secret_values, resolve_secret_env, secret_references, ResolvedEnv,
+};
 pub use types::{
-    EffectContext, EffectPayload, EffectRequest, EffectResponse, EffectScope, EffectSource,
-    EffectType,
+    path_rule_matches, shell_secret_grant_key, EffectContext, EffectPayload, EffectRequest,
+    EffectResponse, EffectScope, EffectSource, EffectType,
 };
@@ -0,0 +1,205 @@
+//! Named secret references for shell effects.
+//!
+//! Agents never see secret values. A shell effect names the secrets it needs
+//! as `${secret:NAME}` placeholders in its environment; the policy gate has to
+//! approve those names, and the desktop substitutes the keychain values only
+//! when it spawns the process.
+
+use std::collections::HashMap;
+use std::process::Command;
+
+const PLACEHOLDER_OPEN: &str = "${secret:";
+const PLACEHOLDER_CLOSE: char = '}';
+/// Keychain service secrets are stored under.
+const KEYCHAIN_SERVICE: &str = "coworkany";
+const REDACTED_SECRET: &str = "[SECRET]";
+
+fn is_secret_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Split `text` into literal runs and secret names. Malformed placeholders
+/// stay literal.
+fn placeholder_parts(text: &str) -> Vec<(bool, &str)> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER_OPEN) {
+        let after_open = &rest[start + PLACEHOLDER_OPEN.len()..];
+        let Some(end) = after_open.find(PLACEHOLDER_CLOSE) else {
+            break;
+        };
+        let name = &after_open[..end];
+        if !is_secret_name(name) {
+            parts.push((false, &rest[..start + PLACEHOLDER_OPEN.len()]));
+            rest = after_open;
+            continue;
+        }
+        parts.push((false, &rest[..start]));
+        parts.push((true, name));
+        rest = &after_open[end + 1..];
+    }
+    parts.push((false, rest));
+    parts.retain(|(is_secret, part)| *is_secret || !part.is_empty());
+    parts
+}
+
+/// Names referenced by `${secret:NAME}` placeholders in `text`.
+pub fn secret_references(text: &str) -> Vec<String> {
+    placeholder_parts(text)
+        .into_iter()
+        .filter(|(is_secret, _)| *is_secret)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Replace every placeholder in `text` with the value `lookup` returns for
+/// its name. The first failed lookup aborts the whole substitution.
+pub fn resolve_secret_placeholders(
+    text: &str,
+    lookup: &mut impl FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut resolved = String::with_capacity(text.len());
+    for (is_secret, part) in placeholder_parts(text) {
+        if is_secret {
+            resolved.push_str(&lookup(part)?);
+        } else {
+            resolved.push_str(part);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Environment for a shell effect after secret substitution.
+#[derive(Debug, Default)]
+pub struct ResolvedEnv {
+    pub env: Vec<(String, String)>,
+    /// Values that were substituted, longest first, for output redaction
+    pub secret_values: Vec<String>,
+}
+
+/// Resolve placeholders in environment values.
+pub fn resolve_secret_env(
+    env: &[(String, String)],
+    lookup: &mut impl FnMut(&str) -> Result<String, String>,
+) -> Result<ResolvedEnv, String> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    let mut resolved_env = Vec::with_capacity(env.len());
+    for (key, value) in env {
+        let resolved = resolve_secret_placeholders(value, &mut |name| {
+            if let Some(value) = values.get(name) {
+                return Ok(value.clone());
+            }
+            let value = lookup(name)?;
+            values.insert(name.to_string(), value.clone());
+            Ok(value)
+        })?;
+        resolved_env.push((key.clone(), resolved));
+    }
+    let mut secret_values: Vec<String> = values.into_values().filter(|v| !v.is_empty()).collect();
+    // Longest first so a secret containing another is masked whole.
+    secret_values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+    Ok(ResolvedEnv {
+        env: resolved_env,
+        secret_values,
+    })
+}
+
+/// Mask any of `secret_values` that a process echoed into its output.
+pub fn redact_secret_values(text: &str, secret_values: &[String]) -> String {
+    let mut redacted = text.to_string();
+    for value in secret_values {
+        if redacted.contains(value.as_str()) {
+            redacted = redacted.replace(value.as_str(), REDACTED_SECRET);
+        }
+    }
+    redacted
+}
+
+/// Read secret `name` from the system keychain.
+pub fn keychain_secret(name: &str) -> Result<String, String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args([
+                "find-generic-password",
+                "-s",
+                KEYCHAIN_SERVICE,
+                "-a",
+                name,
+                "-w",
+            ])
+            .output()
+    } else if cfg!(target_os = "windows") {
+        return Err("secret_store_unavailable: keychain lookup is not supported on Windows".into());
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", KEYCHAIN_SERVICE, "account", name])
+            .output()
+    }
+    .map_err(|e| format!("secret_store_unavailable: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!("secret_not_found: {}", name));
+    }
+    let value = String::from_utf8_lossy(&output.stdout);
+    Ok(value.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Result<String, String> {
+        match name {
+            "GITHUB_TOKEN" => Ok("ghp_live".to_string()),
+            "NPM_TOKEN" => Ok("npm_live".to_string()),
+            other => Err(format!("secret_not_found: {}", other)),
+        }
+    }
+
+    #[test]
+    fn resolves_placeholders_and_redacts_injected_values() {
+        assert_eq!(
+            secret_references("token ${secret:GITHUB_TOKEN} and ${secret:NPM_TOKEN}"),
+            vec!["GITHUB_TOKEN", "NPM_TOKEN"]
+        );
+        assert!(secret_references("${secret:} ${secret:bad name} ${secret:open").is_empty());
+        assert_eq!(
+            resolve_secret_placeholders("Bearer ${secret:GITHUB_TOKEN}", &mut lookup).unwrap(),
+            "Bearer ghp_live"
+        );
+        assert_eq!(
+            resolve_secret_placeholders("${HOME}/${secret:bad name}", &mut lookup).unwrap(),
+            "${HOME}/${secret:bad name}"
+        );
+        assert_eq!(
+            resolve_secret_placeholders("${secret:MISSING}", &mut lookup).unwrap_err(),
+            "secret_not_found: MISSING"
+        );
+
+        let env = vec![
+            ("GH_TOKEN".to_string(), "${secret:GITHUB_TOKEN}".to_string()),
+            (
+                "AUTH".to_string(),
+                "token ${secret:GITHUB_TOKEN}".to_string(),
+            ),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ];
+        let mut lookups = 0;
+        let resolved = resolve_secret_env(&env, &mut |name| {
+            lookups += 1;
+            lookup(name)
+        })
+        .unwrap();
+        assert_eq!(lookups, 1, "each secret is read from the keychain once");
+        assert_eq!(resolved.env[0].1, "ghp_live");
+        assert_eq!(resolved.env[1].1, "token ghp_live");
+        assert_eq!(resolved.env[2].1, "/usr/bin");
+        assert_eq!(
+            redact_secret_values("pushed with ghp_live", &resolved.secret_values),
+            "pushed with [SECRET]"
+        );
+    }
+}
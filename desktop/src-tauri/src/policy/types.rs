@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -42,6 +43,8 @@ pub enum EffectSource {
     Agent,
     Toolpack,
     ClaudeSkill,
+    /// An action the user started from the desktop UI
+    User,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -66,9 +69,133 @@ pub struct EffectScope {
     pub domain_blocklist: Option<Vec<String>>,
     pub max_file_size_bytes: Option<u64>,
     pub timeout_ms: Option<u64>,
+    /// Name of a template in `PolicyConfig::scope_templates` supplying the
+    /// limits left unset here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+impl EffectScope {
+    /// Take each limit left unset from `template`
+    pub fn fill_from(&mut self, template: &EffectScope) {
+        let fill = |own: &mut Option<Vec<String>>, other: &Option<Vec<String>>| {
+            if own.is_none() {
+                own.clone_from(other);
+            }
+        };
+        fill(&mut self.workspace_paths, &template.workspace_paths);
+        fill(&mut self.allowed_extensions, &template.allowed_extensions);
+        fill(&mut self.excluded_paths, &template.excluded_paths);
+        fill(&mut self.command_allowlist, &template.command_allowlist);
+        fill(&mut self.command_blocklist, &template.command_blocklist);
+        fill(&mut self.domain_allowlist, &template.domain_allowlist);
+        fill(&mut self.domain_blocklist, &template.domain_blocklist);
+        self.max_file_size_bytes = self.max_file_size_bytes.or(template.max_file_size_bytes);
+        self.timeout_ms = self.timeout_ms.or(template.timeout_ms);
+    }
+
+    /// This scope further limited by `proposed`: allowlists may only drop
+    /// entries, blocklists only gain them, and the smaller of each cap wins.
+    /// Limits `proposed` leaves unset stay as they are. `Err` names an
+    /// allowlist entry `proposed` would add.
+    pub fn narrowed_by(
+        &self,
+        proposed: &EffectScope,
+        root: Option<&str>,
+    ) -> Result<EffectScope, String> {
+        let paths_within = |entry: &str, allowed: &[String]| {
+            allowed
+                .iter()
+                .any(|rule| path_rule_matches(rule, entry, root))
+        };
+        let hosts_within = |entry: &str, allowed: &[String]| {
+            allowed.iter().any(|rule| host_rule_matches(rule, entry))
+        };
+        let same_within = |entry: &str, allowed: &[String]| allowed.iter().any(|a| a == entry);
+
+        let mut narrowed = self.clone();
+        narrowed.template = None;
+        narrow_allowlist(
+            &mut narrowed.workspace_paths,
+            &proposed.workspace_paths,
+            paths_within,
+        )?;
+        narrow_allowlist(
+            &mut narrowed.allowed_extensions,
+            &proposed.allowed_extensions,
+            same_within,
+        )?;
+        narrow_allowlist(
+            &mut narrowed.command_allowlist,
+            &proposed.command_allowlist,
+            same_within,
+        )?;
+        narrow_allowlist(
+            &mut narrowed.domain_allowlist,
+            &proposed.domain_allowlist,
+            hosts_within,
+        )?;
+        extend_blocklist(&mut narrowed.excluded_paths, &proposed.excluded_paths);
+        extend_blocklist(&mut narrowed.command_blocklist, &proposed.command_blocklist);
+        extend_blocklist(&mut narrowed.domain_blocklist, &proposed.domain_blocklist);
+        let min = |own: Option<u64>, other: Option<u64>| match (own, other) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        narrowed.max_file_size_bytes = min(self.max_file_size_bytes, proposed.max_file_size_bytes);
+        narrowed.timeout_ms = min(self.timeout_ms, proposed.timeout_ms);
+        Ok(narrowed)
+    }
+}
+
+/// Replace `own` with `proposed` if every proposed entry is `within` what
+/// `own` already allows; an unset `own` allows anything.
+fn narrow_allowlist(
+    own: &mut Option<Vec<String>>,
+    proposed: &Option<Vec<String>>,
+    within: impl Fn(&str, &[String]) -> bool,
+) -> Result<(), String> {
+    let Some(proposed) = proposed else {
+        return Ok(());
+    };
+    if let Some(allowed) = own.as_deref() {
+        if let Some(wider) = proposed.iter().find(|entry| !within(entry, allowed)) {
+            return Err(format!(
+                "scope_not_narrower: {} is outside the requested scope",
+                wider
+            ));
+        }
+    }
+    *own = Some(proposed.clone());
+    Ok(())
+}
+
+fn extend_blocklist(own: &mut Option<Vec<String>>, proposed: &Option<Vec<String>>) {
+    let Some(proposed) = proposed else {
+        return;
+    };
+    let list = own.get_or_insert_with(Vec::new);
+    for entry in proposed {
+        if !list.contains(entry) {
+            list.push(entry.clone());
+        }
+    }
+}
+
+/// Whether domain rule `rule` covers `host`: the same host, a subdomain of
+/// it, or anything for `*`. A leading `*.` is optional.
+fn host_rule_matches(rule: &str, host: &str) -> bool {
+    let rule = rule.trim().to_ascii_lowercase();
+    let host = host.trim().to_ascii_lowercase();
+    if rule == "*" {
+        return true;
+    }
+    let rule = rule.strip_prefix("*.").unwrap_or(&rule);
+    let host = host.strip_prefix("*.").unwrap_or(&host);
+    host == rule || host.ends_with(&format!(".{}", rule))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct EffectPayload {
     pub path: Option<String>,
@@ -80,6 +207,10 @@ pub struct EffectPayload {
     pub url: Option<String>,
     pub method: Option<String>,
     pub headers: Option<HashMap<String, String>>,
+    /// Environment for shell effects; values may hold `${secret:NAME}`
+    /// placeholders that are resolved only at execution time
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
     pub description: Option<String>,
 }
 
@@ -106,6 +237,233 @@ pub struct EffectRequest {
     pub scope: Option<EffectScope>,
 }
 
+impl EffectRequest {
+    /// The concrete thing the effect acts on: a path, a command, or a URL.
+    pub fn target(&self) -> Option<&str> {
+        self.payload
+            .path
+            .as_deref()
+            .or(self.payload.command.as_deref())
+            .or(self.payload.url.as_deref())
+    }
+
+    /// Secret names referenced by placeholders in `payload.env`, sorted.
+    pub fn secret_references(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .payload
+            .env
+            .iter()
+            .flat_map(|env| env.values())
+            .flat_map(|value| super::secrets::secret_references(value))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Key tying a secret approval to this exact shell invocation; see
+    /// `shell_secret_grant_key`.
+    pub fn secret_grant_key(&self) -> String {
+        let env: Vec<(String, String)> = self
+            .payload
+            .env
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        shell_secret_grant_key(
+            self.payload.command.as_deref().unwrap_or_default(),
+            self.payload.args.as_deref().unwrap_or_default(),
+            &env,
+        )
+    }
+
+    /// Stable identifier for "the same kind of thing", independent of request id
+    /// and timestamp. Paths are slash-normalized, commands keep their arguments
+    /// with whitespace collapsed, and URLs reduce to their host.
+    pub fn fingerprint(&self) -> String {
+        let target = if let Some(path) = &self.payload.path {
+            normalize_path_target(path)
+        } else if let Some(command) = &self.payload.command {
+            let mut parts: Vec<&str> = command.split_whitespace().collect();
+            if let Some(args) = &self.payload.args {
+                parts.extend(args.iter().map(String::as_str));
+            }
+            parts.join(" ")
+        } else if let Some(url) = &self.payload.url {
+            url::Url::parse(url)
+                .ok()
+                .and_then(|parsed| parsed.host_str().map(str::to_ascii_lowercase))
+                .unwrap_or_else(|| url.trim().to_ascii_lowercase())
+        } else {
+            String::new()
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.effect_type.as_str().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(target.as_bytes());
+        hex::encode(&hasher.finalize()[..8])
+    }
+
+    /// Whether `other` asks for exactly the same thing from the same source:
+    /// equal payloads down to env, headers and content, not just the same
+    /// fingerprint. Only such requests may share one user decision.
+    pub fn same_effect(&self, other: &EffectRequest) -> bool {
+        self.effect_type == other.effect_type
+            && self.source == other.source
+            && self.source_id == other.source_id
+            && self.payload == other.payload
+    }
+
+    /// The workspace the request claims to run in: the first absolute,
+    /// wildcard-free scope workspace path, else an absolute `payload.cwd`.
+    /// The agent controls this, so it only normalizes the request's own
+    /// paths; policy rules resolve against the engine's workspace root.
+    pub fn workspace_root(&self) -> Option<&str> {
+        let is_root = |p: &&str| is_absolute_path(p) && !p.contains('*');
+        self.scope
+            .as_ref()
+            .and_then(|scope| scope.workspace_paths.as_ref())
+            .and_then(|paths| paths.iter().map(String::as_str).find(is_root))
+            .or(self.payload.cwd.as_deref().filter(is_root))
+    }
+
+    /// Rewrite a workspace-relative `payload.path` to its absolute form so
+    /// matching, fingerprints and audit all see the same path.
+    pub fn normalize_paths(&mut self) {
+        let root = self.workspace_root().map(str::to_string);
+        if let Some(path) = self.payload.path.as_mut() {
+            *path = resolve_workspace_path(path, root.as_deref());
+        }
+    }
+
+    /// `payload.path` relative to the workspace when it lies inside it.
+    pub fn display_path(&self) -> Option<String> {
+        let path = self.payload.path.as_deref()?;
+        let root = self.workspace_root();
+        let absolute = resolve_workspace_path(path, root);
+        let relative = root.and_then(|root| {
+            absolute
+                .strip_prefix(resolve_workspace_path(root, None).as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+                .map(str::to_string)
+        });
+        Some(relative.unwrap_or(absolute))
+    }
+}
+
+/// Hash of a shell invocation as approved: the command with whitespace
+/// collapsed, its arguments, and its env with placeholders unresolved, in key
+/// order. A secret grant only covers a run with the same key.
+pub fn shell_secret_grant_key(command: &str, args: &[String], env: &[(String, String)]) -> String {
+    let mut env: Vec<&(String, String)> = env.iter().collect();
+    env.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(command.split_whitespace().collect::<Vec<_>>().join(" "));
+    for arg in args {
+        hasher.update([0u8]);
+        hasher.update(arg.as_bytes());
+    }
+    hasher.update([1u8]);
+    for (key, value) in env {
+        hasher.update(key.as_bytes());
+        hasher.update([b'=']);
+        hasher.update(value.as_bytes());
+        hasher.update([0u8]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+pub(super) fn is_absolute_path(path: &str) -> bool {
+    let path = path.trim();
+    path.starts_with('/')
+        || path.starts_with('\\')
+        || (path.len() >= 2 && path.as_bytes()[1] == b':')
+}
+
+/// Slash-normalize `path`, resolve it against `root` when relative, and fold
+/// `.` and `..` segments lexically.
+pub fn resolve_workspace_path(path: &str, root: Option<&str>) -> String {
+    let path = path.trim().replace('\\', "/");
+    let joined = match root {
+        Some(root) if !is_absolute_path(&path) => {
+            format!("{}/{}", root.trim().replace('\\', "/"), path)
+        }
+        _ => path,
+    };
+
+    let absolute = joined.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if segments.last().is_some_and(|last| *last != "..") => {
+                segments.pop();
+            }
+            ".." if absolute => {}
+            _ => segments.push(segment),
+        }
+    }
+
+    let resolved = segments.join("/");
+    if absolute {
+        format!("/{}", resolved)
+    } else {
+        resolved
+    }
+}
+
+/// Whether `path` is covered by a scope or list rule. Rules and paths may be
+/// absolute or relative to `root`; rules may use `*` within a segment and
+/// `**` for any depth. A rule without wildcards covers that path and
+/// everything below it.
+pub fn path_rule_matches(rule: &str, path: &str, root: Option<&str>) -> bool {
+    let rule = resolve_workspace_path(rule, root);
+    let path = resolve_workspace_path(path, root);
+    let rule_segments: Vec<&str> = rule.split('/').collect();
+    let path_segments: Vec<&str> = path.split('/').collect();
+
+    if !rule.contains('*') {
+        return path_segments.starts_with(&rule_segments);
+    }
+    glob_segments_match(&rule_segments, &path_segments)
+}
+
+fn glob_segments_match(rule: &[&str], path: &[&str]) -> bool {
+    match rule.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=path.len()).any(|skip| glob_segments_match(rest, &path[skip..]))
+        }
+        Some((segment, rest)) => path.split_first().is_some_and(|(first, path_rest)| {
+            glob_segment_matches(segment, first) && glob_segments_match(rest, path_rest)
+        }),
+    }
+}
+
+fn glob_segment_matches(pattern: &str, segment: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == segment;
+    };
+    let Some(remaining) = segment.strip_prefix(prefix) else {
+        return false;
+    };
+    (0..=remaining.len())
+        .filter(|&i| remaining.is_char_boundary(i))
+        .any(|i| glob_segment_matches(rest, &remaining[i..]))
+}
+
+fn normalize_path_target(path: &str) -> String {
+    let normalized = path.trim().replace('\\', "/");
+    let trimmed = normalized.trim_end_matches('/');
+    if trimmed.is_empty() {
+        normalized
+    } else {
+        trimmed.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EffectResponse {
@@ -166,23 +524,108 @@ pub struct RuntimeSecurityAlert {
     pub redaction_applied: Option<bool>,
 }
 
+/// A user denial recorded with "remember", auto-denying matching future requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RememberedDenial {
+    pub id: String,
+    pub fingerprint: String,
+    pub effect_type: EffectType,
+    pub target: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+impl RememberedDenial {
+    pub fn matches(&self, request: &EffectRequest) -> bool {
+        self.fingerprint == request.fingerprint()
+    }
+}
+
+/// Short-lived trust granted by a manual confirmation: matching requests are
+/// auto-approved until `expires_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoApproveWindow {
+    pub fingerprint: String,
+    /// For shell writes, `EffectRequest::secret_grant_key` of the confirmed
+    /// request: the fingerprint ignores env, and the window must not cover
+    /// the command run under another one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell_key: Option<String>,
+    pub effect_type: EffectType,
+    pub target: Option<String>,
+    pub expires_at: String,
+}
+
+impl AutoApproveWindow {
+    pub fn shell_key_for(request: &EffectRequest) -> Option<String> {
+        (request.effect_type == EffectType::ShellWrite).then(|| request.secret_grant_key())
+    }
+
+    pub fn is_open(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        chrono::DateTime::parse_from_rfc3339(&self.expires_at)
+            .map(|expires_at| now < expires_at)
+            .unwrap_or(false)
+    }
+
+    pub fn covers(&self, request: &EffectRequest, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.fingerprint == request.fingerprint()
+            && self.shell_key == Self::shell_key_for(request)
+            && self.is_open(now)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct PolicyLists {
     pub commands: Vec<String>,
     pub domains: Vec<String>,
     pub paths: Vec<String>,
+    /// Secret names shell effects may reference via `${secret:NAME}`
+    #[serde(default)]
+    pub secrets: Vec<String>,
 }
 
+impl PolicyLists {
+    /// `self` with the entries of `extra` appended, without duplicates.
+    pub fn merged(&self, extra: &PolicyLists) -> PolicyLists {
+        let merge = |base: &Vec<String>, extra: &Vec<String>| {
+            let mut merged = base.clone();
+            for entry in extra {
+                if !merged.contains(entry) {
+                    merged.push(entry.clone());
+                }
+            }
+            merged
+        };
+        PolicyLists {
+            commands: merge(&self.commands, &extra.commands),
+            domains: merge(&self.domains, &extra.domains),
+            paths: merge(&self.paths, &extra.paths),
+            secrets: merge(&self.secrets, &extra.secrets),
+        }
+    }
+}
+
+/// Per-workspace adjustments layered over the global config.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
-#[allow(dead_code)]
 pub struct PolicyOverrides {
-    // ...
+    /// Replaces the global confirmation policy for these effect types
+    #[serde(default)]
+    pub default_policies: HashMap<EffectType, ConfirmationPolicy>,
+    /// Denied in this workspace in addition to the global list
+    #[serde(default)]
+    pub denied_effects: Vec<EffectType>,
+    /// Added to the global allowlists
+    #[serde(default)]
+    pub allowlists: PolicyLists,
+    /// Added to the global blocklists
+    #[serde(default)]
+    pub blocklists: PolicyLists,
 }
 
-// ...
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PolicyConfig {
@@ -190,6 +633,22 @@ pub struct PolicyConfig {
     pub allowlists: PolicyLists,
     pub blocklists: PolicyLists,
     pub denied_effects: Vec<EffectType>,
+    /// After a manual confirm, auto-approve matching requests for this many
+    /// seconds (0 disables). Never applies to `Always` policies.
+    #[serde(default)]
+    pub auto_approve_window_secs: u64,
+    /// Overrides keyed by workspace root path
+    #[serde(default)]
+    pub workspace_overrides: HashMap<String, PolicyOverrides>,
+    /// Reusable scopes by name, referenced through `EffectScope::template`
+    #[serde(default)]
+    pub scope_templates: HashMap<String, EffectScope>,
+    /// Template for requests of an effect type whose scope names none
+    #[serde(default)]
+    pub default_scope_templates: HashMap<EffectType, String>,
+    /// Denials the user asked to remember
+    #[serde(default)]
+    pub remembered_denials: Vec<RememberedDenial>,
 }
 
 impl PolicyConfig {
@@ -209,6 +668,11 @@ impl PolicyConfig {
             allowlists: PolicyLists::default(),
             blocklists: PolicyLists::default(),
             denied_effects: vec![EffectType::SecretsRead, EffectType::UiControl],
+            auto_approve_window_secs: 0,
+            workspace_overrides: HashMap::new(),
+            scope_templates: HashMap::new(),
+            default_scope_templates: HashMap::new(),
+            remembered_denials: Vec::new(),
         }
     }
 }
@@ -221,3 +685,138 @@ pub struct RequestOrigin {
     #[serde(rename = "sourceId")]
     pub source_id: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_request(id: &str, effect_type: EffectType, payload: EffectPayload) -> EffectRequest {
+        EffectRequest {
+            id: id.to_string(),
+            timestamp: "2026-03-19T00:00:00Z".to_string(),
+            effect_type,
+            source: EffectSource::Agent,
+            source_id: None,
+            payload,
+            context: None,
+            scope: None,
+        }
+    }
+
+    fn path_payload(path: &str) -> EffectPayload {
+        EffectPayload {
+            path: Some(path.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_request_id() {
+        let a = make_request(
+            "a",
+            EffectType::FilesystemWrite,
+            path_payload("/tmp/ws/a.txt"),
+        );
+        let b = make_request(
+            "b",
+            EffectType::FilesystemWrite,
+            path_payload("/tmp/ws/a.txt"),
+        );
+        assert_eq!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn fingerprint_differs_by_target_and_effect_type() {
+        let a = make_request(
+            "a",
+            EffectType::FilesystemWrite,
+            path_payload("/tmp/ws/a.txt"),
+        );
+        let b = make_request(
+            "a",
+            EffectType::FilesystemWrite,
+            path_payload("/tmp/ws/b.txt"),
+        );
+        let c = make_request(
+            "a",
+            EffectType::FilesystemRead,
+            path_payload("/tmp/ws/a.txt"),
+        );
+        assert_ne!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+    }
+
+    #[test]
+    fn fingerprint_normalizes_paths_commands_and_hosts() {
+        let slash = make_request("a", EffectType::FilesystemRead, path_payload("/tmp/ws/"));
+        let bare = make_request("b", EffectType::FilesystemRead, path_payload("/tmp/ws"));
+        assert_eq!(slash.fingerprint(), bare.fingerprint());
+
+        let spaced = make_request(
+            "a",
+            EffectType::ShellWrite,
+            EffectPayload {
+                command: Some("rm   -rf".to_string()),
+                args: Some(vec!["build".to_string()]),
+                ..Default::default()
+            },
+        );
+        let joined = make_request(
+            "b",
+            EffectType::ShellWrite,
+            EffectPayload {
+                command: Some("rm -rf build".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(spaced.fingerprint(), joined.fingerprint());
+
+        let url = |u: &str| EffectPayload {
+            url: Some(u.to_string()),
+            ..Default::default()
+        };
+        let first = make_request(
+            "a",
+            EffectType::NetworkOutbound,
+            url("https://API.example.com/a"),
+        );
+        let second = make_request(
+            "b",
+            EffectType::NetworkOutbound,
+            url("https://api.example.com/b?q=1"),
+        );
+        let other = make_request(
+            "c",
+            EffectType::NetworkOutbound,
+            url("https://example.org/a"),
+        );
+        assert_eq!(first.fingerprint(), second.fingerprint());
+        assert_ne!(first.fingerprint(), other.fingerprint());
+    }
+
+    #[test]
+    fn resolves_and_matches_workspace_relative_paths() {
+        let root = Some("/work/app");
+        assert_eq!(
+            resolve_workspace_path("src/./a/../b.rs", root),
+            "/work/app/src/b.rs"
+        );
+        assert_eq!(
+            resolve_workspace_path("C:\\work\\a.rs", root),
+            "C:/work/a.rs"
+        );
+        assert_eq!(resolve_workspace_path("/etc/../tmp/", None), "/tmp");
+
+        assert!(path_rule_matches("src/**", "src/deep/mod.rs", root));
+        assert!(path_rule_matches("src/**", "/work/app/src/lib.rs", root));
+        assert!(path_rule_matches("src/*.rs", "/work/app/src/lib.rs", root));
+        assert!(!path_rule_matches("src/*.rs", "src/deep/mod.rs", root));
+        assert!(path_rule_matches("/work/app", "src/lib.rs", root));
+        assert!(!path_rule_matches(
+            "/work/app",
+            "/work/application/lib.rs",
+            root
+        ));
+        assert!(!path_rule_matches("src/**", "/elsewhere/src/lib.rs", root));
+    }
+}
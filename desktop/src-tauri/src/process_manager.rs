@@ -7,6 +7,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tauri::AppHandle;
 use thiserror::Error;
@@ -24,8 +25,23 @@ pub enum ProcessError {
     #[error("Service not running: {0}")]
     NotRunning(String),
 
+    #[error("Service not found: {0}")]
+    NotFound(String),
+
     #[error("Service timeout: {0}")]
     Timeout(String),
+
+    #[error("Failed to save service configs: {0}")]
+    ConfigError(String),
+
+    #[error("Port {0} is not used by a CoworkAny service")]
+    PortNotManaged(u16),
+
+    #[error("Port {port} is held by processes CoworkAny did not start: {pids:?}")]
+    ForeignListener { port: u16, pids: Vec<u32> },
+
+    #[error("ForceNotConfirmed: force-freeing port {0} requires confirm=true")]
+    ForceNotConfirmed(u16),
 }
 
 // ============================================================================
@@ -59,6 +75,155 @@ impl Default for ServiceConfig {
     }
 }
 
+/// Partial update for a service's config; unset fields are left as they are.
+/// The name is the key and cannot be changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceConfigPatch {
+    pub enabled: Option<bool>,
+    pub auto_start: Option<bool>,
+    pub auto_restart: Option<bool>,
+    pub health_check_url: Option<String>,
+    pub health_check_interval_secs: Option<u64>,
+    pub startup_timeout_secs: Option<u64>,
+    pub max_restart_attempts: Option<u32>,
+}
+
+impl ServiceConfig {
+    fn apply_patch(&mut self, patch: ServiceConfigPatch) {
+        if let Some(enabled) = patch.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(auto_start) = patch.auto_start {
+            self.auto_start = auto_start;
+        }
+        if let Some(auto_restart) = patch.auto_restart {
+            self.auto_restart = auto_restart;
+        }
+        if let Some(url) = patch.health_check_url {
+            self.health_check_url = Some(url).filter(|url| !url.is_empty());
+        }
+        if let Some(interval) = patch.health_check_interval_secs {
+            self.health_check_interval_secs = interval;
+        }
+        if let Some(timeout) = patch.startup_timeout_secs {
+            self.startup_timeout_secs = timeout;
+        }
+        if let Some(attempts) = patch.max_restart_attempts {
+            self.max_restart_attempts = attempts;
+        }
+    }
+
+    /// Take over everything but the name from a saved config.
+    fn restore_from(&mut self, saved: &ServiceConfig) {
+        *self = ServiceConfig {
+            name: std::mem::take(&mut self.name),
+            ..saved.clone()
+        };
+    }
+}
+
+// ============================================================================
+// Resource Limits
+// ============================================================================
+
+/// Caps set on the sidecar process when it is spawned. Enforced with
+/// `setrlimit` on Unix; Windows would need a job object, so there the limits
+/// are ignored with a warning.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLimits {
+    /// Address space cap (`RLIMIT_AS`); allocations past it fail
+    pub max_memory_bytes: Option<u64>,
+    /// CPU time cap (`RLIMIT_CPU`); the process gets `SIGXCPU` when it is hit
+    pub max_cpu_secs: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_memory_bytes.is_none() && self.max_cpu_secs.is_none()
+    }
+}
+
+/// The `setrlimit` calls `limits` asks for, soft and hard limit alike
+#[cfg(unix)]
+fn rlimits(limits: &ResourceLimits) -> Vec<(rlimit::Resource, u64)> {
+    let mut rlimits = Vec::new();
+    if let Some(bytes) = limits.max_memory_bytes {
+        rlimits.push((rlimit::Resource::AS, bytes));
+    }
+    if let Some(secs) = limits.max_cpu_secs {
+        rlimits.push((rlimit::Resource::CPU, secs));
+    }
+    rlimits
+}
+
+/// Have `command` set `limits` on the child between fork and exec.
+#[cfg(unix)]
+pub fn apply_resource_limits(command: &mut std::process::Command, limits: &ResourceLimits) {
+    use std::os::unix::process::CommandExt;
+
+    let rlimits = rlimits(limits);
+    if rlimits.is_empty() {
+        return;
+    }
+    // SAFETY: the hook only calls setrlimit, which is async-signal-safe, and
+    // allocates nothing.
+    unsafe {
+        command.pre_exec(move || {
+            for &(resource, limit) in &rlimits {
+                rlimit::setrlimit(resource, limit, limit)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+pub fn apply_resource_limits(_command: &mut std::process::Command, limits: &ResourceLimits) {
+    if !limits.is_unlimited() {
+        warn!(
+            "[ProcessManager] Resource limits are not enforced on Windows: {:?}",
+            limits
+        );
+    }
+}
+
+/// Why a process that exited with `status` likely ran into `limits`. Only
+/// `SIGXCPU` is the CPU limit; running out of address space usually shows up
+/// as an abort or a crash on the failed allocation, so memory violations are
+/// a best guess.
+#[cfg(unix)]
+pub fn limit_violation(
+    status: &std::process::ExitStatus,
+    limits: &ResourceLimits,
+) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let signal = status.signal()?;
+    if let Some(secs) = limits.max_cpu_secs {
+        if signal == libc::SIGXCPU {
+            return Some(format!("exceeded its CPU time limit of {}s", secs));
+        }
+    }
+    if let Some(bytes) = limits.max_memory_bytes {
+        if signal == libc::SIGABRT || signal == libc::SIGSEGV {
+            return Some(format!(
+                "likely exceeded its memory limit of {} bytes",
+                bytes
+            ));
+        }
+    }
+    None
+}
+
+#[cfg(windows)]
+pub fn limit_violation(
+    _status: &std::process::ExitStatus,
+    _limits: &ResourceLimits,
+) -> Option<String> {
+    None
+}
+
 // ============================================================================
 // Service Status
 // ============================================================================
@@ -92,6 +257,7 @@ pub struct ServiceInfo {
 pub trait ManagedService: Send + Sync {
     fn name(&self) -> &str;
     fn config(&self) -> &ServiceConfig;
+    fn config_mut(&mut self) -> &mut ServiceConfig;
     fn spawn(&mut self, app_handle: &AppHandle) -> Result<(), ProcessError>;
     fn shutdown(&mut self);
     fn is_running(&self) -> bool;
@@ -135,6 +301,10 @@ impl ManagedService for NoopManagedService {
         &self.config
     }
 
+    fn config_mut(&mut self) -> &mut ServiceConfig {
+        &mut self.config
+    }
+
     fn spawn(&mut self, _app_handle: &AppHandle) -> Result<(), ProcessError> {
         warn!(
             "[ProcessManager] '{}' start requested but service is retired: {}",
@@ -160,6 +330,22 @@ impl ManagedService for NoopManagedService {
     }
 }
 
+fn service_info(service: &dyn ManagedService) -> ServiceInfo {
+    ServiceInfo {
+        name: service.name().to_string(),
+        status: if service.is_running() {
+            ServiceStatus::Running
+        } else {
+            ServiceStatus::Stopped
+        },
+        pid: service.pid(),
+        uptime_secs: None,
+        restart_count: 0,
+        last_error: None,
+        health_check_url: service.config().health_check_url.clone(),
+    }
+}
+
 fn retired_service_noop_message(name: &str) -> Option<&'static str> {
     match name {
         "rag-service" => Some(
@@ -179,6 +365,8 @@ fn retired_service_noop_message(name: &str) -> Option<&'static str> {
 pub struct ProcessManager {
     services: HashMap<String, Box<dyn ManagedService>>,
     app_handle: Option<AppHandle>,
+    /// File service config edits are saved to
+    config_path: Option<PathBuf>,
 }
 
 impl ProcessManager {
@@ -200,6 +388,7 @@ impl ProcessManager {
         let mut manager = Self {
             services: HashMap::new(),
             app_handle: None,
+            config_path: None,
         };
 
         manager.register_service(Box::new(NoopManagedService::new(
@@ -225,6 +414,85 @@ impl ProcessManager {
         self.services.insert(name, service);
     }
 
+    /// Load saved service configs from `path` over the registered defaults,
+    /// and save later edits there.
+    pub fn load_configs(&mut self, path: PathBuf) {
+        match std::fs::read_to_string(&path)
+            .map(|raw| serde_json::from_str::<Vec<ServiceConfig>>(&raw))
+        {
+            Ok(Ok(saved)) => {
+                for config in &saved {
+                    if let Some(service) = self.services.get_mut(&config.name) {
+                        service.config_mut().restore_from(config);
+                    }
+                }
+                info!(
+                    "[ProcessManager] Loaded service configs from {}",
+                    path.display()
+                );
+            }
+            Ok(Err(e)) => warn!(
+                "[ProcessManager] Ignoring unreadable service configs {}: {}",
+                path.display(),
+                e
+            ),
+            Err(_) => {}
+        }
+        self.config_path = Some(path);
+    }
+
+    /// Configs of all registered services, sorted by name
+    pub fn get_service_configs(&self) -> Vec<ServiceConfig> {
+        let mut configs: Vec<ServiceConfig> = self
+            .services
+            .values()
+            .map(|service| service.config().clone())
+            .collect();
+        configs.sort_by(|a, b| a.name.cmp(&b.name));
+        configs
+    }
+
+    /// Apply `patch` to a service's config and save all configs.
+    pub fn update_service_config(
+        &mut self,
+        name: &str,
+        patch: ServiceConfigPatch,
+    ) -> Result<ServiceConfig, ProcessError> {
+        let service = self
+            .services
+            .get_mut(name)
+            .ok_or_else(|| ProcessError::NotFound(name.to_string()))?;
+        service.config_mut().apply_patch(patch);
+        let updated = service.config().clone();
+        self.persist_configs()?;
+        Ok(updated)
+    }
+
+    fn persist_configs(&self) -> Result<(), ProcessError> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ProcessError::ConfigError(e.to_string()))?;
+        }
+        let json = serde_json::to_string_pretty(&self.get_service_configs())
+            .map_err(|e| ProcessError::ConfigError(e.to_string()))?;
+        std::fs::write(path, json).map_err(|e| ProcessError::ConfigError(e.to_string()))
+    }
+
+    /// Names of the services `start_all` starts: enabled and set to auto-start
+    fn services_to_start(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .services
+            .values()
+            .filter(|service| service.config().enabled && service.config().auto_start)
+            .map(|service| service.name().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
     /// Start all enabled services
     pub fn start_all(&mut self) -> Vec<(String, Result<(), ProcessError>)> {
         let mut results = Vec::new();
@@ -236,15 +504,11 @@ impl ProcessManager {
             }
         };
 
-        let service_names: Vec<String> = self.services.keys().cloned().collect();
-
-        for name in service_names {
+        for name in self.services_to_start() {
             if let Some(service) = self.services.get_mut(&name) {
-                if service.config().enabled && service.config().auto_start {
-                    info!("[ProcessManager] Starting service: {}", name);
-                    let result = service.spawn(&app_handle);
-                    results.push((name, result));
-                }
+                info!("[ProcessManager] Starting service: {}", name);
+                let result = service.spawn(&app_handle);
+                results.push((name, result));
             }
         }
 
@@ -271,10 +535,7 @@ impl ProcessManager {
         if let Some(service) = self.services.get_mut(name) {
             service.spawn(&app_handle)
         } else {
-            Err(ProcessError::NotRunning(format!(
-                "Service not found: {}",
-                name
-            )))
+            Err(ProcessError::NotFound(name.to_string()))
         }
     }
 
@@ -284,10 +545,7 @@ impl ProcessManager {
             service.shutdown();
             Ok(())
         } else {
-            Err(ProcessError::NotRunning(format!(
-                "Service not found: {}",
-                name
-            )))
+            Err(ProcessError::NotFound(name.to_string()))
         }
     }
 
@@ -295,37 +553,15 @@ impl ProcessManager {
     pub fn get_all_status(&self) -> Vec<ServiceInfo> {
         self.services
             .values()
-            .map(|service| ServiceInfo {
-                name: service.name().to_string(),
-                status: if service.is_running() {
-                    ServiceStatus::Running
-                } else {
-                    ServiceStatus::Stopped
-                },
-                pid: service.pid(),
-                uptime_secs: None,
-                restart_count: 0,
-                last_error: None,
-                health_check_url: service.config().health_check_url.clone(),
-            })
+            .map(|service| service_info(service.as_ref()))
             .collect()
     }
 
     /// Get status of a specific service
     pub fn get_service_status(&self, name: &str) -> Option<ServiceInfo> {
-        self.services.get(name).map(|service| ServiceInfo {
-            name: service.name().to_string(),
-            status: if service.is_running() {
-                ServiceStatus::Running
-            } else {
-                ServiceStatus::Stopped
-            },
-            pid: service.pid(),
-            uptime_secs: None,
-            restart_count: 0,
-            last_error: None,
-            health_check_url: service.config().health_check_url.clone(),
-        })
+        self.services
+            .get(name)
+            .map(|service| service_info(service.as_ref()))
     }
 
     /// Check health of a specific service
@@ -333,10 +569,7 @@ impl ProcessManager {
         if let Some(service) = self.services.get(name) {
             service.health_check()
         } else {
-            Err(ProcessError::NotRunning(format!(
-                "Service not found: {}",
-                name
-            )))
+            Err(ProcessError::NotFound(name.to_string()))
         }
     }
 
@@ -357,6 +590,166 @@ impl ProcessManager {
 
         Self::prepare_managed_runtime(&app_handle, name)
     }
+
+    /// Ports of running CoworkAny services, taken from their health check
+    /// URLs. Services with no process of their own (the retired ones) have
+    /// no port here, whatever their URL says.
+    pub fn managed_ports(&self) -> Vec<u16> {
+        let mut ports: Vec<u16> = self
+            .services
+            .values()
+            .filter_map(|service| service_port(service.as_ref()))
+            .map(|(port, _)| port)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        ports
+    }
+
+    /// Kill the process a wedged service left listening on `port`, so it can
+    /// be restarted. Only PIDs the manager spawned and tracks for a service
+    /// on that port are killed, and only with `confirm`; anything else
+    /// holding the port is reported, not touched. Returns the killed PIDs.
+    pub fn force_free_port(&self, port: u16, confirm: bool) -> Result<Vec<u32>, ProcessError> {
+        let owned: Vec<u32> = self
+            .services
+            .values()
+            .filter_map(|service| service_port(service.as_ref()))
+            .filter(|(service_port, _)| *service_port == port)
+            .map(|(_, pid)| pid)
+            .collect();
+        if owned.is_empty() {
+            return Err(ProcessError::PortNotManaged(port));
+        }
+        if !confirm {
+            return Err(ProcessError::ForceNotConfirmed(port));
+        }
+
+        let (pids, foreign) = split_listeners(listener_pids(port)?, &owned);
+        if pids.is_empty() && !foreign.is_empty() {
+            return Err(ProcessError::ForeignListener {
+                port,
+                pids: foreign,
+            });
+        }
+        for pid in &pids {
+            kill_pid(*pid)?;
+        }
+        if !foreign.is_empty() {
+            warn!(
+                "[ProcessManager] Left processes CoworkAny did not start on port {}: {:?}",
+                port, foreign
+            );
+        }
+        warn!(
+            "[ProcessManager] Force-freed port {} (killed {:?})",
+            port, pids
+        );
+        Ok(pids)
+    }
+}
+
+/// Port and PID of a service that has a process running
+fn service_port(service: &dyn ManagedService) -> Option<(u16, u32)> {
+    let pid = service.pid()?;
+    let url = service.config().health_check_url.as_deref()?;
+    let port = url::Url::parse(url).ok()?.port_or_known_default()?;
+    Some((port, pid))
+}
+
+/// Split the PIDs listening on a port into those the manager owns and the
+/// rest. This process is never in either list.
+fn split_listeners(listeners: Vec<u32>, owned: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let own_pid = std::process::id();
+    listeners
+        .into_iter()
+        .filter(|pid| *pid != own_pid)
+        .partition(|pid| owned.contains(pid))
+}
+
+// ============================================================================
+// Port Recovery
+// ============================================================================
+
+/// PIDs of processes listening on TCP `port`
+fn listener_pids(port: u16) -> Result<Vec<u32>, ProcessError> {
+    #[cfg(unix)]
+    {
+        let output = std::process::Command::new("lsof")
+            .args(["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-t"])
+            .output()?;
+        // lsof exits 1 when nothing matches.
+        Ok(parse_lsof_pids(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    #[cfg(windows)]
+    {
+        let output = std::process::Command::new("netstat")
+            .args(["-ano", "-p", "TCP"])
+            .output()?;
+        Ok(parse_netstat_pids(
+            &String::from_utf8_lossy(&output.stdout),
+            port,
+        ))
+    }
+}
+
+fn kill_pid(pid: u32) -> Result<(), ProcessError> {
+    #[cfg(unix)]
+    let status = std::process::Command::new("kill")
+        .args(["-9", &pid.to_string()])
+        .status()?;
+
+    #[cfg(windows)]
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ProcessError::SpawnError(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("Failed to kill process {}", pid),
+        )))
+    }
+}
+
+/// `lsof -t` prints one PID per line
+#[cfg(unix)]
+fn parse_lsof_pids(output: &str) -> Vec<u32> {
+    let mut pids: Vec<u32> = output
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect();
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+/// `netstat -ano` rows look like `TCP  127.0.0.1:8787  0.0.0.0:0  LISTENING  4242`
+#[cfg(windows)]
+fn parse_netstat_pids(output: &str, port: u16) -> Vec<u32> {
+    let suffix = format!(":{}", port);
+    let mut pids: Vec<u32> = output
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            match columns.as_slice() {
+                [proto, local, _, state, pid]
+                    if proto.eq_ignore_ascii_case("tcp")
+                        && local.ends_with(&suffix)
+                        && state.eq_ignore_ascii_case("listening") =>
+                {
+                    pid.parse().ok()
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    pids.sort_unstable();
+    pids.dedup();
+    pids
 }
 
 impl Drop for ProcessManager {
@@ -375,4 +768,201 @@ impl ProcessManagerState {
     pub fn new() -> Self {
         Self(Arc::new(Mutex::new(ProcessManager::new())))
     }
+
+    /// Load and save service configs at `path`.
+    pub fn with_config_path(self, path: PathBuf) -> Self {
+        if let Ok(mut manager) = self.0.lock() {
+            manager.load_configs(path);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A service that reports a running process without starting one
+    struct RunningService {
+        config: ServiceConfig,
+        pid: u32,
+    }
+
+    impl ManagedService for RunningService {
+        fn name(&self) -> &str {
+            &self.config.name
+        }
+
+        fn config(&self) -> &ServiceConfig {
+            &self.config
+        }
+
+        fn config_mut(&mut self) -> &mut ServiceConfig {
+            &mut self.config
+        }
+
+        fn spawn(&mut self, _app_handle: &AppHandle) -> Result<(), ProcessError> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) {}
+
+        fn is_running(&self) -> bool {
+            true
+        }
+
+        fn pid(&self) -> Option<u32> {
+            Some(self.pid)
+        }
+
+        fn health_check(&self) -> Result<bool, ProcessError> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn force_free_port_only_targets_processes_the_manager_started() {
+        // The retired services run nothing, so their URLs claim no port.
+        let mut manager = ProcessManager::new();
+        assert!(manager.managed_ports().is_empty());
+        assert!(matches!(
+            manager.force_free_port(8787, true),
+            Err(ProcessError::PortNotManaged(8787))
+        ));
+
+        let pid = 4141;
+        manager.register_service(Box::new(RunningService {
+            config: ServiceConfig {
+                name: "worker".to_string(),
+                health_check_url: Some("http://127.0.0.1:18787/health".to_string()),
+                ..Default::default()
+            },
+            pid,
+        }));
+        assert_eq!(manager.managed_ports(), vec![18787]);
+        assert!(matches!(
+            manager.force_free_port(18787, false),
+            Err(ProcessError::ForceNotConfirmed(18787))
+        ));
+
+        let (owned, foreign) = split_listeners(vec![pid, 4242, std::process::id()], &[pid]);
+        assert_eq!(owned, vec![pid]);
+        assert_eq!(foreign, vec![4242]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lsof_output_yields_listener_pids() {
+        assert_eq!(parse_lsof_pids("4242\n17\n4242\n\n"), vec![17, 4242]);
+        assert!(parse_lsof_pids("").is_empty());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn netstat_output_yields_listener_pids_for_the_port() {
+        let output = "\
+Active Connections
+
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:135            0.0.0.0:0              LISTENING       1040
+  TCP    127.0.0.1:8787         0.0.0.0:0              LISTENING       4242
+  TCP    127.0.0.1:8787         127.0.0.1:50123        ESTABLISHED     4242
+  TCP    127.0.0.1:18787        0.0.0.0:0              LISTENING       77
+  TCP    [::1]:8787             [::]:0                 LISTENING       4243
+";
+        assert_eq!(parse_netstat_pids(output, 8787), vec![4242, 4243]);
+        assert!(parse_netstat_pids(output, 9999).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resource_limits_become_rlimits_on_the_spawned_process() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let limits = ResourceLimits {
+            max_memory_bytes: Some(512 * 1024 * 1024),
+            max_cpu_secs: Some(5),
+        };
+        assert_eq!(
+            rlimits(&limits),
+            vec![
+                (rlimit::Resource::AS, 512 * 1024 * 1024),
+                (rlimit::Resource::CPU, 5)
+            ]
+        );
+        assert!(rlimits(&ResourceLimits::default()).is_empty());
+
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "ulimit -t"]);
+        apply_resource_limits(&mut command, &limits);
+        let output = command.output().expect("spawn sh");
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "5");
+
+        let killed = std::process::ExitStatus::from_raw(libc::SIGXCPU);
+        assert_eq!(
+            limit_violation(&killed, &limits).as_deref(),
+            Some("exceeded its CPU time limit of 5s")
+        );
+        assert_eq!(limit_violation(&killed, &ResourceLimits::default()), None);
+        let sigkill = std::process::ExitStatus::from_raw(libc::SIGKILL);
+        assert_eq!(limit_violation(&sigkill, &limits), None);
+        let exited = std::process::ExitStatus::from_raw(0);
+        assert_eq!(limit_violation(&exited, &limits), None);
+    }
+
+    #[test]
+    fn service_config_edits_persist_and_disabled_services_are_not_started() {
+        let path = std::env::temp_dir().join(format!(
+            "coworkany-service-configs-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let mut manager = ProcessManager::new();
+        manager.load_configs(path.clone());
+
+        let updated = manager
+            .update_service_config(
+                "rag-service",
+                ServiceConfigPatch {
+                    auto_start: Some(true),
+                    health_check_interval_secs: Some(10),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(updated.enabled && updated.auto_start);
+        assert_eq!(updated.health_check_interval_secs, 10);
+        assert_eq!(manager.services_to_start(), vec!["rag-service"]);
+
+        manager
+            .update_service_config(
+                "rag-service",
+                ServiceConfigPatch {
+                    enabled: Some(false),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(
+            manager.services_to_start().is_empty(),
+            "a disabled service is skipped even with auto_start set"
+        );
+        assert!(matches!(
+            manager.update_service_config("missing-service", ServiceConfigPatch::default()),
+            Err(ProcessError::NotFound(_))
+        ));
+
+        // A fresh manager picks up the saved edits.
+        let mut reloaded = ProcessManager::new();
+        reloaded.load_configs(path.clone());
+        let rag = reloaded
+            .get_service_configs()
+            .into_iter()
+            .find(|config| config.name == "rag-service")
+            .unwrap();
+        assert!(!rag.enabled);
+        assert!(rag.auto_start);
+        assert_eq!(rag.health_check_interval_secs, 10);
+        let _ = std::fs::remove_file(&path);
+    }
 }
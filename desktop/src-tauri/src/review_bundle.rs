@@ -0,0 +1,421 @@
+//! CoworkAny Desktop - Review Bundle Export
+//!
+//! Packages what an agent proposed and what was applied in a workspace into a
+//! portable zip for reviewers: the shadow index, one git-style `.patch` per
+//! entry, and the matching audit records. Paths are made workspace-relative
+//! and credentials redacted so the bundle can leave the machine.
+
+use crate::diff::{
+    compute_unified_diff_with, generate_git_patch, DiffOptions, DiffStats, FilePatch,
+    PatchOperation,
+};
+use crate::policy::{AuditEvent, PolicyEngineState};
+use crate::shadow_fs::{ShadowFileEntry, ShadowFs, ShadowFsError, ShadowFsState, ShadowStatus};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use thiserror::Error;
+use tracing::{info, warn};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Error, Debug)]
+pub enum ReviewBundleError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Serialization error: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// One shadow entry as listed in the bundle manifest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewBundleEntry {
+    pub id: String,
+    pub path: String,
+    pub status: ShadowStatus,
+    pub operation: Option<PatchOperation>,
+    /// Location of the entry's patch inside the bundle
+    pub patch_file: String,
+    pub created_at: String,
+    pub reviewed_at: Option<String>,
+    pub applied_at: Option<String>,
+    pub source: Option<String>,
+}
+
+/// `manifest.json` at the root of the bundle
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewBundleManifest {
+    pub format_version: u32,
+    pub created_at: String,
+    /// Workspace directory name; the absolute root is left out
+    pub workspace: String,
+    pub entries: Vec<ReviewBundleEntry>,
+    /// Totals across every patch in the bundle
+    pub stats: DiffStats,
+    pub shadow_audit_records: usize,
+    pub policy_audit_events: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewBundleSummary {
+    pub path: String,
+    pub entries: usize,
+    pub shadow_audit_records: usize,
+    pub policy_audit_events: usize,
+}
+
+/// What a bundle is built from, copied out of a `ShadowFs` so the zip can be
+/// written without holding its lock
+#[derive(Debug, Clone)]
+pub struct BundleSource {
+    pub workspace_root: PathBuf,
+    pub entries: Vec<ShadowFileEntry>,
+    pub shadow_audit_log: PathBuf,
+}
+
+impl BundleSource {
+    pub fn from_shadow(shadow: &ShadowFs) -> Self {
+        Self {
+            workspace_root: shadow.workspace_root().to_path_buf(),
+            entries: shadow.entries().into_iter().cloned().collect(),
+            shadow_audit_log: shadow.audit_log_path().to_path_buf(),
+        }
+    }
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Write a review bundle for `source`'s workspace to `dest`. Policy audit
+/// events are read from `policy_audit_log` when given; only those acting on
+/// paths inside the workspace are included.
+pub fn write_review_bundle(
+    source: &BundleSource,
+    policy_audit_log: Option<&Path>,
+    dest: &Path,
+) -> Result<ReviewBundleSummary, ReviewBundleError> {
+    let root = source.workspace_root.as_path();
+    let entries = &source.entries;
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(File::create(dest)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest_entries = Vec::with_capacity(entries.len());
+    let mut index = Vec::with_capacity(entries.len());
+    let mut stats = DiffStats::default();
+    for (position, entry) in entries.iter().enumerate() {
+        let path = relative_path(root, &entry.original_path);
+        let patch = bundle_patch(root, entry);
+        let patch_file = format!("patches/{:04}-{}.patch", position + 1, entry.id);
+        zip.start_file(patch_file.as_str(), options)?;
+        zip.write_all(generate_git_patch(&patch).as_bytes())?;
+        stats += patch.stats();
+
+        manifest_entries.push(ReviewBundleEntry {
+            id: entry.id.clone(),
+            path,
+            status: entry.status,
+            operation: Some(patch.operation),
+            patch_file,
+            created_at: entry.created_at.clone(),
+            reviewed_at: entry.reviewed_at.clone(),
+            applied_at: entry.applied_at.clone(),
+            source: entry.source.clone(),
+        });
+        index.push(relative_entry(root, entry, patch));
+    }
+
+    zip.start_file("index.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&index)?)?;
+
+    let ids: Vec<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+    let shadow_records = shadow_audit_records(&source.shadow_audit_log, root, &ids)?;
+    zip.start_file("audit/shadow.jsonl", options)?;
+    zip.write_all(jsonl(&shadow_records)?.as_bytes())?;
+
+    let policy_events = match policy_audit_log {
+        Some(path) if path.exists() => policy_audit_events(path, root)?,
+        _ => Vec::new(),
+    };
+    zip.start_file("audit/policy.jsonl", options)?;
+    zip.write_all(jsonl(&policy_events)?.as_bytes())?;
+
+    let manifest = ReviewBundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        workspace: root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        entries: manifest_entries,
+        stats,
+        shadow_audit_records: shadow_records.len(),
+        policy_audit_events: policy_events.len(),
+    };
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.finish()?;
+
+    Ok(ReviewBundleSummary {
+        path: dest.to_string_lossy().to_string(),
+        entries: manifest.entries.len(),
+        shadow_audit_records: manifest.shadow_audit_records,
+        policy_audit_events: manifest.policy_audit_events,
+    })
+}
+
+/// The entry's patch with workspace-relative paths. Entries indexed without a
+/// patch get one computed from the original and the shadow copy.
+fn bundle_patch(root: &Path, entry: &ShadowFileEntry) -> FilePatch {
+    let mut patch = entry.patch.clone().unwrap_or_else(|| {
+        let original = fs::read_to_string(&entry.original_path).unwrap_or_default();
+        let shadow = fs::read_to_string(&entry.shadow_path).unwrap_or_default();
+        let options = DiffOptions {
+            algorithm: entry.diff_algorithm,
+            ..Default::default()
+        };
+        compute_unified_diff_with(&original, &shadow, "", 3, &options)
+    });
+    patch.file_path = relative_path(root, &entry.original_path);
+    patch.new_file_path = patch
+        .new_file_path
+        .as_deref()
+        .map(|path| relative_path(root, Path::new(path)));
+    patch
+}
+
+fn relative_entry(root: &Path, entry: &ShadowFileEntry, patch: FilePatch) -> ShadowFileEntry {
+    let relative = |path: &Path| PathBuf::from(relative_path(root, path));
+    ShadowFileEntry {
+        original_path: relative(&entry.original_path),
+        shadow_path: relative(&entry.shadow_path),
+        backup_path: entry.backup_path.as_deref().map(relative),
+        trashed_path: entry.trashed_path.as_deref().map(relative),
+        renamed_from: entry.renamed_from.as_deref().map(relative),
+        patch: Some(patch),
+        ..entry.clone()
+    }
+}
+
+/// `path` relative to `root` with `/` separators. Paths outside the
+/// workspace keep only their file name.
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative,
+        Err(_) if path.is_relative() => path,
+        Err(_) => {
+            return path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        }
+    };
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Shadow audit lines for the bundled entries, with paths made relative
+fn shadow_audit_records(
+    path: &Path,
+    root: &Path,
+    ids: &[&str],
+) -> Result<Vec<serde_json::Value>, ReviewBundleError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut records = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let Ok(mut record) = serde_json::from_str::<serde_json::Value>(&line?) else {
+            continue;
+        };
+        let bundled = record
+            .get("id")
+            .and_then(|id| id.as_str())
+            .is_some_and(|id| ids.contains(&id));
+        if !bundled {
+            continue;
+        }
+        for key in ["originalPath", "targetPath"] {
+            if let Some(value) = record.get_mut(key) {
+                if let Some(path) = value.as_str() {
+                    *value = relative_path(root, Path::new(path)).into();
+                }
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Redacted policy audit events whose path or cwd is inside the workspace
+fn policy_audit_events(path: &Path, root: &Path) -> Result<Vec<AuditEvent>, ReviewBundleError> {
+    let inside = |value: &Option<String>| {
+        value
+            .as_deref()
+            .is_some_and(|path| Path::new(path).starts_with(root))
+    };
+    let mut events = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let Ok(mut event) = serde_json::from_str::<AuditEvent>(&line?) else {
+            continue;
+        };
+        let payload = &event.request.payload;
+        if !inside(&payload.path) && !inside(&payload.cwd) {
+            continue;
+        }
+        event.redact();
+        let payload = &mut event.request.payload;
+        for value in [&mut payload.path, &mut payload.cwd] {
+            if let Some(path) = value.as_mut() {
+                *path = relative_path(root, Path::new(path.as_str()));
+            }
+        }
+        events.push(event);
+    }
+    Ok(events)
+}
+
+fn jsonl<T: Serialize>(records: &[T]) -> Result<String, serde_json::Error> {
+    let mut output = String::new();
+    for record in records {
+        output.push_str(&serde_json::to_string(record)?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Export the current workspace's shadow index, patches and audit trail as a
+/// zip at `dest`. The entries are copied under the ShadowFs lock and the zip
+/// is written after it is released.
+#[tauri::command]
+pub async fn export_review_bundle(
+    shadow_state: State<'_, ShadowFsState>,
+    policy_state: State<'_, PolicyEngineState>,
+    dest: String,
+) -> Result<ReviewBundleSummary, String> {
+    let source = {
+        let guard = shadow_state.lock().await;
+        let shadow = guard
+            .as_ref()
+            .ok_or_else(|| ShadowFsError::NotInitialized.to_string())?;
+        BundleSource::from_shadow(shadow)
+    };
+    let policy_audit_log = policy_state.audit_log_path().map(Path::to_path_buf);
+
+    let dest_path = PathBuf::from(&dest);
+    let summary = tokio::task::spawn_blocking(move || {
+        write_review_bundle(&source, policy_audit_log.as_deref(), &dest_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| {
+        warn!("Failed to export review bundle to {}: {}", dest, e);
+        e.to_string()
+    })?;
+    info!(
+        "Exported review bundle with {} entries to {}",
+        summary.entries, summary.path
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use zip::ZipArchive;
+
+    fn unique_temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        std::env::temp_dir().join(format!("coworkany-{name}-{}-{nanos}", std::process::id()))
+    }
+
+    #[test]
+    fn bundle_has_one_patch_per_entry_and_a_manifest() {
+        let workspace = unique_temp_dir("review-bundle");
+        fs::create_dir_all(workspace.join("src")).unwrap();
+        fs::write(workspace.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        let mut shadow = ShadowFs::new(workspace.clone()).unwrap();
+        let modified = shadow
+            .stage_file(&workspace.join("src/lib.rs"), "fn a() {}\nfn b() {}\n")
+            .unwrap();
+        shadow
+            .stage_file(&workspace.join("README.md"), "# Notes\n")
+            .unwrap();
+        shadow.approve(&modified.id).unwrap();
+        shadow.apply(&modified.id, false).unwrap();
+
+        let dest = workspace.join("out/review.zip");
+        let summary =
+            write_review_bundle(&BundleSource::from_shadow(&shadow), None, &dest).unwrap();
+        assert_eq!(summary.entries, 2);
+
+        let mut archive = ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        let patches: Vec<&String> = names
+            .iter()
+            .filter(|name| name.starts_with("patches/") && name.ends_with(".patch"))
+            .collect();
+        assert_eq!(patches.len(), 2);
+        assert!(names.iter().any(|name| name == "index.json"));
+
+        let mut manifest = String::new();
+        archive
+            .by_name("manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        let entries = manifest["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().any(|entry| entry["path"] == "src/lib.rs"));
+        assert!(manifest["shadowAuditRecords"].as_u64().unwrap() >= 1);
+
+        let mut bundle_text = String::new();
+        for name in &names {
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut bundle_text)
+                .unwrap();
+        }
+        let root = workspace.to_string_lossy().to_string();
+        assert!(
+            !bundle_text.contains(&root),
+            "absolute workspace paths must not leak into the bundle"
+        );
+        assert!(bundle_text.contains("diff --git a/README.md b/README.md\nnew file mode 100644"));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+}
@@ -1,8 +1,89 @@
 use screenshots::Screen;
 
+use crate::policy::{EffectPayload, EffectRequest, EffectSource, EffectType, PolicyEngineState};
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
 use std::io::Cursor;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::{OnceCell, Semaphore};
+use tracing::{debug, warn};
+
+const JPEG_QUALITY: u8 = 85;
+/// Longest pre-capture countdown a caller may ask for
+const MAX_CAPTURE_DELAY_MS: u64 = 30_000;
+/// Error code for machines where screen capture cannot work at all, so the
+/// frontend can hide the feature
+pub const CAPTURE_UNSUPPORTED: &str = "capture_unsupported";
+
+static CAPTURE_SUPPORT: OnceLock<Result<usize, String>> = OnceLock::new();
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+/// A rectangle in global (virtual desktop) coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How the pixels were obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMethod {
+    /// Read from the window itself, so overlapping windows are not included
+    Window,
+    /// Cropped from the screen at the window's (or caller's) bounds
+    Region,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveWindowCapture {
+    pub image_base64: String,
+    pub format: CaptureFormat,
+    pub method: CaptureMethod,
+    pub region: CaptureRegion,
+    pub window_title: Option<String>,
+    pub app_name: Option<String>,
+    /// Waited for another capture to finish first
+    pub queued: bool,
+    /// Shared the result of an identical request that was already running
+    pub deduplicated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedWindow {
+    pub id: u32,
+    pub title: String,
+    pub app_name: String,
+    pub bounds: CaptureRegion,
+}
+
+/// What the platform can tell us about the foreground window.
+#[derive(Debug, Clone, Default)]
+pub struct FocusedWindowProbe {
+    /// The platform lets us read another window's pixels directly
+    pub window_capture_supported: bool,
+    pub focused: Option<FocusedWindow>,
+}
 
 pub struct ScreenCapture;
 
@@ -24,9 +105,634 @@ impl ScreenCapture {
         let encoded = BASE64.encode(buffer.get_ref());
         Ok(encoded)
     }
+
+    /// Capture the focused window, falling back to cropping its bounds (or
+    /// `fallback_region` when no focused window can be found) from the screen.
+    pub fn capture_active_window(
+        format: CaptureFormat,
+        fallback_region: Option<CaptureRegion>,
+    ) -> Result<ActiveWindowCapture, String> {
+        let probe = probe_focused_window();
+        let (method, region) = select_capture_method(&probe, fallback_region)?;
+
+        let (image, method) = match (method, probe.focused.as_ref()) {
+            (CaptureMethod::Window, Some(window)) => match capture_window(window.id) {
+                Ok(image) => (image, CaptureMethod::Window),
+                Err(e) => {
+                    warn!("Window capture failed, falling back to region: {}", e);
+                    (capture_region(region)?, CaptureMethod::Region)
+                }
+            },
+            _ => (capture_region(region)?, CaptureMethod::Region),
+        };
+
+        Ok(ActiveWindowCapture {
+            image_base64: encode_image(&image, format)?,
+            format,
+            method,
+            region,
+            window_title: probe.focused.as_ref().map(|w| w.title.clone()),
+            app_name: probe.focused.as_ref().map(|w| w.app_name.clone()),
+            queued: false,
+            deduplicated: false,
+        })
+    }
+}
+
+// ============================================================================
+// Capture queue
+// ============================================================================
+
+/// What a capture job produced; the queue key decides which variant to expect.
+#[derive(Debug, Clone)]
+pub enum CaptureOutput {
+    Screen(String),
+    ActiveWindow(ActiveWindowCapture),
 }
 
+type CaptureResult = Result<CaptureOutput, String>;
+
+#[derive(Debug, Clone)]
+pub struct QueuedCapture {
+    pub output: CaptureOutput,
+    pub queued: bool,
+    pub deduplicated: bool,
+}
+
+/// Runs at most one capture at a time. Requests with the same key that
+/// arrive while one is running share its result instead of capturing again.
+pub struct CaptureQueue {
+    permit: Semaphore,
+    in_flight: Mutex<HashMap<String, Arc<OnceCell<CaptureResult>>>>,
+}
+
+impl Default for CaptureQueue {
+    fn default() -> Self {
+        Self {
+            permit: Semaphore::new(1),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl CaptureQueue {
+    /// Run `job` once the running capture (if any) finishes. With `wait`
+    /// false, a busy queue fails fast with `capture_in_progress` instead.
+    pub async fn run<F, Fut>(&self, key: &str, wait: bool, job: F) -> Result<QueuedCapture, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = CaptureResult>,
+    {
+        let existing = self
+            .in_flight
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(key)
+            .cloned();
+        if let Some(cell) = existing {
+            debug!("Joining in-flight capture {}", key);
+            // Never runs `job` unless the original request was dropped mid-capture.
+            let output = cell
+                .get_or_init(|| async {
+                    let _permit = self.permit.acquire().await.map_err(|e| e.to_string())?;
+                    job().await
+                })
+                .await
+                .clone()?;
+            return Ok(QueuedCapture {
+                output,
+                queued: false,
+                deduplicated: true,
+            });
+        }
+
+        let immediate = self.permit.try_acquire().ok();
+        if immediate.is_none() && !wait {
+            return Err("capture_in_progress".to_string());
+        }
+        let queued = immediate.is_none();
+        if queued {
+            debug!("Capture {} queued behind a running capture", key);
+        }
+
+        // Registered before waiting, so identical requests that arrive while
+        // this one is queued join it too.
+        let cell = Arc::new(OnceCell::new());
+        self.in_flight
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(key.to_string(), cell.clone());
+
+        let result = cell
+            .get_or_init(|| async move {
+                let _permit = match immediate {
+                    Some(permit) => permit,
+                    None => self.permit.acquire().await.map_err(|e| e.to_string())?,
+                };
+                job().await
+            })
+            .await
+            .clone();
+
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            if in_flight.get(key).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+                in_flight.remove(key);
+            }
+        }
+
+        Ok(QueuedCapture {
+            output: result?,
+            queued,
+            deduplicated: false,
+        })
+    }
+}
+
+/// Wait out `delay`, calling `on_tick` with the whole seconds left at the
+/// start and then once a second. Holds no locks or queue permits, so other
+/// captures proceed while a countdown runs.
+async fn countdown(delay: Duration, mut on_tick: impl FnMut(u64)) {
+    let mut remaining = delay;
+    while !remaining.is_zero() {
+        on_tick(remaining.as_millis().div_ceil(1000) as u64);
+        let step = remaining.min(Duration::from_secs(1));
+        tokio::time::sleep(step).await;
+        remaining -= step;
+    }
+}
+
+fn active_window_queue_key(
+    format: CaptureFormat,
+    fallback_region: Option<CaptureRegion>,
+) -> String {
+    match fallback_region {
+        Some(r) => format!(
+            "active_window:{:?}:{},{},{}x{}",
+            format, r.x, r.y, r.width, r.height
+        ),
+        None => format!("active_window:{:?}", format),
+    }
+}
+
+// ============================================================================
+// Platform probing
+// ============================================================================
+
+/// How many screens can be captured, or why none can. Probed once (at
+/// startup) and remembered.
+pub fn capture_support() -> Result<usize, String> {
+    CAPTURE_SUPPORT
+        .get_or_init(|| {
+            let screens = Screen::all()
+                .map(|screens| screens.len())
+                .map_err(|e| e.to_string());
+            classify_capture_support(screens, &DisplayEnv::current())
+        })
+        .clone()
+}
+
+/// `capture_unsupported: <reason>` when capture can't work on this machine
+fn ensure_capture_supported() -> Result<(), String> {
+    capture_support()
+        .map(|_| ())
+        .map_err(|reason| format!("{}: {}", CAPTURE_UNSUPPORTED, reason))
+}
+
+/// The display server a capture probe ran under
+struct DisplayEnv {
+    os: &'static str,
+    x11: bool,
+    wayland: bool,
+}
+
+impl DisplayEnv {
+    fn current() -> Self {
+        Self {
+            os: std::env::consts::OS,
+            x11: std::env::var_os("DISPLAY").is_some(),
+            wayland: std::env::var_os("WAYLAND_DISPLAY").is_some(),
+        }
+    }
+}
+
+/// Turn a screen enumeration result into the number of screens, or a reason
+/// naming the platform when there is nothing to capture.
+fn classify_capture_support(
+    screens: Result<usize, String>,
+    env: &DisplayEnv,
+) -> Result<usize, String> {
+    let problem = match screens {
+        Ok(0) => "no displays detected".to_string(),
+        Ok(count) => return Ok(count),
+        Err(error) => error,
+    };
+    let platform = match env.os {
+        "linux" if !env.x11 && !env.wayland => "linux (headless, no display server)",
+        "linux" if env.wayland => "linux (Wayland session)",
+        os => os,
+    };
+    Err(format!("{}: {}", platform, problem))
+}
+
+/// Wayland compositors don't let clients read other windows' contents.
+fn window_capture_supported() -> bool {
+    !(cfg!(target_os = "linux") && std::env::var_os("WAYLAND_DISPLAY").is_some())
+}
+
+fn probe_focused_window() -> FocusedWindowProbe {
+    let focused = match xcap::Window::all() {
+        Ok(windows) => windows.into_iter().find_map(|window| {
+            let focused = window.is_focused().unwrap_or(false);
+            let minimized = window.is_minimized().unwrap_or(false);
+            if !focused || minimized {
+                return None;
+            }
+            Some(FocusedWindow {
+                id: window.id().ok()?,
+                title: window.title().unwrap_or_default(),
+                app_name: window.app_name().unwrap_or_default(),
+                bounds: CaptureRegion {
+                    x: window.x().ok()?,
+                    y: window.y().ok()?,
+                    width: window.width().ok()?,
+                    height: window.height().ok()?,
+                },
+            })
+        }),
+        Err(e) => {
+            debug!("Window enumeration unavailable: {}", e);
+            None
+        }
+    };
+
+    FocusedWindowProbe {
+        window_capture_supported: window_capture_supported(),
+        focused,
+    }
+}
+
+/// Prefer reading the window directly; otherwise crop its bounds, or the
+/// caller's region when there is no focused window to go on.
+fn select_capture_method(
+    probe: &FocusedWindowProbe,
+    fallback_region: Option<CaptureRegion>,
+) -> Result<(CaptureMethod, CaptureRegion), String> {
+    match &probe.focused {
+        Some(window) if probe.window_capture_supported => {
+            Ok((CaptureMethod::Window, window.bounds))
+        }
+        Some(window) => Ok((CaptureMethod::Region, window.bounds)),
+        None => fallback_region
+            .map(|region| (CaptureMethod::Region, region))
+            .ok_or_else(|| "No focused window found".to_string()),
+    }
+}
+
+// ============================================================================
+// Capture & encoding
+// ============================================================================
+
+fn capture_window(window_id: u32) -> Result<image::RgbaImage, String> {
+    let windows = xcap::Window::all().map_err(|e| e.to_string())?;
+    let window = windows
+        .into_iter()
+        .find(|w| w.id().ok() == Some(window_id))
+        .ok_or("Focused window closed before capture")?;
+    let captured = window.capture_image().map_err(|e| e.to_string())?;
+    let (width, height) = (captured.width(), captured.height());
+    // xcap links a newer `image`; move the raw RGBA buffer across.
+    image::RgbaImage::from_raw(width, height, captured.into_raw())
+        .ok_or_else(|| "Window capture returned a malformed buffer".to_string())
+}
+
+fn capture_region(region: CaptureRegion) -> Result<image::RgbaImage, String> {
+    let screen = Screen::from_point(region.x, region.y).map_err(|e| e.to_string())?;
+    let origin = screen.display_info;
+    screen
+        .capture_area(
+            region.x - origin.x,
+            region.y - origin.y,
+            region.width,
+            region.height,
+        )
+        .map_err(|e| e.to_string())
+}
+
+fn encode_image(image: &image::RgbaImage, format: CaptureFormat) -> Result<String, String> {
+    let mut buffer = Cursor::new(Vec::new());
+    match format {
+        CaptureFormat::Png => image.write_to(&mut buffer, image::ImageOutputFormat::Png),
+        // JPEG has no alpha channel.
+        CaptureFormat::Jpeg => image::DynamicImage::ImageRgba8(image.clone())
+            .to_rgb8()
+            .write_to(&mut buffer, image::ImageOutputFormat::Jpeg(JPEG_QUALITY)),
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(BASE64.encode(buffer.get_ref()))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Capture the primary screen as base64 PNG. With `wait` false, fails with
+/// `capture_in_progress` instead of queueing behind a running capture.
+/// `delay_ms` counts down first, emitting `capture-countdown` each second.
+/// Fails with `capture_unsupported: <reason>` on machines with nothing to
+/// capture, as reported by `get_capabilities`.
+#[tauri::command]
+pub async fn capture_screen(
+    app_handle: AppHandle,
+    queue: State<'_, CaptureQueue>,
+    wait: Option<bool>,
+    delay_ms: Option<u64>,
+) -> Result<String, String> {
+    // Fail before counting down, not after the user has waited.
+    ensure_capture_supported()?;
+    if let Some(delay_ms) = delay_ms.filter(|ms| *ms > 0) {
+        let delay = Duration::from_millis(delay_ms.min(MAX_CAPTURE_DELAY_MS));
+        countdown(delay, |remaining_secs| {
+            let _ = app_handle.emit(
+                "capture-countdown",
+                serde_json::json!({ "remainingSecs": remaining_secs }),
+            );
+        })
+        .await;
+    }
+
+    let captured = queue
+        .run("screen:primary", wait.unwrap_or(true), || async {
+            tauri::async_runtime::spawn_blocking(ScreenCapture::capture_primary)
+                .await
+                .map_err(|e| e.to_string())?
+                .map(CaptureOutput::Screen)
+        })
+        .await?;
+    match captured.output {
+        CaptureOutput::Screen(image_base64) => Ok(image_base64),
+        CaptureOutput::ActiveWindow(_) => Err("Unexpected capture output".to_string()),
+    }
+}
+
+/// Capture the window the user is looking at, on the user's own request from
+/// the UI: no confirmation is asked, but the capture is refused when the
+/// screen-capture effect is blocked by policy.
 #[tauri::command]
-pub async fn capture_screen() -> Result<String, String> {
-    ScreenCapture::capture_primary()
+pub async fn capture_active_window(
+    policy_state: State<'_, PolicyEngineState>,
+    queue: State<'_, CaptureQueue>,
+    format: Option<CaptureFormat>,
+    fallback_region: Option<CaptureRegion>,
+    wait: Option<bool>,
+) -> Result<ActiveWindowCapture, String> {
+    let request = EffectRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        effect_type: EffectType::ScreenCapture,
+        source: EffectSource::User,
+        source_id: None,
+        payload: EffectPayload {
+            description: Some("Capture the active window".to_string()),
+            ..Default::default()
+        },
+        context: None,
+        scope: None,
+    };
+    if let Some(denial) = policy_state.outright_denial(&request).await {
+        return Err(denial);
+    }
+    ensure_capture_supported()?;
+
+    let format = format.unwrap_or_default();
+    let key = active_window_queue_key(format, fallback_region);
+    let captured = queue
+        .run(&key, wait.unwrap_or(true), || async move {
+            tauri::async_runtime::spawn_blocking(move || {
+                ScreenCapture::capture_active_window(format, fallback_region)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+            .map(CaptureOutput::ActiveWindow)
+        })
+        .await?;
+    match captured.output {
+        CaptureOutput::ActiveWindow(capture) => Ok(ActiveWindowCapture {
+            queued: captured.queued,
+            deduplicated: captured.deduplicated,
+            ..capture
+        }),
+        CaptureOutput::Screen(_) => Err("Unexpected capture output".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    fn focused_window() -> FocusedWindow {
+        FocusedWindow {
+            id: 7,
+            title: "notes.md - Editor".to_string(),
+            app_name: "Editor".to_string(),
+            bounds: CaptureRegion {
+                x: 100,
+                y: 80,
+                width: 1280,
+                height: 720,
+            },
+        }
+    }
+
+    #[test]
+    fn classifies_unsupported_capture_with_the_platform_reason() {
+        let headless = DisplayEnv {
+            os: "linux",
+            x11: false,
+            wayland: false,
+        };
+        let wayland = DisplayEnv {
+            os: "linux",
+            x11: false,
+            wayland: true,
+        };
+        let mac = DisplayEnv {
+            os: "macos",
+            x11: false,
+            wayland: false,
+        };
+
+        assert_eq!(classify_capture_support(Ok(2), &headless), Ok(2));
+        assert_eq!(
+            classify_capture_support(Err("XOpenDisplay failed".to_string()), &headless),
+            Err("linux (headless, no display server): XOpenDisplay failed".to_string())
+        );
+        assert_eq!(
+            classify_capture_support(Err("portal denied".to_string()), &wayland),
+            Err("linux (Wayland session): portal denied".to_string())
+        );
+        assert_eq!(
+            classify_capture_support(Ok(0), &mac),
+            Err("macos: no displays detected".to_string())
+        );
+    }
+
+    #[test]
+    fn selects_window_capture_when_supported() {
+        let probe = FocusedWindowProbe {
+            window_capture_supported: true,
+            focused: Some(focused_window()),
+        };
+        assert_eq!(
+            select_capture_method(&probe, None),
+            Ok((CaptureMethod::Window, focused_window().bounds))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_region_capture() {
+        // Unsupported platform: crop the focused window's bounds.
+        let probe = FocusedWindowProbe {
+            window_capture_supported: false,
+            focused: Some(focused_window()),
+        };
+        assert_eq!(
+            select_capture_method(&probe, None),
+            Ok((CaptureMethod::Region, focused_window().bounds))
+        );
+
+        // No focused window: use the caller's region, or fail without one.
+        let fallback = CaptureRegion {
+            x: 0,
+            y: 0,
+            width: 640,
+            height: 480,
+        };
+        let probe = FocusedWindowProbe::default();
+        assert_eq!(
+            select_capture_method(&probe, Some(fallback)),
+            Ok((CaptureMethod::Region, fallback))
+        );
+        assert!(select_capture_method(&probe, None).is_err());
+    }
+
+    /// Job that records how many captures overlap while it "captures".
+    fn tracked_job(
+        running: Arc<AtomicUsize>,
+        max_running: Arc<AtomicUsize>,
+        runs: Arc<AtomicUsize>,
+    ) -> impl FnOnce() -> std::pin::Pin<Box<dyn Future<Output = CaptureResult> + Send>> {
+        move || {
+            Box::pin(async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(CaptureOutput::Screen("image".to_string()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_captures_run_one_at_a_time() {
+        let queue = CaptureQueue::default();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let (first, second) = tokio::join!(
+            queue.run(
+                "region:a",
+                true,
+                tracked_job(running.clone(), max_running.clone(), runs.clone())
+            ),
+            queue.run(
+                "region:b",
+                true,
+                tracked_job(running.clone(), max_running.clone(), runs.clone())
+            ),
+        );
+        let (first, second) = (first.expect("first"), second.expect("second"));
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(!first.queued);
+        assert!(second.queued);
+    }
+
+    #[tokio::test]
+    async fn identical_in_flight_requests_share_one_capture() {
+        let queue = CaptureQueue::default();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let (first, second) = tokio::join!(
+            queue.run(
+                "region:a",
+                true,
+                tracked_job(running.clone(), max_running.clone(), runs.clone())
+            ),
+            queue.run(
+                "region:a",
+                true,
+                tracked_job(running.clone(), max_running.clone(), runs.clone())
+            ),
+        );
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(!first.expect("first").deduplicated);
+        assert!(second.expect("second").deduplicated);
+    }
+
+    #[tokio::test]
+    async fn busy_queue_fails_fast_without_wait() {
+        let queue = CaptureQueue::default();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let (first, second) = tokio::join!(
+            queue.run(
+                "region:a",
+                true,
+                tracked_job(running.clone(), max_running.clone(), runs.clone())
+            ),
+            queue.run(
+                "region:b",
+                false,
+                tracked_job(running.clone(), max_running.clone(), runs.clone())
+            ),
+        );
+
+        assert!(first.is_ok());
+        assert_eq!(second.unwrap_err(), "capture_in_progress");
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn capture_waits_out_the_requested_delay() {
+        let queue = CaptureQueue::default();
+        let delay = Duration::from_millis(1100);
+        let started = Instant::now();
+        let mut ticks = Vec::new();
+
+        countdown(delay, |remaining| ticks.push(remaining)).await;
+        let captured = queue
+            .run("screen:primary", true, || async move {
+                Ok(CaptureOutput::Screen(
+                    started.elapsed().as_millis().to_string(),
+                ))
+            })
+            .await
+            .expect("capture");
+
+        assert_eq!(ticks, vec![2, 1]);
+        let CaptureOutput::Screen(captured_after_ms) = captured.output else {
+            panic!("unexpected capture output");
+        };
+        assert!(captured_after_ms.parse::<u128>().unwrap() >= delay.as_millis());
+    }
 }
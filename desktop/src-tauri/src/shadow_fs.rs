@@ -6,13 +6,23 @@
 //!
 //! Flow: Agent write → Shadow FS → Diff preview → User approve → Atomic write
 
-use crate::diff::{compute_unified_diff, FilePatch, PatchOperation};
+use crate::diff::{
+    apply_patch as apply_file_patch, apply_patch_with_progress, compute_binary_diff,
+    compute_unified_diff, compute_unified_diff_with, generate_unified_diff_string, hunk_offsets,
+    is_binary, reverse_patch, summarize, three_way_merge, DiffAlgorithm, DiffError, DiffOptions,
+    DiffStats, FilePatch, HunkProgress, MergeResult, PatchOperation, DEFAULT_HUNK_FUZZ,
+    HUNK_PROGRESS_MIN_HUNKS,
+};
+use crate::policy::{path_rule_matches, PolicyEngineState};
+use notify::{RecursiveMode, Watcher};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 use thiserror::Error;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
@@ -35,13 +45,58 @@ pub enum ShadowFsError {
         actual_hash: String,
     },
 
-    #[error("Shadow directory not initialized")]
-    #[allow(dead_code)]
+    /// Surfaced to the UI verbatim so it can prompt for a workspace.
+    #[error("ShadowFsNotInitialized: Shadow FS not initialized")]
     NotInitialized,
 
+    #[error("NotRestorable: rejected entry {0} has no retained content to restage")]
+    NotRestorable(String),
+
     #[error("Target already exists: {0}")]
     TargetExists(String),
 
+    #[error("TargetPending: {target} is also the destination of earlier entry {id}, which has not been applied or rejected")]
+    TargetPending { target: String, id: String },
+
+    #[error("NotRevertible: entry {0} is not an applied text change")]
+    NotRevertible(String),
+
+    #[error("NotConflicted: entry {0} is not in conflict")]
+    NotConflicted(String),
+
+    #[error("NotApproved: entry {0} is not approved")]
+    NotApproved(String),
+
+    #[error("NotInTrash: entry {0} is not an applied delete with its file in trash")]
+    NotInTrash(String),
+
+    #[error("SnapshotNotFound: no shadow snapshot {0}")]
+    SnapshotNotFound(String),
+
+    #[error("SymlinkTarget: {0} is a symlink; refusing to write through it")]
+    SymlinkTarget(String),
+
+    #[error("path_protected: {0} is a protected path; the agent may not stage changes to it")]
+    PathProtected(String),
+
+    #[error("path_outside_workspace: {0} resolves outside the workspace root")]
+    OutsideWorkspace(String),
+
+    #[error("QuotaExceeded: shadow store holds {used} bytes, over its {limit} byte quota; clean up applied or rejected changes to stage more")]
+    QuotaExceeded { used: u64, limit: u64 },
+
+    #[error("Patch does not apply: {0}")]
+    Patch(#[from] DiffError),
+
+    #[error("PurgeNotConfirmed: purging shadow state requires confirm=true")]
+    PurgeNotConfirmed,
+
+    #[error("ShadowFsBusy: another shadow operation (such as an apply) is in progress")]
+    Busy,
+
+    #[error("Failed to watch originals: {0}")]
+    Watch(String),
+
     #[error("Failed to serialize: {0}")]
     Serialize(#[from] serde_json::Error),
 }
@@ -59,6 +114,15 @@ pub enum ShadowStatus {
     Rejected,
     Applied,
     Conflict,
+    /// Indexed but its shadow copy is gone; can only be cleaned up
+    Orphaned,
+}
+
+impl ShadowStatus {
+    /// Statuses whose entry still needs its shadow copy to be reviewed or applied
+    fn needs_shadow_copy(self) -> bool {
+        matches!(self, Self::Pending | Self::Approved | Self::Conflict)
+    }
 }
 
 /// A file staged in the shadow filesystem
@@ -74,6 +138,67 @@ pub struct ShadowFileEntry {
     pub created_at: String,
     pub reviewed_at: Option<String>,
     pub patch: Option<FilePatch>,
+    /// Where the change came from when it wasn't proposed by the agent (e.g. `git:HEAD~1`)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Copy of the original taken when the entry was applied with a backup
+    #[serde(default)]
+    pub backup_path: Option<PathBuf>,
+    #[serde(default)]
+    pub applied_at: Option<String>,
+    /// Where the shadow copy was moved when rejected with retention on, or
+    /// the original when an applied delete moved it to trash
+    #[serde(default)]
+    pub trashed_path: Option<PathBuf>,
+    /// Path the file had before an applied rename; `original_path` is then
+    /// the rename target
+    #[serde(default)]
+    pub renamed_from: Option<PathBuf>,
+    /// Algorithm the patch was computed with, reused when it is re-rendered
+    #[serde(default)]
+    pub diff_algorithm: DiffAlgorithm,
+}
+
+/// A change whose before/after content comes from outside the agent, such as
+/// a git diff. Staging it is review-only: the shadow content matches what is
+/// already on disk, so applying it is a no-op unless the file drifts.
+#[derive(Debug, Clone)]
+pub struct ExternalChange {
+    pub operation: PatchOperation,
+    /// Path before the change (the old path for renames)
+    pub path: PathBuf,
+    /// Rename target
+    pub new_path: Option<PathBuf>,
+    /// Content before the change; empty for added files
+    pub base_content: String,
+    /// Content after the change; empty for deleted files
+    pub current_content: String,
+}
+
+/// Outcome of checking the index against the shadow directory on load
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexVerificationReport {
+    /// Number of entries in the index
+    pub checked: usize,
+    /// Entries marked `Orphaned` because their shadow file is missing
+    pub orphaned: Vec<String>,
+    /// Where an unreadable index was moved before it was rebuilt
+    pub quarantined_index: Option<PathBuf>,
+    /// Entries rebuilt as `Pending` from shadow files the unreadable index
+    /// still named
+    #[serde(default)]
+    pub recovered: Vec<String>,
+}
+
+/// What `purge` removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub index_entries: usize,
+    pub shadow_files: usize,
+    pub backups: usize,
+    pub trash_files: usize,
 }
 
 /// Result of applying a shadow file
@@ -83,12 +208,246 @@ pub struct ApplyResult {
     pub file_path: String,
     pub backup_path: Option<String>,
     pub error: Option<String>,
+    /// Regions a conflict merge left between conflict markers for the user
+    /// to resolve
+    #[serde(default)]
+    pub merge_conflicts: usize,
+    /// The original changed since staging and the apply stopped there
+    #[serde(default)]
+    pub conflict: bool,
+}
+
+/// An apply refused because the original changed since it was staged
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchConflict {
+    pub patch_id: String,
+    pub file_path: String,
+    pub expected_hash: String,
+    pub actual_hash: String,
+    /// Unified diff from the file as it is now to the staged content
+    pub diff: Option<String>,
+}
+
+/// Whether a staged entry still applies to its original as it is on disk now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PatchApplicability {
+    /// The original is unchanged since staging
+    Clean,
+    /// The original changed and the staged hunks no longer match it;
+    /// `actual_hash` is empty when the file is gone
+    #[serde(rename_all = "camelCase")]
+    Conflict {
+        expected_hash: String,
+        actual_hash: String,
+    },
+    /// The original changed, but every staged hunk still matches it within
+    /// the fuzz window, the furthest shifted by `offset` lines
+    Fuzzy { offset: isize },
+}
+
+/// What to do when an approved entry's original changed since staging
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Leave the entry unapplied and mark it `Conflict`
+    #[default]
+    Skip,
+    /// Apply over the changed file
+    Overwrite,
+    /// Back up the changed file, then apply over it
+    Backup,
+    /// Three-way merge the staged change into the changed file, leaving
+    /// conflict markers where both touched the same lines. Falls back to
+    /// `Skip` when the staged base can't be recovered.
+    Merge,
+}
+
+/// What `apply` does when the file it writes is a symlink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Fail with `SymlinkTarget`, leaving the link and its target alone
+    #[default]
+    Refuse,
+    /// Remove the link and write a regular file in its place
+    ReplaceLink,
+    /// Write through the link to its target, which may be outside the
+    /// workspace (`follow_symlinks`)
+    Follow,
+}
+
+/// Which side wins when a conflicted entry is resolved without merging
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictChoice {
+    /// The staged content, applied over the changed file after backing it up
+    Ours,
+    /// The file as it is on disk; the staged change is rejected
+    Theirs,
+}
+
+/// Outcome for one entry of `apply_all_approved`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyAllEntry {
+    pub id: String,
+    pub result: ApplyResult,
+}
+
+/// What `apply_all_approved` did, entry by entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyAllSummary {
+    pub applied: usize,
+    pub failed: usize,
+    pub results: Vec<ApplyAllEntry>,
+}
+
+/// Disk space the shadow store takes against its quota
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowStoreUsage {
+    pub used_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    pub exceeded: bool,
+}
+
+/// What `apply_batch` did. Either every entry is in `applied`, or none is
+/// left applied: `failed_id` names the entry that stopped the batch and the
+/// ones written before it are listed in `rolled_back`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchApplyResult {
+    pub success: bool,
+    pub applied: Vec<String>,
+    pub rolled_back: Vec<String>,
+    pub failed_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// What applying an entry would leave on disk, computed without writing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedContentPreview {
+    pub id: String,
+    /// File the apply writes to (the new path for renames)
+    pub target_path: String,
+    /// Resulting file content; `None` when the apply deletes the file
+    pub content: Option<String>,
+    /// The original changed since staging
+    pub conflict: bool,
+    /// Whether an apply with the previewed strategy would go ahead; `false`
+    /// when it would stop at the conflict instead
+    pub applies: bool,
+    /// Conflict regions the merge leaves in `content`
+    pub merge_conflicts: usize,
+}
+
+/// Names a copy of the staging area taken by `ShadowFs::snapshot`
+pub type SnapshotId = String;
+
+/// What `apply` would do for an entry, worked out without writing anything
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyPreview {
+    pub id: String,
+    /// File the apply writes to; the new path for renames
+    pub target_path: String,
+    pub operation: PatchOperation,
+    /// The original changed since staging, so `apply` would merge into it
+    pub conflict: bool,
+    /// Bytes written to `target_path`, after merging a conflict; `None` for
+    /// deletes
+    pub content_length: Option<usize>,
+}
+
+/// Where a `ShadowFs` keeps its state. Defaults to `.coworkany/` in the
+/// workspace; point it elsewhere when the workspace is read-only or slow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowFsConfig {
+    /// Staged copies and `index.json`
+    pub shadow_root: PathBuf,
+    /// Deleted originals and retained rejections
+    pub trash_root: PathBuf,
+    /// JSONL log of applies
+    pub audit_path: PathBuf,
+    /// Rules for paths staging refuses, relative to the workspace or
+    /// absolute; `*` matches within a segment and `**` any depth, and a rule
+    /// without wildcards covers everything below it
+    pub protected_paths: Vec<String>,
+}
+
+/// Protected by default: git internals and the shadow FS's own state
+pub const DEFAULT_PROTECTED_PATHS: &[&str] = &[".git/", ".coworkany/"];
+
+/// Snapshots kept per workspace; taking another drops the oldest
+pub const MAX_SNAPSHOTS: usize = 10;
+
+impl ShadowFsConfig {
+    /// The default layout under `.coworkany/` in `workspace_root`
+    pub fn for_workspace(workspace_root: &Path) -> Self {
+        Self::in_dir(&workspace_root.join(".coworkany"))
+    }
+
+    /// The default layout, rooted at `dir` instead of the workspace
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            shadow_root: dir.join("shadow"),
+            trash_root: dir.join("trash"),
+            audit_path: dir.join("audit-shadow.jsonl"),
+            protected_paths: DEFAULT_PROTECTED_PATHS
+                .iter()
+                .map(|rule| rule.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn with_shadow_root(mut self, shadow_root: PathBuf) -> Self {
+        self.shadow_root = shadow_root;
+        self
+    }
+
+    pub fn with_trash_root(mut self, trash_root: PathBuf) -> Self {
+        self.trash_root = trash_root;
+        self
+    }
+
+    pub fn with_audit_path(mut self, audit_path: PathBuf) -> Self {
+        self.audit_path = audit_path;
+        self
+    }
+
+    /// Replace the protected path rules, defaults included
+    pub fn with_protected_paths(mut self, protected_paths: Vec<String>) -> Self {
+        self.protected_paths = protected_paths;
+        self
+    }
 }
 
 // ============================================================================
 // Shadow FS
 // ============================================================================
 
+/// Hash of a file as of a given (mtime, size). Conflict checks trust an
+/// unchanged pair and skip re-reading the file.
+#[derive(Debug, Clone)]
+struct CachedHash {
+    modified: SystemTime,
+    size: u64,
+    hash: String,
+}
+
+/// Told after each hunk while an apply rebuilds a large patch's content
+pub type HunkProgressSink = Arc<dyn Fn(&FilePatch, HunkProgress) + Send + Sync>;
+
+/// A file watcher and the originals it currently watches
+struct OriginalsWatcher {
+    watcher: notify::RecommendedWatcher,
+    watched: HashSet<PathBuf>,
+}
+
 pub struct ShadowFs {
     /// Root directory for shadow files (e.g., .coworkany/shadow/)
     shadow_root: PathBuf,
@@ -102,45 +461,271 @@ pub struct ShadowFs {
     index_path: PathBuf,
     /// Path to audit log file
     audit_path: PathBuf,
+    /// Content hashes of originals keyed by path
+    hash_cache: HashMap<PathBuf, CachedHash>,
+    /// What the load-time integrity check found
+    load_report: IndexVerificationReport,
+    /// Move rejected shadow copies to trash instead of deleting them
+    retain_rejected: bool,
+    /// Staging is refused while the store is larger than this many bytes
+    quota_bytes: Option<u64>,
+    /// How applies treat a symlinked original
+    symlink_policy: SymlinkPolicy,
+    /// Rules for paths staging refuses; see `ShadowFsConfig::protected_paths`
+    protected_paths: Vec<String>,
+    /// Originals the watcher saw change, not yet checked against the index
+    external_changes: Arc<std::sync::Mutex<HashSet<PathBuf>>>,
+    /// Watches the originals of pending entries, when enabled
+    watcher: Option<OriginalsWatcher>,
+    /// Receives per-hunk progress of large applies
+    hunk_progress: Option<HunkProgressSink>,
+    /// Number of times an original was actually read and hashed
+    #[cfg(test)]
+    hash_reads: usize,
 }
 
 impl ShadowFs {
     /// Create a new ShadowFs instance
     pub fn new(workspace_root: PathBuf) -> Result<Self, ShadowFsError> {
-        let shadow_root = workspace_root.join(".coworkany").join("shadow");
-        let trash_root = workspace_root.join(".coworkany").join("trash");
+        let config = ShadowFsConfig::for_workspace(&workspace_root);
+        Self::with_config(workspace_root, config)
+    }
+
+    /// Create a ShadowFs for `workspace_root` keeping its state where
+    /// `config` says
+    pub fn with_config(
+        workspace_root: PathBuf,
+        config: ShadowFsConfig,
+    ) -> Result<Self, ShadowFsError> {
+        let ShadowFsConfig {
+            shadow_root,
+            trash_root,
+            audit_path,
+            protected_paths,
+        } = config;
         let index_path = shadow_root.join("index.json");
-        let audit_path = workspace_root.join(".coworkany").join("audit-shadow.jsonl");
 
         // Create shadow directory if it doesn't exist
         fs::create_dir_all(&shadow_root)?;
         fs::create_dir_all(&trash_root)?;
+        if let Some(parent) = audit_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
 
         // Load existing index
+        let mut quarantined_index = None;
+        let mut recovered = Vec::new();
         let files = if index_path.exists() {
             let content = fs::read_to_string(&index_path)?;
-            serde_json::from_str(&content).unwrap_or_default()
+            match serde_json::from_str(&content) {
+                Ok(files) => files,
+                Err(e) => {
+                    // Keep a copy of the unreadable index around; the index
+                    // itself stays put until the recovered one replaces it.
+                    let quarantine = index_path.with_extension(format!(
+                        "json.corrupt-{}",
+                        chrono::Utc::now().format("%Y%m%d%H%M%S")
+                    ));
+                    warn!(
+                        "Shadow index {:?} is unreadable ({}), copying it to {:?}",
+                        index_path, e, quarantine
+                    );
+                    fs::copy(&index_path, &quarantine)?;
+                    quarantined_index = Some(quarantine);
+                    let files = recover_index(&workspace_root, &shadow_root, &content)?;
+                    warn!(
+                        "Recovered {} pending shadow entries from {:?}",
+                        files.len(),
+                        shadow_root
+                    );
+                    recovered = files.keys().cloned().collect();
+                    recovered.sort();
+                    files
+                }
+            }
         } else {
             HashMap::new()
         };
 
-        Ok(Self {
+        let mut shadow_fs = Self {
             shadow_root,
             trash_root,
             workspace_root,
             files,
             index_path,
             audit_path,
+            hash_cache: HashMap::new(),
+            load_report: IndexVerificationReport::default(),
+            retain_rejected: false,
+            quota_bytes: None,
+            symlink_policy: SymlinkPolicy::default(),
+            protected_paths,
+            external_changes: Arc::default(),
+            watcher: None,
+            hunk_progress: None,
+            #[cfg(test)]
+            hash_reads: 0,
+        };
+
+        let mut report = shadow_fs.verify_index()?;
+        if quarantined_index.is_some() {
+            shadow_fs.save_index()?;
+        }
+        report.quarantined_index = quarantined_index;
+        report.recovered = recovered;
+        shadow_fs.load_report = report;
+        Ok(shadow_fs)
+    }
+
+    /// Mark entries whose shadow file is missing as `Orphaned` so review and
+    /// apply don't fail on them later. Persists the index if anything changed.
+    pub fn verify_index(&mut self) -> Result<IndexVerificationReport, ShadowFsError> {
+        let mut orphaned: Vec<String> = Vec::new();
+        for entry in self.files.values_mut() {
+            if entry.status.needs_shadow_copy() && !entry.shadow_path.exists() {
+                warn!(
+                    "Shadow file for {:?} is missing ({:?}), marking entry {} orphaned",
+                    entry.original_path, entry.shadow_path, entry.id
+                );
+                entry.status = ShadowStatus::Orphaned;
+                orphaned.push(entry.id.clone());
+            }
+        }
+        orphaned.sort();
+
+        if !orphaned.is_empty() {
+            self.save_index()?;
+        }
+
+        Ok(IndexVerificationReport {
+            checked: self.files.len(),
+            orphaned,
+            ..Default::default()
+        })
+    }
+
+    /// Integrity report from when this instance loaded its index
+    pub fn load_report(&self) -> &IndexVerificationReport {
+        &self.load_report
+    }
+
+    /// Workspace root this instance stages changes for
+    pub fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+
+    /// `path` joined onto the workspace root when relative, refused if it
+    /// leaves the root once `.` and `..` are resolved.
+    pub fn resolve_in_workspace(&self, path: &str) -> Result<PathBuf, ShadowFsError> {
+        resolve_in_root(&self.workspace_root, path)
+    }
+
+    /// When on, `reject` moves the shadow copy to trash so the change can be
+    /// brought back with `restage_rejected` until trash cleanup removes it.
+    pub fn set_retain_rejected(&mut self, retain: bool) {
+        self.retain_rejected = retain;
+    }
+
+    pub fn set_symlink_policy(&mut self, policy: SymlinkPolicy) {
+        self.symlink_policy = policy;
+    }
+
+    /// Report per-hunk progress to `sink` when an apply writes a patch of at
+    /// least `HUNK_PROGRESS_MIN_HUNKS` hunks.
+    pub fn set_hunk_progress(&mut self, sink: Option<HunkProgressSink>) {
+        self.hunk_progress = sink;
+    }
+
+    /// Cap the bytes the shadow and trash directories may hold; `None`
+    /// removes the cap.
+    pub fn set_quota(&mut self, max_total_bytes: Option<u64>) {
+        self.quota_bytes = max_total_bytes;
+    }
+
+    /// Bytes held by the shadow and trash directories, snapshots included
+    pub fn store_size(&self) -> Result<u64, ShadowFsError> {
+        Ok(dir_size(&self.shadow_root)? + dir_size(&self.trash_root)?)
+    }
+
+    pub fn usage(&self) -> Result<ShadowStoreUsage, ShadowFsError> {
+        let used_bytes = self.store_size()?;
+        Ok(ShadowStoreUsage {
+            used_bytes,
+            quota_bytes: self.quota_bytes,
+            exceeded: self.quota_bytes.is_some_and(|limit| used_bytes > limit),
         })
     }
 
+    /// Whether the store is over its quota. Checked before staging so a burst
+    /// of large files fails with `QuotaExceeded` instead of filling the disk.
+    pub fn quota_exceeded(&self) -> Result<bool, ShadowFsError> {
+        Ok(self.usage()?.exceeded)
+    }
+
+    fn check_quota(&self) -> Result<(), ShadowFsError> {
+        let Some(limit) = self.quota_bytes else {
+            return Ok(());
+        };
+        let used = self.store_size()?;
+        if used > limit {
+            return Err(ShadowFsError::QuotaExceeded { used, limit });
+        }
+        Ok(())
+    }
+
+    /// Refuse `path` when a protected path rule covers it
+    fn check_protected(&self, path: &Path) -> Result<(), ShadowFsError> {
+        let root = self.workspace_root.to_string_lossy();
+        let target = path.to_string_lossy();
+        if self
+            .protected_paths
+            .iter()
+            .any(|rule| protected_rule_matches(rule, &target, &root, CASE_INSENSITIVE_FS))
+        {
+            return Err(ShadowFsError::PathProtected(target.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Refuse an entry whose source or rename target is protected
+    fn check_protected_patch(
+        &self,
+        original_path: &Path,
+        patch: Option<&FilePatch>,
+    ) -> Result<(), ShadowFsError> {
+        self.check_protected(original_path)?;
+        match patch.and_then(|p| p.new_file_path.as_deref()) {
+            Some(target) => self.check_protected(Path::new(target)),
+            None => Ok(()),
+        }
+    }
+
     /// Stage a file modification
     pub fn stage_file(
         &mut self,
         original_path: &Path,
         new_content: &str,
     ) -> Result<ShadowFileEntry, ShadowFsError> {
-        self.stage_file_with_patch(original_path, new_content, None)
+        self.stage_file_with_algorithm(original_path, new_content, DiffAlgorithm::default())
+    }
+
+    /// Stage a file modification, diffing it with `algorithm`.
+    pub fn stage_file_with_algorithm(
+        &mut self,
+        original_path: &Path,
+        new_content: &str,
+        algorithm: DiffAlgorithm,
+    ) -> Result<ShadowFileEntry, ShadowFsError> {
+        self.stage_one(original_path, new_content.as_bytes(), None, algorithm)
+    }
+
+    /// Stage raw bytes, e.g. a binary file or text that isn't UTF-8.
+    pub fn stage_file_bytes(
+        &mut self,
+        original_path: &Path,
+        new_content: &[u8],
+    ) -> Result<ShadowFileEntry, ShadowFsError> {
+        self.stage_one(original_path, new_content, None, DiffAlgorithm::default())
     }
 
     /// Stage a file modification with an explicit patch override.
@@ -150,76 +735,345 @@ impl ShadowFs {
         new_content: &str,
         patch_override: Option<FilePatch>,
     ) -> Result<ShadowFileEntry, ShadowFsError> {
-        let id = Uuid::new_v4().to_string();
+        self.stage_one(
+            original_path,
+            new_content.as_bytes(),
+            patch_override,
+            DiffAlgorithm::default(),
+        )
+    }
+
+    fn stage_one(
+        &mut self,
+        original_path: &Path,
+        new_content: &[u8],
+        patch_override: Option<FilePatch>,
+        algorithm: DiffAlgorithm,
+    ) -> Result<ShadowFileEntry, ShadowFsError> {
+        self.check_protected_patch(original_path, patch_override.as_ref())?;
+        self.check_quota()?;
+        let prepared = prepare_stage(
+            &self.workspace_root,
+            original_path,
+            new_content.to_vec(),
+            patch_override,
+            algorithm,
+        )?;
+        let entry = self.commit_staged(prepared)?;
+        self.save_index()?;
+
+        info!("Staged file: {:?}", original_path);
+        Ok(entry)
+    }
+
+    /// Stage many files at once. Reading, hashing and diffing run on up to
+    /// `max_threads` worker threads (sequentially when `max_threads <= 1`);
+    /// entries are returned in input order either way.
+    pub fn stage_files(
+        &mut self,
+        files: Vec<(PathBuf, String)>,
+        max_threads: usize,
+    ) -> Result<Vec<ShadowFileEntry>, ShadowFsError> {
+        self.check_stageable(files.iter().map(|(path, _)| path.as_path()))?;
+        let files = files
+            .into_iter()
+            .map(|(path, content)| (path, content.into_bytes()))
+            .collect();
+        let prepared = prepare_stages(&self.workspace_root, files, max_threads)?;
+        self.commit_prepared(prepared)
+    }
+
+    /// The checks a batch stage runs before any file is read
+    fn check_stageable<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a Path>,
+    ) -> Result<(), ShadowFsError> {
+        for path in paths {
+            self.check_protected(path)?;
+        }
+        self.check_quota()
+    }
+
+    /// Add a batch prepared by `prepare_stages` to the index
+    fn commit_prepared(
+        &mut self,
+        prepared: Vec<PreparedStage>,
+    ) -> Result<Vec<ShadowFileEntry>, ShadowFsError> {
+        let mut entries = Vec::with_capacity(prepared.len());
+        for item in prepared {
+            entries.push(self.commit_staged(item)?);
+        }
+        self.save_index()?;
 
-        // Read original if it exists
-        let (original_exists, original_content, original_hash) = if original_path.exists() {
-            let content = fs::read_to_string(original_path)?;
-            let hash = compute_hash(&content);
-            (true, content, Some(hash))
+        info!("Staged {} files", entries.len());
+        Ok(entries)
+    }
+
+    /// Stage changes that already happened outside the agent for review.
+    pub fn stage_external_changes(
+        &mut self,
+        changes: Vec<ExternalChange>,
+        source: &str,
+    ) -> Result<Vec<ShadowFileEntry>, ShadowFsError> {
+        self.check_quota()?;
+        let mut entries = Vec::with_capacity(changes.len());
+        for change in changes {
+            let prepared = self.prepare_external(change, source)?;
+            entries.push(self.commit_staged(prepared)?);
+        }
+        self.save_index()?;
+
+        info!("Staged {} external changes from {}", entries.len(), source);
+        Ok(entries)
+    }
+
+    fn prepare_external(
+        &self,
+        change: ExternalChange,
+        source: &str,
+    ) -> Result<PreparedStage, ShadowFsError> {
+        let relative = |path: &Path| {
+            path.strip_prefix(&self.workspace_root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        };
+
+        let mut patch = compute_unified_diff(
+            &change.base_content,
+            &change.current_content,
+            &relative(&change.path),
+            3,
+        );
+        patch.operation = change.operation;
+        patch.new_file_path = change
+            .new_path
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string());
+        patch.description = Some(format!("Changed since {}", source));
+
+        // Hash what is on disk now so a later apply detects drift.
+        let original_hash = if change.path.is_file() {
+            Some(compute_file_hash(&change.path)?)
         } else {
-            (false, String::new(), None)
+            None
         };
 
+        Ok(PreparedStage {
+            original_exists: original_hash.is_some(),
+            original_path: change.path,
+            original_hash,
+            shadow_hash: compute_hash(&change.current_content),
+            new_content: change.current_content.into_bytes(),
+            patch: Some(patch),
+            patch_overridden: true,
+            source: Some(source.to_string()),
+            diff_algorithm: DiffAlgorithm::default(),
+        })
+    }
+
+    /// Write the shadow copy for a prepared stage and add it to the index.
+    fn commit_staged(&mut self, prepared: PreparedStage) -> Result<ShadowFileEntry, ShadowFsError> {
+        let id = Uuid::new_v4().to_string();
+
+        if let Some(hash) = &prepared.original_hash {
+            self.remember_hash(&prepared.original_path, hash);
+        }
+
         // Write to shadow location
         let shadow_path = self.shadow_root.join(&id);
-        fs::write(&shadow_path, new_content)?;
-        let shadow_hash = compute_hash(new_content);
+        fs::write(&shadow_path, &prepared.new_content)?;
 
-        let patch = match patch_override {
-            Some(mut patch) => {
+        let mut patch = prepared.patch;
+        if prepared.patch_overridden {
+            if let Some(patch) = patch.as_mut() {
                 patch.id = id.clone();
-                Some(patch)
-            }
-            None => {
-                // Compute diff
-                let relative_path = original_path
-                    .strip_prefix(&self.workspace_root)
-                    .unwrap_or(original_path)
-                    .to_string_lossy()
-                    .to_string();
-                Some(compute_unified_diff(
-                    &original_content,
-                    new_content,
-                    &relative_path,
-                    3,
-                ))
             }
-        };
+        }
 
         let entry = ShadowFileEntry {
             id: id.clone(),
-            original_path: original_path.to_path_buf(),
-            original_exists,
-            original_hash,
+            original_path: prepared.original_path,
+            original_exists: prepared.original_exists,
+            original_hash: prepared.original_hash,
             shadow_path,
-            shadow_hash,
+            shadow_hash: prepared.shadow_hash,
             status: ShadowStatus::Pending,
             created_at: chrono::Utc::now().to_rfc3339(),
             reviewed_at: None,
             patch,
+            source: prepared.source,
+            backup_path: None,
+            applied_at: None,
+            trashed_path: None,
+            renamed_from: None,
+            diff_algorithm: prepared.diff_algorithm,
         };
 
         self.files.insert(id, entry.clone());
-        self.save_index()?;
-
-        info!("Staged file: {:?}", original_path);
+        self.sync_watches();
         Ok(entry)
     }
 
+    /// Watch the originals of pending and approved entries so an edit made
+    /// outside the app turns the entry into a `Conflict` before anyone tries
+    /// to apply it. `on_change` runs on the watcher's thread after each change
+    /// and should arrange for `sync_external_changes` to be called. Files that
+    /// don't exist yet aren't watched.
+    pub fn watch_originals(
+        &mut self,
+        on_change: impl Fn() + Send + 'static,
+    ) -> Result<(), ShadowFsError> {
+        let changes = self.external_changes.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            if let Ok(mut changes) = changes.lock() {
+                changes.extend(event.paths);
+            }
+            on_change();
+        })
+        .map_err(|e| ShadowFsError::Watch(e.to_string()))?;
+
+        self.watcher = Some(OriginalsWatcher {
+            watcher,
+            watched: HashSet::new(),
+        });
+        self.sync_watches();
+        Ok(())
+    }
+
+    pub fn unwatch_originals(&mut self) {
+        self.watcher = None;
+    }
+
+    /// Watch exactly the existing originals of pending and approved entries
+    fn sync_watches(&mut self) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        let wanted: HashSet<PathBuf> = self
+            .files
+            .values()
+            .filter(|e| matches!(e.status, ShadowStatus::Pending | ShadowStatus::Approved))
+            .map(|e| e.original_path.clone())
+            .filter(|path| path.exists())
+            .collect();
+
+        let stale: Vec<PathBuf> = watcher.watched.difference(&wanted).cloned().collect();
+        for path in stale {
+            let _ = watcher.watcher.unwatch(&path);
+            watcher.watched.remove(&path);
+        }
+        for path in wanted {
+            if watcher.watched.contains(&path) {
+                continue;
+            }
+            match watcher.watcher.watch(&path, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    watcher.watched.insert(path);
+                }
+                Err(e) => debug!("Not watching {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// Mark `Conflict` the pending and approved entries whose original the
+    /// watcher saw change and that no longer match what was staged against.
+    /// Returns their ids.
+    pub fn sync_external_changes(&mut self) -> Vec<String> {
+        let changed = match self.external_changes.lock() {
+            Ok(mut changes) => std::mem::take(&mut *changes),
+            Err(_) => return Vec::new(),
+        };
+
+        let candidates: Vec<(String, PathBuf, Option<String>)> = self
+            .files
+            .values()
+            .filter(|e| matches!(e.status, ShadowStatus::Pending | ShadowStatus::Approved))
+            .filter(|e| changed.contains(&e.original_path))
+            .map(|e| {
+                (
+                    e.id.clone(),
+                    e.original_path.clone(),
+                    e.original_hash.clone(),
+                )
+            })
+            .collect();
+
+        let mut conflicted = Vec::new();
+        for (id, path, expected_hash) in candidates {
+            let actual_hash = if path.is_file() {
+                self.current_hash(&path).ok()
+            } else {
+                None
+            };
+            if actual_hash != expected_hash {
+                if let Some(entry) = self.files.get_mut(&id) {
+                    entry.status = ShadowStatus::Conflict;
+                }
+                conflicted.push(id);
+            }
+        }
+
+        if !conflicted.is_empty() {
+            info!(
+                "{} shadow entries conflict with changes made on disk",
+                conflicted.len()
+            );
+            if let Err(e) = self.save_index() {
+                warn!("Failed to save index after external changes: {}", e);
+            }
+        }
+        self.sync_watches();
+        conflicted.sort();
+        conflicted
+    }
+
     /// Get a shadow file entry
     pub fn get(&self, id: &str) -> Option<&ShadowFileEntry> {
         self.files.get(id)
     }
 
-    /// List all pending shadow files
+    /// All indexed entries, oldest first
+    pub fn entries(&self) -> Vec<&ShadowFileEntry> {
+        let mut entries: Vec<&ShadowFileEntry> = self.files.values().collect();
+        entries.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        entries
+    }
+
+    /// JSONL log of shadow actions (stage, apply, reject, ...)
+    pub fn audit_log_path(&self) -> &Path {
+        &self.audit_path
+    }
+
+    /// List shadow files awaiting review: pending, or in conflict with the
+    /// original on disk
     pub fn list_pending(&self) -> Vec<&ShadowFileEntry> {
         self.files
             .values()
-            .filter(|e| e.status == ShadowStatus::Pending)
+            .filter(|e| matches!(e.status, ShadowStatus::Pending | ShadowStatus::Conflict))
             .collect()
     }
 
+    /// Applied entries, newest first, optionally limited to one file. Relative
+    /// paths are resolved against the workspace root.
+    pub fn apply_history(&self, path: Option<&Path>) -> Vec<&ShadowFileEntry> {
+        let path = path.map(|p| self.workspace_root.join(p));
+        let mut history: Vec<&ShadowFileEntry> = self
+            .files
+            .values()
+            .filter(|e| e.status == ShadowStatus::Applied)
+            .filter(|e| path.as_ref().is_none_or(|p| &e.original_path == p))
+            .collect();
+        history.sort_by(|a, b| b.applied_at.cmp(&a.applied_at));
+        history
+    }
+
     /// Approve a shadow file for application
     pub fn approve(&mut self, id: &str) -> Result<&ShadowFileEntry, ShadowFsError> {
         let entry = self
@@ -244,17 +1098,411 @@ impl ShadowFs {
         entry.status = ShadowStatus::Rejected;
         entry.reviewed_at = Some(chrono::Utc::now().to_rfc3339());
 
-        // Clean up shadow file
         if entry.shadow_path.exists() {
-            fs::remove_file(&entry.shadow_path)?;
+            if self.retain_rejected {
+                let trashed_path = self.trash_root.join(format!("rejected-{}", entry.id));
+                move_file(&entry.shadow_path, &trashed_path)?;
+                // Trash retention counts from the rejection, not from staging.
+                fs::File::options()
+                    .write(true)
+                    .open(&trashed_path)?
+                    .set_modified(SystemTime::now())?;
+                entry.trashed_path = Some(trashed_path);
+            } else {
+                fs::remove_file(&entry.shadow_path)?;
+            }
+        }
+
+        self.save_index()?;
+        Ok(())
+    }
+
+    /// Bring a rejected entry back to `Pending` from its retained trash copy.
+    /// Fails once trash cleanup has removed the copy.
+    pub fn restage_rejected(&mut self, id: &str) -> Result<&ShadowFileEntry, ShadowFsError> {
+        let entry = self
+            .files
+            .get_mut(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?;
+
+        let trashed_path = entry
+            .trashed_path
+            .clone()
+            .filter(|path| entry.status == ShadowStatus::Rejected && path.is_file())
+            .ok_or_else(|| ShadowFsError::NotRestorable(id.to_string()))?;
+
+        move_file(&trashed_path, &entry.shadow_path)?;
+        entry.status = ShadowStatus::Pending;
+        entry.reviewed_at = None;
+        entry.trashed_path = None;
+
+        self.save_index()?;
+        Ok(self.files.get(id).unwrap())
+    }
+
+    /// Stage the undo of an applied entry as a new pending entry: its patch
+    /// reversed, applied to the file as it is now.
+    pub fn stage_revert(&mut self, id: &str) -> Result<ShadowFileEntry, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?
+            .clone();
+        let patch = match (entry.status, &entry.patch) {
+            (ShadowStatus::Applied, Some(patch)) if patch.operation != PatchOperation::Binary => {
+                patch
+            }
+            _ => return Err(ShadowFsError::NotRevertible(id.to_string())),
+        };
+
+        let mut reverse = reverse_patch(patch);
+        let content = match patch.operation {
+            PatchOperation::Create => String::new(),
+            PatchOperation::Delete => reverse.full_content.clone().unwrap_or_default(),
+            _ => apply_file_patch(&fs::read_to_string(&entry.original_path)?, &reverse)?,
+        };
+        if patch.operation == PatchOperation::Rename {
+            // After the apply the entry points at the rename target; the
+            // patch still names the old path relative to the workspace.
+            let old_path = self.workspace_root.join(&patch.file_path);
+            reverse.new_file_path = Some(old_path.to_string_lossy().to_string());
+        }
+        reverse.description = Some(format!("Revert {}", entry.id));
+
+        let reverted = self.stage_file_with_patch(&entry.original_path, &content, Some(reverse))?;
+        info!("Staged revert of {} as {}", entry.id, reverted.id);
+        Ok(reverted)
+    }
+
+    /// Apply `ids` in order as one unit. Every entry is checked up front; if
+    /// an apply fails anyway, the entries already written are restored from
+    /// the backups taken for the batch and left `Approved`.
+    pub fn apply_batch(&mut self, ids: &[String], create_backup: bool) -> BatchApplyResult {
+        let mut result = BatchApplyResult::default();
+        for id in ids {
+            if let Err(err) = self.verify_batch_entry(id) {
+                result.failed_id = Some(id.clone());
+                result.error = Some(err.to_string());
+                return result;
+            }
+        }
+
+        // Entries as they were before the batch, with their staged content
+        let mut written: Vec<(ShadowFileEntry, Vec<u8>)> = Vec::new();
+        for id in ids {
+            let before = self.files[id].clone();
+            let applied = fs::read(&before.shadow_path)
+                .map_err(ShadowFsError::from)
+                .and_then(|staged| {
+                    let applied = self.apply_with_strategy(id, true, ConflictStrategy::Skip)?;
+                    Ok((applied, staged))
+                });
+            match applied {
+                Ok((applied, staged)) if applied.success => written.push((before, staged)),
+                Ok((applied, _)) => {
+                    result.error = applied.error;
+                    result.failed_id = Some(id.clone());
+                    break;
+                }
+                Err(err) => {
+                    result.error = Some(err.to_string());
+                    result.failed_id = Some(id.clone());
+                    break;
+                }
+            }
+        }
+
+        if result.failed_id.is_some() {
+            for (before, staged) in written.into_iter().rev() {
+                let id = before.id.clone();
+                match self.undo_batch_apply(before, &staged) {
+                    Ok(()) => result.rolled_back.insert(0, id),
+                    Err(err) => warn!("Failed to roll back {} after batch failure: {}", id, err),
+                }
+            }
+            warn!(
+                "Batch apply stopped at {:?}; rolled back {} entries",
+                result.failed_id,
+                result.rolled_back.len()
+            );
+            return result;
+        }
+
+        for (before, _) in &written {
+            if !create_backup {
+                if let Some(e) = self.files.get_mut(&before.id) {
+                    if let Some(backup) = e.backup_path.take() {
+                        let _ = fs::remove_file(backup);
+                    }
+                }
+            }
+            result.applied.push(before.id.clone());
+        }
+        if let Err(err) = self.save_index() {
+            warn!("Failed to save index after batch apply: {}", err);
+        }
+        result.success = true;
+        info!("Applied batch of {} shadow files", result.applied.len());
+        result
+    }
+
+    /// Whether `id` would apply without conflict right now
+    fn verify_batch_entry(&mut self, id: &str) -> Result<(), ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?
+            .clone();
+        if entry.status != ShadowStatus::Approved {
+            return Err(ShadowFsError::NotApproved(id.to_string()));
+        }
+        if let (true, Some(expected_hash)) = (entry.original_exists, &entry.original_hash) {
+            let actual_hash = self.current_hash(&entry.original_path)?;
+            if &actual_hash != expected_hash {
+                return Err(ShadowFsError::Conflict {
+                    expected_hash: expected_hash.clone(),
+                    actual_hash,
+                });
+            }
+        }
+        let target_path = apply_target(&entry);
+        if target_path != entry.original_path && target_path.exists() {
+            return Err(ShadowFsError::TargetExists(
+                target_path.to_string_lossy().to_string(),
+            ));
+        }
+        self.check_rename_target(&entry)
+    }
+
+    /// Refuse a rename onto a path that an entry staged before it, still
+    /// pending or approved, will also write.
+    fn check_rename_target(&self, entry: &ShadowFileEntry) -> Result<(), ShadowFsError> {
+        if entry.patch.as_ref().map(|p| p.operation) != Some(PatchOperation::Rename) {
+            return Ok(());
+        }
+        let target = apply_target(entry);
+        let earlier = self.files.values().find(|other| {
+            other.id != entry.id
+                && matches!(other.status, ShadowStatus::Pending | ShadowStatus::Approved)
+                && (&other.created_at, &other.id) < (&entry.created_at, &entry.id)
+                && apply_target(other) == target
+        });
+        match earlier {
+            Some(other) => Err(ShadowFsError::TargetPending {
+                target: target.to_string_lossy().to_string(),
+                id: other.id.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Put the files an apply wrote back the way they were in `before`, and
+    /// the entry back to `before` with its staged content.
+    fn undo_batch_apply(
+        &mut self,
+        before: ShadowFileEntry,
+        staged: &[u8],
+    ) -> Result<(), ShadowFsError> {
+        let applied = self
+            .files
+            .get(&before.id)
+            .ok_or_else(|| ShadowFsError::NotFound(before.id.clone()))?
+            .clone();
+
+        // After a rename the entry points at the target
+        if applied.original_path != before.original_path && applied.original_path.exists() {
+            fs::remove_file(&applied.original_path)?;
+        }
+        match &applied.backup_path {
+            Some(backup) => move_file(backup, &before.original_path)?,
+            None if before.original_path.exists() => fs::remove_file(&before.original_path)?,
+            None => {}
         }
 
+        fs::write(&before.shadow_path, staged)?;
+        self.audit("rollback", &before, Some(&before.original_path));
+        self.files.insert(before.id.clone(), before);
         self.save_index()?;
         Ok(())
     }
 
-    /// Apply an approved shadow file to the real filesystem
+    /// Re-diff a conflicted entry's staged content against the file as it is
+    /// now and put it back to `Pending` under the same id, so the change can
+    /// be reviewed again instead of discarded.
+    pub fn restage_against_current(&mut self, id: &str) -> Result<&ShadowFileEntry, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?;
+        if entry.status != ShadowStatus::Conflict {
+            return Err(ShadowFsError::NotConflicted(id.to_string()));
+        }
+
+        let content = fs::read(&entry.shadow_path)?;
+        let old_patch = entry.patch.clone();
+        let prepared = prepare_stage(
+            &self.workspace_root,
+            &entry.original_path,
+            content,
+            None,
+            entry.diff_algorithm,
+        )?;
+        if let Some(hash) = &prepared.original_hash {
+            self.remember_hash(&prepared.original_path, hash);
+        }
+
+        let mut patch = prepared.patch;
+        if let Some(old_patch) = old_patch
+            .as_ref()
+            .filter(|p| matches!(p.operation, PatchOperation::Delete | PatchOperation::Rename))
+        {
+            if let Some(patch) = patch.as_mut() {
+                patch.operation = old_patch.operation;
+                patch.new_file_path = old_patch.new_file_path.clone();
+            }
+        }
+
+        let entry = self.files.get_mut(id).unwrap();
+        entry.original_exists = prepared.original_exists;
+        entry.original_hash = prepared.original_hash;
+        entry.patch = patch;
+        entry.status = ShadowStatus::Pending;
+        entry.reviewed_at = None;
+
+        self.save_index()?;
+        info!("Restaged {} against current content", id);
+        Ok(self.files.get(id).unwrap())
+    }
+
+    /// Settle a conflicted entry by taking one side whole: `Ours` leaves it
+    /// `Applied` with the changed file in `backup_path`, `Theirs` leaves it
+    /// `Rejected` and the file untouched.
+    pub fn resolve_conflict(
+        &mut self,
+        id: &str,
+        choice: ConflictChoice,
+    ) -> Result<&ShadowFileEntry, ShadowFsError> {
+        let entry = self
+            .files
+            .get_mut(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?;
+        if entry.status != ShadowStatus::Conflict {
+            return Err(ShadowFsError::NotConflicted(id.to_string()));
+        }
+
+        match choice {
+            ConflictChoice::Ours => {
+                entry.status = ShadowStatus::Approved;
+                entry.reviewed_at = Some(chrono::Utc::now().to_rfc3339());
+                if let Err(err) = self.apply_with_strategy(id, true, ConflictStrategy::Backup) {
+                    if let Some(entry) = self.files.get_mut(id) {
+                        entry.status = ShadowStatus::Conflict;
+                    }
+                    self.save_index()?;
+                    return Err(err);
+                }
+            }
+            ConflictChoice::Theirs => self.reject(id)?,
+        }
+
+        info!("Resolved conflict {} with {:?}", id, choice);
+        Ok(self.files.get(id).unwrap())
+    }
+
+    /// Reject every entry in `Conflict` status, removing its shadow file.
+    /// With `restage`, each conflicting change is first staged again against
+    /// the current on-disk content so it can be reviewed afresh.
+    /// Returns the number of conflicts cleared.
+    pub fn reject_all_conflicts(&mut self, restage: bool) -> Result<usize, ShadowFsError> {
+        let conflict_ids: Vec<String> = self
+            .files
+            .values()
+            .filter(|e| e.status == ShadowStatus::Conflict)
+            .map(|e| e.id.clone())
+            .collect();
+
+        for id in &conflict_ids {
+            if restage {
+                let entry = self.files[id].clone();
+                if let Ok(content) = fs::read_to_string(&entry.shadow_path) {
+                    let mut restaged = self.stage_file(&entry.original_path, &content)?;
+                    if let Some(old_patch) = entry.patch.as_ref().filter(|p| {
+                        matches!(p.operation, PatchOperation::Delete | PatchOperation::Rename)
+                    }) {
+                        if let Some(patch) = restaged.patch.as_mut() {
+                            patch.operation = old_patch.operation;
+                            patch.new_file_path = old_patch.new_file_path.clone();
+                        }
+                        self.files.insert(restaged.id.clone(), restaged);
+                    }
+                }
+            }
+            self.reject(id)?;
+        }
+
+        if !conflict_ids.is_empty() {
+            info!("Rejected {} conflicting shadow files", conflict_ids.len());
+        }
+        Ok(conflict_ids.len())
+    }
+
+    /// Apply an approved shadow file to the real filesystem. If the original
+    /// changed since staging, the staged change is merged into it.
     pub fn apply(&mut self, id: &str, create_backup: bool) -> Result<ApplyResult, ShadowFsError> {
+        self.apply_with_strategy(id, create_backup, ConflictStrategy::Merge)
+    }
+
+    /// Apply every `Approved` entry, oldest first, resolving conflicts with
+    /// `strategy`. A failing entry is recorded and the rest still apply.
+    pub fn apply_all_approved(&mut self, strategy: ConflictStrategy) -> ApplyAllSummary {
+        let mut approved: Vec<(String, String)> = self
+            .files
+            .values()
+            .filter(|e| e.status == ShadowStatus::Approved)
+            .map(|e| (e.created_at.clone(), e.id.clone()))
+            .collect();
+        approved.sort();
+
+        let mut summary = ApplyAllSummary::default();
+        for (_, id) in approved {
+            let result = match self.apply_with_strategy(&id, false, strategy) {
+                Ok(result) => result,
+                Err(err) => ApplyResult {
+                    success: false,
+                    file_path: self
+                        .files
+                        .get(&id)
+                        .map(|e| e.original_path.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    backup_path: None,
+                    conflict: matches!(err, ShadowFsError::Conflict { .. }),
+                    error: Some(err.to_string()),
+                    merge_conflicts: 0,
+                },
+            };
+            if result.success {
+                summary.applied += 1;
+            } else {
+                summary.failed += 1;
+            }
+            summary.results.push(ApplyAllEntry { id, result });
+        }
+
+        info!(
+            "Applied {} approved shadow files ({} failed)",
+            summary.applied, summary.failed
+        );
+        summary
+    }
+
+    /// Apply an approved shadow file, resolving a changed original with
+    /// `strategy`.
+    pub fn apply_with_strategy(
+        &mut self,
+        id: &str,
+        mut create_backup: bool,
+        strategy: ConflictStrategy,
+    ) -> Result<ApplyResult, ShadowFsError> {
         let entry = self
             .files
             .get(id)
@@ -267,48 +1515,76 @@ impl ShadowFs {
                 file_path: entry.original_path.to_string_lossy().to_string(),
                 backup_path: None,
                 error: Some("File not approved".to_string()),
+                merge_conflicts: 0,
+                conflict: false,
             });
         }
+        self.check_protected_patch(&entry.original_path, entry.patch.as_ref())?;
 
         // Check for conflicts
+        let mut merged = None;
         if entry.original_exists {
             if let Some(ref expected_hash) = entry.original_hash {
-                let current_content = fs::read_to_string(&entry.original_path)?;
-                let current_hash = compute_hash(&current_content);
+                let current_hash = self.current_hash(&entry.original_path)?;
 
                 if &current_hash != expected_hash {
-                    // Mark as conflict
-                    if let Some(e) = self.files.get_mut(id) {
-                        e.status = ShadowStatus::Conflict;
+                    if strategy == ConflictStrategy::Merge {
+                        merged = merge_into_current(&entry, expected_hash);
                     }
-                    self.save_index()?;
+                    if strategy == ConflictStrategy::Skip
+                        || (strategy == ConflictStrategy::Merge && merged.is_none())
+                    {
+                        // Mark as conflict
+                        if let Some(e) = self.files.get_mut(id) {
+                            e.status = ShadowStatus::Conflict;
+                        }
+                        self.save_index()?;
 
-                    return Err(ShadowFsError::Conflict {
-                        expected_hash: expected_hash.clone(),
-                        actual_hash: current_hash,
-                    });
+                        return Err(ShadowFsError::Conflict {
+                            expected_hash: expected_hash.clone(),
+                            actual_hash: current_hash,
+                        });
+                    }
+                    warn!(
+                        "Applying {:?} over changes made since staging ({:?})",
+                        entry.original_path, strategy
+                    );
+                    if let Some(MergeResult::Conflicted { conflicts, .. }) = &merged {
+                        warn!(
+                            "Merge left {} conflict region(s) in {:?}",
+                            conflicts.len(),
+                            entry.original_path
+                        );
+                    }
+                    create_backup |= strategy == ConflictStrategy::Backup;
                 }
             }
         }
 
+        self.check_rename_target(&entry)?;
+
         let patch = entry.patch.clone();
-        let target_path = patch
-            .as_ref()
-            .and_then(|p| p.new_file_path.clone())
-            .unwrap_or_else(|| entry.original_path.to_string_lossy().to_string());
-        let target_path = PathBuf::from(target_path);
+        let target_path = apply_target(&entry);
+        self.check_parent_in_workspace(&entry.original_path)?;
+        self.check_parent_in_workspace(&target_path)?;
+        // A delete moves the link itself to trash; anything else writes to it.
+        let replace_link = match patch.as_ref().map(|p| p.operation) {
+            Some(PatchOperation::Delete) => false,
+            _ => self.replaces_symlink(&entry.original_path)?,
+        };
 
         let original_exists = entry.original_path.exists();
 
         // Create backup if requested
         let backup_path = if create_backup && original_exists {
-            let backup = entry.original_path.with_extension("bak");
-            fs::copy(&entry.original_path, &backup)?;
-            Some(backup.to_string_lossy().to_string())
+            let backup = self.backup_path_for(&entry.id);
+            copy_atomic(&entry.original_path, &backup)?;
+            Some(backup)
         } else {
             None
         };
 
+        let mut trashed = None;
         match patch.as_ref().map(|p| &p.operation) {
             Some(PatchOperation::Delete) => {
                 if entry.original_path.exists() {
@@ -323,6 +1599,7 @@ impl ShadowFs {
                         debug!("Trash rename failed, copied instead: {}", err);
                     }
                     self.audit("delete", &entry, Some(&trashed_path));
+                    trashed = Some(trashed_path);
                 }
             }
             Some(PatchOperation::Rename) => {
@@ -337,17 +1614,29 @@ impl ShadowFs {
                     }
                     fs::rename(&entry.original_path, &target_path)?;
                 }
+                if replace_link {
+                    fs::remove_file(&target_path)?;
+                }
 
-                let shadow_content = fs::read_to_string(&entry.shadow_path)?;
-                fs::write(&target_path, shadow_content)?;
+                let shadow_content = match &merged {
+                    Some(merge) => merged_content(merge).as_bytes().to_vec(),
+                    None => fs::read(&entry.shadow_path)?,
+                };
+                write_atomic(&target_path, &shadow_content)?;
                 self.audit("rename", &entry, Some(&target_path));
             }
             _ => {
-                let shadow_content = fs::read_to_string(&entry.shadow_path)?;
+                let shadow_content = match &merged {
+                    Some(merge) => merged_content(merge).as_bytes().to_vec(),
+                    None => self.rebuild_with_progress(&entry)?,
+                };
                 if let Some(parent) = entry.original_path.parent() {
                     fs::create_dir_all(parent)?;
                 }
-                fs::write(&entry.original_path, shadow_content)?;
+                if replace_link {
+                    fs::remove_file(&entry.original_path)?;
+                }
+                write_atomic(&entry.original_path, &shadow_content)?;
                 self.audit("apply", &entry, Some(&entry.original_path));
             }
         }
@@ -355,7 +1644,13 @@ impl ShadowFs {
         // Update status
         if let Some(e) = self.files.get_mut(id) {
             e.status = ShadowStatus::Applied;
+            e.backup_path = backup_path.clone();
+            e.applied_at = Some(chrono::Utc::now().to_rfc3339());
+            if trashed.is_some() {
+                e.trashed_path = trashed;
+            }
             if let Some(PatchOperation::Rename) = patch.as_ref().map(|p| &p.operation) {
+                e.renamed_from = Some(e.original_path.clone());
                 e.original_path = target_path.clone();
             }
         }
@@ -372,12 +1667,268 @@ impl ShadowFs {
         Ok(ApplyResult {
             success: true,
             file_path: target_path.to_string_lossy().to_string(),
-            backup_path,
+            backup_path: backup_path.map(|p| p.to_string_lossy().to_string()),
             error: None,
+            merge_conflicts: match merged {
+                Some(MergeResult::Conflicted { conflicts, .. }) => conflicts.len(),
+                _ => 0,
+            },
+            conflict: false,
+        })
+    }
+
+    /// The staged content of `entry`. With a progress sink set and a large
+    /// patch, it is rebuilt hunk by hunk from the (unchanged) original so the
+    /// sink sees each hunk land; the staged copy is used if that doesn't
+    /// reproduce it exactly.
+    fn rebuild_with_progress(&self, entry: &ShadowFileEntry) -> Result<Vec<u8>, ShadowFsError> {
+        let staged = fs::read(&entry.shadow_path)?;
+        let (Some(sink), Some(patch)) = (&self.hunk_progress, entry.patch.as_ref()) else {
+            return Ok(staged);
+        };
+        if patch.operation != PatchOperation::Modify || patch.hunks.len() < HUNK_PROGRESS_MIN_HUNKS
+        {
+            return Ok(staged);
+        }
+        let original = fs::read_to_string(&entry.original_path)?;
+        match apply_patch_with_progress(&original, patch, DEFAULT_HUNK_FUZZ, |progress| {
+            sink(patch, progress)
+        }) {
+            Ok(rebuilt) if rebuilt.as_bytes() == staged.as_slice() => Ok(staged),
+            _ => {
+                debug!(
+                    "Hunks of {} don't rebuild the staged content; writing it as staged",
+                    entry.id
+                );
+                Ok(staged)
+            }
+        }
+    }
+
+    /// Content `apply` would write for `id`, without touching the workspace.
+    /// Works for entries that are not approved yet so they can be reviewed.
+    pub fn preview_applied_content(
+        &mut self,
+        id: &str,
+    ) -> Result<AppliedContentPreview, ShadowFsError> {
+        self.preview_applied_content_with_strategy(id, ConflictStrategy::Merge)
+    }
+
+    /// Content `apply_with_strategy(id, .., strategy)` would write. A changed
+    /// original is resolved the same way the apply would, so a `Merge`
+    /// preview shows the merged file rather than the staged one.
+    pub fn preview_applied_content_with_strategy(
+        &mut self,
+        id: &str,
+        strategy: ConflictStrategy,
+    ) -> Result<AppliedContentPreview, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?
+            .clone();
+
+        let mut content = applied_content(&entry)?;
+        let mut conflict = false;
+        let mut applies = true;
+        let mut merge_conflicts = 0;
+        if let (Some(expected_hash), true) = (&entry.original_hash, entry.original_exists) {
+            conflict = &self.current_hash(&entry.original_path)? != expected_hash;
+            if conflict {
+                let merged = match strategy {
+                    ConflictStrategy::Merge => merge_into_current(&entry, expected_hash),
+                    _ => None,
+                };
+                match merged {
+                    Some(merge) => {
+                        if let MergeResult::Conflicted { conflicts, .. } = &merge {
+                            merge_conflicts = conflicts.len();
+                        }
+                        content = Some(merged_content(&merge).to_string());
+                    }
+                    None => {
+                        applies =
+                            !matches!(strategy, ConflictStrategy::Skip | ConflictStrategy::Merge);
+                    }
+                }
+            }
+        }
+
+        Ok(AppliedContentPreview {
+            id: entry.id.clone(),
+            target_path: apply_target(&entry).to_string_lossy().to_string(),
+            content,
+            conflict,
+            applies,
+            merge_conflicts,
+        })
+    }
+
+    /// Dry run of `apply(id, ..)`: the target, operation and resulting size,
+    /// with nothing written, backed up or moved to trash.
+    pub fn preview_apply(&mut self, id: &str) -> Result<ApplyPreview, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?
+            .clone();
+
+        let mut conflict = false;
+        let mut merged = None;
+        if let (Some(expected_hash), true) = (&entry.original_hash, entry.original_exists) {
+            if &self.current_hash(&entry.original_path)? != expected_hash {
+                conflict = true;
+                merged = merge_into_current(&entry, expected_hash);
+            }
+        }
+        let content_length = match &merged {
+            Some(merge) => Some(merged_content(merge).len()),
+            None => match entry.patch.as_ref().map(|p| p.operation) {
+                Some(PatchOperation::Delete) => None,
+                _ => Some(fs::metadata(&entry.shadow_path)?.len() as usize),
+            },
+        };
+
+        Ok(ApplyPreview {
+            id: entry.id.clone(),
+            target_path: apply_target(&entry).to_string_lossy().to_string(),
+            operation: entry
+                .patch
+                .as_ref()
+                .map_or(PatchOperation::Modify, |patch| patch.operation),
+            conflict,
+            content_length,
+        })
+    }
+
+    /// Whether `id` still applies to the file on disk, without changing the
+    /// entry or the workspace.
+    pub fn check_applies(&mut self, id: &str) -> Result<PatchApplicability, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?
+            .clone();
+
+        let expected_hash = match (&entry.original_hash, entry.original_exists) {
+            (Some(expected_hash), true) => expected_hash.clone(),
+            _ => return Ok(PatchApplicability::Clean),
+        };
+        if !entry.original_path.exists() {
+            return Ok(PatchApplicability::Conflict {
+                expected_hash,
+                actual_hash: String::new(),
+            });
+        }
+        let actual_hash = self.current_hash(&entry.original_path)?;
+        if actual_hash == expected_hash {
+            return Ok(PatchApplicability::Clean);
+        }
+
+        let offsets = entry
+            .patch
+            .as_ref()
+            .filter(|patch| {
+                !patch.hunks.is_empty()
+                    && matches!(
+                        patch.operation,
+                        PatchOperation::Modify | PatchOperation::Rename
+                    )
+            })
+            .zip(fs::read_to_string(&entry.original_path).ok())
+            .and_then(|(patch, current)| hunk_offsets(&current, patch, DEFAULT_HUNK_FUZZ).ok());
+        Ok(match offsets {
+            Some(offsets) => PatchApplicability::Fuzzy {
+                offset: offsets
+                    .into_iter()
+                    .max_by_key(|offset| offset.unsigned_abs())
+                    .unwrap_or(0),
+            },
+            None => PatchApplicability::Conflict {
+                expected_hash,
+                actual_hash,
+            },
+        })
+    }
+
+    /// Details of a conflict `apply` reported for `id`, including the diff
+    /// from the current original to the staged content when both are readable.
+    pub fn patch_conflict(
+        &self,
+        id: &str,
+        expected_hash: &str,
+        actual_hash: &str,
+    ) -> Result<PatchConflict, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?;
+        let diff = match (
+            fs::read_to_string(&entry.original_path),
+            applied_content(entry),
+        ) {
+            (Ok(current), Ok(Some(staged))) => {
+                let relative = entry
+                    .original_path
+                    .strip_prefix(&self.workspace_root)
+                    .unwrap_or(&entry.original_path)
+                    .to_string_lossy()
+                    .to_string();
+                Some(generate_unified_diff_string(&compute_unified_diff_with(
+                    &current,
+                    &staged,
+                    &relative,
+                    3,
+                    &DiffOptions {
+                        algorithm: entry.diff_algorithm,
+                        ..Default::default()
+                    },
+                )))
+            }
+            _ => None,
+        };
+        Ok(PatchConflict {
+            patch_id: entry.id.clone(),
+            file_path: entry.original_path.to_string_lossy().to_string(),
+            expected_hash: expected_hash.to_string(),
+            actual_hash: actual_hash.to_string(),
+            diff,
         })
     }
 
-    /// Rollback an applied change using backup
+    /// Put the file an applied delete moved to trash back at its original
+    /// path.
+    pub fn restore_from_trash(&mut self, id: &str) -> Result<&ShadowFileEntry, ShadowFsError> {
+        let entry = self
+            .files
+            .get(id)
+            .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?;
+        let is_applied_delete = entry.status == ShadowStatus::Applied
+            && entry.patch.as_ref().map(|p| p.operation) == Some(PatchOperation::Delete);
+        let trashed_path = entry
+            .trashed_path
+            .clone()
+            .filter(|path| is_applied_delete && path.is_file())
+            .ok_or_else(|| ShadowFsError::NotInTrash(id.to_string()))?;
+        if entry.original_path.exists() {
+            return Err(ShadowFsError::TargetExists(
+                entry.original_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        move_file(&trashed_path, &entry.original_path)?;
+        let entry = self.files.get_mut(id).unwrap();
+        entry.trashed_path = None;
+        let entry = entry.clone();
+        self.audit("restore", &entry, Some(&entry.original_path));
+        self.save_index()?;
+        info!("Restored from trash: {:?}", entry.original_path);
+        Ok(self.files.get(id).unwrap())
+    }
+
+    /// Rollback an applied change: a delete is restored from trash, a rename
+    /// moved back to its old path (from the backup, if one was taken), and a
+    /// modification restored from its backup.
     #[allow(dead_code)]
     pub fn rollback(&mut self, id: &str) -> Result<(), ShadowFsError> {
         let entry = self
@@ -385,9 +1936,52 @@ impl ShadowFs {
             .get(id)
             .ok_or_else(|| ShadowFsError::NotFound(id.to_string()))?;
 
-        let backup_path = entry.original_path.with_extension("bak");
+        if entry.status == ShadowStatus::Applied
+            && entry.patch.as_ref().map(|p| p.operation) == Some(PatchOperation::Delete)
+        {
+            self.restore_from_trash(id)?;
+            return Ok(());
+        }
+
+        if let Some(renamed_from) = entry.renamed_from.clone() {
+            let target = entry.original_path.clone();
+            if renamed_from.exists() {
+                return Err(ShadowFsError::TargetExists(
+                    renamed_from.to_string_lossy().to_string(),
+                ));
+            }
+            let backup = entry.backup_path.clone().filter(|path| path.exists());
+            match backup {
+                Some(backup) => {
+                    move_file(&backup, &renamed_from)?;
+                    if target.exists() {
+                        fs::remove_file(&target)?;
+                    }
+                }
+                None => move_file(&target, &renamed_from)?,
+            }
+            let entry = self.files.get_mut(id).unwrap();
+            entry.original_path = renamed_from;
+            entry.renamed_from = None;
+            entry.backup_path = None;
+            let entry = entry.clone();
+            self.audit("rollback", &entry, Some(&target));
+            self.save_index()?;
+            info!(
+                "Rolled back rename: {:?} -> {:?}",
+                target, entry.original_path
+            );
+            return Ok(());
+        }
+
+        // Entries applied before backups were recorded fall back to where
+        // the entry's backup would be kept.
+        let backup_path = entry
+            .backup_path
+            .clone()
+            .unwrap_or_else(|| self.backup_path_for(&entry.id));
         if backup_path.exists() {
-            fs::copy(&backup_path, &entry.original_path)?;
+            copy_atomic(&backup_path, &entry.original_path)?;
             fs::remove_file(&backup_path)?;
             info!("Rolled back: {:?}", entry.original_path);
         } else {
@@ -410,8 +2004,10 @@ impl ShadowFs {
                 if let Ok(created) = chrono::DateTime::parse_from_rfc3339(&entry.created_at) {
                     let age = now.signed_duration_since(created);
                     age.num_hours() as u64 > max_age_hours
-                        && (entry.status == ShadowStatus::Applied
-                            || entry.status == ShadowStatus::Rejected)
+                        && matches!(
+                            entry.status,
+                            ShadowStatus::Applied | ShadowStatus::Rejected | ShadowStatus::Orphaned
+                        )
                 } else {
                     false
                 }
@@ -437,16 +2033,176 @@ impl ShadowFs {
         Ok(removed)
     }
 
-    /// Clean up trashed files older than the given hours.
-    pub fn cleanup_trash(&mut self, max_age_hours: u64) -> Result<usize, ShadowFsError> {
-        let now = chrono::Utc::now();
+    /// Drop applied, rejected and orphaned entries, oldest first, deleting
+    /// their shadow copies and trashed files until the store is no larger
+    /// than `max_total_bytes`, then snapshots, oldest first, if that wasn't
+    /// enough. Entries holding no files are kept for history. Returns the
+    /// number of entries removed.
+    pub fn cleanup_by_size(&mut self, max_total_bytes: u64) -> Result<usize, ShadowFsError> {
+        let mut total = self.store_size()?;
+        if total <= max_total_bytes {
+            return Ok(0);
+        }
+
+        let mut candidates: Vec<(String, String)> = self
+            .files
+            .values()
+            .filter(|e| {
+                matches!(
+                    e.status,
+                    ShadowStatus::Applied | ShadowStatus::Rejected | ShadowStatus::Orphaned
+                )
+            })
+            .map(|e| (e.created_at.clone(), e.id.clone()))
+            .collect();
+        candidates.sort();
+
         let mut removed = 0;
+        for (_, id) in candidates {
+            if total <= max_total_bytes {
+                break;
+            }
+            let entry = &self.files[&id];
+            let held: Vec<PathBuf> = [&entry.shadow_path]
+                .into_iter()
+                .chain(entry.backup_path.as_ref())
+                .chain(entry.trashed_path.as_ref())
+                .filter(|path| path.is_file())
+                .cloned()
+                .collect();
+            if held.is_empty() {
+                continue;
+            }
+            for path in held {
+                let size = fs::metadata(&path)?.len();
+                fs::remove_file(&path)?;
+                total = total.saturating_sub(size);
+            }
+            self.files.remove(&id);
+            removed += 1;
+        }
 
-        if !self.trash_root.exists() {
-            return Ok(0);
+        if removed > 0 {
+            self.save_index()?;
+            info!(
+                "Removed {} shadow entries to bring the store under {} bytes",
+                removed, max_total_bytes
+            );
         }
 
-        for entry in fs::read_dir(&self.trash_root)? {
+        for id in self.list_snapshots()? {
+            if total <= max_total_bytes {
+                break;
+            }
+            let size = dir_size(&self.snapshot_dir(&id))?;
+            self.delete_snapshot(&id)?;
+            total = total.saturating_sub(size);
+        }
+        Ok(removed)
+    }
+
+    /// Forget the applied entries among `ids` along with their shadow copies,
+    /// backups and trashed originals, once nothing can need them (the
+    /// caller decides, e.g. because the change is committed). Entries that
+    /// aren't applied are left alone. Returns the ids removed.
+    pub fn prune_applied(&mut self, ids: &[String]) -> Result<Vec<String>, ShadowFsError> {
+        let mut pruned = Vec::new();
+        for id in ids {
+            if !matches!(
+                self.files.get(id).map(|e| e.status),
+                Some(ShadowStatus::Applied)
+            ) {
+                continue;
+            }
+            let Some(entry) = self.files.remove(id) else {
+                continue;
+            };
+            let held = [
+                Some(&entry.shadow_path),
+                entry.backup_path.as_ref(),
+                entry.trashed_path.as_ref(),
+            ];
+            for path in held.into_iter().flatten() {
+                if path.is_file() {
+                    fs::remove_file(path)?;
+                }
+            }
+            pruned.push(entry.id);
+        }
+
+        if !pruned.is_empty() {
+            self.save_index()?;
+            info!("Pruned {} applied shadow entries", pruned.len());
+        }
+        Ok(pruned)
+    }
+
+    /// Forget every pending and historical edit for this workspace: indexed
+    /// shadow copies, backups left by applies, trash, and the index itself.
+    /// Refuses unless `confirm` is set.
+    pub fn purge(&mut self, confirm: bool) -> Result<PurgeReport, ShadowFsError> {
+        if !confirm {
+            return Err(ShadowFsError::PurgeNotConfirmed);
+        }
+
+        let mut report = PurgeReport {
+            index_entries: self.files.len(),
+            ..Default::default()
+        };
+
+        // Only backups the index records; anything else is left alone.
+        for entry in self.files.values() {
+            let Some(backup) = entry.backup_path.as_ref() else {
+                continue;
+            };
+            if backup.is_file() && fs::remove_file(backup).is_ok() {
+                report.backups += 1;
+            }
+        }
+
+        // Only shadow copies of indexed entries; snapshots, in-flight temp
+        // files and anything else under the shadow root are left alone.
+        for id in self.files.keys() {
+            let path = self.shadow_root.join(id);
+            if path.is_file() {
+                fs::remove_file(&path)?;
+                report.shadow_files += 1;
+            }
+        }
+
+        if self.trash_root.exists() {
+            for dir_entry in fs::read_dir(&self.trash_root)? {
+                let path = dir_entry?.path();
+                if path.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+                report.trash_files += 1;
+            }
+        }
+
+        self.files.clear();
+        self.hash_cache.clear();
+        self.save_index()?;
+
+        info!(
+            "Purged shadow state for {:?}: {:?}",
+            self.workspace_root, report
+        );
+        Ok(report)
+    }
+
+    /// Clean up trashed files older than the given hours.
+    pub fn cleanup_trash(&mut self, max_age_hours: u64) -> Result<usize, ShadowFsError> {
+        let now = chrono::Utc::now();
+        let mut removed = 0;
+
+        if !self.trash_root.exists() {
+            return Ok(0);
+        }
+
+        for entry in fs::read_dir(&self.trash_root)? {
             let entry = entry?;
             let metadata = entry.metadata()?;
             let modified = metadata.modified().ok();
@@ -470,16 +2226,211 @@ impl ShadowFs {
         Ok(removed)
     }
 
+    /// Copy the index and every shadow file it references, so
+    /// `restore_snapshot` can put the staging area back as it is now.
+    /// Originals, backups and trash are not part of a snapshot.
+    pub fn snapshot(&self) -> Result<SnapshotId, ShadowFsError> {
+        let id = Uuid::new_v4().to_string();
+        let dir = self.snapshot_dir(&id);
+        fs::create_dir_all(&dir)?;
+        for entry in self.files.values() {
+            if entry.shadow_path.is_file() {
+                fs::copy(&entry.shadow_path, dir.join(&entry.id))?;
+            }
+        }
+        // Written last: a snapshot without its index was never finished.
+        let content = serde_json::to_string_pretty(&self.files)?;
+        fs::write(dir.join("index.json"), content)?;
+        info!("Snapshotted {} shadow entries as {}", self.files.len(), id);
+
+        let snapshots = self.list_snapshots()?;
+        let excess = snapshots.len().saturating_sub(MAX_SNAPSHOTS);
+        for old in &snapshots[..excess] {
+            self.delete_snapshot(old)?;
+        }
+        Ok(id)
+    }
+
+    /// Finished snapshots, oldest first
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotId>, ShadowFsError> {
+        let root = self.shadow_root.join("snapshots");
+        if !root.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut snapshots = Vec::new();
+        for dir_entry in fs::read_dir(&root)? {
+            let dir_entry = dir_entry?;
+            let Ok(taken_at) = fs::metadata(dir_entry.path().join("index.json"))
+                .and_then(|metadata| metadata.modified())
+            else {
+                continue;
+            };
+            snapshots.push((
+                taken_at,
+                dir_entry.file_name().to_string_lossy().to_string(),
+            ));
+        }
+        snapshots.sort();
+        Ok(snapshots.into_iter().map(|(_, id)| id).collect())
+    }
+
+    /// Remove snapshot `id` and the shadow copies it holds
+    pub fn delete_snapshot(&self, id: &str) -> Result<(), ShadowFsError> {
+        let dir = self.snapshot_dir(id);
+        if !valid_snapshot_id(id) || !dir.is_dir() {
+            return Err(ShadowFsError::SnapshotNotFound(id.to_string()));
+        }
+        fs::remove_dir_all(&dir)?;
+        debug!("Deleted shadow snapshot {}", id);
+        Ok(())
+    }
+
+    /// Put the staging area back to snapshot `id`: entries staged since are
+    /// dropped with their shadow copies, and the snapshotted entries and
+    /// shadow copies come back. Files already written to the workspace stay
+    /// as they are.
+    pub fn restore_snapshot(&mut self, id: &str) -> Result<(), ShadowFsError> {
+        let dir = self.snapshot_dir(id);
+        let index_path = dir.join("index.json");
+        if !valid_snapshot_id(id) || !index_path.is_file() {
+            return Err(ShadowFsError::SnapshotNotFound(id.to_string()));
+        }
+        let files: HashMap<String, ShadowFileEntry> =
+            serde_json::from_str(&fs::read_to_string(&index_path)?)?;
+
+        for (entry_id, entry) in &self.files {
+            if !files.contains_key(entry_id)
+                && entry.shadow_path.starts_with(&self.shadow_root)
+                && entry.shadow_path.is_file()
+            {
+                fs::remove_file(&entry.shadow_path)?;
+            }
+        }
+        for entry in files.values() {
+            let copy = dir.join(&entry.id);
+            if copy.is_file() {
+                fs::copy(&copy, &entry.shadow_path)?;
+            }
+        }
+
+        self.files = files;
+        self.hash_cache.clear();
+        self.save_index()?;
+        self.sync_watches();
+        info!("Restored shadow snapshot {}", id);
+        Ok(())
+    }
+
     // -------------------------------------------------------------------------
     // Internal helpers
     // -------------------------------------------------------------------------
 
+    /// Whether an apply writing `path` must first remove the symlink there.
+    /// Fails with `SymlinkTarget` when the policy refuses symlinks.
+    fn replaces_symlink(&self, path: &Path) -> Result<bool, ShadowFsError> {
+        let is_symlink = fs::symlink_metadata(path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        if !is_symlink {
+            return Ok(false);
+        }
+        match self.symlink_policy {
+            SymlinkPolicy::Refuse => Err(ShadowFsError::SymlinkTarget(
+                path.to_string_lossy().to_string(),
+            )),
+            SymlinkPolicy::ReplaceLink => Ok(true),
+            SymlinkPolicy::Follow => Ok(false),
+        }
+    }
+
+    /// Refuse `path` when a symlinked directory above it leads out of the
+    /// workspace, unless the policy is `Follow`. The last component is
+    /// `replaces_symlink`'s business.
+    fn check_parent_in_workspace(&self, path: &Path) -> Result<(), ShadowFsError> {
+        if self.symlink_policy == SymlinkPolicy::Follow {
+            return Ok(());
+        }
+        // The parent may not exist yet; its nearest existing ancestor decides.
+        let Some(existing) = path.ancestors().skip(1).find(|dir| dir.exists()) else {
+            return Ok(());
+        };
+        let root = fs::canonicalize(&self.workspace_root)?;
+        if fs::canonicalize(existing)?.starts_with(&root) {
+            Ok(())
+        } else {
+            Err(ShadowFsError::OutsideWorkspace(
+                path.to_string_lossy().to_string(),
+            ))
+        }
+    }
+
+    fn snapshot_dir(&self, id: &str) -> PathBuf {
+        self.shadow_root.join("snapshots").join(id)
+    }
+
+    /// Where an apply of entry `id` keeps the original it replaced. Keyed by
+    /// id under the shadow root so backups never land next to (or on top of)
+    /// the user's own files.
+    fn backup_path_for(&self, id: &str) -> PathBuf {
+        self.shadow_root.join("backups").join(format!("{}.bak", id))
+    }
+
+    /// Write the index to a temporary file and rename it into place, so
+    /// being killed mid-save leaves the previous index intact.
     fn save_index(&self) -> Result<(), ShadowFsError> {
         let content = serde_json::to_string_pretty(&self.files)?;
-        fs::write(&self.index_path, content)?;
+        let partial = self.index_path.with_extension("json.tmp");
+        fs::write(&partial, content)?;
+        fs::rename(&partial, &self.index_path)?;
         Ok(())
     }
 
+    /// Hash the file at `path`, reusing the cached hash when its mtime and
+    /// size are unchanged since it was last hashed.
+    fn current_hash(&mut self, path: &Path) -> Result<String, ShadowFsError> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        let size = metadata.len();
+
+        if let Some(cached) = self.hash_cache.get(path) {
+            if cached.modified == modified && cached.size == size {
+                return Ok(cached.hash.clone());
+            }
+        }
+
+        let hash = compute_file_hash(path)?;
+        #[cfg(test)]
+        {
+            self.hash_reads += 1;
+        }
+        self.hash_cache.insert(
+            path.to_path_buf(),
+            CachedHash {
+                modified,
+                size,
+                hash: hash.clone(),
+            },
+        );
+        Ok(hash)
+    }
+
+    fn remember_hash(&mut self, path: &Path, hash: &str) {
+        let Ok(metadata) = fs::metadata(path) else {
+            return;
+        };
+        let Ok(modified) = metadata.modified() else {
+            return;
+        };
+        self.hash_cache.insert(
+            path.to_path_buf(),
+            CachedHash {
+                modified,
+                size: metadata.len(),
+                hash: hash.to_string(),
+            },
+        );
+    }
+
     fn build_trash_path(&self, original_path: &Path, id: &str) -> PathBuf {
         let filename = original_path
             .file_name()
@@ -512,33 +2463,483 @@ impl ShadowFs {
 // Helpers
 // ============================================================================
 
-fn compute_hash(content: &str) -> String {
+/// Snapshot ids name a directory under the shadow root and nothing else
+fn valid_snapshot_id(id: &str) -> bool {
+    !id.is_empty() && !id.contains(['/', '\\', '.'])
+}
+
+/// Total size of the files under `dir`; zero when it doesn't exist
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+/// Rebuild an index from the shadow files in `shadow_root`, taking each
+/// one's original path from what is left of the unreadable `index`. Every
+/// recovered entry is `Pending` and diffed against its original as it is now;
+/// shadow files the index no longer names are left alone.
+fn recover_index(
+    workspace_root: &Path,
+    shadow_root: &Path,
+    index: &str,
+) -> Result<HashMap<String, ShadowFileEntry>, ShadowFsError> {
+    let mut files = HashMap::new();
+    for dir_entry in fs::read_dir(shadow_root)? {
+        let shadow_path = dir_entry?.path();
+        let Some(id) = shadow_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| Uuid::parse_str(name).is_ok())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if !shadow_path.is_file() {
+            continue;
+        }
+        let Some(original_path) = indexed_original_path(index, &id) else {
+            warn!(
+                "Cannot recover shadow file {:?}: its original path is lost",
+                shadow_path
+            );
+            continue;
+        };
+        let content = match fs::read(&shadow_path) {
+            Ok(content) => content,
+            Err(err) => {
+                warn!("Cannot read shadow file {:?}: {}", shadow_path, err);
+                continue;
+            }
+        };
+
+        let prepared = match prepare_stage(
+            workspace_root,
+            &original_path,
+            content,
+            None,
+            DiffAlgorithm::default(),
+        ) {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                warn!("Cannot recover shadow file {:?}: {}", shadow_path, err);
+                continue;
+            }
+        };
+        let created_at = fs::metadata(&shadow_path)
+            .and_then(|metadata| metadata.modified())
+            .map(chrono::DateTime::<chrono::Utc>::from)
+            .unwrap_or_else(|_| chrono::Utc::now())
+            .to_rfc3339();
+        files.insert(
+            id.clone(),
+            ShadowFileEntry {
+                id,
+                original_path: prepared.original_path,
+                original_exists: prepared.original_exists,
+                original_hash: prepared.original_hash,
+                shadow_path,
+                shadow_hash: prepared.shadow_hash,
+                status: ShadowStatus::Pending,
+                created_at,
+                reviewed_at: None,
+                patch: prepared.patch,
+                source: None,
+                backup_path: None,
+                applied_at: None,
+                trashed_path: None,
+                renamed_from: None,
+                diff_algorithm: prepared.diff_algorithm,
+            },
+        );
+    }
+    Ok(files)
+}
+
+/// The `original_path` recorded for entry `id` in a possibly truncated
+/// index, which lists it right after the entry's id
+fn indexed_original_path(index: &str, id: &str) -> Option<PathBuf> {
+    let id_field = format!("\"id\": \"{}\"", id);
+    let rest = &index[index.find(&id_field)? + id_field.len()..];
+    let rest = rest.trim_start().strip_prefix(',')?.trim_start();
+    let value = rest.strip_prefix("\"original_path\":")?;
+    serde_json::Deserializer::from_str(value)
+        .into_iter::<PathBuf>()
+        .next()?
+        .ok()
+}
+
+/// Rename, falling back to copy-and-remove across filesystems.
+fn move_file(from: &Path, to: &Path) -> Result<(), ShadowFsError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Err(err) = fs::rename(from, to) {
+        debug!("Rename failed, copying instead: {}", err);
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn resolve_in_root(root: &Path, path: &str) -> Result<PathBuf, ShadowFsError> {
+    let mut resolved = PathBuf::new();
+    for component in root.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    if resolved.starts_with(root) && resolved != root {
+        Ok(resolved)
+    } else {
+        Err(ShadowFsError::OutsideWorkspace(path.to_string()))
+    }
+}
+
+/// A temporary path beside `path`, unique so concurrent writers never share
+/// it
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(format!(".{}.tmp", Uuid::new_v4()));
+    PathBuf::from(partial)
+}
+
+/// Write `content` to a temporary file beside `path` and rename it into
+/// place, keeping the permissions of the file it replaces. A symlink at
+/// `path` is written through, as `fs::write` would.
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), ShadowFsError> {
+    let path = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    let partial = partial_path(&path);
+    fs::write(&partial, content)?;
+    let renamed = match fs::metadata(&path) {
+        Ok(metadata) => fs::set_permissions(&partial, metadata.permissions()),
+        Err(_) => Ok(()),
+    }
+    .and_then(|()| fs::rename(&partial, &path));
+    if let Err(err) = renamed {
+        let _ = fs::remove_file(&partial);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Copy `from` to a temporary file beside `to` and rename it into place, so
+/// `to` is never left half-written.
+fn copy_atomic(from: &Path, to: &Path) -> Result<(), ShadowFsError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = partial_path(to);
+    fs::copy(from, &partial)?;
+    if let Err(err) = fs::rename(&partial, to) {
+        let _ = fs::remove_file(&partial);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Everything needed to stage a file that can be computed without touching
+/// the shadow index, so it can run off the main thread.
+struct PreparedStage {
+    original_path: PathBuf,
+    original_exists: bool,
+    original_hash: Option<String>,
+    new_content: Vec<u8>,
+    shadow_hash: String,
+    patch: Option<FilePatch>,
+    patch_overridden: bool,
+    source: Option<String>,
+    diff_algorithm: DiffAlgorithm,
+}
+
+fn prepare_stage(
+    workspace_root: &Path,
+    original_path: &Path,
+    new_content: Vec<u8>,
+    patch_override: Option<FilePatch>,
+    algorithm: DiffAlgorithm,
+) -> Result<PreparedStage, ShadowFsError> {
+    let (original_exists, original_hash) = if original_path.exists() {
+        (true, Some(compute_file_hash(original_path)?))
+    } else {
+        (false, None)
+    };
+
+    let shadow_hash = compute_hash(&new_content);
+    let patch_overridden = patch_override.is_some();
+
+    let patch = match patch_override {
+        Some(patch) => Some(patch),
+        None => {
+            // Read original to diff against; it may be binary, so as bytes
+            let original_content = if original_exists {
+                fs::read(original_path)?
+            } else {
+                Vec::new()
+            };
+            let relative_path = original_path
+                .strip_prefix(workspace_root)
+                .unwrap_or(original_path)
+                .to_string_lossy()
+                .to_string();
+            if is_binary(&original_content) || is_binary(&new_content) {
+                Some(compute_binary_diff(
+                    &original_content,
+                    &new_content,
+                    &relative_path,
+                ))
+            } else {
+                Some(compute_unified_diff_with(
+                    &String::from_utf8_lossy(&original_content),
+                    &String::from_utf8_lossy(&new_content),
+                    &relative_path,
+                    3,
+                    &DiffOptions {
+                        algorithm,
+                        ..Default::default()
+                    },
+                ))
+            }
+        }
+    };
+
+    Ok(PreparedStage {
+        original_path: original_path.to_path_buf(),
+        original_exists,
+        original_hash,
+        new_content,
+        shadow_hash,
+        patch,
+        patch_overridden,
+        source: None,
+        diff_algorithm: algorithm,
+    })
+}
+
+fn prepare_stages(
+    workspace_root: &Path,
+    files: Vec<(PathBuf, Vec<u8>)>,
+    max_threads: usize,
+) -> Result<Vec<PreparedStage>, ShadowFsError> {
+    let prepare = |(path, content): (PathBuf, Vec<u8>)| {
+        prepare_stage(
+            workspace_root,
+            &path,
+            content,
+            None,
+            DiffAlgorithm::default(),
+        )
+    };
+
+    if max_threads <= 1 || files.len() <= 1 {
+        return files.into_iter().map(prepare).collect();
+    }
+
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(max_threads)
+        .build()
+    {
+        // `collect` on an indexed parallel iterator preserves input order.
+        Ok(pool) => pool.install(|| files.into_par_iter().map(prepare).collect()),
+        Err(e) => {
+            warn!(
+                "Failed to build staging thread pool, staging sequentially: {}",
+                e
+            );
+            files.into_iter().map(prepare).collect()
+        }
+    }
+}
+
+/// Filesystems that fold case by default, where `.GIT/hooks` is `.git/hooks`
+const CASE_INSENSITIVE_FS: bool = cfg!(any(target_os = "macos", windows));
+
+/// `path_rule_matches`, folding case first when the filesystem would
+fn protected_rule_matches(rule: &str, path: &str, root: &str, case_insensitive: bool) -> bool {
+    if case_insensitive {
+        path_rule_matches(
+            &rule.to_lowercase(),
+            &path.to_lowercase(),
+            Some(&root.to_lowercase()),
+        )
+    } else {
+        path_rule_matches(rule, path, Some(root))
+    }
+}
+
+/// Path an apply writes to: the rename target if there is one
+fn apply_target(entry: &ShadowFileEntry) -> PathBuf {
+    entry
+        .patch
+        .as_ref()
+        .and_then(|p| p.new_file_path.clone())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| entry.original_path.clone())
+}
+
+/// Content an apply writes to `apply_target`; `None` for deletions
+fn applied_content(entry: &ShadowFileEntry) -> Result<Option<String>, ShadowFsError> {
+    match entry.patch.as_ref().map(|p| &p.operation) {
+        Some(PatchOperation::Delete) => Ok(None),
+        _ => Ok(Some(fs::read_to_string(&entry.shadow_path)?)),
+    }
+}
+
+/// Merge the staged change into the original as it is on disk now. The base
+/// is recovered by undoing the staged patch, and only trusted if it hashes to
+/// what was staged against; `None` when it can't be recovered.
+fn merge_into_current(entry: &ShadowFileEntry, expected_hash: &str) -> Option<MergeResult> {
+    let patch = entry.patch.as_ref().filter(|patch| {
+        !patch.hunks.is_empty()
+            && matches!(
+                patch.operation,
+                PatchOperation::Modify | PatchOperation::Rename
+            )
+    })?;
+    let staged = fs::read_to_string(&entry.shadow_path).ok()?;
+    let base = apply_file_patch(&staged, &reverse_patch(patch)).ok()?;
+    if compute_hash(&base) != expected_hash {
+        return None;
+    }
+    let current = fs::read_to_string(&entry.original_path).ok()?;
+    Some(three_way_merge(&base, &current, &staged))
+}
+
+fn merged_content(merge: &MergeResult) -> &str {
+    match merge {
+        MergeResult::Clean { content } | MergeResult::Conflicted { content, .. } => content,
+    }
+}
+
+fn compute_hash(content: impl AsRef<[u8]>) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
+    hasher.update(content.as_ref());
     let result = hasher.finalize();
     hex::encode(result)
 }
 
+/// `compute_hash` of the file at `path`, read in chunks so large or
+/// non-UTF-8 files are never held in memory whole
+pub fn compute_file_hash(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
 // ============================================================================
 // Tauri Commands
 // ============================================================================
 
 use std::sync::Arc;
+use tauri::Emitter;
 use tokio::sync::Mutex;
 
 pub type ShadowFsState = Arc<Mutex<Option<ShadowFs>>>;
 
+fn not_initialized() -> String {
+    ShadowFsError::NotInitialized.to_string()
+}
+
+/// (Re)initialize the shadow FS for a workspace. On failure the previous
+/// instance, if any, is left in place.
+fn init_shadow_slot(
+    slot: &mut Option<ShadowFs>,
+    workspace_root: PathBuf,
+) -> Result<&mut ShadowFs, ShadowFsError> {
+    let shadow_fs = ShadowFs::new(workspace_root)?;
+    Ok(slot.insert(shadow_fs))
+}
+
+#[tauri::command]
+pub async fn init_shadow_fs(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, ShadowFsState>,
+    policy: tauri::State<'_, PolicyEngineState>,
+    workspace_path: String,
+    retain_rejected: Option<bool>,
+    symlink_policy: Option<SymlinkPolicy>,
+) -> Result<String, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = init_shadow_slot(&mut guard, PathBuf::from(&workspace_path)).map_err(|e| {
+        warn!(
+            "Failed to initialize Shadow FS at {}: {}",
+            workspace_path, e
+        );
+        e.to_string()
+    })?;
+    shadow_fs.set_retain_rejected(retain_rejected.unwrap_or(false));
+    shadow_fs.set_symlink_policy(symlink_policy.unwrap_or_default());
+    shadow_fs.set_hunk_progress(Some(Arc::new(move |patch, progress| {
+        let _ = app_handle.emit(
+            "apply-hunk-progress",
+            serde_json::json!({
+                "patchId": patch.id,
+                "filePath": patch.file_path,
+                "applied": progress.applied,
+                "total": progress.total,
+            }),
+        );
+    })));
+
+    info!("Shadow FS initialized at {:?}", shadow_fs.workspace_root());
+    // Workspace policy overrides follow this root, not what requests claim.
+    policy.engine.lock().await.set_workspace_root(Some(
+        shadow_fs.workspace_root().to_string_lossy().to_string(),
+    ));
+    let report = shadow_fs.load_report();
+    if !report.orphaned.is_empty() || report.quarantined_index.is_some() {
+        warn!(
+            "Shadow index repaired on load: {} orphaned entries, quarantined index: {:?}",
+            report.orphaned.len(),
+            report.quarantined_index
+        );
+    }
+    Ok(shadow_fs.workspace_root().to_string_lossy().to_string())
+}
+
 #[tauri::command]
 pub async fn stage_file(
     state: tauri::State<'_, ShadowFsState>,
     file_path: String,
     content: String,
+    algorithm: Option<DiffAlgorithm>,
 ) -> Result<ShadowFileEntry, String> {
     let mut guard = state.lock().await;
-    let shadow_fs = guard.as_mut().ok_or("Shadow FS not initialized")?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
 
     shadow_fs
-        .stage_file(Path::new(&file_path), &content)
+        .stage_file_with_algorithm(
+            Path::new(&file_path),
+            &content,
+            algorithm.unwrap_or_default(),
+        )
         .map_err(|e| e.to_string())
 }
 
@@ -549,30 +2950,111 @@ pub async fn stage_file_with_patch(
     patch: Option<FilePatch>,
 ) -> Result<ShadowFileEntry, String> {
     let mut guard = state.lock().await;
-    let shadow_fs = guard.as_mut().ok_or("Shadow FS not initialized")?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
 
     shadow_fs
         .stage_file_with_patch(Path::new(&file_path), &content, patch)
         .map_err(|e| e.to_string())
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageFileRequest {
+    pub file_path: String,
+    pub content: String,
+}
+
+#[tauri::command]
+pub async fn stage_files(
+    state: tauri::State<'_, ShadowFsState>,
+    files: Vec<StageFileRequest>,
+    max_threads: Option<usize>,
+) -> Result<Vec<ShadowFileEntry>, String> {
+    let max_threads = max_threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let files: Vec<(PathBuf, Vec<u8>)> = files
+        .into_iter()
+        .map(|file| (PathBuf::from(file.file_path), file.content.into_bytes()))
+        .collect();
+
+    let workspace_root = {
+        let guard = state.lock().await;
+        let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+        shadow_fs
+            .check_stageable(files.iter().map(|(path, _)| path.as_path()))
+            .map_err(|e| e.to_string())?;
+        shadow_fs.workspace_root().to_path_buf()
+    };
+
+    // Hashing and diffing a large batch can take a while; do it without the
+    // lock so other shadow commands aren't queued behind it.
+    let root = workspace_root.clone();
+    let prepared = tokio::task::spawn_blocking(move || prepare_stages(&root, files, max_threads))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+    if shadow_fs.workspace_root() != workspace_root.as_path() {
+        return Err(format!(
+            "workspace_changed: shadow workspace moved to {} while staging",
+            shadow_fs.workspace_root().display()
+        ));
+    }
+    shadow_fs
+        .commit_prepared(prepared)
+        .map_err(|e| e.to_string())
+}
+
+/// Re-run the shadow index integrity check and return what it found.
+#[tauri::command]
+pub async fn verify_shadow_index(
+    state: tauri::State<'_, ShadowFsState>,
+) -> Result<IndexVerificationReport, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs.verify_index().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn list_pending_patches(
     state: tauri::State<'_, ShadowFsState>,
 ) -> Result<Vec<ShadowFileEntry>, String> {
-    let guard = state.lock().await;
-    let shadow_fs = guard.as_ref().ok_or("Shadow FS not initialized")?;
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+    shadow_fs.sync_external_changes();
 
     Ok(shadow_fs.list_pending().into_iter().cloned().collect())
 }
 
+/// Added and removed lines across all pending patches
+#[tauri::command]
+pub async fn get_pending_diff_stats(
+    state: tauri::State<'_, ShadowFsState>,
+) -> Result<DiffStats, String> {
+    let guard = state.lock().await;
+    let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+
+    let patches: Vec<FilePatch> = shadow_fs
+        .list_pending()
+        .into_iter()
+        .filter_map(|entry| entry.patch.clone())
+        .collect();
+    Ok(summarize(&patches))
+}
+
 #[tauri::command]
 pub async fn approve_patch(
     state: tauri::State<'_, ShadowFsState>,
     patch_id: String,
 ) -> Result<ShadowFileEntry, String> {
     let mut guard = state.lock().await;
-    let shadow_fs = guard.as_mut().ok_or("Shadow FS not initialized")?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
 
     shadow_fs
         .approve(&patch_id)
@@ -586,34 +3068,2033 @@ pub async fn reject_patch(
     patch_id: String,
 ) -> Result<(), String> {
     let mut guard = state.lock().await;
-    let shadow_fs = guard.as_mut().ok_or("Shadow FS not initialized")?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
 
     shadow_fs.reject(&patch_id).map_err(|e| e.to_string())
 }
 
+/// Stage the undo of an applied patch for review
 #[tauri::command]
-pub async fn apply_patch(
+pub async fn stage_revert(
     state: tauri::State<'_, ShadowFsState>,
     patch_id: String,
-    create_backup: bool,
-) -> Result<ApplyResult, String> {
+) -> Result<ShadowFileEntry, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs.stage_revert(&patch_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn restage_rejected(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+) -> Result<ShadowFileEntry, String> {
     let mut guard = state.lock().await;
-    let shadow_fs = guard.as_mut().ok_or("Shadow FS not initialized")?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
 
     shadow_fs
-        .apply(&patch_id, create_backup)
+        .restage_rejected(&patch_id)
+        .cloned()
         .map_err(|e| e.to_string())
 }
 
+/// Undo an applied delete by moving the file back from trash
 #[tauri::command]
-pub async fn cleanup_trash(
+pub async fn restore_from_trash(
     state: tauri::State<'_, ShadowFsState>,
-    max_age_hours: u64,
-) -> Result<usize, String> {
+    patch_id: String,
+) -> Result<ShadowFileEntry, String> {
     let mut guard = state.lock().await;
-    let shadow_fs = guard.as_mut().ok_or("Shadow FS not initialized")?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
 
     shadow_fs
-        .cleanup_trash(max_age_hours)
+        .restore_from_trash(&patch_id)
+        .cloned()
         .map_err(|e| e.to_string())
 }
+
+/// Turn watching of pending entries' originals on or off. While on, entries
+/// whose original is edited on disk become `Conflict` and their ids are
+/// emitted as `shadow-conflicts`.
+#[tauri::command]
+pub async fn watch_shadow_originals(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ShadowFsState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let shared = state.inner().clone();
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    if !enabled {
+        shadow_fs.unwatch_originals();
+        return Ok(());
+    }
+    shadow_fs
+        .watch_originals(move || {
+            let shared = shared.clone();
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut guard = shared.lock().await;
+                if let Some(shadow_fs) = guard.as_mut() {
+                    let conflicted = shadow_fs.sync_external_changes();
+                    if !conflicted.is_empty() {
+                        let _ = app.emit("shadow-conflicts", &conflicted);
+                    }
+                }
+            });
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Snapshot the staging area before a risky operation
+#[tauri::command]
+pub async fn snapshot_shadow_state(
+    state: tauri::State<'_, ShadowFsState>,
+) -> Result<SnapshotId, String> {
+    let guard = state.lock().await;
+    let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+
+    shadow_fs.snapshot().map_err(|e| e.to_string())
+}
+
+/// Snapshots that can be restored, oldest first
+#[tauri::command]
+pub async fn list_shadow_snapshots(
+    state: tauri::State<'_, ShadowFsState>,
+) -> Result<Vec<SnapshotId>, String> {
+    let guard = state.lock().await;
+    let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+
+    shadow_fs.list_snapshots().map_err(|e| e.to_string())
+}
+
+/// Discard a snapshot that is no longer needed
+#[tauri::command]
+pub async fn delete_shadow_snapshot(
+    state: tauri::State<'_, ShadowFsState>,
+    snapshot_id: String,
+) -> Result<(), String> {
+    let guard = state.lock().await;
+    let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .delete_snapshot(&snapshot_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Roll the staging area back to `snapshot_id`, returning what is pending
+/// afterwards
+#[tauri::command]
+pub async fn restore_shadow_snapshot(
+    state: tauri::State<'_, ShadowFsState>,
+    snapshot_id: String,
+) -> Result<Vec<ShadowFileEntry>, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .restore_snapshot(&snapshot_id)
+        .map_err(|e| e.to_string())?;
+    Ok(shadow_fs.list_pending().into_iter().cloned().collect())
+}
+
+/// Resolve a conflict by keeping the staged content or the file on disk
+#[tauri::command]
+pub async fn resolve_conflict(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+    choice: ConflictChoice,
+) -> Result<ShadowFileEntry, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .resolve_conflict(&patch_id, choice)
+        .cloned()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn restage_against_current(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+) -> Result<ShadowFileEntry, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .restage_against_current(&patch_id)
+        .cloned()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reject_all_conflicts(
+    state: tauri::State<'_, ShadowFsState>,
+    restage: Option<bool>,
+) -> Result<usize, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .reject_all_conflicts(restage.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// Apply from the review UI. A conflict comes back as a failed result with
+/// `conflict` set, alongside the details for the `patch-conflict` event.
+fn apply_flagging_conflict(
+    shadow_fs: &mut ShadowFs,
+    patch_id: &str,
+    create_backup: bool,
+) -> (Result<ApplyResult, String>, Option<PatchConflict>) {
+    match shadow_fs.apply(patch_id, create_backup) {
+        Ok(result) => (Ok(result), None),
+        Err(ShadowFsError::Conflict {
+            expected_hash,
+            actual_hash,
+        }) => {
+            let conflict = shadow_fs
+                .patch_conflict(patch_id, &expected_hash, &actual_hash)
+                .ok();
+            let file_path = shadow_fs
+                .get(patch_id)
+                .map(|entry| entry.original_path.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let result = ApplyResult {
+                success: false,
+                file_path,
+                backup_path: None,
+                error: Some(
+                    ShadowFsError::Conflict {
+                        expected_hash,
+                        actual_hash,
+                    }
+                    .to_string(),
+                ),
+                merge_conflicts: 0,
+                conflict: true,
+            };
+            (Ok(result), conflict)
+        }
+        Err(err) => (Err(err.to_string()), None),
+    }
+}
+
+#[tauri::command]
+pub async fn apply_patch(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+    create_backup: bool,
+) -> Result<ApplyResult, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    let (result, conflict) = apply_flagging_conflict(shadow_fs, &patch_id, create_backup);
+    if let Some(conflict) = &conflict {
+        warn!("Patch {} conflicts with {}", patch_id, conflict.file_path);
+        let _ = app_handle.emit("patch-conflict", conflict);
+    }
+    result
+}
+
+/// Apply every approved patch, continuing past individual failures
+#[tauri::command]
+pub async fn apply_all_approved(
+    state: tauri::State<'_, ShadowFsState>,
+    strategy: Option<ConflictStrategy>,
+) -> Result<ApplyAllSummary, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    Ok(shadow_fs.apply_all_approved(strategy.unwrap_or_default()))
+}
+
+/// Apply `patch_ids` all-or-nothing, in order
+#[tauri::command]
+pub async fn apply_patches_atomic(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_ids: Vec<String>,
+    create_backup: Option<bool>,
+) -> Result<BatchApplyResult, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    Ok(shadow_fs.apply_batch(&patch_ids, create_backup.unwrap_or(false)))
+}
+
+/// Final content an apply would produce, without writing it
+#[tauri::command]
+pub async fn preview_applied_content(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+    strategy: Option<ConflictStrategy>,
+) -> Result<AppliedContentPreview, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    // `apply_patch` merges into a changed original unless told otherwise.
+    shadow_fs
+        .preview_applied_content_with_strategy(
+            &patch_id,
+            strategy.unwrap_or(ConflictStrategy::Merge),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// What applying a patch would change, for a final confirmation
+#[tauri::command]
+pub async fn preview_apply(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+) -> Result<ApplyPreview, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .preview_apply(&patch_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Whether a pending patch still applies to the file on disk, so the UI can
+/// flag stale patches before offering to apply them
+#[tauri::command]
+pub async fn check_patch_applies(
+    state: tauri::State<'_, ShadowFsState>,
+    patch_id: String,
+) -> Result<PatchApplicability, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .check_applies(&patch_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Applied changes with their backups and timestamps, newest first
+#[tauri::command]
+pub async fn get_apply_history(
+    state: tauri::State<'_, ShadowFsState>,
+    path: Option<String>,
+) -> Result<Vec<ShadowFileEntry>, String> {
+    let guard = state.lock().await;
+    let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+
+    Ok(shadow_fs
+        .apply_history(path.as_deref().map(Path::new))
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// Wipe all shadow state for the current workspace. Fails fast instead of
+/// waiting if another shadow operation (such as an apply) holds the lock.
+#[tauri::command]
+pub async fn purge_shadow_state(
+    state: tauri::State<'_, ShadowFsState>,
+    confirm: bool,
+) -> Result<PurgeReport, String> {
+    let mut guard = state
+        .try_lock()
+        .map_err(|_| ShadowFsError::Busy.to_string())?;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs.purge(confirm).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cleanup_trash(
+    state: tauri::State<'_, ShadowFsState>,
+    max_age_hours: u64,
+) -> Result<usize, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .cleanup_trash(max_age_hours)
+        .map_err(|e| e.to_string())
+}
+
+/// Delete the oldest applied or rejected shadow data until the store fits
+/// in `max_total_bytes`
+#[tauri::command]
+pub async fn cleanup_shadow_by_size(
+    state: tauri::State<'_, ShadowFsState>,
+    max_total_bytes: u64,
+) -> Result<usize, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs
+        .cleanup_by_size(max_total_bytes)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_shadow_store_usage(
+    state: tauri::State<'_, ShadowFsState>,
+) -> Result<ShadowStoreUsage, String> {
+    let guard = state.lock().await;
+    let shadow_fs = guard.as_ref().ok_or_else(not_initialized)?;
+
+    shadow_fs.usage().map_err(|e| e.to_string())
+}
+
+/// Set or clear (`None`) the store quota staging is checked against
+#[tauri::command]
+pub async fn set_shadow_quota(
+    state: tauri::State<'_, ShadowFsState>,
+    max_total_bytes: Option<u64>,
+) -> Result<ShadowStoreUsage, String> {
+    let mut guard = state.lock().await;
+    let shadow_fs = guard.as_mut().ok_or_else(not_initialized)?;
+
+    shadow_fs.set_quota(max_total_bytes);
+    shadow_fs.usage().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        std::env::temp_dir().join(format!("coworkany-{name}-{}-{nanos}", std::process::id()))
+    }
+
+    #[test]
+    fn not_initialized_error_carries_stable_code() {
+        assert!(not_initialized().starts_with("ShadowFsNotInitialized"));
+    }
+
+    #[test]
+    fn init_failure_keeps_slot_empty_then_reinit_succeeds() {
+        let workspace = unique_temp_dir("shadow-init");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        // A regular file where the .coworkany directory should go blocks init.
+        let blocker = workspace.join(".coworkany");
+        fs::write(&blocker, "not a directory").expect("write blocker");
+
+        let mut slot = None;
+        assert!(init_shadow_slot(&mut slot, workspace.clone()).is_err());
+        assert!(slot.is_none());
+
+        fs::remove_file(&blocker).expect("remove blocker");
+        let shadow_fs = init_shadow_slot(&mut slot, workspace.clone()).expect("re-init");
+        assert_eq!(shadow_fs.workspace_root(), workspace.as_path());
+        assert!(slot.is_some());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn conflict_check_reuses_hash_until_file_changes() {
+        let workspace = unique_temp_dir("shadow-hash-cache");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let target = workspace.join("big.txt");
+        fs::write(&target, "original\n").expect("write original");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let first = shadow_fs.current_hash(&target).expect("hash");
+        let second = shadow_fs.current_hash(&target).expect("hash");
+        assert_eq!(first, second);
+        assert_eq!(shadow_fs.hash_reads, 1);
+
+        fs::write(&target, "changed content\n").expect("modify original");
+        let third = shadow_fs.current_hash(&target).expect("hash");
+        assert_ne!(first, third);
+        assert_eq!(shadow_fs.hash_reads, 2);
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn file_hash_streams_large_non_utf8_content() {
+        let dir = unique_temp_dir("shadow-file-hash");
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("blob.bin");
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8 | 0x80).collect();
+        fs::write(&path, &content).expect("write blob");
+
+        assert_eq!(
+            compute_file_hash(&path).expect("hash"),
+            compute_hash(&content)
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn configured_roots_keep_state_out_of_the_workspace() {
+        let workspace = unique_temp_dir("shadow-config-workspace");
+        let state_dir = unique_temp_dir("shadow-config-state");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let config = ShadowFsConfig::in_dir(&state_dir)
+            .with_audit_path(state_dir.join("logs").join("apply.jsonl"));
+        let mut shadow_fs =
+            ShadowFs::with_config(workspace.clone(), config).expect("init shadow fs");
+
+        let target = workspace.join("notes.txt");
+        let entry = shadow_fs.stage_file(&target, "staged\n").expect("stage");
+        assert!(entry.shadow_path.starts_with(state_dir.join("shadow")));
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "staged\n");
+        assert!(state_dir.join("logs").join("apply.jsonl").is_file());
+        assert!(!workspace.join(".coworkany").exists());
+
+        let _ = fs::remove_dir_all(&workspace);
+        let _ = fs::remove_dir_all(&state_dir);
+    }
+
+    #[test]
+    fn staging_into_protected_paths_is_refused() {
+        let workspace = unique_temp_dir("shadow-protected");
+        fs::create_dir_all(workspace.join(".git")).expect("create .git");
+        fs::write(workspace.join(".git").join("config"), "[core]\n").expect("write config");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let error = shadow_fs
+            .stage_file(
+                &workspace.join(".git").join("config"),
+                "[core]\nbare = true\n",
+            )
+            .expect_err("staging into .git is refused");
+        assert!(matches!(error, ShadowFsError::PathProtected(_)));
+        assert!(error.to_string().starts_with("path_protected"));
+        assert!(shadow_fs
+            .stage_file_with_patch(&workspace.join(".coworkany/index.json"), "{}", None)
+            .is_err());
+        assert!(shadow_fs.list_pending().is_empty());
+
+        shadow_fs
+            .stage_file(&workspace.join("src").join("main.rs"), "fn main() {}\n")
+            .expect("a normal source file stages");
+
+        let source = workspace.join("hook.sh");
+        fs::write(&source, "echo hi\n").expect("write hook source");
+        let mut rename = compute_unified_diff("echo hi\n", "echo hi\n", "hook.sh", 3);
+        rename.operation = PatchOperation::Rename;
+        rename.new_file_path = Some(
+            workspace
+                .join(".git/hooks/pre-commit")
+                .to_string_lossy()
+                .to_string(),
+        );
+        let error = shadow_fs
+            .stage_file_with_patch(&source, "echo hi\n", Some(rename))
+            .expect_err("renaming into .git is refused");
+        assert!(matches!(error, ShadowFsError::PathProtected(_)));
+
+        let root = workspace.to_string_lossy();
+        let folded = workspace.join(".GIT/hooks/pre-commit");
+        let folded = folded.to_string_lossy();
+        assert!(protected_rule_matches(".git/", &folded, &root, true));
+        assert!(!protected_rule_matches(".git/", &folded, &root, false));
+
+        let config = ShadowFsConfig::for_workspace(&workspace)
+            .with_protected_paths(vec!["**/.env".to_string(), "*.lock".to_string()]);
+        let mut shadow_fs = ShadowFs::with_config(workspace.clone(), config).expect("init");
+        let env = workspace.join("app").join(".env");
+        assert!(shadow_fs.stage_file(&env, "SECRET=1\n").is_err());
+        assert!(shadow_fs
+            .stage_files(vec![(workspace.join("Cargo.lock"), String::new())], 1)
+            .is_err());
+        shadow_fs
+            .stage_file(&workspace.join(".git").join("config"), "[core]\n")
+            .expect("custom rules replace the defaults");
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn external_change_to_an_original_surfaces_as_a_conflict() {
+        let workspace = unique_temp_dir("shadow-external-change");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let edited = workspace.join("edited.txt");
+        let touched = workspace.join("touched.txt");
+        fs::write(&edited, "base\n").expect("write edited");
+        fs::write(&touched, "base\n").expect("write touched");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let edited_entry = shadow_fs.stage_file(&edited, "agent\n").expect("stage");
+        let touched_entry = shadow_fs.stage_file(&touched, "agent\n").expect("stage");
+        shadow_fs.approve(&edited_entry.id).expect("approve");
+
+        // What the watcher reports; `touched` changed back to the same content.
+        fs::write(&edited, "edited elsewhere\n").expect("edit original");
+        shadow_fs
+            .external_changes
+            .lock()
+            .unwrap()
+            .extend([edited.clone(), touched.clone()]);
+
+        assert_eq!(
+            shadow_fs.sync_external_changes(),
+            vec![edited_entry.id.clone()]
+        );
+        assert_eq!(
+            shadow_fs.get(&edited_entry.id).map(|e| e.status),
+            Some(ShadowStatus::Conflict)
+        );
+        assert_eq!(
+            shadow_fs.get(&touched_entry.id).map(|e| e.status),
+            Some(ShadowStatus::Pending)
+        );
+        let listed: HashSet<&str> = shadow_fs
+            .list_pending()
+            .into_iter()
+            .map(|e| e.id.as_str())
+            .collect();
+        assert!(listed.contains(edited_entry.id.as_str()));
+        assert!(shadow_fs.sync_external_changes().is_empty());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn staging_primes_hash_cache_for_apply() {
+        let workspace = unique_temp_dir("shadow-hash-prime");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let target = workspace.join("file.txt");
+        fs::write(&target, "before\n").expect("write original");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let entry = shadow_fs.stage_file(&target, "after\n").expect("stage");
+        shadow_fs.approve(&entry.id).expect("approve");
+        let result = shadow_fs.apply(&entry.id, false).expect("apply");
+
+        assert!(result.success);
+        assert_eq!(shadow_fs.hash_reads, 0);
+        assert_eq!(fs::read_to_string(&target).expect("read"), "after\n");
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn check_applies_reports_clean_fuzzy_and_conflicting_patches() {
+        let workspace = unique_temp_dir("shadow-check-applies");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let target = workspace.join("lines.txt");
+        let original: String = (0..12).map(|i| format!("line{i}\n")).collect();
+        fs::write(&target, &original).expect("write original");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let entry = shadow_fs
+            .stage_file(&target, &original.replace("line6\n", "changed\n"))
+            .expect("stage");
+        assert_eq!(
+            shadow_fs.check_applies(&entry.id).expect("check"),
+            PatchApplicability::Clean
+        );
+
+        // Lines added above the hunk leave it applicable, shifted.
+        fs::write(&target, format!("new0\nnew1\n{original}")).expect("drift");
+        assert_eq!(
+            shadow_fs.check_applies(&entry.id).expect("check"),
+            PatchApplicability::Fuzzy { offset: 2 }
+        );
+
+        // Rewriting the hunk's context is a real conflict.
+        fs::write(&target, original.replace("line5\n", "edited\n")).expect("edit");
+        match shadow_fs.check_applies(&entry.id).expect("check") {
+            PatchApplicability::Conflict {
+                expected_hash,
+                actual_hash,
+            } => {
+                assert_eq!(Some(&expected_hash), entry.original_hash.as_ref());
+                assert_ne!(actual_hash, expected_hash);
+            }
+            other => panic!("expected conflict, got {other:?}"),
+        }
+        assert_eq!(
+            shadow_fs.get(&entry.id).expect("entry").status,
+            ShadowStatus::Pending
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn staged_revert_restores_the_content_before_apply() {
+        let workspace = unique_temp_dir("shadow-revert");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let target = workspace.join("notes.txt");
+        fs::write(&target, "one\ntwo\nthree\n").expect("write original");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let entry = shadow_fs
+            .stage_file(&target, "one\n2\nthree\n")
+            .expect("stage");
+        assert!(matches!(
+            shadow_fs.stage_revert(&entry.id),
+            Err(ShadowFsError::NotRevertible(_))
+        ));
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+
+        let revert = shadow_fs.stage_revert(&entry.id).expect("stage revert");
+        assert_eq!(revert.status, ShadowStatus::Pending);
+        let patch = revert.patch.as_ref().expect("patch");
+        assert_eq!((patch.additions, patch.deletions), (1, 1));
+        shadow_fs.approve(&revert.id).expect("approve revert");
+        shadow_fs.apply(&revert.id, false).expect("apply revert");
+        assert_eq!(
+            fs::read_to_string(&target).expect("read"),
+            "one\ntwo\nthree\n"
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn staging_over_a_binary_file_records_a_binary_patch() {
+        let workspace = unique_temp_dir("shadow-binary");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let target = workspace.join("logo.png");
+        let png = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00];
+        fs::write(&target, png).expect("write original");
+        assert!(is_binary(&png));
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let entry = shadow_fs
+            .stage_file(&target, "placeholder\n")
+            .expect("stage");
+        let patch = entry.patch.as_ref().expect("patch");
+        assert_eq!(patch.operation, PatchOperation::Binary);
+        assert!(patch.hunks.is_empty());
+        assert_eq!((patch.additions, patch.deletions), (12, png.len()));
+        assert_eq!(
+            generate_unified_diff_string(patch),
+            "Binary files a/logo.png and b/logo.png differ\n"
+        );
+
+        shadow_fs.approve(&entry.id).expect("approve");
+        assert!(shadow_fs.apply(&entry.id, false).expect("apply").success);
+        assert_eq!(fs::read_to_string(&target).expect("read"), "placeholder\n");
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    fn stage_conflicts(shadow_fs: &mut ShadowFs, workspace: &Path, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| {
+                let target = workspace.join(format!("conflict-{i}.txt"));
+                fs::write(&target, "base\n").expect("write original");
+                let entry = shadow_fs.stage_file(&target, "agent\n").expect("stage");
+                shadow_fs.approve(&entry.id).expect("approve");
+                fs::write(&target, "edited by user\n").expect("drift original");
+                assert!(matches!(
+                    shadow_fs.apply_with_strategy(&entry.id, false, ConflictStrategy::Skip),
+                    Err(ShadowFsError::Conflict { .. })
+                ));
+                entry.id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reject_all_conflicts_rejects_and_cleans_shadow_files() {
+        let workspace = unique_temp_dir("shadow-reject-conflicts");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let ids = stage_conflicts(&mut shadow_fs, &workspace, 3);
+        let pending = shadow_fs
+            .stage_file(&workspace.join("other.txt"), "pending\n")
+            .expect("stage pending");
+
+        assert_eq!(shadow_fs.reject_all_conflicts(false).expect("reject"), 3);
+        for id in &ids {
+            let entry = shadow_fs.get(id).expect("entry");
+            assert_eq!(entry.status, ShadowStatus::Rejected);
+            assert!(!entry.shadow_path.exists());
+        }
+        assert_eq!(
+            shadow_fs.get(&pending.id).map(|e| e.status),
+            Some(ShadowStatus::Pending)
+        );
+        assert_eq!(shadow_fs.reject_all_conflicts(false).expect("reject"), 0);
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn reject_all_conflicts_can_restage_against_current_content() {
+        let workspace = unique_temp_dir("shadow-restage-conflicts");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        stage_conflicts(&mut shadow_fs, &workspace, 2);
+
+        assert_eq!(shadow_fs.reject_all_conflicts(true).expect("reject"), 2);
+        let pending = shadow_fs.list_pending();
+        assert_eq!(pending.len(), 2);
+        for entry in pending {
+            assert_eq!(
+                entry.original_hash.as_deref(),
+                Some(compute_hash("edited by user\n").as_str())
+            );
+            assert_eq!(
+                fs::read_to_string(&entry.shadow_path).expect("shadow"),
+                "agent\n"
+            );
+        }
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn restage_against_current_turns_a_conflict_back_into_a_pending_change() {
+        let workspace = unique_temp_dir("shadow-restage-current");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let ids = stage_conflicts(&mut shadow_fs, &workspace, 1);
+        let target = workspace.join("conflict-0.txt");
+
+        let entry = shadow_fs
+            .restage_against_current(&ids[0])
+            .expect("restage")
+            .clone();
+        assert_eq!(entry.id, ids[0]);
+        assert_eq!(entry.status, ShadowStatus::Pending);
+        assert_eq!(
+            entry.original_hash.as_deref(),
+            Some(compute_hash("edited by user\n").as_str())
+        );
+        let patch = entry.patch.as_ref().expect("patch");
+        assert_eq!(
+            apply_file_patch("edited by user\n", patch).expect("patch applies"),
+            "agent\n"
+        );
+
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+        assert_eq!(fs::read_to_string(&target).expect("applied"), "agent\n");
+        assert!(matches!(
+            shadow_fs.restage_against_current(&entry.id),
+            Err(ShadowFsError::NotConflicted(_))
+        ));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn resolve_conflict_ours_applies_staged_content_with_backup() {
+        let workspace = unique_temp_dir("shadow-resolve-ours");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let ids = stage_conflicts(&mut shadow_fs, &workspace, 1);
+        let target = workspace.join("conflict-0.txt");
+
+        let entry = shadow_fs
+            .resolve_conflict(&ids[0], ConflictChoice::Ours)
+            .expect("resolve ours")
+            .clone();
+        assert_eq!(entry.status, ShadowStatus::Applied);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "agent\n");
+        let backup = entry.backup_path.expect("backup of the edited file");
+        assert_eq!(fs::read_to_string(backup).unwrap(), "edited by user\n");
+        assert!(matches!(
+            shadow_fs.resolve_conflict(&ids[0], ConflictChoice::Ours),
+            Err(ShadowFsError::NotConflicted(_))
+        ));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn resolve_conflict_theirs_keeps_the_file_on_disk() {
+        let workspace = unique_temp_dir("shadow-resolve-theirs");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        let ids = stage_conflicts(&mut shadow_fs, &workspace, 1);
+        let target = workspace.join("conflict-0.txt");
+
+        let entry = shadow_fs
+            .resolve_conflict(&ids[0], ConflictChoice::Theirs)
+            .expect("resolve theirs")
+            .clone();
+        assert_eq!(entry.status, ShadowStatus::Rejected);
+        assert!(!entry.shadow_path.exists());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "edited by user\n");
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn size_cleanup_drops_oldest_shadow_data_and_quota_blocks_staging() {
+        let workspace = unique_temp_dir("shadow-size-cleanup");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        shadow_fs.set_retain_rejected(true);
+
+        let large = "x".repeat(4096);
+        let oldest = shadow_fs
+            .stage_file(&workspace.join("oldest.txt"), &large)
+            .expect("stage oldest");
+        shadow_fs.reject(&oldest.id).expect("reject oldest");
+        let newer = shadow_fs
+            .stage_file(&workspace.join("newer.txt"), &large)
+            .expect("stage newer");
+        shadow_fs.reject(&newer.id).expect("reject newer");
+        let pending = shadow_fs
+            .stage_file(&workspace.join("pending.txt"), &large)
+            .expect("stage pending");
+
+        let size = shadow_fs.store_size().expect("size");
+        assert_eq!(shadow_fs.cleanup_by_size(size).expect("cleanup"), 0);
+        assert_eq!(shadow_fs.cleanup_by_size(size - 1).expect("cleanup"), 1);
+        assert!(shadow_fs.get(&oldest.id).is_none());
+        assert!(shadow_fs.get(&newer.id).is_some());
+        assert!(pending.shadow_path.exists());
+
+        shadow_fs.set_quota(Some(1024));
+        assert!(shadow_fs.quota_exceeded().expect("quota"));
+        assert!(matches!(
+            shadow_fs.stage_file(&workspace.join("more.txt"), &large),
+            Err(ShadowFsError::QuotaExceeded { limit: 1024, .. })
+        ));
+        shadow_fs.set_quota(None);
+        shadow_fs
+            .stage_file(&workspace.join("more.txt"), &large)
+            .expect("stage without quota");
+
+        // An applied entry's backup goes with it.
+        fs::write(workspace.join("applied.txt"), &large).expect("write original");
+        let applied = shadow_fs
+            .stage_file(&workspace.join("applied.txt"), "applied\n")
+            .expect("stage applied");
+        shadow_fs.approve(&applied.id).expect("approve");
+        let backup = shadow_fs
+            .apply(&applied.id, true)
+            .expect("apply")
+            .backup_path
+            .expect("backup");
+        assert!(Path::new(&backup).is_file());
+        shadow_fs.cleanup_by_size(0).expect("cleanup");
+        assert!(shadow_fs.get(&applied.id).is_none());
+        assert!(!Path::new(&backup).exists());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn rejected_content_is_recoverable_when_retained() {
+        let workspace = unique_temp_dir("shadow-retain-rejected");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::write(workspace.join("notes.txt"), "before\n").expect("write original");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let discarded = shadow_fs
+            .stage_file(&workspace.join("notes.txt"), "discarded\n")
+            .expect("stage");
+        shadow_fs.reject(&discarded.id).expect("reject");
+        assert!(matches!(
+            shadow_fs.restage_rejected(&discarded.id),
+            Err(ShadowFsError::NotRestorable(_))
+        ));
+
+        shadow_fs.set_retain_rejected(true);
+        let entry = shadow_fs
+            .stage_file(&workspace.join("notes.txt"), "after\n")
+            .expect("stage");
+        shadow_fs.reject(&entry.id).expect("reject");
+        let rejected = shadow_fs.get(&entry.id).expect("entry").clone();
+        assert_eq!(rejected.status, ShadowStatus::Rejected);
+        assert!(!rejected.shadow_path.exists());
+        let trashed_path = rejected.trashed_path.expect("trashed path");
+        assert!(trashed_path.starts_with(&shadow_fs.trash_root));
+
+        let restaged = shadow_fs.restage_rejected(&entry.id).expect("restage");
+        assert_eq!(restaged.status, ShadowStatus::Pending);
+        assert_eq!(restaged.trashed_path, None);
+        assert_eq!(
+            fs::read_to_string(&restaged.shadow_path).expect("shadow"),
+            "after\n"
+        );
+        assert!(!trashed_path.exists());
+        shadow_fs.approve(&entry.id).expect("approve");
+        assert!(shadow_fs.apply(&entry.id, false).expect("apply").success);
+        assert_eq!(
+            fs::read_to_string(workspace.join("notes.txt")).expect("read"),
+            "after\n"
+        );
+
+        // Once trash cleanup removes the copy the entry can't come back.
+        let other = shadow_fs
+            .stage_file(&workspace.join("other.txt"), "other\n")
+            .expect("stage");
+        shadow_fs.reject(&other.id).expect("reject");
+        let trashed_path = shadow_fs
+            .get(&other.id)
+            .unwrap()
+            .trashed_path
+            .clone()
+            .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&trashed_path)
+            .and_then(|file| {
+                file.set_modified(SystemTime::now() - std::time::Duration::from_secs(3 * 3600))
+            })
+            .expect("age trashed copy");
+        assert_eq!(shadow_fs.cleanup_trash(1).expect("cleanup trash"), 1);
+        assert!(matches!(
+            shadow_fs.restage_rejected(&other.id),
+            Err(ShadowFsError::NotRestorable(_))
+        ));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn apply_merges_a_changed_original_instead_of_aborting() {
+        let workspace = unique_temp_dir("shadow-merge");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let target = workspace.join("merge.txt");
+        fs::write(&target, "a\nb\nc\nd\ne\n").expect("write original");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let entry = shadow_fs
+            .stage_file(&target, "a\nb\nc\nd\nagent\n")
+            .expect("stage");
+        shadow_fs.approve(&entry.id).expect("approve");
+        fs::write(&target, "user\nb\nc\nd\ne\n").expect("edit original");
+        let result = shadow_fs.apply(&entry.id, false).expect("apply merges");
+        assert!(result.success);
+        assert_eq!(result.merge_conflicts, 0);
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "user\nb\nc\nd\nagent\n"
+        );
+
+        let entry = shadow_fs
+            .stage_file(&target, "user\nb\nc\nd\nagent again\n")
+            .expect("restage");
+        shadow_fs.approve(&entry.id).expect("approve");
+        fs::write(&target, "user\nb\nc\nd\nuser again\n").expect("edit original");
+        let result = shadow_fs
+            .apply(&entry.id, false)
+            .expect("apply with markers");
+        assert_eq!(result.merge_conflicts, 1);
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "user\nb\nc\nd\n<<<<<<< ours\nuser again\n=======\nagent again\n>>>>>>> theirs\n"
+        );
+        assert_eq!(
+            shadow_fs.get(&entry.id).map(|e| e.status),
+            Some(ShadowStatus::Applied)
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn apply_batch_rolls_back_written_entries_when_one_fails() {
+        let workspace = unique_temp_dir("shadow-apply-batch");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::write(workspace.join("modified.txt"), "before\n").expect("write original");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let modified = shadow_fs
+            .stage_file(&workspace.join("modified.txt"), "after\n")
+            .expect("stage modified");
+        let created = shadow_fs
+            .stage_file(&workspace.join("created.txt"), "new\n")
+            .expect("stage created");
+        let broken = shadow_fs
+            .stage_file(&workspace.join("broken.txt"), "broken\n")
+            .expect("stage broken");
+        let ids = vec![modified.id.clone(), created.id.clone(), broken.id.clone()];
+        for id in &ids {
+            shadow_fs.approve(id).expect("approve");
+        }
+        let broken_content = fs::read(&broken.shadow_path).expect("read shadow");
+        fs::remove_file(&broken.shadow_path).expect("drop shadow copy");
+
+        let result = shadow_fs.apply_batch(&ids, false);
+        assert!(!result.success);
+        assert_eq!(result.failed_id.as_deref(), Some(broken.id.as_str()));
+        assert!(result.applied.is_empty());
+        assert_eq!(
+            result.rolled_back,
+            [modified.id.clone(), created.id.clone()]
+        );
+        assert_eq!(
+            fs::read_to_string(workspace.join("modified.txt")).unwrap(),
+            "before\n"
+        );
+        assert!(!workspace.join("created.txt").exists());
+        assert!(!workspace.join("modified.bak").exists());
+        for id in &ids[..2] {
+            let entry = shadow_fs.get(id).expect("entry");
+            assert_eq!(entry.status, ShadowStatus::Approved);
+            assert!(entry.shadow_path.exists());
+        }
+
+        fs::write(&broken.shadow_path, broken_content).expect("restore shadow copy");
+        let result = shadow_fs.apply_batch(&ids, false);
+        assert!(result.success);
+        assert_eq!(result.applied, ids);
+        assert_eq!(
+            fs::read_to_string(workspace.join("modified.txt")).unwrap(),
+            "after\n"
+        );
+        assert!(!workspace.join("modified.bak").exists());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn preview_apply_reports_targets_without_touching_disk() {
+        let workspace = unique_temp_dir("shadow-preview-apply");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let old_path = workspace.join("old.txt");
+        let new_path = workspace.join("new.txt");
+        let doomed = workspace.join("doomed.txt");
+        let edited = workspace.join("edited.txt");
+        fs::write(&old_path, "keep\n").expect("write rename source");
+        fs::write(&doomed, "doomed\n").expect("write delete source");
+        fs::write(&edited, "one\ntwo\nthree\n").expect("write modify source");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let mut rename = compute_unified_diff("keep\n", "kept\n", "old.txt", 3);
+        rename.operation = PatchOperation::Rename;
+        rename.new_file_path = Some(new_path.to_string_lossy().to_string());
+        let renamed = shadow_fs
+            .stage_file_with_patch(&old_path, "kept\n", Some(rename))
+            .expect("stage rename");
+        let mut delete = compute_unified_diff("doomed\n", "", "doomed.txt", 3);
+        delete.operation = PatchOperation::Delete;
+        let deleted = shadow_fs
+            .stage_file_with_patch(&doomed, "", Some(delete))
+            .expect("stage delete");
+        let modified = shadow_fs
+            .stage_file(&edited, "ONE\ntwo\nthree\n")
+            .expect("stage modify");
+        fs::write(&edited, "one\ntwo\nthree!\n").expect("edit meanwhile");
+
+        let preview = shadow_fs
+            .preview_apply(&renamed.id)
+            .expect("preview rename");
+        assert_eq!(preview.operation, PatchOperation::Rename);
+        assert_eq!(preview.target_path, new_path.to_string_lossy());
+        assert_eq!(preview.content_length, Some("kept\n".len()));
+        assert!(!preview.conflict);
+
+        let preview = shadow_fs
+            .preview_apply(&deleted.id)
+            .expect("preview delete");
+        assert_eq!(preview.operation, PatchOperation::Delete);
+        assert_eq!(preview.content_length, None);
+
+        let preview = shadow_fs
+            .preview_apply(&modified.id)
+            .expect("preview modify");
+        assert!(preview.conflict);
+        assert_eq!(preview.content_length, Some("ONE\ntwo\nthree!\n".len()));
+
+        assert_eq!(fs::read_to_string(&old_path).unwrap(), "keep\n");
+        assert!(!new_path.exists());
+        assert_eq!(fs::read_to_string(&doomed).unwrap(), "doomed\n");
+        assert_eq!(fs::read_to_string(&edited).unwrap(), "one\ntwo\nthree!\n");
+        for id in [&renamed.id, &deleted.id, &modified.id] {
+            let entry = shadow_fs.get(id).unwrap();
+            assert_eq!(entry.status, ShadowStatus::Pending);
+            assert!(entry.shadow_path.exists());
+        }
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_does_not_write_through_symlinks_unless_following() {
+        let workspace = unique_temp_dir("shadow-symlink-workspace");
+        let outside = unique_temp_dir("shadow-symlink-outside");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::create_dir_all(&outside).expect("create outside dir");
+        let target = outside.join("secret.txt");
+        let link = workspace.join("link.txt");
+        fs::write(&target, "outside\n").expect("write target");
+        std::os::unix::fs::symlink(&target, &link).expect("create symlink");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let entry = shadow_fs.stage_file(&link, "agent\n").expect("stage");
+        shadow_fs.approve(&entry.id).expect("approve");
+        assert!(matches!(
+            shadow_fs.apply(&entry.id, false),
+            Err(ShadowFsError::SymlinkTarget(_))
+        ));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "outside\n");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        shadow_fs.set_symlink_policy(SymlinkPolicy::ReplaceLink);
+        shadow_fs
+            .apply(&entry.id, false)
+            .expect("apply replacing link");
+        assert_eq!(fs::read_to_string(&target).unwrap(), "outside\n");
+        assert!(fs::symlink_metadata(&link).unwrap().is_file());
+        assert_eq!(fs::read_to_string(&link).unwrap(), "agent\n");
+
+        let _ = fs::remove_dir_all(&workspace);
+        let _ = fs::remove_dir_all(&outside);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_does_not_write_through_symlinked_directories() {
+        let workspace = unique_temp_dir("shadow-symlink-dir-workspace");
+        let outside = unique_temp_dir("shadow-symlink-dir-outside");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::create_dir_all(&outside).expect("create outside dir");
+        std::os::unix::fs::symlink(&outside, workspace.join("linked")).expect("link dir");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let through = workspace.join("linked").join("new.txt");
+        let entry = shadow_fs.stage_file(&through, "agent\n").expect("stage");
+        shadow_fs.approve(&entry.id).expect("approve");
+        assert!(matches!(
+            shadow_fs.apply(&entry.id, false),
+            Err(ShadowFsError::OutsideWorkspace(_))
+        ));
+        assert!(!outside.join("new.txt").exists());
+
+        let source = workspace.join("local.txt");
+        fs::write(&source, "local\n").expect("write source");
+        let mut rename = compute_unified_diff("local\n", "local\n", "local.txt", 3);
+        rename.operation = PatchOperation::Rename;
+        rename.new_file_path = Some(
+            workspace
+                .join("linked/nested/moved.txt")
+                .to_string_lossy()
+                .to_string(),
+        );
+        let renamed = shadow_fs
+            .stage_file_with_patch(&source, "local\n", Some(rename))
+            .expect("stage rename");
+        shadow_fs.approve(&renamed.id).expect("approve rename");
+        assert!(matches!(
+            shadow_fs.apply(&renamed.id, false),
+            Err(ShadowFsError::OutsideWorkspace(_))
+        ));
+        assert!(source.exists());
+        assert!(!outside.join("nested").exists());
+
+        shadow_fs.set_symlink_policy(SymlinkPolicy::Follow);
+        shadow_fs
+            .apply(&entry.id, false)
+            .expect("following is opted into");
+        assert_eq!(
+            fs::read_to_string(outside.join("new.txt")).unwrap(),
+            "agent\n"
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+        let _ = fs::remove_dir_all(&outside);
+    }
+
+    #[test]
+    fn rollback_undoes_applied_renames_and_deletes() {
+        let workspace = unique_temp_dir("shadow-rollback-rename-delete");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let old_path = workspace.join("old.txt");
+        let new_path = workspace.join("new.txt");
+        let doomed = workspace.join("doomed.txt");
+        fs::write(&old_path, "keep\n").expect("write rename source");
+        fs::write(&doomed, "doomed\n").expect("write delete source");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let mut rename = compute_unified_diff("keep\n", "keep\n", "old.txt", 3);
+        rename.operation = PatchOperation::Rename;
+        rename.new_file_path = Some(new_path.to_string_lossy().to_string());
+        let renamed = shadow_fs
+            .stage_file_with_patch(&old_path, "keep\n", Some(rename))
+            .expect("stage rename");
+        let mut delete = compute_unified_diff("doomed\n", "", "doomed.txt", 3);
+        delete.operation = PatchOperation::Delete;
+        let deleted = shadow_fs
+            .stage_file_with_patch(&doomed, "", Some(delete))
+            .expect("stage delete");
+        for id in [&renamed.id, &deleted.id] {
+            shadow_fs.approve(id).expect("approve");
+            shadow_fs.apply(id, true).expect("apply");
+        }
+        assert!(new_path.exists() && !old_path.exists() && !doomed.exists());
+        assert_eq!(
+            shadow_fs
+                .get(&renamed.id)
+                .and_then(|e| e.renamed_from.clone()),
+            Some(old_path.clone())
+        );
+
+        shadow_fs.rollback(&renamed.id).expect("rollback rename");
+        assert!(!new_path.exists());
+        assert_eq!(fs::read_to_string(&old_path).unwrap(), "keep\n");
+        assert_eq!(shadow_fs.get(&renamed.id).unwrap().original_path, old_path);
+
+        shadow_fs.rollback(&deleted.id).expect("rollback delete");
+        assert_eq!(fs::read_to_string(&doomed).unwrap(), "doomed\n");
+        assert!(matches!(
+            shadow_fs.restore_from_trash(&deleted.id),
+            Err(ShadowFsError::NotInTrash(_))
+        ));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn rename_onto_the_destination_of_an_earlier_pending_entry_is_refused() {
+        let workspace = unique_temp_dir("shadow-rename-target-pending");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let destination = workspace.join("dest.txt");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let mut ids = Vec::new();
+        for name in ["first.txt", "second.txt"] {
+            let source = workspace.join(name);
+            fs::write(&source, format!("{name}\n")).expect("write source");
+            let mut rename = compute_unified_diff("", "", name, 3);
+            rename.operation = PatchOperation::Rename;
+            rename.new_file_path = Some(destination.to_string_lossy().to_string());
+            let entry = shadow_fs
+                .stage_file_with_patch(&source, &format!("{name}\n"), Some(rename))
+                .expect("stage rename");
+            shadow_fs.approve(&entry.id).expect("approve");
+            ids.push(entry.id);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        match shadow_fs.apply(&ids[1], false) {
+            Err(ShadowFsError::TargetPending { id, .. }) => assert_eq!(id, ids[0]),
+            other => panic!("expected the later rename to be refused, got {:?}", other),
+        }
+        assert!(!destination.exists());
+        assert!(workspace.join("second.txt").exists());
+
+        shadow_fs.apply(&ids[0], false).expect("apply first rename");
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "first.txt\n");
+        assert!(matches!(
+            shadow_fs.apply(&ids[1], false),
+            Err(ShadowFsError::TargetExists(_))
+        ));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn apply_all_approved_continues_past_failures_and_backs_up_conflicts() {
+        let workspace = unique_temp_dir("shadow-apply-all");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::write(workspace.join("clean.txt"), "clean before\n").expect("write clean");
+        fs::write(workspace.join("edited.txt"), "edited before\n").expect("write edited");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let clean = shadow_fs
+            .stage_file(&workspace.join("clean.txt"), "clean after\n")
+            .expect("stage clean");
+        let edited = shadow_fs
+            .stage_file(&workspace.join("edited.txt"), "agent edit\n")
+            .expect("stage edited");
+        let unapproved = shadow_fs
+            .stage_file(&workspace.join("pending.txt"), "pending\n")
+            .expect("stage pending");
+        let broken = shadow_fs
+            .stage_file(&workspace.join("broken.txt"), "broken\n")
+            .expect("stage broken");
+        for id in [&clean.id, &edited.id, &broken.id] {
+            shadow_fs.approve(id).expect("approve");
+        }
+        fs::write(workspace.join("edited.txt"), "user edit\n").expect("edit original");
+        fs::remove_file(&broken.shadow_path).expect("drop shadow copy");
+
+        let summary = shadow_fs.apply_all_approved(ConflictStrategy::Backup);
+        assert_eq!(summary.applied, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.results.len(), 3);
+        let result = |id: &str| {
+            &summary
+                .results
+                .iter()
+                .find(|entry| entry.id == id)
+                .expect("result for entry")
+                .result
+        };
+
+        assert!(result(&clean.id).success);
+        assert_eq!(result(&clean.id).backup_path, None);
+        assert_eq!(
+            fs::read_to_string(workspace.join("clean.txt")).unwrap(),
+            "clean after\n"
+        );
+
+        let edited_result = result(&edited.id);
+        assert!(edited_result.success);
+        let backup = edited_result
+            .backup_path
+            .as_ref()
+            .expect("conflict backed up");
+        assert_eq!(fs::read_to_string(backup).unwrap(), "user edit\n");
+        assert_eq!(
+            fs::read_to_string(workspace.join("edited.txt")).unwrap(),
+            "agent edit\n"
+        );
+
+        assert!(!result(&broken.id).success);
+        assert!(result(&broken.id).error.is_some());
+        assert_eq!(
+            shadow_fs.get(&unapproved.id).map(|e| e.status),
+            Some(ShadowStatus::Pending)
+        );
+
+        // Under the default strategy a conflict is left for review instead.
+        fs::write(workspace.join("clean.txt"), "changed again\n").expect("edit clean");
+        let again = shadow_fs
+            .stage_file(&workspace.join("clean.txt"), "second pass\n")
+            .expect("restage");
+        shadow_fs.approve(&again.id).expect("approve");
+        fs::write(workspace.join("clean.txt"), "user wins\n").expect("edit clean");
+        let summary = shadow_fs.apply_all_approved(ConflictStrategy::Skip);
+        assert_eq!((summary.applied, summary.failed), (0, 2));
+        assert_eq!(
+            shadow_fs.get(&again.id).map(|e| e.status),
+            Some(ShadowStatus::Conflict)
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn parallel_and_sequential_staging_match() {
+        let workspace = unique_temp_dir("shadow-parallel");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let files: Vec<(PathBuf, Vec<u8>)> = (0..16)
+            .map(|i| {
+                let path = workspace.join(format!("file-{i}.txt"));
+                if i % 3 != 0 {
+                    fs::write(&path, format!("line a\nline {i}\nline c\n")).expect("write");
+                }
+                (
+                    path,
+                    format!("line a\nline {} changed\nline c\n", i).into_bytes(),
+                )
+            })
+            .collect();
+
+        let summarize = |prepared: Vec<PreparedStage>| {
+            prepared
+                .into_iter()
+                .map(|p| {
+                    let patch = p.patch.expect("computed patch");
+                    (
+                        p.original_path,
+                        p.original_exists,
+                        p.original_hash,
+                        p.shadow_hash,
+                        patch.additions,
+                        patch.deletions,
+                        patch
+                            .hunks
+                            .into_iter()
+                            .map(|h| h.content)
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let sequential =
+            summarize(prepare_stages(&workspace, files.clone(), 1).expect("sequential"));
+        let parallel = summarize(prepare_stages(&workspace, files, 4).expect("parallel"));
+        assert_eq!(sequential, parallel);
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn load_marks_entries_with_missing_shadow_files_orphaned() {
+        let workspace = unique_temp_dir("shadow-orphaned");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::write(workspace.join("kept.txt"), "kept\n").expect("write kept");
+        fs::write(workspace.join("lost.txt"), "lost\n").expect("write lost");
+
+        let (kept_id, lost_id) = {
+            let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+            let kept = shadow_fs
+                .stage_file(&workspace.join("kept.txt"), "kept v2\n")
+                .expect("stage kept");
+            let lost = shadow_fs
+                .stage_file(&workspace.join("lost.txt"), "lost v2\n")
+                .expect("stage lost");
+            shadow_fs.save_index().expect("save index");
+            fs::remove_file(&lost.shadow_path).expect("remove shadow file");
+            (kept.id, lost.id)
+        };
+
+        let shadow_fs = ShadowFs::new(workspace.clone()).expect("reload shadow fs");
+        let report = shadow_fs.load_report();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.orphaned, vec![lost_id.clone()]);
+        assert!(report.quarantined_index.is_none());
+        assert_eq!(
+            shadow_fs.get(&lost_id).unwrap().status,
+            ShadowStatus::Orphaned
+        );
+        assert_eq!(
+            shadow_fs.get(&kept_id).unwrap().status,
+            ShadowStatus::Pending
+        );
+
+        // The repair is persisted, so the next load finds nothing new.
+        let reloaded = ShadowFs::new(workspace.clone()).expect("reload again");
+        assert!(reloaded.load_report().orphaned.is_empty());
+        assert_eq!(
+            reloaded.get(&lost_id).unwrap().status,
+            ShadowStatus::Orphaned
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn unreadable_index_is_quarantined_on_load() {
+        let workspace = unique_temp_dir("shadow-corrupt-index");
+        let shadow_root = workspace.join(".coworkany").join("shadow");
+        fs::create_dir_all(&shadow_root).expect("create shadow root");
+        fs::write(shadow_root.join("index.json"), "{ not json").expect("write index");
+
+        let shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let quarantined = shadow_fs
+            .load_report()
+            .quarantined_index
+            .clone()
+            .expect("quarantined index");
+        assert_eq!(
+            fs::read_to_string(&quarantined).expect("read quarantined"),
+            "{ not json"
+        );
+        let index = fs::read_to_string(shadow_root.join("index.json")).expect("read index");
+        assert_eq!(index.trim(), "{}", "the recovered index replaces it");
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn truncated_index_is_rebuilt_from_shadow_files() {
+        let workspace = unique_temp_dir("shadow-recover-index");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        fs::write(workspace.join("a.txt"), "a\n").expect("write a");
+        fs::write(workspace.join("b.txt"), "b\n").expect("write b");
+        fs::write(workspace.join("c.txt"), "c\n").expect("write c");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let first = shadow_fs
+            .stage_file(&workspace.join("a.txt"), "a2\n")
+            .expect("stage a");
+        let second = shadow_fs
+            .stage_file(&workspace.join("b.txt"), "b2\n")
+            .expect("stage b");
+        shadow_fs.approve(&second.id).expect("approve b");
+        let lost = shadow_fs
+            .stage_file(&workspace.join("c.txt"), "c2\n")
+            .expect("stage c");
+        drop(shadow_fs);
+        // An original that can no longer be diffed against
+        fs::remove_file(workspace.join("c.txt")).expect("remove c");
+        fs::create_dir(workspace.join("c.txt")).expect("replace c with a dir");
+
+        // As if the app died halfway through writing the index
+        let index_path = workspace.join(".coworkany/shadow/index.json");
+        let index = fs::read_to_string(&index_path).expect("read index");
+        let cut = index.rfind("\"shadow_path\"").expect("second entry");
+        fs::write(&index_path, &index[..cut]).expect("truncate index");
+
+        let shadow_fs = ShadowFs::new(workspace.clone()).expect("recover");
+        let report = shadow_fs.load_report();
+        assert!(report.quarantined_index.is_some());
+        let mut expected = vec![first.id.clone(), second.id.clone()];
+        expected.sort();
+        assert_eq!(report.recovered, expected, "c.txt is skipped, not fatal");
+        assert!(shadow_fs.get(&lost.id).is_none());
+        for staged in [&first, &second] {
+            let entry = shadow_fs.get(&staged.id).expect("recovered entry");
+            assert_eq!(entry.status, ShadowStatus::Pending);
+            assert_eq!(entry.original_path, staged.original_path);
+            assert_eq!(entry.patch.as_ref().unwrap().additions, 1);
+        }
+        let reloaded = ShadowFs::new(workspace.clone()).expect("reload");
+        assert_eq!(reloaded.list_pending().len(), 2);
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn purge_requires_confirmation_then_clears_everything() {
+        let workspace = unique_temp_dir("shadow-purge");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let applied_path = workspace.join("applied.txt");
+        let pending_path = workspace.join("pending.txt");
+        let deleted_path = workspace.join("deleted.txt");
+        let user_backup = workspace.join("notes.bak");
+        fs::write(&applied_path, "v1\n").expect("write applied");
+        fs::write(&pending_path, "v1\n").expect("write pending");
+        fs::write(&deleted_path, "bye\n").expect("write deleted");
+        fs::write(&user_backup, "mine\n").expect("write user backup");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let applied = shadow_fs
+            .stage_file(&applied_path, "v2\n")
+            .expect("stage applied");
+        shadow_fs.approve(&applied.id).expect("approve");
+        shadow_fs
+            .apply(&applied.id, true)
+            .expect("apply with backup");
+        shadow_fs
+            .stage_file(&pending_path, "v2\n")
+            .expect("stage pending");
+        let deleted = shadow_fs
+            .stage_file_with_patch(
+                &deleted_path,
+                "",
+                Some(crate::diff::FilePatch {
+                    operation: PatchOperation::Delete,
+                    ..compute_unified_diff("bye\n", "", "deleted.txt", 3)
+                }),
+            )
+            .expect("stage delete");
+        shadow_fs.approve(&deleted.id).expect("approve delete");
+        shadow_fs.apply(&deleted.id, false).expect("apply delete");
+
+        let stray = shadow_fs.shadow_root.join("in-flight.tmp");
+        fs::write(&stray, "partial").expect("write stray temp file");
+
+        assert!(matches!(
+            shadow_fs.purge(false),
+            Err(ShadowFsError::PurgeNotConfirmed)
+        ));
+        assert_eq!(
+            shadow_fs.files.len(),
+            3,
+            "refused purge leaves state intact"
+        );
+
+        let report = shadow_fs.purge(true).expect("purge");
+        assert_eq!(report.index_entries, 3);
+        assert_eq!(report.shadow_files, 1);
+        assert_eq!(report.backups, 1);
+        assert_eq!(report.trash_files, 1);
+
+        assert!(shadow_fs.files.is_empty());
+        assert!(!shadow_fs.backup_path_for(&applied.id).exists());
+        assert!(user_backup.exists(), "unrelated .bak files are kept");
+        assert!(stray.exists(), "files the index doesn't own are kept");
+        assert_eq!(fs::read_dir(&shadow_fs.trash_root).unwrap().count(), 0);
+        let reloaded = ShadowFs::new(workspace.clone()).expect("reload");
+        assert!(reloaded.files.is_empty());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn apply_records_backup_path_in_history() {
+        let workspace = unique_temp_dir("shadow-apply-history");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let first = workspace.join("first.txt");
+        let second = workspace.join("second.txt");
+        fs::write(&first, "v1\n").expect("write first");
+        fs::write(&second, "v1\n").expect("write second");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let backed_up = shadow_fs.stage_file(&first, "v2\n").expect("stage first");
+        shadow_fs.approve(&backed_up.id).expect("approve first");
+        let result = shadow_fs.apply(&backed_up.id, true).expect("apply first");
+        let plain = shadow_fs.stage_file(&second, "v2\n").expect("stage second");
+        shadow_fs.approve(&plain.id).expect("approve second");
+        shadow_fs.apply(&plain.id, false).expect("apply second");
+
+        let backup = shadow_fs.backup_path_for(&backed_up.id);
+        assert!(!first.with_extension("bak").exists());
+        let entry = shadow_fs.get(&backed_up.id).expect("entry");
+        assert_eq!(entry.backup_path.as_ref(), Some(&backup));
+        assert_eq!(
+            result.backup_path,
+            Some(backup.to_string_lossy().to_string())
+        );
+        assert!(entry.applied_at.is_some());
+
+        let history = shadow_fs.apply_history(None);
+        assert_eq!(history.len(), 2);
+        let for_first = shadow_fs.apply_history(Some(Path::new("first.txt")));
+        assert_eq!(for_first.len(), 1);
+        assert_eq!(for_first[0].backup_path.as_ref(), Some(&backup));
+
+        let reloaded = ShadowFs::new(workspace.clone()).expect("reload");
+        assert_eq!(
+            reloaded
+                .get(&backed_up.id)
+                .and_then(|e| e.backup_path.clone()),
+            Some(backup)
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn patch_paths_resolve_inside_the_workspace_only() {
+        let root = Path::new("/work/repo");
+        assert_eq!(
+            resolve_in_root(root, "src/lib.rs").unwrap(),
+            root.join("src/lib.rs")
+        );
+        assert_eq!(
+            resolve_in_root(root, "./src/../README.md").unwrap(),
+            root.join("README.md")
+        );
+        assert_eq!(
+            resolve_in_root(root, "/work/repo/a.txt").unwrap(),
+            root.join("a.txt")
+        );
+        for escaping in ["../other/a.txt", "src/../../a.txt", "/etc/passwd", "."] {
+            assert!(matches!(
+                resolve_in_root(root, escaping),
+                Err(ShadowFsError::OutsideWorkspace(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn large_apply_reports_each_hunk_and_writes_the_staged_content() {
+        let workspace = unique_temp_dir("shadow-hunk-progress");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let path = workspace.join("big.txt");
+        let original: String = (0..400).map(|i| format!("line {i}\n")).collect();
+        let staged: String = (0..400)
+            .map(|i| match i % 20 {
+                0 => format!("changed {i}\n"),
+                _ => format!("line {i}\n"),
+            })
+            .collect();
+        fs::write(&path, &original).expect("write original");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        shadow_fs.set_hunk_progress(Some(Arc::new(move |_, progress| {
+            sink.lock().unwrap().push(progress.applied);
+        })));
+        let entry = shadow_fs.stage_file(&path, &staged).expect("stage");
+        let hunks = entry.patch.as_ref().unwrap().hunks.len();
+        assert!(hunks >= HUNK_PROGRESS_MIN_HUNKS);
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+
+        assert_eq!(*seen.lock().unwrap(), (1..=hunks).collect::<Vec<_>>());
+        assert_eq!(fs::read_to_string(&path).unwrap(), staged);
+        let leftovers: Vec<_> = fs::read_dir(&workspace)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_keeps_the_mode_of_the_file_it_replaces() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let workspace = unique_temp_dir("shadow-apply-mode");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let path = workspace.join("run.sh");
+        fs::write(&path, "#!/bin/sh\necho old\n").expect("write original");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let entry = shadow_fs
+            .stage_file(&path, "#!/bin/sh\necho new\n")
+            .expect("stage");
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "#!/bin/sh\necho new\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn non_utf8_bytes_stage_and_apply_unchanged() {
+        let workspace = unique_temp_dir("shadow-bytes");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let path = workspace.join("latin1.txt");
+        fs::write(&path, b"caf\xe9\n").expect("write original");
+        let bytes: &[u8] = b"na\xefve caf\xe9\n\xff";
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let entry = shadow_fs
+            .stage_file_bytes(&path, bytes)
+            .expect("stage bytes");
+        assert_eq!(fs::read(&entry.shadow_path).unwrap(), bytes);
+        assert_eq!(entry.shadow_hash, compute_hash(bytes));
+        assert_eq!(
+            entry.patch.as_ref().map(|p| p.operation),
+            Some(PatchOperation::Binary)
+        );
+
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn backups_of_same_stem_files_do_not_collide() {
+        let workspace = unique_temp_dir("shadow-backup-collide");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let rust = workspace.join("lib.rs");
+        let ts = workspace.join("lib.ts");
+        let user_backup = workspace.join("lib.bak");
+        fs::write(&rust, "rust v1\n").expect("write lib.rs");
+        fs::write(&ts, "ts v1\n").expect("write lib.ts");
+        fs::write(&user_backup, "mine\n").expect("write lib.bak");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let mut ids = Vec::new();
+        for (path, content) in [(&rust, "rust v2\n"), (&ts, "ts v2\n")] {
+            let entry = shadow_fs.stage_file(path, content).expect("stage");
+            shadow_fs.approve(&entry.id).expect("approve");
+            shadow_fs.apply(&entry.id, true).expect("apply");
+            ids.push(entry.id);
+        }
+        assert_eq!(fs::read_to_string(&user_backup).unwrap(), "mine\n");
+
+        for id in &ids {
+            shadow_fs.rollback(id).expect("rollback");
+        }
+        assert_eq!(fs::read_to_string(&rust).unwrap(), "rust v1\n");
+        assert_eq!(fs::read_to_string(&ts).unwrap(), "ts v1\n");
+        assert_eq!(fs::read_to_string(&user_backup).unwrap(), "mine\n");
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn preview_matches_content_written_by_apply() {
+        let workspace = unique_temp_dir("shadow-preview");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let modified = workspace.join("modified.txt");
+        let doomed = workspace.join("doomed.txt");
+        fs::write(&modified, "v1\r\nkeep\r\n").expect("write modified");
+        fs::write(&doomed, "bye\n").expect("write doomed");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let entry = shadow_fs
+            .stage_file(&modified, "v2\r\nkeep\r\n")
+            .expect("stage modify");
+        let preview = shadow_fs
+            .preview_applied_content(&entry.id)
+            .expect("preview");
+        assert!(!preview.conflict);
+        assert_eq!(fs::read_to_string(&modified).unwrap(), "v1\r\nkeep\r\n");
+
+        shadow_fs.approve(&entry.id).expect("approve");
+        let result = shadow_fs.apply(&entry.id, false).expect("apply");
+        assert_eq!(preview.target_path, result.file_path);
+        assert_eq!(
+            preview.content.as_deref(),
+            Some(fs::read_to_string(&modified).unwrap().as_str())
+        );
+
+        let deletion = shadow_fs
+            .stage_file_with_patch(
+                &doomed,
+                "",
+                Some(crate::diff::FilePatch {
+                    operation: PatchOperation::Delete,
+                    ..compute_unified_diff("bye\n", "", "doomed.txt", 3)
+                }),
+            )
+            .expect("stage delete");
+        let preview = shadow_fs
+            .preview_applied_content(&deletion.id)
+            .expect("preview delete");
+        assert_eq!(preview.content, None);
+
+        fs::write(&doomed, "edited meanwhile\n").expect("drift");
+        assert!(
+            shadow_fs
+                .preview_applied_content(&deletion.id)
+                .expect("preview after drift")
+                .conflict
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn preview_merges_like_apply_when_the_original_changed() {
+        let workspace = unique_temp_dir("shadow-preview-merge");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let path = workspace.join("notes.txt");
+        fs::write(&path, "one\ntwo\nthree\nfour\nfive\n").expect("write original");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let entry = shadow_fs
+            .stage_file(&path, "one\ntwo\nthree\nfour\nFIVE\n")
+            .expect("stage");
+        fs::write(&path, "ONE\ntwo\nthree\nfour\nfive\n").expect("edit after staging");
+
+        let preview = shadow_fs
+            .preview_applied_content(&entry.id)
+            .expect("preview merge");
+        assert!(preview.conflict);
+        assert!(preview.applies);
+        assert_eq!(preview.merge_conflicts, 0);
+        assert_eq!(
+            preview.content.as_deref(),
+            Some("ONE\ntwo\nthree\nfour\nFIVE\n")
+        );
+
+        let skipped = shadow_fs
+            .preview_applied_content_with_strategy(&entry.id, ConflictStrategy::Skip)
+            .expect("preview skip");
+        assert!(skipped.conflict);
+        assert!(!skipped.applies);
+
+        shadow_fs.approve(&entry.id).expect("approve");
+        shadow_fs.apply(&entry.id, false).expect("apply");
+        assert_eq!(
+            preview.content.as_deref(),
+            Some(fs::read_to_string(&path).unwrap().as_str())
+        );
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn ui_apply_flags_a_conflict_it_cannot_merge() {
+        let workspace = unique_temp_dir("shadow-ui-conflict");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let doomed = workspace.join("doomed.txt");
+        fs::write(&doomed, "bye\n").expect("write doomed");
+
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("shadow fs");
+        let deletion = shadow_fs
+            .stage_file_with_patch(
+                &doomed,
+                "",
+                Some(crate::diff::FilePatch {
+                    operation: PatchOperation::Delete,
+                    ..compute_unified_diff("bye\n", "", "doomed.txt", 3)
+                }),
+            )
+            .expect("stage delete");
+        shadow_fs.approve(&deletion.id).expect("approve");
+        fs::write(&doomed, "edited meanwhile\n").expect("drift");
+
+        let (result, conflict) = apply_flagging_conflict(&mut shadow_fs, &deletion.id, false);
+        let result = result.expect("a conflict is a result, not an error");
+        assert!(!result.success);
+        assert!(result.conflict);
+        assert_eq!(result.file_path, doomed.to_string_lossy());
+        let conflict = conflict.expect("details for the patch-conflict event");
+        assert_eq!(conflict.patch_id, deletion.id);
+        assert_eq!(
+            Some(&conflict.expected_hash),
+            deletion.original_hash.as_ref()
+        );
+        assert!(doomed.exists(), "nothing is deleted on conflict");
+
+        let (result, conflict) = apply_flagging_conflict(&mut shadow_fs, "missing", false);
+        assert!(result.is_err());
+        assert!(conflict.is_none());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn snapshots_beyond_the_limit_are_dropped_oldest_first() {
+        let workspace = unique_temp_dir("shadow-snapshot-retention");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+        shadow_fs
+            .stage_file(&workspace.join("kept.txt"), "v1\n")
+            .expect("stage");
+
+        let first = shadow_fs.snapshot().expect("first snapshot");
+        let mut taken = vec![first.clone()];
+        for _ in 0..MAX_SNAPSHOTS {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            taken.push(shadow_fs.snapshot().expect("snapshot"));
+        }
+        assert_eq!(shadow_fs.list_snapshots().unwrap(), taken[1..].to_vec());
+        assert!(!shadow_fs.snapshot_dir(&first).exists());
+
+        let newest = taken.last().unwrap();
+        shadow_fs.delete_snapshot(newest).expect("delete");
+        assert!(!shadow_fs.list_snapshots().unwrap().contains(newest));
+        assert!(matches!(
+            shadow_fs.delete_snapshot("../kept"),
+            Err(ShadowFsError::SnapshotNotFound(_))
+        ));
+
+        // Past everything else, a size cleanup also sheds snapshots.
+        shadow_fs.cleanup_by_size(0).expect("cleanup");
+        assert!(shadow_fs.list_snapshots().unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn restoring_a_snapshot_returns_to_its_pending_set() {
+        let workspace = unique_temp_dir("shadow-snapshot");
+        fs::create_dir_all(&workspace).expect("create workspace");
+        let mut shadow_fs = ShadowFs::new(workspace.clone()).expect("init shadow fs");
+
+        let kept = shadow_fs
+            .stage_file(&workspace.join("kept.txt"), "v1\n")
+            .expect("stage kept");
+        let snapshot = shadow_fs.snapshot().expect("snapshot");
+
+        let later = shadow_fs
+            .stage_file(&workspace.join("later.txt"), "later\n")
+            .expect("stage later");
+        fs::write(&kept.shadow_path, "overwritten\n").expect("edit shadow copy");
+        shadow_fs.approve(&kept.id).expect("approve kept");
+
+        shadow_fs.restore_snapshot(&snapshot).expect("restore");
+        let pending: Vec<&str> = shadow_fs
+            .list_pending()
+            .iter()
+            .map(|entry| entry.id.as_str())
+            .collect();
+        assert_eq!(pending, [kept.id.as_str()]);
+        assert_eq!(
+            shadow_fs.get(&kept.id).unwrap().status,
+            ShadowStatus::Pending
+        );
+        assert_eq!(fs::read_to_string(&kept.shadow_path).unwrap(), "v1\n");
+        assert!(!later.shadow_path.exists());
+
+        let reloaded = ShadowFs::new(workspace.clone()).expect("reload");
+        assert!(reloaded.get(&later.id).is_none());
+        assert!(matches!(
+            shadow_fs.restore_snapshot("../missing"),
+            Err(ShadowFsError::SnapshotNotFound(_))
+        ));
+
+        let _ = fs::remove_dir_all(&workspace);
+    }
+}
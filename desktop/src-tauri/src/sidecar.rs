@@ -8,7 +8,7 @@
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader, Read, Write};
@@ -19,7 +19,7 @@ use std::os::unix::net::UnixStream;
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
@@ -31,17 +31,25 @@ use tokio::process::Command as TokioCommand;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::diff::{apply_patch as apply_patch_diff, DiffHunk, FilePatch, PatchOperation};
+use crate::diff::{
+    apply_patch as apply_patch_diff, apply_patch_with_progress, parse_unified_diff, DiffHunk,
+    FilePatch, LineEnding, PatchOperation, DEFAULT_HUNK_FUZZ, HUNK_PROGRESS_MIN_HUNKS,
+};
 use crate::platform_runtime::{
     build_platform_runtime_context, resolve_app_data_dir, resolve_app_dir,
-    resolve_sidecar_entry_path, PlatformRuntimeContext,
+    sidecar_entry_candidates, PlatformRuntimeContext,
 };
 use crate::policy::commands as policy_commands;
 use crate::policy::{
-    EffectContext, EffectPayload, EffectRequest, EffectResponse, EffectScope, EffectSource,
-    EffectType, PolicyEngineState,
+    keychain_secret, redact_secret_values, resolve_secret_env, secret_references,
+    shell_secret_grant_key, EffectContext, EffectPayload, EffectRequest, EffectResponse,
+    EffectScope, EffectSource, EffectType, ExecutionResult, PolicyEngineState, ResolvedEnv,
+    SecretGrant,
+};
+use crate::process_manager::{apply_resource_limits, limit_violation, ResourceLimits};
+use crate::shadow_fs::{
+    self, ApplyResult, ConflictStrategy, PatchConflict, ShadowFs, ShadowFsError, ShadowFsState,
 };
-use crate::shadow_fs::{self, ShadowFsState};
 
 struct PackagedSidecar {
     executable: std::path::PathBuf,
@@ -93,6 +101,13 @@ struct SidecarLlmProfile {
 }
 
 const STDERR_NOISE_LOG_INTERVAL: Duration = Duration::from_secs(30);
+/// How long `shutdown` waits for the sidecar to exit on its own before
+/// killing it
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(25);
+/// Commands that may wait to be written to the sidecar before `send_command`
+/// reports backpressure
+pub const DEFAULT_STDIN_QUEUE_CAPACITY: usize = 256;
 const JSON_LOG_PREVIEW_MAX_CHARS: usize = 2_048;
 const STREAM_DELTA_LOG_PREVIEW_MAX_CHARS: usize = 320;
 const SIDECAR_METRICS_LOG_PREFIX: &str = "[coworkany-metrics]";
@@ -121,8 +136,25 @@ pub enum SidecarError {
     #[error("Failed to send command: {0}")]
     SendError(String),
 
+    /// The sidecar went away while the command was waiting for its reply.
+    #[error("Sidecar disconnected: {0}")]
+    Disconnected(String),
+
+    /// The sidecar isn't keeping up; the caller should retry later.
+    #[error("Sidecar stdin queue is full ({capacity} commands pending)")]
+    Backpressure { capacity: usize },
+
     #[error("Failed to serialize command: {0}")]
     SerializeError(#[from] serde_json::Error),
+
+    /// No development entry exists at any searched path; retrying won't help.
+    #[error("Sidecar not found: no sidecar entry at any of {} searched paths", .searched.len())]
+    EntryMissing { searched: Vec<std::path::PathBuf> },
+
+    /// The sidecar answered `hello` with a protocol version this build can't
+    /// speak; respawning the same sidecar won't help.
+    #[error("Sidecar protocol {sidecar} is incompatible with desktop protocol {desktop}")]
+    Incompatible { desktop: String, sidecar: String },
 }
 
 // ============================================================================
@@ -317,22 +349,552 @@ impl IpcCommand {
     }
 }
 
+/// Poll `child` until it exits or `grace` runs out; whether it exited
+fn wait_for_exit(child: &mut Child, grace: Duration) -> bool {
+    let deadline = Instant::now() + grace;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return true,
+            Ok(None) if Instant::now() < deadline => thread::sleep(SHUTDOWN_POLL_INTERVAL),
+            Ok(None) => return false,
+            Err(error) => {
+                warn!("Failed to check sidecar status during shutdown: {}", error);
+                return false;
+            }
+        }
+    }
+}
+
 fn chrono_now() -> String {
     chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
 }
 
+// ============================================================================
+// RAG Storage Paths
+// ============================================================================
+
+/// Key under which RAG path overrides are stored in `settings.json`.
+pub const RAG_PATHS_SETTINGS_KEY: &str = "ragPaths";
+
+/// RAG path overrides as persisted in settings. Empty values mean "not set".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RagPathSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chroma_path: Option<String>,
+}
+
+/// Vault and vector-store directories handed to the embedded RAG runtime
+/// as `VAULT_PATH` / `CHROMA_PATH`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RagPaths {
+    pub vault_path: std::path::PathBuf,
+    pub chroma_path: std::path::PathBuf,
+}
+
+/// Resolve the RAG paths: settings win over the environment, which wins
+/// over the `~/.coworkany` defaults.
+pub fn resolve_rag_paths(
+    settings: &RagPathSettings,
+    env: impl Fn(&str) -> Option<String>,
+    home_dir: &Path,
+) -> RagPaths {
+    let pick = |configured: Option<&String>, env_key: &str, default_leaf: &str| {
+        configured
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .or_else(|| env(env_key).filter(|value| !value.trim().is_empty()))
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| home_dir.join(".coworkany").join(default_leaf))
+    };
+
+    RagPaths {
+        vault_path: pick(settings.vault_path.as_ref(), "VAULT_PATH", "vault"),
+        chroma_path: pick(settings.chroma_path.as_ref(), "CHROMA_PATH", "chroma"),
+    }
+}
+
+/// Read the RAG overrides from `settings.json`, treating a missing or
+/// unreadable store as "no overrides".
+pub fn load_rag_path_settings(app_data_dir: &Path) -> RagPathSettings {
+    fs::read_to_string(app_data_dir.join("settings.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+        .and_then(|store| store.get(RAG_PATHS_SETTINGS_KEY).cloned())
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Sidecar Launch Config
+// ============================================================================
+
+/// Key under which the sidecar launch config is stored in `settings.json`.
+pub const SIDECAR_CONFIG_SETTINGS_KEY: &str = "sidecarConfig";
+
+/// How to start the sidecar. `Auto` keeps the built-in search: the packaged
+/// sidecar in an app bundle, else the development entry via tsx, npx or bun.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfiguredRuntime {
+    #[default]
+    Auto,
+    Bun,
+    NodeTsx,
+    Deno,
+    /// Program to run, given the entry as its argument when there is one
+    Custom(String),
+}
+
+impl ConfiguredRuntime {
+    /// Parse `COWORKANY_SIDECAR_RUNTIME`: `auto`, `bun`, `node_tsx`, `deno`
+    /// or `custom:<program>`
+    fn from_env_value(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(program) = value.strip_prefix("custom:") {
+            let program = program.trim();
+            return (!program.is_empty()).then(|| Self::Custom(program.to_string()));
+        }
+        match value.to_ascii_lowercase().replace('-', "_").as_str() {
+            "auto" => Some(Self::Auto),
+            "bun" => Some(Self::Bun),
+            "node_tsx" | "tsx" => Some(Self::NodeTsx),
+            "deno" => Some(Self::Deno),
+            _ => None,
+        }
+    }
+}
+
+/// Where and how the sidecar is launched. Unset paths fall back to the
+/// built-in entry search and the entry's project directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarConfig {
+    #[serde(default)]
+    pub runtime: ConfiguredRuntime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_path: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "ResourceLimits::is_unlimited")]
+    pub resource_limits: ResourceLimits,
+}
+
+/// Resolve the launch config: stored settings win over the environment
+/// (`COWORKANY_SIDECAR_RUNTIME`, `COWORKANY_SIDECAR_ENTRY`,
+/// `COWORKANY_SIDECAR_WORKDIR`), field by field.
+pub fn resolve_sidecar_config(
+    settings: &SidecarConfig,
+    env: impl Fn(&str) -> Option<String>,
+) -> SidecarConfig {
+    let env_path = |key: &str| {
+        env(key)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map(std::path::PathBuf::from)
+    };
+    SidecarConfig {
+        runtime: match &settings.runtime {
+            ConfiguredRuntime::Auto => env("COWORKANY_SIDECAR_RUNTIME")
+                .and_then(|value| ConfiguredRuntime::from_env_value(&value))
+                .unwrap_or_default(),
+            runtime => runtime.clone(),
+        },
+        entry_path: settings
+            .entry_path
+            .clone()
+            .or_else(|| env_path("COWORKANY_SIDECAR_ENTRY")),
+        working_dir: settings
+            .working_dir
+            .clone()
+            .or_else(|| env_path("COWORKANY_SIDECAR_WORKDIR")),
+        resource_limits: settings.resource_limits.clone(),
+    }
+}
+
+/// The launch config from `settings.json` and the environment. A missing or
+/// unreadable store counts as "no settings".
+pub fn load_sidecar_config(app_data_dir: &Path) -> SidecarConfig {
+    let settings = fs::read_to_string(app_data_dir.join("settings.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+        .and_then(|store| store.get(SIDECAR_CONFIG_SETTINGS_KEY).cloned())
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    resolve_sidecar_config(&settings, |key| std::env::var(key).ok())
+}
+
+/// Directory a sidecar entry runs from by default: the project above `src/`
+/// for a source entry, else the entry's own directory.
+fn default_sidecar_working_dir(entry: &Path) -> Option<std::path::PathBuf> {
+    let parent = entry.parent()?;
+    match parent.file_name() {
+        Some(name) if name == "src" => parent.parent().map(Path::to_path_buf),
+        _ => Some(parent.to_path_buf()),
+    }
+}
+
+/// `entry` as an argument for a command run in `working_dir`: relative when
+/// it lies inside it, as the development scripts expect.
+fn entry_argument(entry: &Path, working_dir: &Path) -> std::ffi::OsString {
+    entry
+        .strip_prefix(working_dir)
+        .unwrap_or(entry)
+        .as_os_str()
+        .to_os_string()
+}
+
+/// The command for an explicitly configured runtime; `None` for `Auto`
+fn configured_command(
+    runtime: &ConfiguredRuntime,
+    entry: Option<&Path>,
+    working_dir: &Path,
+) -> Option<(Command, SidecarRuntime)> {
+    let entry = entry.map(|entry| entry_argument(entry, working_dir));
+    let (mut command, launched) = match runtime {
+        ConfiguredRuntime::Auto => return None,
+        ConfiguredRuntime::Bun => {
+            let mut command = Command::new("bun");
+            command.arg("run");
+            (command, SidecarRuntime::Bun)
+        }
+        ConfiguredRuntime::NodeTsx => {
+            let mut command = Command::new("node");
+            command.arg(working_dir.join("node_modules/tsx/dist/cli.mjs"));
+            (command, SidecarRuntime::NodeTsx)
+        }
+        ConfiguredRuntime::Deno => {
+            let mut command = Command::new("deno");
+            command.args(["run", "-A"]);
+            (command, SidecarRuntime::Deno)
+        }
+        ConfiguredRuntime::Custom(program) => (Command::new(program), SidecarRuntime::Custom),
+    };
+    command.args(entry);
+    command.current_dir(working_dir);
+    Some((command, launched))
+}
+
+/// Check that `path` is (or can be created as) a directory we can write to.
+pub fn ensure_writable_dir(path: &Path) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path.display()));
+    }
+    fs::create_dir_all(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+    let probe = path.join(format!(".coworkany-write-check-{}", Uuid::new_v4()));
+    fs::write(&probe, b"")
+        .map_err(|e| format!("Directory is not writable: {} ({})", path.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
 // ============================================================================
 // Sidecar Manager
 // ============================================================================
 
+/// Runtime used to start the sidecar process, in the order the launcher
+/// tries them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SidecarRuntime {
+    /// Compiled `coworkany-sidecar` binary from the app bundle
+    PackagedBinary,
+    /// Bundled Node runtime running `coworkany-sidecar-node.mjs`
+    PackagedNode,
+    /// `node node_modules/tsx/dist/cli.mjs src/main.ts`
+    NodeTsx,
+    /// `npx tsx src/main.ts`
+    Npx,
+    /// `bun run src/main.ts`
+    Bun,
+    /// `deno run -A <entry>`, only when configured
+    Deno,
+    /// A program named in `SidecarConfig`, such as a production binary
+    Custom,
+    /// Reused an already-running sidecar over its singleton transport
+    SingletonAttach,
+}
+
+/// What the manager actually launched (or attached to) on the last spawn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchedRuntime {
+    pub runtime: SidecarRuntime,
+    pub launch_mode: String,
+    /// Program and arguments; the transport descriptor for singleton attach
+    pub command_line: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+}
+
+/// IPC protocol version spoken by this desktop build, as `MAJOR.MINOR`.
+/// Bump the major version for changes an older sidecar would misparse.
+pub const DESKTOP_PROTOCOL_VERSION: &str = "1.0";
+
+/// Outcome of the `hello` handshake performed on every spawn/attach.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolHandshake {
+    pub desktop_version: String,
+    /// `None` when the sidecar did not answer `hello` (predates the handshake)
+    pub sidecar_version: Option<String>,
+    pub compatible: bool,
+}
+
+impl ProtocolHandshake {
+    fn new(sidecar_version: Option<String>) -> Self {
+        let compatible = sidecar_version
+            .as_deref()
+            .is_some_and(|version| protocol_versions_compatible(DESKTOP_PROTOCOL_VERSION, version));
+        Self {
+            desktop_version: DESKTOP_PROTOCOL_VERSION.to_string(),
+            sidecar_version,
+            compatible,
+        }
+    }
+
+    /// Refuse a sidecar that answered with an incompatible version. One that
+    /// did not answer at all predates the handshake and is let through.
+    fn check(&self) -> Result<(), SidecarError> {
+        match &self.sidecar_version {
+            Some(sidecar) if !self.compatible => Err(SidecarError::Incompatible {
+                desktop: self.desktop_version.clone(),
+                sidecar: sidecar.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Versions are compatible when their major components match. Minor bumps
+/// only add messages the other side can safely ignore.
+fn protocol_versions_compatible(desktop: &str, sidecar: &str) -> bool {
+    let major = |version: &str| {
+        version
+            .trim()
+            .trim_start_matches('v')
+            .split('.')
+            .next()
+            .and_then(|major| major.parse::<u32>().ok())
+    };
+    matches!((major(desktop), major(sidecar)), (Some(a), Some(b)) if a == b)
+}
+
+/// Most recent problem lines kept for the diagnostics panel.
+const PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT: usize = 20;
+const PROTOCOL_DIAGNOSTIC_SAMPLE_MAX_CHARS: usize = 512;
+
+/// Why a protocol line from the sidecar was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolIssueKind {
+    /// Looked like a JSON object but failed to parse
+    ParseFailure,
+    /// Parsed, but carried no usable `type`
+    Unclassified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolIssueSample {
+    pub kind: ProtocolIssueKind,
+    /// Offending line, truncated
+    pub line: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub timestamp: String,
+}
+
+/// Counts of stdout protocol lines the reader had to drop, plus a bounded
+/// sample of the most recent ones.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolDiagnostics {
+    pub parse_failures: u64,
+    pub unclassified: u64,
+    pub samples: std::collections::VecDeque<ProtocolIssueSample>,
+}
+
+impl ProtocolDiagnostics {
+    pub fn total(&self) -> u64 {
+        self.parse_failures + self.unclassified
+    }
+
+    /// Fold another sidecar's diagnostics into this one, keeping the most
+    /// recent samples across both.
+    pub fn merge(&mut self, other: ProtocolDiagnostics) {
+        self.parse_failures += other.parse_failures;
+        self.unclassified += other.unclassified;
+        self.samples.extend(other.samples);
+        self.samples
+            .make_contiguous()
+            .sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        while self.samples.len() > PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT {
+            self.samples.pop_front();
+        }
+    }
+
+    fn record(&mut self, kind: ProtocolIssueKind, line: &str, error: Option<String>) {
+        match kind {
+            ProtocolIssueKind::ParseFailure => self.parse_failures += 1,
+            ProtocolIssueKind::Unclassified => self.unclassified += 1,
+        }
+        if self.samples.len() == PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ProtocolIssueSample {
+            kind,
+            line: truncate_log_line(line, PROTOCOL_DIAGNOSTIC_SAMPLE_MAX_CHARS),
+            error,
+            timestamp: chrono_now(),
+        });
+    }
+}
+
+pub type SharedProtocolDiagnostics = Arc<Mutex<ProtocolDiagnostics>>;
+
+/// Sidecar stderr lines kept for a log panel opened after they were printed
+pub const STDERR_BACKLOG_LIMIT: usize = 200;
+
+/// How serious a sidecar stderr line looks, guessed from its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StderrSeverity {
+    Info,
+    Warn,
+    Error,
+}
+
+/// One sidecar stderr line, as emitted in `sidecar-stderr`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarStderrLine {
+    pub line: String,
+    pub severity: StderrSeverity,
+    pub timestamp: String,
+    /// Workspace of a dedicated sidecar; `None` for the default one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+}
+
+/// Most recent stderr lines, oldest first; kept across restarts so the
+/// lines before a crash survive it
+pub type SharedStderrBacklog = Arc<Mutex<VecDeque<SidecarStderrLine>>>;
+
+fn push_stderr_backlog(backlog: &Mutex<VecDeque<SidecarStderrLine>>, line: SidecarStderrLine) {
+    if let Ok(mut backlog) = backlog.lock() {
+        if backlog.len() >= STDERR_BACKLOG_LIMIT {
+            backlog.pop_front();
+        }
+        backlog.push_back(line);
+    }
+}
+
+/// Parse and classify one JSON-object stdout line. Lines that can't be used
+/// are logged and recorded in `diagnostics` instead of being silently lost.
+fn parse_protocol_line(
+    line: &str,
+    diagnostics: &Mutex<ProtocolDiagnostics>,
+) -> Option<(serde_json::Value, SidecarMessageKind)> {
+    let issue = match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(message) => match classify_sidecar_message(&message) {
+            Some(kind) => return Some((message, kind)),
+            None => {
+                warn!(
+                    "Ignoring unclassified sidecar message: {}",
+                    truncate_log_line(line, JSON_LOG_PREVIEW_MAX_CHARS)
+                );
+                (ProtocolIssueKind::Unclassified, None)
+            }
+        },
+        Err(e) => {
+            warn!(
+                "Failed to parse sidecar output as JSON: {} - preview: {}",
+                e,
+                truncate_log_line(line, 256)
+            );
+            (ProtocolIssueKind::ParseFailure, Some(e.to_string()))
+        }
+    };
+    if let Ok(mut diagnostics) = diagnostics.lock() {
+        diagnostics.record(issue.0, line, issue.1);
+    }
+    None
+}
+
+/// Packaged launches go through the bundled Node runtime only when node mode
+/// is requested and both the runtime and its entry script are present.
+fn packaged_runtime(use_node_runtime: bool, can_launch_via_node: bool) -> SidecarRuntime {
+    if use_node_runtime && can_launch_via_node {
+        SidecarRuntime::PackagedNode
+    } else {
+        SidecarRuntime::PackagedBinary
+    }
+}
+
+/// Development runtimes to try in order. A local tsx install is used on its
+/// own; otherwise npx is tried first with bun as the fallback.
+fn development_runtimes(tsx_installed: bool) -> &'static [SidecarRuntime] {
+    if tsx_installed {
+        &[SidecarRuntime::NodeTsx]
+    } else {
+        &[SidecarRuntime::Npx, SidecarRuntime::Bun]
+    }
+}
+
+/// Render a command as a single shell-like line for logs and status.
+fn describe_command(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| {
+            let part = part.to_string_lossy();
+            if part.contains(char::is_whitespace) {
+                format!("\"{}\"", part)
+            } else {
+                part.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub struct SidecarManager {
     child: Option<Child>,
     command_writer: Option<SharedCommandWriter>,
+    /// Bounded queue in front of `command_writer`, drained by a writer thread
+    command_queue: Option<CommandQueue>,
     stdout_handle: Option<thread::JoinHandle<()>>,
     stdout_drain_handle: Option<thread::JoinHandle<()>>,
     stderr_handle: Option<thread::JoinHandle<()>>,
     pending_responses: Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+    /// Waiters from `send_command_streaming` that also take the command's
+    /// `command_progress` messages; the same channel as in `pending_responses`
+    progress_subscribers: Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
     transport_healthy: Arc<AtomicBool>,
+    launched_runtime: Option<LaunchedRuntime>,
+    protocol_handshake: Option<ProtocolHandshake>,
+    /// Protocol lines dropped by the stdout reader; kept across restarts
+    protocol_diagnostics: SharedProtocolDiagnostics,
+    stderr_backlog: SharedStderrBacklog,
+    /// Paths searched by the last spawn that found no sidecar entry
+    missing_entry: Option<Vec<std::path::PathBuf>>,
+    /// Workspace this manager's sidecar is dedicated to; `None` for the
+    /// default sidecar
+    workspace: Option<String>,
+    /// How long `shutdown` lets the sidecar flush and exit before killing it
+    shutdown_grace: Duration,
+    /// Capacity of the command queue created at the next spawn
+    stdin_queue_capacity: usize,
+    /// Limits the running sidecar process was started with
+    resource_limits: ResourceLimits,
+}
+
+struct CommandQueue {
+    sender: SyncSender<String>,
+    capacity: usize,
 }
 
 enum CommandWriter {
@@ -408,14 +970,109 @@ impl SidecarManager {
         Self {
             child: None,
             command_writer: None,
+            command_queue: None,
             stdout_handle: None,
             stdout_drain_handle: None,
             stderr_handle: None,
             pending_responses: Arc::new(Mutex::new(HashMap::new())),
+            progress_subscribers: Arc::new(Mutex::new(HashMap::new())),
             transport_healthy: Arc::new(AtomicBool::new(false)),
+            launched_runtime: None,
+            protocol_handshake: None,
+            protocol_diagnostics: Arc::default(),
+            stderr_backlog: Arc::default(),
+            missing_entry: None,
+            workspace: None,
+            shutdown_grace: DEFAULT_SHUTDOWN_GRACE,
+            stdin_queue_capacity: DEFAULT_STDIN_QUEUE_CAPACITY,
+            resource_limits: ResourceLimits::default(),
         }
     }
 
+    /// Manager for a sidecar dedicated to one workspace. It uses its own
+    /// singleton transport so it never attaches to another workspace's
+    /// sidecar.
+    pub fn for_workspace(workspace: &str) -> Self {
+        let mut manager = Self::new();
+        manager.workspace = Some(workspace.to_string());
+        manager
+    }
+
+    pub fn workspace(&self) -> Option<&str> {
+        self.workspace.as_deref()
+    }
+
+    /// Runtime and command line of the current sidecar transport, if any.
+    pub fn launched_runtime(&self) -> Option<&LaunchedRuntime> {
+        self.launched_runtime.as_ref()
+    }
+
+    /// Result of the protocol handshake with the current transport, if any.
+    pub fn protocol_handshake(&self) -> Option<&ProtocolHandshake> {
+        self.protocol_handshake.as_ref()
+    }
+
+    /// Protocol version the current sidecar agreed to, for gating features
+    /// on it; `None` before a compatible handshake.
+    pub fn negotiated_protocol_version(&self) -> Option<&str> {
+        self.protocol_handshake
+            .as_ref()
+            .filter(|handshake| handshake.compatible)
+            .and_then(|handshake| handshake.sidecar_version.as_deref())
+    }
+
+    /// Paths searched when the last spawn found no sidecar entry.
+    pub fn missing_entry(&self) -> Option<&[std::path::PathBuf]> {
+        self.missing_entry.as_deref()
+    }
+
+    fn report_missing_entry(&mut self, app_handle: &AppHandle, searched: &[std::path::PathBuf]) {
+        let searched_paths: Vec<String> = searched
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        error!(
+            "Sidecar entry not found; searched: {}",
+            searched_paths.join(", ")
+        );
+        let _ = app_handle.emit(
+            "sidecar-missing",
+            json!({
+                "workspace": self.workspace,
+                "searchedPaths": searched_paths,
+                "message": "Sidecar not found. Set COWORKANY_SIDECAR_ENTRY or reinstall the app.",
+            }),
+        );
+        self.missing_entry = Some(searched.to_vec());
+    }
+
+    /// Snapshot of the stdout lines the reader could not parse or classify.
+    pub fn protocol_diagnostics(&self) -> ProtocolDiagnostics {
+        self.protocol_diagnostics
+            .lock()
+            .map(|diagnostics| diagnostics.clone())
+            .unwrap_or_default()
+    }
+
+    /// Up to `limit` of the latest sidecar stderr lines, oldest first
+    pub fn recent_stderr(&self, limit: usize) -> Vec<SidecarStderrLine> {
+        self.stderr_backlog
+            .lock()
+            .map(|backlog| {
+                let skip = backlog.len().saturating_sub(limit);
+                backlog.iter().skip(skip).cloned().collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn record_launched_runtime(&mut self, launched: LaunchedRuntime) {
+        info!(
+            "Sidecar launched: runtime={:?} mode={} pid={:?} command={}",
+            launched.runtime, launched.launch_mode, launched.pid, launched.command_line
+        );
+        self.launched_runtime = Some(launched);
+    }
+
     /// Spawn the sidecar process and start listening for events
     pub fn spawn(&mut self, app_handle: AppHandle) -> Result<(), SidecarError> {
         if self.transport_healthy.load(Ordering::SeqCst) && self.command_writer.is_some() {
@@ -428,19 +1085,28 @@ impl SidecarManager {
 
         let app_dir = resolve_app_dir();
         let app_data_dir = resolve_app_data_dir(&app_handle);
+        let workspace = self.workspace.clone();
+        let singleton_key = Self::singleton_key(&app_data_dir, workspace.as_deref());
         let mut launch_mode = "development".to_string();
 
-        if let Some(attached) = Self::try_attach_singleton_transport(&app_data_dir)? {
+        if let Some(attached) = Self::try_attach_singleton_transport(&singleton_key)? {
             info!(
                 "Attached to existing sidecar singleton transport: {}",
                 attached.descriptor
             );
             launch_mode = "singleton_attach".to_string();
-            self.command_writer = Some(attached.writer.clone());
+            self.set_command_writer(attached.writer.clone());
             self.child = None;
+            self.record_launched_runtime(LaunchedRuntime {
+                runtime: SidecarRuntime::SingletonAttach,
+                launch_mode: launch_mode.clone(),
+                command_line: attached.descriptor.clone(),
+                pid: None,
+            });
 
             let runtime_context = build_platform_runtime_context(&app_handle, Some(&launch_mode));
-            self.start_reader_threads(attached.reader, None, app_handle, attached.writer);
+            self.start_reader_threads(attached.reader, None, app_handle.clone(), attached.writer);
+            self.perform_protocol_handshake(&app_handle, Duration::from_secs(2))?;
 
             if let Err(error) = self.send_runtime_bootstrap(&runtime_context) {
                 self.invalidate_transport("failed to bootstrap attached sidecar runtime context");
@@ -456,39 +1122,76 @@ impl SidecarManager {
 
         info!("No reusable sidecar transport found; spawning new sidecar process");
 
+        let config = load_sidecar_config(Path::new(&app_data_dir));
         let force_development = Self::force_development_sidecar();
         let prefer_packaged = Self::running_from_app_bundle();
-        let packaged = if force_development || !prefer_packaged {
-            None
-        } else {
-            Self::resolve_packaged_sidecar(&app_handle)
-        };
-        let mut child = if let Some(packaged) = packaged {
+        let packaged =
+            if config.runtime != ConfiguredRuntime::Auto || force_development || !prefer_packaged {
+                None
+            } else {
+                Self::resolve_packaged_sidecar(&app_handle)
+            };
+        let spawned = if config.runtime != ConfiguredRuntime::Auto {
+            launch_mode = "configured".to_string();
+            Self::spawn_configured_sidecar(&config, &app_dir, &app_data_dir, workspace.as_deref())
+        } else if let Some(packaged) = packaged {
             launch_mode = "packaged".to_string();
-            Self::spawn_packaged_sidecar(&packaged, &app_dir, &app_data_dir).or_else(|error| {
+            Self::spawn_packaged_sidecar(
+                &packaged,
+                &config.resource_limits,
+                &app_dir,
+                &app_data_dir,
+                workspace.as_deref(),
+            )
+            .or_else(|error| {
                 warn!(
                     "Failed to start packaged sidecar ({}), falling back to development entry",
                     error
                 );
                 launch_mode = "development".to_string();
-                Self::spawn_development_sidecar(&app_dir, &app_data_dir)
-            })?
+                Self::spawn_development_sidecar(
+                    &config,
+                    &app_dir,
+                    &app_data_dir,
+                    workspace.as_deref(),
+                )
+            })
         } else {
             if force_development {
                 info!("COWORKANY_FORCE_DEVELOPMENT_SIDECAR enabled; skipping packaged sidecar");
             } else if !prefer_packaged {
                 info!("Running outside app bundle; using development sidecar");
             }
-            Self::spawn_development_sidecar(&app_dir, &app_data_dir)?
+            Self::spawn_development_sidecar(&config, &app_dir, &app_data_dir, workspace.as_deref())
+        };
+        let (mut child, runtime, command_line) = match spawned {
+            Ok(spawned) => {
+                self.missing_entry = None;
+                spawned
+            }
+            Err(error) => {
+                if let SidecarError::EntryMissing { searched } = &error {
+                    self.report_missing_entry(&app_handle, searched);
+                }
+                self.transport_healthy.store(false, Ordering::SeqCst);
+                return Err(error);
+            }
         };
 
         info!("Sidecar spawned with PID: {:?}", child.id());
+        self.resource_limits = config.resource_limits.clone();
+        self.record_launched_runtime(LaunchedRuntime {
+            runtime,
+            launch_mode: launch_mode.clone(),
+            command_line,
+            pid: Some(child.id()),
+        });
 
         // Keep consuming child stdout to avoid pipe backpressure.
         let stdout = child.stdout.take().expect("Failed to get stdout");
         let stderr = child.stderr.take().expect("Failed to get stderr");
         let attached = Self::try_attach_singleton_transport_with_retry(
-            &app_data_dir,
+            &singleton_key,
             300,
             Duration::from_millis(50),
         )?;
@@ -518,11 +1221,12 @@ impl SidecarManager {
                 false,
             )
         };
-        self.command_writer = Some(command_writer.clone());
+        self.set_command_writer(command_writer.clone());
         self.child = Some(child);
 
         let runtime_context = build_platform_runtime_context(&app_handle, Some(&launch_mode));
-        self.start_reader_threads(reader, Some(stderr), app_handle, command_writer);
+        self.start_reader_threads(reader, Some(stderr), app_handle.clone(), command_writer);
+        self.perform_protocol_handshake(&app_handle, Duration::from_secs(2))?;
 
         if let Err(error) = self.send_runtime_bootstrap(&runtime_context) {
             self.invalidate_transport("failed to bootstrap sidecar runtime context");
@@ -570,6 +1274,24 @@ impl SidecarManager {
     pub fn send_command_async(
         &self,
         command: serde_json::Value,
+    ) -> Result<Receiver<serde_json::Value>, SidecarError> {
+        self.send_command_with_receiver(command, false)
+    }
+
+    /// Like `send_command_async`, but the receiver also gets each
+    /// `command_progress` message for the command, in order, before the
+    /// response. Wait on it with `recv_streaming`.
+    pub fn send_command_streaming(
+        &self,
+        command: serde_json::Value,
+    ) -> Result<Receiver<serde_json::Value>, SidecarError> {
+        self.send_command_with_receiver(command, true)
+    }
+
+    fn send_command_with_receiver(
+        &self,
+        command: serde_json::Value,
+        stream_progress: bool,
     ) -> Result<Receiver<serde_json::Value>, SidecarError> {
         let command_id = command
             .get("id")
@@ -578,6 +1300,13 @@ impl SidecarManager {
             .to_string();
 
         let (tx, rx) = mpsc::channel();
+        if stream_progress {
+            let mut subscribers = self
+                .progress_subscribers
+                .lock()
+                .map_err(|e| SidecarError::SendError(e.to_string()))?;
+            subscribers.insert(command_id.clone(), tx.clone());
+        }
         {
             let mut pending = self
                 .pending_responses
@@ -598,6 +1327,75 @@ impl SidecarManager {
         if let Ok(mut pending) = self.pending_responses.lock() {
             pending.remove(command_id);
         }
+        if let Ok(mut subscribers) = self.progress_subscribers.lock() {
+            subscribers.remove(command_id);
+        }
+    }
+
+    /// Exchange protocol versions with the sidecar. An incompatible answer is
+    /// emitted as `sidecar-incompatible` and fails the spawn with the
+    /// transport torn down, so it is never reported as running. A sidecar too
+    /// old to answer is only emitted as `sidecar-version-mismatch`, so the UI
+    /// can explain why commands may be ignored. The wait runs while the
+    /// caller holds the manager lock, so `timeout` should stay short.
+    fn perform_protocol_handshake(
+        &mut self,
+        app_handle: &AppHandle,
+        timeout: Duration,
+    ) -> Result<(), SidecarError> {
+        let command_id = Uuid::new_v4().to_string();
+        let command = json!({
+            "id": command_id,
+            "timestamp": chrono_now(),
+            "type": "hello",
+            "payload": {
+                "protocolVersion": DESKTOP_PROTOCOL_VERSION
+            }
+        });
+        let sidecar_version = match self.send_command_async(command) {
+            Ok(receiver) => match receiver.recv_timeout(timeout) {
+                Ok(response) => response
+                    .get("payload")
+                    .and_then(|payload| payload.get("protocolVersion"))
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string),
+                Err(_) => {
+                    // An old sidecar never answers; don't leave the waiter behind.
+                    self.clear_pending_response(&command_id);
+                    None
+                }
+            },
+            Err(error) => {
+                warn!("Failed to send hello to sidecar: {}", error);
+                None
+            }
+        };
+
+        let handshake = ProtocolHandshake::new(sidecar_version);
+        if let Err(error) = handshake.check() {
+            error!("Refusing sidecar: {}", error);
+            if let Err(e) = app_handle.emit("sidecar-incompatible", &handshake) {
+                error!("Failed to emit sidecar-incompatible: {}", e);
+            }
+            self.invalidate_transport("sidecar protocol version is incompatible");
+            return Err(error);
+        }
+        if handshake.compatible {
+            info!(
+                "Sidecar protocol handshake ok: desktop={} sidecar={:?}",
+                handshake.desktop_version, handshake.sidecar_version
+            );
+        } else {
+            warn!(
+                "Sidecar did not answer the protocol handshake: desktop={}",
+                handshake.desktop_version
+            );
+            if let Err(e) = app_handle.emit("sidecar-version-mismatch", &handshake) {
+                error!("Failed to emit sidecar-version-mismatch: {}", e);
+            }
+        }
+        self.protocol_handshake = Some(handshake);
+        Ok(())
     }
 
     fn send_runtime_bootstrap(
@@ -690,8 +1488,41 @@ impl SidecarManager {
         Err(SidecarError::SendError(error.to_string()))
     }
 
-    /// Shutdown the sidecar process
+    /// How many commands may queue for the sidecar before `send_command`
+    /// returns `SidecarError::Backpressure`
+    pub fn stdin_queue_capacity(&self) -> usize {
+        self.stdin_queue_capacity
+    }
+
+    /// Takes effect the next time the sidecar is spawned or attached.
+    pub fn set_stdin_queue_capacity(&mut self, capacity: usize) {
+        self.stdin_queue_capacity = capacity.max(1);
+    }
+
+    /// How long `shutdown` waits for the sidecar to exit on its own
+    pub fn shutdown_grace(&self) -> Duration {
+        self.shutdown_grace
+    }
+
+    pub fn set_shutdown_grace(&mut self, grace: Duration) {
+        self.shutdown_grace = grace;
+    }
+
+    /// Shutdown the sidecar process: ask it to exit with a `shutdown`
+    /// command so it can flush task state, and kill it only if it is still
+    /// running after the grace period.
     pub fn shutdown(&mut self) {
+        if self.child.is_some() && self.transport_healthy.load(Ordering::SeqCst) {
+            let command = json!({
+                "id": Uuid::new_v4().to_string(),
+                "timestamp": chrono_now(),
+                "type": "shutdown",
+                "payload": {}
+            });
+            if let Err(error) = self.send_raw_command(command) {
+                debug!("Failed to send shutdown command to sidecar: {}", error);
+            }
+        }
         self.transport_healthy.store(false, Ordering::SeqCst);
         fail_pending_responses(
             &self.pending_responses,
@@ -699,15 +1530,19 @@ impl SidecarManager {
             "Sidecar was shut down before the request completed",
         );
         self.close_command_writer();
+        self.launched_runtime = None;
+        self.protocol_handshake = None;
 
         if let Some(mut child) = self.child.take() {
             info!("Shutting down sidecar...");
 
-            // Give it a moment to exit gracefully
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Force kill if still running
-            let _ = child.kill();
+            let grace = self.shutdown_grace();
+            if wait_for_exit(&mut child, grace) {
+                info!("Sidecar exited on its own");
+            } else {
+                warn!("Sidecar still running after {:?}; killing it", grace);
+                let _ = child.kill();
+            }
             let _ = child.wait();
 
             info!("Sidecar shutdown complete");
@@ -728,6 +1563,8 @@ impl SidecarManager {
 
         fail_pending_responses(&self.pending_responses, "sidecar_disconnected", reason);
         self.close_command_writer();
+        self.launched_runtime = None;
+        self.protocol_handshake = None;
 
         if let Some(mut child) = self.child.take() {
             let _ = child.kill();
@@ -754,9 +1591,13 @@ impl SidecarManager {
                 Ok(Some(status)) => {
                     // Process has exited
                     warn!("Sidecar process exited with status: {:?}", status);
+                    if let Some(reason) = limit_violation(&status, &self.resource_limits) {
+                        error!("Sidecar process {}", reason);
+                    }
                     // Clean up the dead process
                     self.child = None;
                     self.command_writer = None;
+                    self.command_queue = None;
                     false
                 }
                 Ok(None) => {
@@ -782,6 +1623,12 @@ impl SidecarManager {
     ) {
         let transport_healthy = self.transport_healthy.clone();
         let pending_responses = self.pending_responses.clone();
+        let progress_subscribers = self.progress_subscribers.clone();
+        let protocol_diagnostics = self.protocol_diagnostics.clone();
+
+        let stderr_backlog = self.stderr_backlog.clone();
+        let workspace = self.workspace.clone();
+        let stderr_app_handle = app_handle.clone();
 
         let stdout_handle = thread::spawn(move || {
             Self::stdout_reader_loop(
@@ -789,14 +1636,16 @@ impl SidecarManager {
                 app_handle,
                 command_writer,
                 pending_responses,
+                progress_subscribers,
                 transport_healthy,
+                protocol_diagnostics,
             );
         });
         self.stdout_handle = Some(stdout_handle);
 
         self.stderr_handle = stderr.map(|stderr| {
             thread::spawn(move || {
-                Self::stderr_reader_loop(stderr);
+                Self::stderr_reader_loop(stderr, stderr_app_handle, stderr_backlog, workspace);
             })
         });
     }
@@ -817,10 +1666,41 @@ impl SidecarManager {
         })
     }
 
+    /// Route commands to `writer` through a bounded queue, so a stalled
+    /// sidecar pushes back on callers instead of blocking them or letting
+    /// commands pile up.
+    fn set_command_writer(&mut self, writer: SharedCommandWriter) {
+        let capacity = self.stdin_queue_capacity();
+        let (sender, receiver) = mpsc::sync_channel::<String>(capacity);
+        let thread_writer = writer.clone();
+        let transport_healthy = self.transport_healthy.clone();
+        thread::spawn(move || {
+            for line in receiver {
+                if let Err(error) = write_json_line(&thread_writer, &line) {
+                    error!("Failed to write command to sidecar: {}", error);
+                    transport_healthy.store(false, Ordering::SeqCst);
+                    return;
+                }
+            }
+            // Every sender is gone and what was queued is written: close the
+            // transport now rather than dropping queued commands.
+            if let Ok(mut guard) = thread_writer.lock() {
+                guard.shutdown();
+            }
+            debug!("Stdin writer loop ended");
+        });
+        self.command_writer = Some(writer);
+        self.command_queue = Some(CommandQueue { sender, capacity });
+    }
+
     fn close_command_writer(&mut self) {
         let Some(command_writer) = self.command_writer.take() else {
             return;
         };
+        // The writer thread closes the transport once the queue drains.
+        if self.command_queue.take().is_some() {
+            return;
+        }
 
         match command_writer.lock() {
             Ok(mut guard) => guard.shutdown(),
@@ -835,12 +1715,28 @@ impl SidecarManager {
     // Internal threads
     // -------------------------------------------------------------------------
 
+    /// Stdout closed: nothing will answer the commands still waiting, so tell
+    /// each of them now instead of letting them wait out their timeouts.
+    fn disconnect_pending_waiters(
+        pending_responses: &Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+        transport_healthy: &AtomicBool,
+    ) {
+        transport_healthy.store(false, Ordering::SeqCst);
+        fail_pending_responses(
+            pending_responses,
+            "sidecar_disconnected",
+            "Sidecar connection closed before the request completed",
+        );
+    }
+
     fn stdout_reader_loop(
         stdout: Box<dyn Read + Send>,
         app_handle: AppHandle,
         command_writer: SharedCommandWriter,
         pending_responses: Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+        progress_subscribers: Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
         transport_healthy: Arc<AtomicBool>,
+        protocol_diagnostics: SharedProtocolDiagnostics,
     ) {
         let reader = BufReader::new(stdout);
         let mut suppressed_non_protocol_lines = 0usize;
@@ -873,77 +1769,57 @@ impl SidecarManager {
                         suppressed_non_protocol_lines = 0;
                     }
 
-                    match serde_json::from_str::<serde_json::Value>(&line) {
-                        Ok(message) => {
-                            if !message.is_object() {
-                                warn!("Ignoring sidecar stdout JSON value that is not an object");
-                                continue;
+                    let Some((message, kind)) = parse_protocol_line(&line, &protocol_diagnostics)
+                    else {
+                        stream_delta_log_aggregator.flush();
+                        continue;
+                    };
+                    if let Some(stream_entry) = extract_stream_delta_log_entry(&message) {
+                        stream_delta_log_aggregator.push(stream_entry);
+                    } else {
+                        stream_delta_log_aggregator.flush();
+                        debug!(
+                            "Received from sidecar: {}",
+                            truncate_log_line(&line, JSON_LOG_PREVIEW_MAX_CHARS)
+                        );
+                    }
+
+                    match kind {
+                        SidecarMessageKind::TaskEvent => {
+                            if let Err(e) = app_handle.emit("task-event", &message) {
+                                error!("Failed to emit task-event: {}", e);
                             }
-                            if let Some(stream_entry) = extract_stream_delta_log_entry(&message) {
-                                stream_delta_log_aggregator.push(stream_entry);
-                            } else {
-                                stream_delta_log_aggregator.flush();
-                                debug!(
-                                    "Received from sidecar: {}",
-                                    truncate_log_line(&line, JSON_LOG_PREVIEW_MAX_CHARS)
-                                );
+                        }
+                        SidecarMessageKind::CanonicalStreamEvent => {
+                            if let Err(e) = app_handle.emit("canonical-stream-event", &message) {
+                                error!("Failed to emit canonical-stream-event: {}", e);
                             }
-
-                            match classify_sidecar_message(&message) {
-                                Some(SidecarMessageKind::TaskEvent) => {
-                                    if let Err(e) = app_handle.emit("task-event", &message) {
-                                        error!("Failed to emit task-event: {}", e);
-                                    }
-                                }
-                                Some(SidecarMessageKind::CanonicalStreamEvent) => {
-                                    if let Err(e) =
-                                        app_handle.emit("canonical-stream-event", &message)
-                                    {
-                                        error!("Failed to emit canonical-stream-event: {}", e);
-                                    }
-                                }
-                                Some(SidecarMessageKind::VoiceState) => {
-                                    let payload =
-                                        message.get("payload").cloned().unwrap_or(message.clone());
-                                    if let Err(e) = app_handle.emit("voice-state", payload) {
-                                        error!("Failed to emit voice-state: {}", e);
-                                    }
-                                }
-                                Some(SidecarMessageKind::IpcResponse) => {
-                                    if let Some(command_id) = message
-                                        .get("commandId")
-                                        .and_then(|v| v.as_str())
-                                        .map(|v| v.to_string())
-                                    {
-                                        if let Ok(mut pending) = pending_responses.lock() {
-                                            if let Some(waiter) = pending.remove(&command_id) {
-                                                let _ = waiter.send(message.clone());
-                                            }
-                                        }
-                                    }
-                                    if let Err(e) = app_handle.emit("ipc-response", &message) {
-                                        error!("Failed to emit ipc-response: {}", e);
-                                    }
-                                }
-                                Some(SidecarMessageKind::IpcCommand) => {
-                                    handle_sidecar_command(
-                                        message,
-                                        app_handle.clone(),
-                                        command_writer.clone(),
-                                    );
-                                }
-                                None => warn!(
-                                    "Ignoring unclassified sidecar message: {}",
-                                    truncate_log_line(&line, JSON_LOG_PREVIEW_MAX_CHARS)
-                                ),
+                        }
+                        SidecarMessageKind::VoiceState => {
+                            let payload =
+                                message.get("payload").cloned().unwrap_or(message.clone());
+                            if let Err(e) = app_handle.emit("voice-state", payload) {
+                                error!("Failed to emit voice-state: {}", e);
                             }
                         }
-                        Err(e) => {
-                            stream_delta_log_aggregator.flush();
-                            warn!(
-                                "Failed to parse sidecar output as JSON: {} - preview: {}",
-                                e,
-                                truncate_log_line(&line, 256)
+                        SidecarMessageKind::CommandProgress => {
+                            deliver_command_progress(&progress_subscribers, &message);
+                        }
+                        SidecarMessageKind::IpcResponse => {
+                            deliver_command_response(
+                                &pending_responses,
+                                &progress_subscribers,
+                                &message,
+                            );
+                            if let Err(e) = app_handle.emit("ipc-response", &message) {
+                                error!("Failed to emit ipc-response: {}", e);
+                            }
+                        }
+                        SidecarMessageKind::IpcCommand => {
+                            handle_sidecar_command(
+                                message,
+                                app_handle.clone(),
+                                command_writer.clone(),
                             );
                         }
                     }
@@ -963,12 +1839,7 @@ impl SidecarManager {
             );
         }
 
-        transport_healthy.store(false, Ordering::SeqCst);
-        fail_pending_responses(
-            &pending_responses,
-            "sidecar_disconnected",
-            "Sidecar connection closed before the request completed",
-        );
+        Self::disconnect_pending_waiters(&pending_responses, &transport_healthy);
         info!("Stdout reader loop ended (sidecar closed stdout)");
 
         // Notify frontend that sidecar has disconnected
@@ -1037,6 +1908,22 @@ impl SidecarManager {
             || lower.contains("could not determine executable to run")
     }
 
+    /// Severity of a line that isn't noise; the same call as the log level
+    fn stderr_severity(line: &str, category: SidecarStderrCategory) -> StderrSeverity {
+        match category {
+            SidecarStderrCategory::Important => {
+                if Self::is_likely_error_stderr_line(line)
+                    && !Self::is_expected_mcp_warning_stderr_line(line)
+                {
+                    StderrSeverity::Error
+                } else {
+                    StderrSeverity::Warn
+                }
+            }
+            _ => StderrSeverity::Info,
+        }
+    }
+
     fn log_sidecar_stderr_line(line: &str, category: SidecarStderrCategory) {
         match category {
             SidecarStderrCategory::Heartbeat
@@ -1051,19 +1938,21 @@ impl SidecarManager {
             SidecarStderrCategory::RoutineInfo => {
                 info!("Sidecar {}", line);
             }
-            SidecarStderrCategory::Important => {
-                if Self::is_likely_error_stderr_line(line)
-                    && !Self::is_expected_mcp_warning_stderr_line(line)
-                {
-                    error!("Sidecar {}", line);
-                } else {
-                    warn!("Sidecar {}", line);
-                }
-            }
+            SidecarStderrCategory::Important => match Self::stderr_severity(line, category) {
+                StderrSeverity::Error => error!("Sidecar {}", line),
+                _ => warn!("Sidecar {}", line),
+            },
         }
     }
 
-    fn stderr_reader_loop(stderr: std::process::ChildStderr) {
+    /// Log each stderr line, and forward all but the noisy categories as
+    /// `sidecar-stderr` events, keeping them in `backlog` for late viewers.
+    fn stderr_reader_loop(
+        stderr: std::process::ChildStderr,
+        app_handle: AppHandle,
+        backlog: SharedStderrBacklog,
+        workspace: Option<String>,
+    ) {
         let reader = BufReader::new(stderr);
         let mut noisy_state: HashMap<SidecarStderrCategory, (Instant, usize)> = HashMap::new();
 
@@ -1074,6 +1963,16 @@ impl SidecarManager {
                         continue;
                     }
                     let category = Self::classify_sidecar_stderr_line(&line);
+                    if !Self::is_noisy_sidecar_stderr_category(category) {
+                        let forwarded = SidecarStderrLine {
+                            line: line.clone(),
+                            severity: Self::stderr_severity(&line, category),
+                            timestamp: chrono_now(),
+                            workspace: workspace.clone(),
+                        };
+                        let _ = app_handle.emit("sidecar-stderr", &forwarded);
+                        push_stderr_backlog(&backlog, forwarded);
+                    }
                     if Self::is_noisy_sidecar_stderr_category(category) {
                         let now = Instant::now();
                         let entry = noisy_state
@@ -1141,19 +2040,25 @@ impl SidecarManager {
         if !self.transport_healthy.load(Ordering::SeqCst) {
             return Err(SidecarError::NotRunning);
         }
-        let command_writer = self
-            .command_writer
+        let queue = self
+            .command_queue
             .as_ref()
             .ok_or(SidecarError::NotRunning)?;
-        write_json_line(command_writer, line)
+        match queue.sender.try_send(line.to_string()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(SidecarError::Backpressure {
+                capacity: queue.capacity,
+            }),
+            Err(TrySendError::Disconnected(_)) => Err(SidecarError::NotRunning),
+        }
     }
 
     fn try_attach_singleton_transport(
-        app_data_dir: &str,
+        singleton_key: &str,
     ) -> Result<Option<AttachedSingletonTransport>, SidecarError> {
         #[cfg(unix)]
         {
-            let socket_path = Self::sidecar_singleton_socket_path(app_data_dir);
+            let socket_path = Self::sidecar_singleton_socket_path(singleton_key);
             let stream = match UnixStream::connect(&socket_path) {
                 Ok(stream) => stream,
                 Err(error) => {
@@ -1187,7 +2092,7 @@ impl SidecarManager {
 
         #[cfg(windows)]
         {
-            let socket_path = Self::sidecar_singleton_socket_path(app_data_dir);
+            let socket_path = Self::sidecar_singleton_socket_path(singleton_key);
             let mut last_busy_error: Option<std::io::Error> = None;
 
             for _ in 0..3 {
@@ -1239,19 +2144,19 @@ impl SidecarManager {
 
         #[cfg(all(not(unix), not(windows)))]
         {
-            let _ = app_data_dir;
+            let _ = singleton_key;
             Ok(None)
         }
     }
 
     fn try_attach_singleton_transport_with_retry(
-        app_data_dir: &str,
+        singleton_key: &str,
         attempts: usize,
         delay: Duration,
     ) -> Result<Option<AttachedSingletonTransport>, SidecarError> {
         let attempts = attempts.max(1);
         for attempt in 0..attempts {
-            if let Some(attached) = Self::try_attach_singleton_transport(app_data_dir)? {
+            if let Some(attached) = Self::try_attach_singleton_transport(singleton_key)? {
                 return Ok(Some(attached));
             }
             if attempt + 1 < attempts {
@@ -1263,9 +2168,11 @@ impl SidecarManager {
 
     fn spawn_packaged_sidecar(
         packaged: &PackagedSidecar,
+        limits: &ResourceLimits,
         app_dir: &str,
         app_data_dir: &str,
-    ) -> Result<Child, SidecarError> {
+        workspace: Option<&str>,
+    ) -> Result<(Child, SidecarRuntime, String), SidecarError> {
         info!(
             "Resolved packaged sidecar binary: {}",
             packaged.executable.display()
@@ -1287,7 +2194,8 @@ impl SidecarManager {
             );
         }
 
-        let mut command = if use_node_runtime && can_launch_via_node {
+        let runtime = packaged_runtime(use_node_runtime, can_launch_via_node);
+        let mut command = if runtime == SidecarRuntime::PackagedNode {
             let node_binary = packaged
                 .node_binary
                 .as_ref()
@@ -1330,17 +2238,128 @@ impl SidecarManager {
                 .env("PLAYWRIGHT_BROWSERS_PATH", playwright_browsers_path);
         }
 
-        Self::apply_singleton_env(&mut command, app_data_dir);
+        Self::apply_singleton_env(&mut command, app_data_dir, workspace);
         Self::apply_proxy_env(&mut command, app_data_dir);
         Self::apply_llm_env(&mut command, app_data_dir);
+        Self::apply_rag_env(&mut command, app_data_dir);
         Self::apply_chat_runtime_env(&mut command);
-        command.spawn().map_err(SidecarError::from)
+        apply_resource_limits(&mut command, limits);
+        let command_line = describe_command(&command);
+        let child = command.spawn().map_err(SidecarError::from)?;
+        Ok((child, runtime, command_line))
+    }
+
+    /// Entries to look for: the configured one first, then the built-in
+    /// guesses
+    fn entry_candidates(config: &SidecarConfig) -> Vec<std::path::PathBuf> {
+        let mut candidates: Vec<std::path::PathBuf> = config.entry_path.iter().cloned().collect();
+        for candidate in sidecar_entry_candidates() {
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        candidates
+    }
+
+    fn spawn_development_sidecar(
+        config: &SidecarConfig,
+        app_dir: &str,
+        app_data_dir: &str,
+        workspace: Option<&str>,
+    ) -> Result<(Child, SidecarRuntime, String), SidecarError> {
+        Self::spawn_development_sidecar_from(
+            &Self::entry_candidates(config),
+            config.working_dir.as_deref(),
+            &config.resource_limits,
+            app_dir,
+            app_data_dir,
+            workspace,
+        )
+    }
+
+    /// Start the sidecar with the runtime `config` names, without falling
+    /// back to any other. A `Custom` program may run without an entry.
+    fn spawn_configured_sidecar(
+        config: &SidecarConfig,
+        app_dir: &str,
+        app_data_dir: &str,
+        workspace: Option<&str>,
+    ) -> Result<(Child, SidecarRuntime, String), SidecarError> {
+        let candidates = Self::entry_candidates(config);
+        let entry = candidates
+            .iter()
+            .find(|candidate| candidate.is_file())
+            .cloned();
+        if entry.is_none() && !matches!(config.runtime, ConfiguredRuntime::Custom(_)) {
+            return Err(SidecarError::EntryMissing {
+                searched: candidates,
+            });
+        }
+        let working_dir = config
+            .working_dir
+            .clone()
+            .or_else(|| entry.as_deref().and_then(default_sidecar_working_dir))
+            .unwrap_or_else(|| std::path::PathBuf::from(app_dir));
+
+        let (mut command, runtime) =
+            configured_command(&config.runtime, entry.as_deref(), &working_dir)
+                .expect("spawn only configures non-auto runtimes");
+        info!(
+            "Launching sidecar with configured runtime {:?} in {}",
+            config.runtime,
+            working_dir.display()
+        );
+        Self::prepare_sidecar_command(&mut command, app_dir, app_data_dir, workspace);
+        apply_resource_limits(&mut command, &config.resource_limits);
+        let command_line = describe_command(&command);
+        let child = command.spawn().map_err(SidecarError::from)?;
+        Ok((child, runtime, command_line))
     }
 
-    fn spawn_development_sidecar(app_dir: &str, app_data_dir: &str) -> Result<Child, SidecarError> {
-        let sidecar_path = resolve_sidecar_entry_path().map_err(SidecarError::SendError)?;
-        let sidecar_dir = sidecar_path.parent().unwrap().parent().unwrap();
+    /// Pipes and environment every desktop-spawned sidecar gets
+    fn prepare_sidecar_command(
+        command: &mut Command,
+        app_dir: &str,
+        app_data_dir: &str,
+        workspace: Option<&str>,
+    ) {
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .env("COWORKANY_APP_DIR", app_dir)
+            .env("COWORKANY_APP_DATA_DIR", app_data_dir);
+        Self::apply_singleton_env(command, app_data_dir, workspace);
+        Self::apply_proxy_env(command, app_data_dir);
+        Self::apply_llm_env(command, app_data_dir);
+        Self::apply_rag_env(command, app_data_dir);
+        Self::apply_chat_runtime_env(command);
+    }
+
+    /// Start the development sidecar from the first existing entry in
+    /// `candidates`, in `working_dir` or else the entry's project directory,
+    /// under `limits`. Nothing is launched when no entry exists.
+    fn spawn_development_sidecar_from(
+        candidates: &[std::path::PathBuf],
+        working_dir: Option<&Path>,
+        limits: &ResourceLimits,
+        app_dir: &str,
+        app_data_dir: &str,
+        workspace: Option<&str>,
+    ) -> Result<(Child, SidecarRuntime, String), SidecarError> {
+        let sidecar_path = candidates
+            .iter()
+            .find(|candidate| candidate.is_file())
+            .cloned()
+            .ok_or_else(|| SidecarError::EntryMissing {
+                searched: candidates.to_vec(),
+            })?;
+        let sidecar_dir = working_dir
+            .map(Path::to_path_buf)
+            .or_else(|| default_sidecar_working_dir(&sidecar_path))
+            .unwrap_or_else(|| std::path::PathBuf::from(app_dir));
         let tsx_path = sidecar_dir.join("node_modules/tsx/dist/cli.mjs");
+        let entry = entry_argument(&sidecar_path, &sidecar_dir);
 
         info!(
             "Resolved development sidecar entry: {}",
@@ -1350,62 +2369,50 @@ impl SidecarManager {
         // Prefer Node/tsx in development. Bun has been observed to delay
         // stdin delivery for desktop-spawned sidecar IPC, which surfaces as
         // `timed out waiting on channel` on the desktop side.
-        if tsx_path.exists() {
-            let mut node_cmd = Command::new("node");
-            node_cmd
-                .current_dir(sidecar_dir)
-                .args([tsx_path.to_str().unwrap(), "src/main.ts"])
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .env("COWORKANY_APP_DIR", app_dir)
-                .env("COWORKANY_APP_DATA_DIR", app_data_dir);
-            Self::apply_singleton_env(&mut node_cmd, app_data_dir);
-            Self::apply_proxy_env(&mut node_cmd, app_data_dir);
-            Self::apply_llm_env(&mut node_cmd, app_data_dir);
-            Self::apply_chat_runtime_env(&mut node_cmd);
-
-            return node_cmd.spawn().map_err(SidecarError::from);
-        }
-
-        let cmd = if cfg!(target_os = "windows") {
-            "npx.cmd"
-        } else {
-            "npx"
-        };
-        let mut npx_cmd = Command::new(cmd);
-        npx_cmd
-            .current_dir(sidecar_dir)
-            .args(["tsx", "src/main.ts"])
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .env("COWORKANY_APP_DIR", app_dir)
-            .env("COWORKANY_APP_DATA_DIR", app_data_dir);
-        Self::apply_singleton_env(&mut npx_cmd, app_data_dir);
-        Self::apply_proxy_env(&mut npx_cmd, app_data_dir);
-        Self::apply_llm_env(&mut npx_cmd, app_data_dir);
-        Self::apply_chat_runtime_env(&mut npx_cmd);
+        let mut last_error = None;
+        for &runtime in development_runtimes(tsx_path.exists()) {
+            let mut command = match runtime {
+                SidecarRuntime::NodeTsx => {
+                    let mut command = Command::new("node");
+                    command.arg(&tsx_path).arg(&entry);
+                    command
+                }
+                SidecarRuntime::Npx => {
+                    let mut command = Command::new(if cfg!(target_os = "windows") {
+                        "npx.cmd"
+                    } else {
+                        "npx"
+                    });
+                    command.arg("tsx").arg(&entry);
+                    command
+                }
+                _ => {
+                    let mut command = Command::new("bun");
+                    command.arg("run").arg(&entry);
+                    command
+                }
+            };
+            command.current_dir(&sidecar_dir);
+            Self::prepare_sidecar_command(&mut command, app_dir, app_data_dir, workspace);
+            apply_resource_limits(&mut command, limits);
 
-        npx_cmd
-            .spawn()
-            .or_else(|_| {
-                let mut bun_cmd = Command::new("bun");
-                bun_cmd
-                    .current_dir(sidecar_dir)
-                    .args(["run", "src/main.ts"])
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .env("COWORKANY_APP_DIR", app_dir)
-                    .env("COWORKANY_APP_DATA_DIR", app_data_dir);
-                Self::apply_singleton_env(&mut bun_cmd, app_data_dir);
-                Self::apply_proxy_env(&mut bun_cmd, app_data_dir);
-                Self::apply_llm_env(&mut bun_cmd, app_data_dir);
-                Self::apply_chat_runtime_env(&mut bun_cmd);
-                bun_cmd.spawn()
-            })
-            .map_err(SidecarError::from)
+            let command_line = describe_command(&command);
+            match command.spawn() {
+                Ok(child) => return Ok((child, runtime, command_line)),
+                Err(error) => {
+                    warn!("Failed to start sidecar via {}: {}", command_line, error);
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(SidecarError::from(
+            last_error.expect("development_runtimes is never empty"),
+        ))
+    }
+
+    pub(crate) fn packaged_sidecar_available(app_handle: &AppHandle) -> bool {
+        Self::resolve_packaged_sidecar(app_handle).is_some()
     }
 
     fn resolve_packaged_sidecar(app_handle: &AppHandle) -> Option<PackagedSidecar> {
@@ -2129,9 +3136,9 @@ impl SidecarManager {
         Some((url, bypass))
     }
 
-    fn sidecar_singleton_socket_path(app_data_dir: &str) -> String {
+    fn sidecar_singleton_socket_path(singleton_key: &str) -> String {
         let mut hasher = DefaultHasher::new();
-        app_data_dir.hash(&mut hasher);
+        singleton_key.hash(&mut hasher);
         let fingerprint = hasher.finish();
 
         #[cfg(target_os = "windows")]
@@ -2148,11 +3155,41 @@ impl SidecarManager {
         }
     }
 
-    fn apply_singleton_env(command: &mut Command, app_data_dir: &str) {
-        let socket_path = Self::sidecar_singleton_socket_path(app_data_dir);
+    /// Key the singleton transport is derived from. Workspace sidecars get
+    /// their own key so each one listens on a separate socket.
+    fn singleton_key(app_data_dir: &str, workspace: Option<&str>) -> String {
+        match workspace {
+            Some(workspace) => format!("{app_data_dir}#workspace={workspace}"),
+            None => app_data_dir.to_string(),
+        }
+    }
+
+    fn apply_singleton_env(command: &mut Command, app_data_dir: &str, workspace: Option<&str>) {
+        let socket_path =
+            Self::sidecar_singleton_socket_path(&Self::singleton_key(app_data_dir, workspace));
         command
             .env("COWORKANY_SIDECAR_SINGLETON", "1")
             .env("COWORKANY_SIDECAR_SOCKET_PATH", socket_path);
+        if let Some(workspace) = workspace {
+            command.env("COWORKANY_WORKSPACE_PATH", workspace);
+        }
+    }
+
+    fn apply_rag_env(command: &mut Command, app_data_dir: &str) {
+        let paths = Self::rag_paths(Path::new(app_data_dir));
+        command
+            .env("VAULT_PATH", &paths.vault_path)
+            .env("CHROMA_PATH", &paths.chroma_path);
+    }
+
+    /// RAG paths the next spawn will pass to the sidecar.
+    pub fn rag_paths(app_data_dir: &Path) -> RagPaths {
+        let home_dir = dirs::home_dir().unwrap_or_else(|| app_data_dir.to_path_buf());
+        resolve_rag_paths(
+            &load_rag_path_settings(app_data_dir),
+            |key| std::env::var(key).ok(),
+            &home_dir,
+        )
     }
 
     fn apply_proxy_env(command: &mut Command, app_data_dir: &str) {
@@ -2223,6 +3260,8 @@ enum SidecarMessageKind {
     TaskEvent,
     CanonicalStreamEvent,
     IpcResponse,
+    /// Intermediate `command_progress` for a command still awaiting its response
+    CommandProgress,
     IpcCommand,
     VoiceState,
 }
@@ -2379,6 +3418,9 @@ fn classify_sidecar_message(message: &serde_json::Value) -> Option<SidecarMessag
     if msg_type == "canonical_message" || msg_type == "canonical_message_delta" {
         return Some(SidecarMessageKind::CanonicalStreamEvent);
     }
+    if msg_type == COMMAND_PROGRESS_TYPE {
+        return Some(SidecarMessageKind::CommandProgress);
+    }
     if msg_type.ends_with("_response") {
         return Some(SidecarMessageKind::IpcResponse);
     }
@@ -2420,6 +3462,8 @@ struct ProtocolDiffHunk {
     content: String,
     header: Option<String>,
     context: Option<String>,
+    #[serde(default)]
+    no_newline_at_eof: bool,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -2435,6 +3479,8 @@ struct ProtocolFilePatch {
     additions: usize,
     deletions: usize,
     description: Option<String>,
+    #[serde(default)]
+    line_ending: LineEnding,
 }
 
 fn convert_patch_operation(operation: ProtocolPatchOperation) -> PatchOperation {
@@ -2457,6 +3503,8 @@ fn convert_hunks(hunks: Vec<ProtocolDiffHunk>) -> Vec<DiffHunk> {
             content: hunk.content,
             header: hunk.header.unwrap_or_default(),
             context: hunk.context,
+            no_newline_at_eof: hunk.no_newline_at_eof,
+            line_segments: Vec::new(),
         })
         .collect()
 }
@@ -2473,14 +3521,78 @@ fn build_file_patch(patch: ProtocolFilePatch) -> FilePatch {
         additions: patch.additions,
         deletions: patch.deletions,
         description: patch.description,
+        line_ending: patch.line_ending,
+        truncated_context: false,
     }
 }
 
-fn apply_patch_to_content(original: &str, patch: FilePatch) -> Result<String, String> {
-    apply_patch_diff(original, &patch).map_err(|e| e.to_string())
+/// Apply hunks in memory. Large patches emit `apply-hunk-progress` after each
+/// hunk so the UI can show how far along the apply is.
+fn apply_patch_to_content(
+    app_handle: &AppHandle,
+    original: &str,
+    patch: FilePatch,
+) -> Result<String, String> {
+    if patch.hunks.len() < HUNK_PROGRESS_MIN_HUNKS {
+        return apply_patch_diff(original, &patch).map_err(|e| e.to_string());
+    }
+    apply_patch_with_progress(original, &patch, DEFAULT_HUNK_FUZZ, |progress| {
+        let _ = app_handle.emit(
+            "apply-hunk-progress",
+            json!({
+                "patchId": patch.id,
+                "filePath": patch.file_path,
+                "applied": progress.applied,
+                "total": progress.total,
+            }),
+        );
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Substitute approved `${secret:NAME}` placeholders in a shell effect's env
+/// with keychain values. Returns the env to spawn with and the injected values
+/// so they can be masked in the output sent back to the agent. Secrets not
+/// allowlisted need the grant of a confirmed request for this exact
+/// command, arguments and env, which this run uses up.
+async fn inject_shell_secrets(
+    app_handle: &AppHandle,
+    command: &str,
+    args: &[String],
+    env_vars: Vec<(String, String)>,
+) -> Result<(ResolvedEnv, Option<SecretGrant>), String> {
+    if env_vars
+        .iter()
+        .all(|(_, value)| secret_references(value).is_empty())
+    {
+        let resolved = ResolvedEnv {
+            env: env_vars,
+            secret_values: Vec::new(),
+        };
+        return Ok((resolved, None));
+    }
+
+    let state = app_handle.state::<PolicyEngineState>();
+    let mut engine = state.engine.lock().await;
+    let grant = engine.take_secret_grant(&shell_secret_grant_key(command, args, &env_vars));
+    let injected = resolve_secret_env(&env_vars, &mut |name| {
+        if !engine.secret_injection_allowed(name, grant.as_ref()) {
+            return Err(format!("secret_not_approved: {}", name));
+        }
+        keychain_secret(name)
+    })?;
+    info!(
+        "Injecting {} approved secret(s) into shell effect env",
+        injected.secret_values.len()
+    );
+    Ok((injected, grant))
 }
 
-fn build_effect_request_for_patch(operation: PatchOperation, path: &str) -> EffectRequest {
+fn build_effect_request_for_patch(
+    operation: PatchOperation,
+    path: &str,
+    workspace_root: Option<&Path>,
+) -> EffectRequest {
     EffectRequest {
         id: Uuid::new_v4().to_string(),
         timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
@@ -2495,6 +3607,7 @@ fn build_effect_request_for_patch(operation: PatchOperation, path: &str) -> Effe
                 PatchOperation::Rename => "rename".to_string(),
                 PatchOperation::Create => "create".to_string(),
                 PatchOperation::Modify => "modify".to_string(),
+                PatchOperation::Binary => "binary".to_string(),
             }),
             command: None,
             args: None,
@@ -2502,14 +3615,51 @@ fn build_effect_request_for_patch(operation: PatchOperation, path: &str) -> Effe
             url: None,
             method: None,
             headers: None,
+            env: None,
             description: Some(format!("patch {}", operation_as_str(&operation))),
         },
         context: Some(EffectContext {
             task_id: None,
-            tool_name: Some("apply_patch".to_string()),
-            reasoning: Some("Patch apply requires filesystem write".to_string()),
+            tool_name: Some("apply_patch".to_string()),
+            reasoning: Some("Patch apply requires filesystem write".to_string()),
+        }),
+        scope: Some(EffectScope {
+            workspace_paths: workspace_root.map(|root| vec![root.to_string_lossy().to_string()]),
+            ..Default::default()
+        }),
+    }
+}
+
+/// The shell effect being executed. `approved_request_id` is the request the
+/// user approved for it, so its `executed` audit event lines up with the
+/// request and confirmation; a fresh id is used when there is none.
+fn build_effect_request_for_shell(
+    command: &str,
+    args: &[String],
+    cwd: Option<&str>,
+    approved_request_id: Option<&str>,
+) -> EffectRequest {
+    EffectRequest {
+        id: approved_request_id
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        effect_type: EffectType::ShellWrite,
+        source: EffectSource::Agent,
+        source_id: None,
+        payload: EffectPayload {
+            command: Some(command.to_string()),
+            args: Some(args.to_vec()),
+            cwd: cwd.map(|dir| dir.to_string()),
+            description: Some(format!("exec {}", command)),
+            ..Default::default()
+        },
+        context: Some(EffectContext {
+            task_id: None,
+            tool_name: Some("exec_shell".to_string()),
+            reasoning: None,
         }),
-        scope: Some(EffectScope::default()),
+        scope: None,
     }
 }
 
@@ -2519,6 +3669,7 @@ fn operation_as_str(operation: &PatchOperation) -> &'static str {
         PatchOperation::Modify => "modify",
         PatchOperation::Delete => "delete",
         PatchOperation::Rename => "rename",
+        PatchOperation::Binary => "binary",
     }
 }
 
@@ -2669,19 +3820,29 @@ fn handle_sidecar_command(
 
                 let payload = message.get("payload").cloned().unwrap_or_default();
                 let patch_value = payload.get("patch").cloned().unwrap_or_default();
-                let patch: Option<ProtocolFilePatch> =
-                    serde_json::from_value(patch_value.clone()).ok();
+                // Either the structured hunk form or raw `diff -u` text
+                let patch = match patch_value.as_str() {
+                    Some(text) => parse_unified_diff(text).map_err(|e| e.to_string()),
+                    None => serde_json::from_value::<ProtocolFilePatch>(patch_value)
+                        .map(build_file_patch)
+                        .map_err(|_| "invalid_patch".to_string()),
+                };
 
-                let Some(patch) = patch else {
-                    send_raw(
-                        &command_writer,
-                        build_error_response(
-                            &command_id,
-                            "propose_patch_response",
-                            "invalid_patch",
-                        ),
-                    );
-                    return;
+                // Diff paths are workspace-relative; pin them under the root
+                let state = app_handle.state::<ShadowFsState>();
+                let patch = match patch {
+                    Ok(patch) => resolve_patch_paths(&state, patch).await,
+                    Err(err) => Err(err),
+                };
+                let patch = match patch {
+                    Ok(patch) => patch,
+                    Err(err) => {
+                        send_raw(
+                            &command_writer,
+                            build_error_response(&command_id, "propose_patch_response", &err),
+                        );
+                        return;
+                    }
                 };
 
                 let source_path = patch.file_path.clone();
@@ -2689,22 +3850,22 @@ fn handle_sidecar_command(
                     .new_file_path
                     .clone()
                     .unwrap_or_else(|| patch.file_path.clone());
-                let stage_path = if matches!(patch.operation, ProtocolPatchOperation::Rename) {
+                let stage_path = if patch.operation == PatchOperation::Rename {
                     source_path.clone()
                 } else {
                     target_path.clone()
                 };
 
-                let patch_override = build_file_patch(patch.clone());
+                let patch_override = patch.clone();
                 let content_result = if let Some(full_content) = patch.full_content.clone() {
                     Ok(full_content)
-                } else if matches!(patch.operation, ProtocolPatchOperation::Delete) {
+                } else if patch.operation == PatchOperation::Delete {
                     Ok(String::new())
-                } else if matches!(patch.operation, ProtocolPatchOperation::Rename) {
+                } else if patch.operation == PatchOperation::Rename {
                     fs::read_to_string(&source_path).map_err(|e| e.to_string())
                 } else {
                     let original_content = fs::read_to_string(&source_path).unwrap_or_default();
-                    apply_patch_to_content(&original_content, patch_override.clone())
+                    apply_patch_to_content(&app_handle, &original_content, patch_override.clone())
                 };
 
                 let content = match content_result {
@@ -2718,7 +3879,6 @@ fn handle_sidecar_command(
                     }
                 };
 
-                let state = app_handle.state::<ShadowFsState>();
                 let result = shadow_fs::stage_file_with_patch(
                     state,
                     stage_path,
@@ -2771,18 +3931,31 @@ fn handle_sidecar_command(
                 };
 
                 let shadow_state = app_handle.state::<ShadowFsState>();
-                let (patch_operation, patch_path) = {
+                let (patch_operation, patch_path, workspace_root) = {
                     let guard = shadow_state.lock().await;
                     let shadow_fs = guard.as_ref();
                     let entry = shadow_fs.and_then(|fs| fs.get(patch_id));
                     let operation = entry.and_then(|e| e.patch.as_ref()).map(|p| p.operation);
                     let path = entry.map(|e| e.original_path.to_string_lossy().to_string());
-                    (operation, path)
+                    let root = shadow_fs.map(|fs| fs.workspace_root().to_path_buf());
+                    (operation, path, root)
                 };
 
-                if let (Some(operation), Some(path)) = (patch_operation, patch_path.clone()) {
+                let effect_request =
+                    patch_operation
+                        .zip(patch_path.as_deref())
+                        .map(|(operation, path)| {
+                            build_effect_request_for_patch(
+                                operation,
+                                path,
+                                workspace_root.as_deref(),
+                            )
+                        });
+
+                if let (Some(operation), Some(path), Some(request)) =
+                    (patch_operation, patch_path.clone(), effect_request.clone())
+                {
                     if matches!(operation, PatchOperation::Delete | PatchOperation::Rename) {
-                        let request = build_effect_request_for_patch(operation, &path);
                         let state = app_handle.state::<PolicyEngineState>();
                         let policy = policy_commands::request_effect(request, state).await;
 
@@ -2806,25 +3979,21 @@ fn handle_sidecar_command(
                     }
                 }
 
-                let result =
-                    shadow_fs::apply_patch(shadow_state, patch_id.to_string(), create_backup).await;
-
-                let response_msg = match &result {
-                    Ok(apply_result) => json!({
-                        "type": "apply_patch_response",
-                        "commandId": command_id,
-                        "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
-                        "payload": {
-                            "patchId": patch_id,
-                            "success": apply_result.success,
-                            "filePath": apply_result.file_path,
-                            "appliedAt": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
-                            "backupPath": apply_result.backup_path,
-                            "error": apply_result.error,
-                        }
-                    }),
-                    Err(err) => build_error_response(&command_id, "apply_patch_response", err),
-                };
+                let policy_state = app_handle.state::<PolicyEngineState>();
+                let (result, conflict) = apply_and_record_execution(
+                    &shadow_state,
+                    &policy_state,
+                    effect_request.as_ref(),
+                    patch_id,
+                    create_backup,
+                )
+                .await;
+                if let Some(conflict) = &conflict {
+                    warn!("Patch {} conflicts with {}", patch_id, conflict.file_path);
+                    let _ = app_handle.emit("patch-conflict", conflict);
+                }
+                let response_msg =
+                    build_apply_patch_response(&command_id, patch_id, &result, conflict.as_ref());
 
                 if let Ok(apply_result) = &result {
                     if apply_result.success {
@@ -2833,6 +4002,7 @@ fn handle_sidecar_command(
                             Some(PatchOperation::Rename) => "rename",
                             Some(PatchOperation::Create) => "create",
                             Some(PatchOperation::Modify) => "modify",
+                            Some(PatchOperation::Binary) => "binary",
                             None => "apply",
                         };
 
@@ -3063,6 +4233,39 @@ fn handle_sidecar_command(
                     return;
                 }
 
+                let effect_request_id = payload
+                    .get("effectRequestId")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string());
+
+                let (
+                    ResolvedEnv {
+                        env: env_vars,
+                        secret_values,
+                    },
+                    grant,
+                ) = match inject_shell_secrets(&app_handle, &command, &args, env_vars).await {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        send_raw(
+                            &command_writer,
+                            build_error_response(&command_id, "exec_shell_response", &err),
+                        );
+                        return;
+                    }
+                };
+
+                let effect_request = build_effect_request_for_shell(
+                    &command,
+                    &args,
+                    cwd.as_deref(),
+                    effect_request_id
+                        .as_deref()
+                        .or(grant.as_ref().map(|grant| grant.request_id.as_str())),
+                );
+                let policy_state = app_handle.state::<PolicyEngineState>();
+                let started = Instant::now();
+
                 let mut process = TokioCommand::new(&command);
                 process.args(args);
                 process.stdin(Stdio::piped());
@@ -3079,6 +4282,12 @@ fn handle_sidecar_command(
                 let mut child = match process.spawn() {
                     Ok(child) => child,
                     Err(err) => {
+                        policy_state
+                            .record_execution(
+                                &effect_request,
+                                &ExecutionResult::finished(started, Some(err.to_string())),
+                            )
+                            .await;
                         send_raw(
                             &command_writer,
                             build_error_response(
@@ -3094,6 +4303,12 @@ fn handle_sidecar_command(
                 if let Some(input) = stdin {
                     if let Some(mut stdin_pipe) = child.stdin.take() {
                         if let Err(err) = stdin_pipe.write_all(input.as_bytes()).await {
+                            policy_state
+                                .record_execution(
+                                    &effect_request,
+                                    &ExecutionResult::finished(started, Some(err.to_string())),
+                                )
+                                .await;
                             send_raw(
                                 &command_writer,
                                 build_error_response(
@@ -3113,6 +4328,19 @@ fn handle_sidecar_command(
                 )
                 .await;
 
+                let error = match &output {
+                    Ok(Ok(output)) if output.status.success() => None,
+                    Ok(Ok(output)) => Some(match output.status.code() {
+                        Some(code) => format!("exited with code {}", code),
+                        None => "terminated by signal".to_string(),
+                    }),
+                    Ok(Err(err)) => Some(err.to_string()),
+                    Err(_) => Some("command_timeout".to_string()),
+                };
+                policy_state
+                    .record_execution(&effect_request, &ExecutionResult::finished(started, error))
+                    .await;
+
                 let response_msg = match output {
                     Ok(Ok(output)) => json!({
                         "type": "exec_shell_response",
@@ -3121,8 +4349,8 @@ fn handle_sidecar_command(
                         "payload": {
                             "success": output.status.success(),
                             "exitCode": output.status.code(),
-                            "stdout": String::from_utf8_lossy(&output.stdout).to_string(),
-                            "stderr": String::from_utf8_lossy(&output.stderr).to_string(),
+                            "stdout": redact_secret_values(&String::from_utf8_lossy(&output.stdout), &secret_values),
+                            "stderr": redact_secret_values(&String::from_utf8_lossy(&output.stderr), &secret_values),
                             "timedOut": false,
                         }
                     }),
@@ -3415,104 +4643,672 @@ fn send_raw(command_writer: &SharedCommandWriter, message: serde_json::Value) {
             );
         }
     }
-}
+}
+
+/// Apply `patch_id`, keeping the details of a conflict so it can be reported
+/// apart from other failures. Agent applies never merge: conflict markers go
+/// in front of the user only through the UI's apply, so a changed original is
+/// reported back for the agent to restage instead.
+fn apply_reporting_conflict(
+    shadow_fs: &mut ShadowFs,
+    patch_id: &str,
+    create_backup: bool,
+) -> (Result<ApplyResult, String>, Option<PatchConflict>) {
+    match shadow_fs.apply_with_strategy(patch_id, create_backup, ConflictStrategy::Skip) {
+        Ok(result) => (Ok(result), None),
+        Err(ShadowFsError::Conflict {
+            expected_hash,
+            actual_hash,
+        }) => {
+            let conflict = shadow_fs
+                .patch_conflict(patch_id, &expected_hash, &actual_hash)
+                .ok();
+            let message = ShadowFsError::Conflict {
+                expected_hash,
+                actual_hash,
+            }
+            .to_string();
+            (Err(message), conflict)
+        }
+        Err(err) => (Err(err.to_string()), None),
+    }
+}
+
+/// Rewrite a proposed patch's paths to absolute ones inside the ShadowFs
+/// workspace, refusing any that would escape it.
+async fn resolve_patch_paths(
+    shadow_state: &ShadowFsState,
+    mut patch: FilePatch,
+) -> Result<FilePatch, String> {
+    let guard = shadow_state.lock().await;
+    let shadow_fs = guard
+        .as_ref()
+        .ok_or_else(|| ShadowFsError::NotInitialized.to_string())?;
+    let resolve = |path: &str| {
+        shadow_fs
+            .resolve_in_workspace(path)
+            .map(|p| p.to_string_lossy().to_string())
+            .map_err(|e| e.to_string())
+    };
+    patch.file_path = resolve(&patch.file_path)?;
+    if let Some(new_path) = patch.new_file_path.as_deref() {
+        patch.new_file_path = Some(resolve(new_path)?);
+    }
+    Ok(patch)
+}
+
+/// Apply a staged patch and, when it was requested as an effect, record the
+/// outcome against that request so the audit trail runs through to execution.
+async fn apply_and_record_execution(
+    shadow_state: &ShadowFsState,
+    policy: &PolicyEngineState,
+    request: Option<&EffectRequest>,
+    patch_id: &str,
+    create_backup: bool,
+) -> (Result<ApplyResult, String>, Option<PatchConflict>) {
+    let started = Instant::now();
+    let (result, conflict) = match shadow_state.lock().await.as_mut() {
+        Some(shadow_fs) => apply_reporting_conflict(shadow_fs, patch_id, create_backup),
+        None => (Err(ShadowFsError::NotInitialized.to_string()), None),
+    };
+    if let Some(request) = request {
+        let error = match &result {
+            Ok(applied) if applied.success => None,
+            Ok(applied) => Some(
+                applied
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "apply_failed".to_string()),
+            ),
+            Err(err) => Some(err.clone()),
+        };
+        policy
+            .record_execution(request, &ExecutionResult::finished(started, error))
+            .await;
+    }
+    (result, conflict)
+}
+
+fn build_apply_patch_response(
+    command_id: &str,
+    patch_id: &str,
+    result: &Result<ApplyResult, String>,
+    conflict: Option<&PatchConflict>,
+) -> serde_json::Value {
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    match (result, conflict) {
+        (Ok(apply_result), _) => json!({
+            "type": "apply_patch_response",
+            "commandId": command_id,
+            "timestamp": timestamp,
+            "payload": {
+                "patchId": patch_id,
+                "success": apply_result.success,
+                "filePath": apply_result.file_path,
+                "appliedAt": timestamp,
+                "backupPath": apply_result.backup_path,
+                "error": apply_result.error,
+            }
+        }),
+        (Err(err), Some(conflict)) => json!({
+            "type": "apply_patch_response",
+            "commandId": command_id,
+            "timestamp": timestamp,
+            "payload": {
+                "patchId": patch_id,
+                "success": false,
+                "filePath": conflict.file_path,
+                "error": err,
+                "conflict": true,
+                "expectedHash": conflict.expected_hash,
+                "actualHash": conflict.actual_hash,
+            }
+        }),
+        (Err(err), None) => build_error_response(command_id, "apply_patch_response", err),
+    }
+}
+
+fn build_error_response(command_id: &str, response_type: &str, error: &str) -> serde_json::Value {
+    json!({
+        "type": response_type,
+        "commandId": command_id,
+        "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "payload": {
+            "success": false,
+            "error": error
+        }
+    })
+}
+
+/// The error a `transport_error_response` from `fail_pending_responses`
+/// stands for; `None` for any other response.
+pub fn transport_error(response: &serde_json::Value) -> Option<SidecarError> {
+    if response.get("type").and_then(|value| value.as_str()) != Some("transport_error_response") {
+        return None;
+    }
+    let payload = response.get("payload");
+    let field = |name: &str| {
+        payload
+            .and_then(|payload| payload.get(name))
+            .and_then(|value| value.as_str())
+    };
+    let details = field("details")
+        .or_else(|| field("error"))
+        .unwrap_or("sidecar transport failed")
+        .to_string();
+    Some(match field("error") {
+        Some("sidecar_disconnected" | "sidecar_shutdown") => SidecarError::Disconnected(details),
+        _ => SidecarError::SendError(details),
+    })
+}
+
+/// Message type a long-running command sends, under its `commandId`, to
+/// report progress before its terminal `_response`
+pub const COMMAND_PROGRESS_TYPE: &str = "command_progress";
+
+/// Hand a `command_progress` message to the streaming waiter of its command.
+/// Progress for a command nobody streams is dropped.
+fn deliver_command_progress(
+    progress_subscribers: &Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+    message: &serde_json::Value,
+) {
+    let Some(command_id) = message.get("commandId").and_then(|v| v.as_str()) else {
+        return;
+    };
+    if let Ok(subscribers) = progress_subscribers.lock() {
+        if let Some(subscriber) = subscribers.get(command_id) {
+            let _ = subscriber.send(message.clone());
+        }
+    }
+}
+
+/// Resolve the waiter of a response's command and end its progress stream
+fn deliver_command_response(
+    pending_responses: &Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+    progress_subscribers: &Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+    message: &serde_json::Value,
+) {
+    let Some(command_id) = message.get("commandId").and_then(|v| v.as_str()) else {
+        return;
+    };
+    if let Ok(mut subscribers) = progress_subscribers.lock() {
+        subscribers.remove(command_id);
+    }
+    if let Ok(mut pending) = pending_responses.lock() {
+        if let Some(waiter) = pending.remove(command_id) {
+            let _ = waiter.send(message.clone());
+        }
+    }
+}
+
+/// Wait on a `send_command_streaming` receiver for the terminal response,
+/// passing each `command_progress` message to `on_progress` as it arrives.
+/// `idle_timeout` restarts with every progress message, so a command that
+/// keeps reporting is not cut off however long it runs.
+pub fn recv_streaming(
+    receiver: &Receiver<serde_json::Value>,
+    idle_timeout: Duration,
+    mut on_progress: impl FnMut(&serde_json::Value),
+) -> Result<serde_json::Value, mpsc::RecvTimeoutError> {
+    loop {
+        let message = receiver.recv_timeout(idle_timeout)?;
+        if message.get("type").and_then(|v| v.as_str()) == Some(COMMAND_PROGRESS_TYPE) {
+            on_progress(&message);
+            continue;
+        }
+        return Ok(message);
+    }
+}
+
+fn fail_pending_responses(
+    pending_responses: &Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
+    error_code: &str,
+    error_message: &str,
+) {
+    let pending = match pending_responses.lock() {
+        Ok(mut guard) => std::mem::take(&mut *guard),
+        Err(error) => {
+            error!("Failed to lock pending sidecar responses: {}", error);
+            return;
+        }
+    };
+
+    for (command_id, waiter) in pending {
+        let _ = waiter.send(json!({
+            "type": "transport_error_response",
+            "commandId": command_id,
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "payload": {
+                "success": false,
+                "error": error_code,
+                "details": error_message,
+            }
+        }));
+    }
+}
+
+impl Default for SidecarManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Deliver a confirmed effect response. The request may have come from any
+/// sidecar, so every live one receives it; the others ignore unknown ids.
+pub fn forward_effect_response_to_sidecar(
+    state: &SidecarState,
+    response: &EffectResponse,
+) -> Result<(), String> {
+    let command = json!({
+        "id": Uuid::new_v4().to_string(),
+        "type": "request_effect_response",
+        "commandId": Uuid::new_v4().to_string(),
+        "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "payload": {
+            "response": response
+        }
+    });
+
+    let result = state
+        .manager
+        .lock()
+        .map_err(|e| format!("failed to lock sidecar state: {}", e))?
+        .send_raw_command(command.clone())
+        .map_err(|e| e.to_string());
+
+    let workspace_managers = match state.workspaces.lock() {
+        Ok(workspaces) => workspaces.managers(),
+        Err(_) => Vec::new(),
+    };
+    for manager in workspace_managers {
+        if let Ok(manager) = manager.lock() {
+            if manager.transport_healthy.load(Ordering::SeqCst) {
+                let _ = manager.send_raw_command(command.clone());
+            }
+        }
+    }
+    result
+}
+
+impl Drop for SidecarManager {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// ============================================================================
+// Thread-safe wrapper for Tauri state
+// ============================================================================
+
+/// The default sidecar plus any dedicated per-workspace sidecars.
+pub struct SidecarState {
+    /// Sidecar for the primary workspace and anything not routed elsewhere
+    pub manager: Arc<Mutex<SidecarManager>>,
+    /// Dedicated sidecars of further workspaces
+    pub workspaces: Arc<Mutex<WorkspaceSidecars>>,
+}
+
+impl SidecarState {
+    pub fn new() -> Self {
+        Self {
+            manager: Arc::new(Mutex::new(SidecarManager::new())),
+            workspaces: Arc::new(Mutex::new(WorkspaceSidecars::default())),
+        }
+    }
+
+    pub fn default_route(&self) -> SidecarRoute {
+        SidecarRoute::default_sidecar(&self.manager)
+    }
+
+    /// Sidecar that should run tasks for `workspace_path`.
+    pub fn route_workspace(&self, workspace_path: &str) -> Result<SidecarRoute, String> {
+        let mut workspaces = self.workspaces.lock().map_err(|e| e.to_string())?;
+        Ok(workspaces
+            .route(workspace_path)?
+            .unwrap_or_else(|| SidecarRoute::default_sidecar(&self.manager)))
+    }
+
+    /// Sidecar that owns `task_id`; tasks that were never routed to a
+    /// workspace sidecar belong to the default one.
+    pub fn route_task(&self, task_id: &str) -> SidecarRoute {
+        self.workspaces
+            .lock()
+            .ok()
+            .and_then(|workspaces| workspaces.route_task(task_id))
+            .unwrap_or_else(|| SidecarRoute::default_sidecar(&self.manager))
+    }
+}
+
+/// Upper bound on dedicated workspace sidecars running at the same time.
+pub const MAX_WORKSPACE_SIDECARS: usize = 4;
+
+/// Restarts the watchdog attempts for a workspace sidecar before giving up.
+const MAX_WORKSPACE_SIDECAR_RESTARTS: u32 = 3;
+
+/// How long a restarted workspace sidecar must stay up before its restart
+/// budget is refilled.
+const WORKSPACE_SIDECAR_STABLE_AFTER: Duration = Duration::from_secs(120);
+
+/// What one watchdog pass found among the workspace sidecars
+#[derive(Default)]
+pub struct CrashedSidecars {
+    /// Sidecars to respawn now
+    pub restart: Vec<(String, Arc<Mutex<SidecarManager>>, WatchdogState)>,
+    /// Sidecars that just used up their restart budget and are given up on
+    pub failed: Vec<(String, Arc<Mutex<SidecarManager>>)>,
+}
+
+/// Where a task-scoped command should go.
+#[derive(Clone)]
+pub struct SidecarRoute {
+    pub manager: Arc<Mutex<SidecarManager>>,
+    /// Watchdog of a workspace sidecar; `None` for the default sidecar,
+    /// which is covered by the app-wide [`WatchdogState`]
+    pub watchdog: Option<WatchdogState>,
+    pub workspace: Option<String>,
+}
+
+impl SidecarRoute {
+    fn default_sidecar(manager: &Arc<Mutex<SidecarManager>>) -> Self {
+        Self {
+            manager: manager.clone(),
+            watchdog: None,
+            workspace: None,
+        }
+    }
+}
+
+struct WorkspaceSidecar {
+    manager: Arc<Mutex<SidecarManager>>,
+    watchdog: WatchdogState,
+    restarts: RestartBudget,
+}
+
+/// Watchdog restarts of one workspace sidecar. Each restart waits twice as
+/// long after the previous one (2s, 4s, ...); the count only resets once the
+/// sidecar has stayed up for [`WORKSPACE_SIDECAR_STABLE_AFTER`].
+#[derive(Debug, Default)]
+struct RestartBudget {
+    attempts: u32,
+    last_restart: Option<Instant>,
+}
+
+impl RestartBudget {
+    /// The sidecar was seen running at `now`
+    fn running(&mut self, now: Instant) {
+        let stable = self
+            .last_restart
+            .is_some_and(|at| now.saturating_duration_since(at) >= WORKSPACE_SIDECAR_STABLE_AFTER);
+        if stable {
+            *self = Self::default();
+        }
+    }
+
+    fn exhausted(&self) -> bool {
+        self.attempts >= MAX_WORKSPACE_SIDECAR_RESTARTS
+    }
+
+    /// Whether the backoff since the last restart is over at `now`; if so
+    /// the restart is counted.
+    fn try_restart(&mut self, now: Instant) -> bool {
+        if let Some(at) = self.last_restart {
+            let backoff = Duration::from_secs(1 << self.attempts);
+            if now.saturating_duration_since(at) < backoff {
+                return false;
+            }
+        }
+        self.attempts += 1;
+        self.last_restart = Some(now);
+        true
+    }
+}
+
+/// Per-workspace sidecars. The first workspace that starts a task keeps
+/// using the default sidecar; each further workspace gets a dedicated one,
+/// up to [`MAX_WORKSPACE_SIDECARS`], so task state and config do not bleed
+/// between workspaces.
+#[derive(Default)]
+pub struct WorkspaceSidecars {
+    primary: Option<String>,
+    sidecars: HashMap<String, WorkspaceSidecar>,
+    task_workspaces: HashMap<String, String>,
+}
+
+impl WorkspaceSidecars {
+    /// Normalized map key for a workspace path.
+    fn workspace_key(workspace_path: &str) -> String {
+        let trimmed = workspace_path.trim();
+        let stripped = trimmed.trim_end_matches(['/', '\\']);
+        if stripped.is_empty() {
+            trimmed.to_string()
+        } else {
+            stripped.to_string()
+        }
+    }
+
+    /// `None` routes to the default sidecar. A new dedicated manager is
+    /// created (but not spawned) the first time a secondary workspace is
+    /// seen.
+    pub fn route(&mut self, workspace_path: &str) -> Result<Option<SidecarRoute>, String> {
+        let key = Self::workspace_key(workspace_path);
+        if key.is_empty() {
+            return Ok(None);
+        }
+        let primary = self.primary.get_or_insert_with(|| key.clone());
+        if *primary == key {
+            return Ok(None);
+        }
+
+        if !self.sidecars.contains_key(&key) {
+            if self.sidecars.len() >= MAX_WORKSPACE_SIDECARS {
+                return Err(format!(
+                    "Too many active workspaces: at most {} additional workspace sidecars can run at once",
+                    MAX_WORKSPACE_SIDECARS
+                ));
+            }
+            info!("Creating dedicated sidecar for workspace {}", key);
+            self.sidecars.insert(
+                key.clone(),
+                WorkspaceSidecar {
+                    manager: Arc::new(Mutex::new(SidecarManager::for_workspace(&key))),
+                    watchdog: WatchdogState::new(true),
+                    restarts: RestartBudget::default(),
+                },
+            );
+        }
+
+        let sidecar = &self.sidecars[&key];
+        Ok(Some(SidecarRoute {
+            manager: sidecar.manager.clone(),
+            watchdog: Some(sidecar.watchdog.clone()),
+            workspace: Some(key),
+        }))
+    }
+
+    /// Remember which workspace sidecar a task was started on.
+    pub fn bind_task(&mut self, task_id: &str, workspace_path: &str) {
+        let key = Self::workspace_key(workspace_path);
+        if self.sidecars.contains_key(&key) {
+            self.task_workspaces.insert(task_id.to_string(), key);
+        }
+    }
+
+    pub fn route_task(&self, task_id: &str) -> Option<SidecarRoute> {
+        let key = self.task_workspaces.get(task_id)?;
+        let sidecar = self.sidecars.get(key)?;
+        Some(SidecarRoute {
+            manager: sidecar.manager.clone(),
+            watchdog: Some(sidecar.watchdog.clone()),
+            workspace: Some(key.clone()),
+        })
+    }
+
+    pub fn managers(&self) -> Vec<Arc<Mutex<SidecarManager>>> {
+        self.sidecars
+            .values()
+            .map(|sidecar| sidecar.manager.clone())
+            .collect()
+    }
+
+    /// Workspaces that currently have a dedicated sidecar.
+    pub fn workspaces(&self) -> Vec<String> {
+        let mut workspaces: Vec<String> = self.sidecars.keys().cloned().collect();
+        workspaces.sort();
+        workspaces
+    }
+
+    /// Shut down and forget one workspace's sidecar. Its tasks fall back to
+    /// the default sidecar.
+    pub fn shutdown_workspace(&mut self, workspace_path: &str) -> bool {
+        let key = Self::workspace_key(workspace_path);
+        let Some(sidecar) = self.sidecars.remove(&key) else {
+            return false;
+        };
+        sidecar.watchdog.stop();
+        if let Ok(mut manager) = sidecar.manager.lock() {
+            manager.shutdown();
+        }
+        self.task_workspaces
+            .retain(|_, workspace| *workspace != key);
+        true
+    }
 
-fn build_error_response(command_id: &str, response_type: &str, error: &str) -> serde_json::Value {
-    json!({
-        "type": response_type,
-        "commandId": command_id,
-        "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
-        "payload": {
-            "success": false,
-            "error": error
+    pub fn shutdown_all(&mut self) {
+        for workspace in self.workspaces() {
+            self.shutdown_workspace(&workspace);
         }
-    })
-}
+    }
 
-fn fail_pending_responses(
-    pending_responses: &Arc<Mutex<HashMap<String, Sender<serde_json::Value>>>>,
-    error_code: &str,
-    error_message: &str,
-) {
-    let pending = match pending_responses.lock() {
-        Ok(mut guard) => std::mem::take(&mut *guard),
-        Err(error) => {
-            error!("Failed to lock pending sidecar responses: {}", error);
-            return;
-        }
-    };
+    /// Workspace sidecars the watchdog should respawn. Each sidecar has its
+    /// own suppression flag and restart budget; one that keeps crashing is
+    /// suppressed without affecting the others and reported as failed once.
+    pub fn crashed(&mut self) -> CrashedSidecars {
+        self.crashed_at(Instant::now())
+    }
 
-    for (command_id, waiter) in pending {
-        let _ = waiter.send(json!({
-            "type": "transport_error_response",
-            "commandId": command_id,
-            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
-            "payload": {
-                "success": false,
-                "error": error_code,
-                "details": error_message,
+    fn crashed_at(&mut self, now: Instant) -> CrashedSidecars {
+        let mut crashed = CrashedSidecars::default();
+        for (workspace, sidecar) in self.sidecars.iter_mut() {
+            let running = match sidecar.manager.try_lock() {
+                Ok(mut manager) => manager.is_running(),
+                // Busy with a command or spawn; check again next tick.
+                Err(_) => continue,
+            };
+            if running {
+                sidecar.restarts.running(now);
+                continue;
             }
-        }));
+            if !sidecar.watchdog.should_restart(running) {
+                continue;
+            }
+            if sidecar.restarts.exhausted() {
+                error!(
+                    "Sidecar watchdog: workspace {} exceeded {} restarts, giving up",
+                    workspace, MAX_WORKSPACE_SIDECAR_RESTARTS
+                );
+                sidecar.watchdog.suppress();
+                crashed
+                    .failed
+                    .push((workspace.clone(), sidecar.manager.clone()));
+                continue;
+            }
+            if !sidecar.restarts.try_restart(now) {
+                continue;
+            }
+            crashed.restart.push((
+                workspace.clone(),
+                sidecar.manager.clone(),
+                sidecar.watchdog.clone(),
+            ));
+        }
+        crashed
     }
 }
 
-impl Default for SidecarManager {
+impl Default for SidecarState {
     fn default() -> Self {
         Self::new()
     }
 }
 
-pub fn forward_effect_response_to_sidecar(
-    state: &SidecarState,
-    response: &EffectResponse,
-) -> Result<(), String> {
-    let manager = state
-        .0
-        .lock()
-        .map_err(|e| format!("failed to lock sidecar state: {}", e))?;
-
-    manager
-        .send_raw_command(json!({
-            "id": Uuid::new_v4().to_string(),
-            "type": "request_effect_response",
-            "commandId": Uuid::new_v4().to_string(),
-            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
-            "payload": {
-                "response": response
-            }
-        }))
-        .map_err(|e| e.to_string())
+/// Controls for the sidecar watchdog thread. `suppressed` keeps it from
+/// restarting a sidecar the user shut down (or, in lazy-spawn mode, one that
+/// was never started) until the next explicit spawn; `stopped` ends the loop
+/// when the app exits.
+#[derive(Clone, Default)]
+pub struct WatchdogState {
+    suppressed: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
 }
 
-impl Drop for SidecarManager {
-    fn drop(&mut self) {
-        self.shutdown();
+impl WatchdogState {
+    /// In lazy-spawn mode the watchdog starts suppressed so it does not start
+    /// the sidecar before anything asks for it.
+    pub fn new(lazy_spawn: bool) -> Self {
+        Self {
+            suppressed: Arc::new(AtomicBool::new(lazy_spawn)),
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
     }
-}
 
-// ============================================================================
-// Thread-safe wrapper for Tauri state
-// ============================================================================
+    /// Hold off auto-restart, e.g. after an intentional shutdown
+    pub fn suppress(&self) {
+        self.suppressed.store(true, Ordering::SeqCst);
+    }
 
-pub struct SidecarState(pub Arc<Mutex<SidecarManager>>);
+    /// Re-enable auto-restart after an explicit spawn
+    pub fn resume(&self) {
+        self.suppressed.store(false, Ordering::SeqCst);
+    }
 
-impl SidecarState {
-    pub fn new() -> Self {
-        Self(Arc::new(Mutex::new(SidecarManager::new())))
+    pub fn is_suppressed(&self) -> bool {
+        self.suppressed.load(Ordering::SeqCst)
     }
-}
 
-impl Default for SidecarState {
-    fn default() -> Self {
-        Self::new()
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Whether a sidecar found not running should be restarted
+    pub fn should_restart(&self, running: bool) -> bool {
+        !running && !self.is_suppressed() && !self.is_stopped()
+    }
+
+    /// Sleep for `duration` in short slices, returning early (with `false`)
+    /// once the watchdog is stopped.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while !self.is_stopped() {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            thread::sleep((deadline - now).min(Duration::from_millis(250)));
+        }
+        false
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        classify_sidecar_message, extract_stream_delta_log_entry, is_json_object_line,
-        is_sidecar_metrics_line, truncate_log_line, SidecarManager, SidecarMessageKind,
+        apply_and_record_execution, apply_reporting_conflict, build_apply_patch_response,
+        build_effect_request_for_patch, build_effect_request_for_shell, chrono_now,
+        classify_sidecar_message, configured_command, default_sidecar_working_dir,
+        deliver_command_progress, deliver_command_response, describe_command, development_runtimes,
+        ensure_writable_dir, extract_stream_delta_log_entry, is_json_object_line,
+        is_sidecar_metrics_line, load_rag_path_settings, packaged_runtime, parse_protocol_line,
+        protocol_versions_compatible, push_stderr_backlog, recv_streaming, resolve_rag_paths,
+        resolve_sidecar_config, transport_error, truncate_log_line, CommandWriter,
+        ConfiguredRuntime, ProtocolDiagnostics, ProtocolHandshake, ProtocolIssueKind,
+        RagPathSettings, RestartBudget, SidecarConfig, SidecarError, SidecarManager,
+        SidecarMessageKind, SidecarRuntime, SidecarState, SidecarStderrLine, StderrSeverity,
+        WatchdogState, WorkspaceSidecars, DESKTOP_PROTOCOL_VERSION, MAX_WORKSPACE_SIDECARS,
+        PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT, STDERR_BACKLOG_LIMIT, WORKSPACE_SIDECAR_STABLE_AFTER,
     };
     use serde_json::json;
     use std::collections::HashMap;
@@ -3521,11 +5317,11 @@ mod tests {
     use std::io::{BufRead, BufReader};
     #[cfg(unix)]
     use std::os::unix::net::UnixListener;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::process::Command;
-    use std::sync::{LazyLock, Mutex};
+    use std::sync::{Arc, LazyLock, Mutex};
     use std::thread;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
     static ENV_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
@@ -4336,4 +6132,710 @@ mod tests {
             "Unhandled panic: failed to bind rpc socket"
         ));
     }
+
+    #[test]
+    fn sidecar_config_prefers_settings_then_env_and_builds_the_configured_command() {
+        let env = |key: &str| match key {
+            "COWORKANY_SIDECAR_RUNTIME" => Some("custom:/opt/coworkany/sidecar".to_string()),
+            "COWORKANY_SIDECAR_WORKDIR" => Some("/srv/sidecar".to_string()),
+            _ => None,
+        };
+        let from_env = resolve_sidecar_config(&SidecarConfig::default(), env);
+        assert_eq!(
+            from_env.runtime,
+            ConfiguredRuntime::Custom("/opt/coworkany/sidecar".to_string())
+        );
+        assert_eq!(from_env.working_dir, Some(PathBuf::from("/srv/sidecar")));
+        assert_eq!(from_env.entry_path, None);
+
+        let stored: SidecarConfig = serde_json::from_value(json!({
+            "runtime": "deno",
+            "entryPath": "/work/sidecar/src/main.ts",
+            "resourceLimits": { "maxCpuSecs": 600 }
+        }))
+        .unwrap();
+        let resolved = resolve_sidecar_config(&stored, env);
+        assert_eq!(resolved.runtime, ConfiguredRuntime::Deno);
+        assert_eq!(
+            resolved.resource_limits,
+            ResourceLimits {
+                max_memory_bytes: None,
+                max_cpu_secs: Some(600),
+            }
+        );
+        assert_eq!(resolved.working_dir, Some(PathBuf::from("/srv/sidecar")));
+        assert_eq!(
+            resolve_sidecar_config(&SidecarConfig::default(), |_| None),
+            SidecarConfig::default()
+        );
+
+        let entry = PathBuf::from("/work/sidecar/src/main.ts");
+        let working_dir = default_sidecar_working_dir(&entry).unwrap();
+        assert_eq!(working_dir, PathBuf::from("/work/sidecar"));
+        let (command, runtime) =
+            configured_command(&ConfiguredRuntime::Deno, Some(&entry), &working_dir).unwrap();
+        assert_eq!(runtime, SidecarRuntime::Deno);
+        assert_eq!(describe_command(&command), "deno run -A src/main.ts");
+        let (command, _) = configured_command(
+            &ConfiguredRuntime::Bun,
+            Some(&entry),
+            Path::new("/elsewhere"),
+        )
+        .unwrap();
+        assert_eq!(
+            describe_command(&command),
+            "bun run /work/sidecar/src/main.ts"
+        );
+        let (command, runtime) = configured_command(
+            &ConfiguredRuntime::Custom("/opt/coworkany/sidecar".to_string()),
+            None,
+            Path::new("/opt/coworkany"),
+        )
+        .unwrap();
+        assert_eq!(runtime, SidecarRuntime::Custom);
+        assert_eq!(describe_command(&command), "/opt/coworkany/sidecar");
+        assert!(configured_command(&ConfiguredRuntime::Auto, Some(&entry), &working_dir).is_none());
+    }
+
+    #[test]
+    fn launched_runtime_follows_available_binaries() {
+        assert_eq!(packaged_runtime(true, true), SidecarRuntime::PackagedNode);
+        assert_eq!(
+            packaged_runtime(true, false),
+            SidecarRuntime::PackagedBinary
+        );
+        assert_eq!(
+            packaged_runtime(false, true),
+            SidecarRuntime::PackagedBinary
+        );
+        assert_eq!(development_runtimes(true), &[SidecarRuntime::NodeTsx]);
+        assert_eq!(
+            development_runtimes(false),
+            &[SidecarRuntime::Npx, SidecarRuntime::Bun]
+        );
+
+        let mut command = Command::new("node");
+        command.args(["/opt/Cowork Any/tsx/cli.mjs", "src/main.ts"]);
+        assert_eq!(
+            describe_command(&command),
+            "node \"/opt/Cowork Any/tsx/cli.mjs\" src/main.ts"
+        );
+    }
+
+    #[test]
+    fn suppressed_watchdog_does_not_respawn_after_shutdown() {
+        let watchdog = WatchdogState::new(false);
+        assert!(
+            watchdog.should_restart(false),
+            "crashed sidecar is restarted"
+        );
+        assert!(!watchdog.should_restart(true));
+
+        // shutdown_sidecar suppresses; the watchdog handle shares the flag.
+        let watchdog_thread_view = watchdog.clone();
+        watchdog.suppress();
+        assert!(!watchdog_thread_view.should_restart(false));
+
+        watchdog.resume();
+        assert!(watchdog_thread_view.should_restart(false));
+
+        watchdog.stop();
+        assert!(!watchdog_thread_view.should_restart(false));
+        assert!(!watchdog_thread_view.sleep(std::time::Duration::from_secs(60)));
+
+        assert!(
+            !WatchdogState::new(true).should_restart(false),
+            "lazy mode waits for a spawn"
+        );
+    }
+
+    #[test]
+    fn protocol_handshake_requires_matching_major_version() {
+        assert!(protocol_versions_compatible("1.0", "1.4"));
+        assert!(protocol_versions_compatible("1.2", "v1.0"));
+        assert!(!protocol_versions_compatible("1.0", "2.0"));
+        assert!(!protocol_versions_compatible("1.0", "garbage"));
+
+        let current = ProtocolHandshake::new(Some(DESKTOP_PROTOCOL_VERSION.to_string()));
+        assert!(current.compatible);
+        assert_eq!(current.desktop_version, DESKTOP_PROTOCOL_VERSION);
+
+        let skewed = ProtocolHandshake::new(Some("99.0".to_string()));
+        assert!(!skewed.compatible);
+        assert_eq!(
+            serde_json::to_value(&skewed).expect("serialize handshake"),
+            json!({
+                "desktopVersion": DESKTOP_PROTOCOL_VERSION,
+                "sidecarVersion": "99.0",
+                "compatible": false,
+            })
+        );
+
+        assert!(
+            !ProtocolHandshake::new(None).compatible,
+            "a sidecar that ignores hello predates the handshake"
+        );
+    }
+
+    #[test]
+    fn incompatible_sidecar_is_refused_and_never_negotiated() {
+        let skewed = ProtocolHandshake::new(Some("2.3".to_string()));
+        match skewed.check() {
+            Err(SidecarError::Incompatible { desktop, sidecar }) => {
+                assert_eq!(desktop, DESKTOP_PROTOCOL_VERSION);
+                assert_eq!(sidecar, "2.3");
+            }
+            other => panic!("expected Incompatible, got {:?}", other.err()),
+        }
+        assert!(ProtocolHandshake::new(None).check().is_ok());
+
+        let mut manager = SidecarManager::new();
+        manager.protocol_handshake = Some(skewed);
+        assert_eq!(manager.negotiated_protocol_version(), None);
+        manager.protocol_handshake = Some(ProtocolHandshake::new(Some("1.4".to_string())));
+        assert_eq!(manager.negotiated_protocol_version(), Some("1.4"));
+    }
+
+    #[test]
+    fn protocol_diagnostics_record_malformed_and_unclassified_lines() {
+        let diagnostics = Mutex::new(ProtocolDiagnostics::default());
+
+        let parsed = parse_protocol_line(r#"{"type":"task_started","taskId":"t1"}"#, &diagnostics);
+        assert!(matches!(parsed, Some((_, SidecarMessageKind::TaskEvent))));
+
+        assert!(
+            parse_protocol_line(r#"{"type":"task_started","taskId":}"#, &diagnostics).is_none()
+        );
+        assert!(parse_protocol_line(r#"{"payload":{"ok":true}}"#, &diagnostics).is_none());
+        assert!(parse_protocol_line(r#"{"type":42}"#, &diagnostics).is_none());
+
+        let snapshot = diagnostics.lock().unwrap().clone();
+        assert_eq!(snapshot.parse_failures, 1);
+        assert_eq!(snapshot.unclassified, 2);
+        assert_eq!(snapshot.total(), 3);
+        let kinds: Vec<_> = snapshot.samples.iter().map(|sample| sample.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ProtocolIssueKind::ParseFailure,
+                ProtocolIssueKind::Unclassified,
+                ProtocolIssueKind::Unclassified,
+            ]
+        );
+        assert!(snapshot.samples[0].error.is_some());
+        assert_eq!(snapshot.samples[1].line, r#"{"payload":{"ok":true}}"#);
+
+        for _ in 0..PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT {
+            parse_protocol_line("{not json}", &diagnostics);
+        }
+        let snapshot = diagnostics.lock().unwrap().clone();
+        assert_eq!(
+            snapshot.parse_failures,
+            1 + PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT as u64
+        );
+        assert_eq!(snapshot.samples.len(), PROTOCOL_DIAGNOSTIC_SAMPLE_LIMIT);
+        assert!(snapshot
+            .samples
+            .iter()
+            .all(|sample| sample.kind == ProtocolIssueKind::ParseFailure));
+    }
+
+    #[test]
+    fn routes_tasks_to_their_workspace_sidecar() {
+        let state = SidecarState::new();
+
+        let primary = state.route_workspace("/work/alpha/").expect("route alpha");
+        assert!(
+            primary.workspace.is_none(),
+            "first workspace uses the default"
+        );
+        assert!(Arc::ptr_eq(&primary.manager, &state.manager));
+
+        let beta = state.route_workspace("/work/beta").expect("route beta");
+        assert_eq!(beta.workspace.as_deref(), Some("/work/beta"));
+        assert!(!Arc::ptr_eq(&beta.manager, &state.manager));
+        assert_eq!(
+            beta.manager.lock().expect("beta manager").workspace(),
+            Some("/work/beta")
+        );
+        let beta_again = state.route_workspace("/work/beta/").expect("route beta");
+        assert!(Arc::ptr_eq(&beta.manager, &beta_again.manager));
+
+        state
+            .workspaces
+            .lock()
+            .expect("workspaces")
+            .bind_task("task-beta", "/work/beta");
+        assert!(Arc::ptr_eq(
+            &state.route_task("task-beta").manager,
+            &beta.manager
+        ));
+        assert!(Arc::ptr_eq(
+            &state.route_task("task-alpha").manager,
+            &state.manager
+        ));
+
+        for index in 1..MAX_WORKSPACE_SIDECARS {
+            state
+                .route_workspace(&format!("/work/extra-{index}"))
+                .expect("within cap");
+        }
+        assert!(state.route_workspace("/work/one-too-many").is_err());
+        assert!(state.route_workspace("/work/alpha").is_ok());
+    }
+
+    #[test]
+    fn workspace_sidecars_have_independent_lifecycles() {
+        let mut workspaces = WorkspaceSidecars::default();
+        assert!(workspaces.route("/work/alpha").expect("route").is_none());
+        let beta = workspaces
+            .route("/work/beta")
+            .expect("route")
+            .expect("beta");
+        let gamma = workspaces
+            .route("/work/gamma")
+            .expect("route")
+            .expect("gamma");
+        let beta_watchdog = beta.watchdog.clone().expect("beta watchdog");
+        let gamma_watchdog = gamma.watchdog.clone().expect("gamma watchdog");
+
+        // Never spawned: nothing to restart yet.
+        let start = Instant::now();
+        assert!(workspaces.crashed_at(start).restart.is_empty());
+
+        // After a spawn (which resumes the watchdog) a dead sidecar is
+        // reported for restart without touching the other workspace.
+        beta_watchdog.resume();
+        let crashed = workspaces.crashed_at(start).restart;
+        assert_eq!(crashed.len(), 1);
+        assert_eq!(crashed[0].0, "/work/beta");
+        assert!(gamma_watchdog.is_suppressed());
+
+        // Further restarts back off, and the budget is per workspace.
+        let at = |secs| start + Duration::from_secs(secs);
+        assert!(workspaces.crashed_at(at(1)).restart.is_empty());
+        assert_eq!(workspaces.crashed_at(at(2)).restart.len(), 1);
+        assert!(workspaces.crashed_at(at(5)).restart.is_empty());
+        assert_eq!(workspaces.crashed_at(at(6)).restart.len(), 1);
+
+        // Giving up is reported once.
+        let given_up = workspaces.crashed_at(at(60));
+        assert!(given_up.restart.is_empty());
+        assert_eq!(given_up.failed.len(), 1);
+        assert_eq!(given_up.failed[0].0, "/work/beta");
+        assert!(beta_watchdog.is_suppressed());
+        assert!(workspaces.crashed_at(at(61)).failed.is_empty());
+
+        workspaces.bind_task("task-gamma", "/work/gamma");
+        assert!(workspaces.shutdown_workspace("/work/gamma"));
+        assert!(gamma_watchdog.is_stopped());
+        assert!(workspaces.route_task("task-gamma").is_none());
+        assert_eq!(workspaces.workspaces(), vec!["/work/beta".to_string()]);
+        assert!(!workspaces.shutdown_workspace("/work/gamma"));
+    }
+
+    #[test]
+    fn restart_budget_refills_only_after_the_sidecar_stays_up() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut budget = RestartBudget::default();
+        assert!(budget.try_restart(at(0)));
+
+        // Up briefly between crashes: the count carries over.
+        budget.running(at(1));
+        assert!(!budget.try_restart(at(1)));
+        assert!(budget.try_restart(at(2)));
+        budget.running(at(3));
+        assert!(budget.try_restart(at(6)));
+        assert!(budget.exhausted());
+
+        budget.running(at(6) + WORKSPACE_SIDECAR_STABLE_AFTER);
+        assert!(!budget.exhausted());
+        assert!(budget.try_restart(at(7) + WORKSPACE_SIDECAR_STABLE_AFTER));
+    }
+
+    #[test]
+    fn rag_paths_prefer_settings_then_env_then_home_default() {
+        let home = PathBuf::from("/home/tester");
+        let env = |key: &str| match key {
+            "VAULT_PATH" => Some("/env/vault".to_string()),
+            "CHROMA_PATH" => Some("/env/chroma".to_string()),
+            _ => None,
+        };
+        let no_env = |_: &str| None;
+
+        let defaults = resolve_rag_paths(&RagPathSettings::default(), no_env, &home);
+        assert_eq!(defaults.vault_path, home.join(".coworkany").join("vault"));
+        assert_eq!(defaults.chroma_path, home.join(".coworkany").join("chroma"));
+
+        let from_env = resolve_rag_paths(&RagPathSettings::default(), env, &home);
+        assert_eq!(from_env.vault_path, PathBuf::from("/env/vault"));
+        assert_eq!(from_env.chroma_path, PathBuf::from("/env/chroma"));
+
+        let settings = RagPathSettings {
+            vault_path: Some("/settings/vault".to_string()),
+            chroma_path: Some("  ".to_string()),
+        };
+        let mixed = resolve_rag_paths(&settings, env, &home);
+        assert_eq!(mixed.vault_path, PathBuf::from("/settings/vault"));
+        assert_eq!(
+            mixed.chroma_path,
+            PathBuf::from("/env/chroma"),
+            "blank settings fall through to env"
+        );
+
+        let app_data_dir = unique_temp_dir("sidecar-rag-settings");
+        fs::create_dir_all(&app_data_dir).expect("create temp app data dir");
+        fs::write(
+            app_data_dir.join("settings.json"),
+            r#"{ "ragPaths": { "vaultPath": "/stored/vault" }, "theme": "dark" }"#,
+        )
+        .expect("write settings");
+        assert_eq!(
+            load_rag_path_settings(&app_data_dir).vault_path.as_deref(),
+            Some("/stored/vault")
+        );
+        assert!(ensure_writable_dir(&app_data_dir.join("vault")).is_ok());
+        assert!(ensure_writable_dir(std::path::Path::new("relative/vault")).is_err());
+        let _ = fs::remove_dir_all(&app_data_dir);
+    }
+
+    #[test]
+    fn missing_sidecar_entry_is_reported_instead_of_spawning() {
+        let root = unique_temp_dir("sidecar-missing");
+        fs::create_dir_all(root.join("sidecar/src/main.ts")).unwrap();
+        let candidates = vec![
+            root.join("missing/sidecar/src/main.ts"),
+            // A directory where the entry should be is not an entry.
+            root.join("sidecar/src/main.ts"),
+        ];
+
+        match SidecarManager::spawn_development_sidecar_from(
+            &candidates,
+            None,
+            &ResourceLimits::default(),
+            "",
+            "",
+            None,
+        ) {
+            Err(SidecarError::EntryMissing { searched }) => assert_eq!(searched, candidates),
+            Err(other) => panic!("expected EntryMissing, got {other}"),
+            Ok(_) => panic!("nothing should be spawned without an entry"),
+        }
+        let message = SidecarError::EntryMissing {
+            searched: candidates.clone(),
+        }
+        .to_string();
+        assert!(message.contains("Sidecar not found"));
+        assert!(message.contains("2 searched paths"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn conflicting_apply_reports_patch_conflict_and_flags_the_response() {
+        let workspace = unique_temp_dir("patch-conflict");
+        fs::create_dir_all(&workspace).unwrap();
+        let file = workspace.join("notes.txt");
+        fs::write(&file, "one\n").unwrap();
+        let mut shadow = crate::shadow_fs::ShadowFs::new(workspace.clone()).unwrap();
+        let entry = shadow.stage_file(&file, "one\ntwo\n").unwrap();
+        shadow.approve(&entry.id).unwrap();
+        fs::write(&file, "changed elsewhere\n").unwrap();
+
+        let (result, conflict) = apply_reporting_conflict(&mut shadow, &entry.id, true);
+        let conflict = conflict.expect("conflict is reported for the patch-conflict event");
+        assert_eq!(conflict.patch_id, entry.id);
+        assert_eq!(Some(&conflict.expected_hash), entry.original_hash.as_ref());
+        assert_ne!(conflict.actual_hash, conflict.expected_hash);
+        let diff = conflict.diff.as_deref().unwrap();
+        assert!(diff.contains("-changed elsewhere") && diff.contains("+two"));
+
+        let response = build_apply_patch_response("cmd-1", &entry.id, &result, Some(&conflict));
+        assert_eq!(response["payload"]["conflict"], json!(true));
+        assert_eq!(response["payload"]["success"], json!(false));
+        assert_eq!(
+            response["payload"]["actualHash"],
+            json!(conflict.actual_hash)
+        );
+
+        // Other failures stay plain errors with no conflict flag.
+        let (result, conflict) = apply_reporting_conflict(&mut shadow, "missing", true);
+        assert!(conflict.is_none());
+        let response = build_apply_patch_response("cmd-2", "missing", &result, None);
+        assert!(response["payload"].get("conflict").is_none());
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[tokio::test]
+    async fn successful_apply_logs_an_executed_audit_event() {
+        let workspace = unique_temp_dir("apply-executed");
+        fs::create_dir_all(&workspace).unwrap();
+        let file = workspace.join("notes.txt");
+        fs::write(&file, "one\n").unwrap();
+        let mut shadow = crate::shadow_fs::ShadowFs::new(workspace.clone()).unwrap();
+        let entry = shadow.stage_file(&file, "one\ntwo\n").unwrap();
+        shadow.approve(&entry.id).unwrap();
+        let shadow_state = std::sync::Arc::new(tokio::sync::Mutex::new(Some(shadow)));
+
+        let log_path = workspace.join("policy-audit.jsonl");
+        let policy =
+            crate::policy::PolicyEngineState::new(Box::new(crate::policy::ConsoleAuditSink))
+                .with_audit_log(log_path.clone());
+        let request = build_effect_request_for_patch(
+            crate::diff::PatchOperation::Modify,
+            &file.to_string_lossy(),
+            Some(&workspace),
+        );
+
+        let (result, _) =
+            apply_and_record_execution(&shadow_state, &policy, Some(&request), &entry.id, false)
+                .await;
+        assert!(result.unwrap().success);
+
+        let log = fs::read_to_string(&log_path).unwrap();
+        let event: crate::policy::AuditEvent = log
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .find(|event: &crate::policy::AuditEvent| event.event_type == "executed")
+            .expect("executed event is logged");
+        assert_eq!(event.request.id, request.id);
+        assert_eq!(event.event_type, "executed");
+        let execution = event.execution.expect("execution outcome is recorded");
+        assert!(execution.success);
+        assert!(execution.error.is_none());
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[tokio::test]
+    async fn shell_executed_event_carries_the_approved_request_id() {
+        let workspace = unique_temp_dir("shell-executed");
+        fs::create_dir_all(&workspace).unwrap();
+        let log_path = workspace.join("policy-audit.jsonl");
+        let policy =
+            crate::policy::PolicyEngineState::new(Box::new(crate::policy::ConsoleAuditSink))
+                .with_audit_log(log_path.clone());
+
+        let args = vec!["--version".to_string()];
+        let request = build_effect_request_for_shell("git", &args, None, Some("approved-1"));
+        assert_eq!(request.id, "approved-1");
+        let fresh = build_effect_request_for_shell("git", &args, None, None);
+        assert_ne!(fresh.id, "approved-1");
+
+        policy
+            .record_execution(
+                &request,
+                &crate::policy::ExecutionResult::finished(std::time::Instant::now(), None),
+            )
+            .await;
+
+        let log = fs::read_to_string(&log_path).unwrap();
+        let event: crate::policy::AuditEvent = log
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .find(|event: &crate::policy::AuditEvent| event.event_type == "executed")
+            .expect("executed event is logged");
+        assert_eq!(event.request.id, "approved-1");
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_sends_shutdown_command_and_waits_for_exit() {
+        let dir = unique_temp_dir("sidecar-graceful-shutdown");
+        fs::create_dir_all(&dir).unwrap();
+        let received = dir.join("received");
+        // Stands in for a sidecar that takes a moment to flush before exiting
+        let script = format!(
+            "read line; sleep 0.3; printf '%s' \"$line\" > '{}'",
+            received.display()
+        );
+        let mut child = Command::new("sh")
+            .args(["-c", &script])
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .expect("spawn sh");
+
+        let mut manager = SidecarManager::new();
+        assert_eq!(manager.shutdown_grace(), super::DEFAULT_SHUTDOWN_GRACE);
+        manager.set_shutdown_grace(std::time::Duration::from_secs(5));
+        manager
+            .transport_healthy
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        manager.set_command_writer(Arc::new(Mutex::new(CommandWriter::Child(
+            child.stdin.take().unwrap(),
+        ))));
+        manager.child = Some(child);
+
+        manager.shutdown();
+
+        let line = fs::read_to_string(&received).expect("sidecar exited on its own");
+        let command: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(command["type"], "shutdown");
+        assert!(manager.child.is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn full_stdin_queue_reports_backpressure() {
+        // Never reads stdin, like a sidecar that has stalled
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 5"])
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .expect("spawn sh");
+
+        let mut manager = SidecarManager::new();
+        manager.set_stdin_queue_capacity(2);
+        manager
+            .transport_healthy
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        manager.set_command_writer(Arc::new(Mutex::new(CommandWriter::Child(
+            child.stdin.take().unwrap(),
+        ))));
+
+        // Bigger than a pipe buffer, so the writer thread blocks on the first
+        let big = json!({ "type": "noop", "payload": "x".repeat(256 * 1024) });
+        let mut sent = 0;
+        let error = loop {
+            match manager.send_raw_command(big.clone()) {
+                Ok(()) => sent += 1,
+                Err(error) => break error,
+            }
+            assert!(sent <= 4, "queue never filled");
+        };
+        assert!(matches!(error, SidecarError::Backpressure { capacity: 2 }));
+
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    #[test]
+    fn stdout_eof_resolves_pending_waiters_as_disconnected() {
+        let manager = SidecarManager::new();
+        manager
+            .transport_healthy
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let (tx, rx) = std::sync::mpsc::channel();
+        manager
+            .pending_responses
+            .lock()
+            .unwrap()
+            .insert("cmd-waiting".to_string(), tx);
+
+        // The stdout reader hits EOF the way it does when the sidecar dies
+        let pending = manager.pending_responses.clone();
+        let healthy = manager.transport_healthy.clone();
+        let reader = thread::spawn(move || {
+            for _ in BufReader::new(std::io::empty()).lines() {}
+            SidecarManager::disconnect_pending_waiters(&pending, &healthy);
+        });
+        reader.join().unwrap();
+
+        let response = rx
+            .recv_timeout(std::time::Duration::from_millis(100))
+            .expect("waiter resolved without waiting out a timeout");
+        assert_eq!(response["commandId"], "cmd-waiting");
+        assert!(matches!(
+            transport_error(&response),
+            Some(SidecarError::Disconnected(_))
+        ));
+        assert!(manager.pending_responses.lock().unwrap().is_empty());
+        assert!(!manager
+            .transport_healthy
+            .load(std::sync::atomic::Ordering::SeqCst));
+        assert!(transport_error(&json!({ "type": "task_started" })).is_none());
+    }
+
+    #[test]
+    fn streaming_waiter_sees_progress_before_the_final_response() {
+        let manager = SidecarManager::new();
+        let (tx, rx) = std::sync::mpsc::channel();
+        manager
+            .progress_subscribers
+            .lock()
+            .unwrap()
+            .insert("cmd-scan".to_string(), tx.clone());
+        manager
+            .pending_responses
+            .lock()
+            .unwrap()
+            .insert("cmd-scan".to_string(), tx);
+
+        let pending = manager.pending_responses.clone();
+        let subscribers = manager.progress_subscribers.clone();
+        let reader = thread::spawn(move || {
+            for message in [
+                json!({ "type": "command_progress", "commandId": "cmd-scan", "payload": { "done": 1 } }),
+                json!({ "type": "command_progress", "commandId": "cmd-other", "payload": { "done": 9 } }),
+                json!({ "type": "command_progress", "commandId": "cmd-scan", "payload": { "done": 2 } }),
+                json!({ "type": "scan_default_repos_response", "commandId": "cmd-scan", "payload": { "success": true } }),
+            ] {
+                match classify_sidecar_message(&message) {
+                    Some(SidecarMessageKind::CommandProgress) => {
+                        deliver_command_progress(&subscribers, &message)
+                    }
+                    Some(SidecarMessageKind::IpcResponse) => {
+                        deliver_command_response(&pending, &subscribers, &message)
+                    }
+                    _ => panic!("unexpected message kind"),
+                }
+            }
+        });
+
+        let mut progress = Vec::new();
+        let response = recv_streaming(&rx, Duration::from_secs(1), |message| {
+            progress.push(message["payload"]["done"].as_u64().unwrap());
+        })
+        .expect("final response resolves the call");
+        reader.join().unwrap();
+
+        assert_eq!(progress, vec![1, 2]);
+        assert_eq!(response["type"], "scan_default_repos_response");
+        assert!(manager.pending_responses.lock().unwrap().is_empty());
+        assert!(manager.progress_subscribers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stderr_backlog_keeps_the_latest_lines_with_a_severity_guess() {
+        let severity = |line: &str| {
+            SidecarManager::stderr_severity(
+                line,
+                SidecarManager::classify_sidecar_stderr_line(line),
+            )
+        };
+        assert_eq!(severity("[INFO] Server ready"), StderrSeverity::Info);
+        assert_eq!(severity("Deprecated option ignored"), StderrSeverity::Warn);
+        assert_eq!(severity("[ERROR] Task crashed"), StderrSeverity::Error);
+        assert_eq!(
+            severity("Failed to connect to MCP server github"),
+            StderrSeverity::Warn
+        );
+
+        let manager = SidecarManager::new();
+        for i in 0..STDERR_BACKLOG_LIMIT + 5 {
+            push_stderr_backlog(
+                &manager.stderr_backlog,
+                SidecarStderrLine {
+                    line: format!("line {}", i),
+                    severity: StderrSeverity::Info,
+                    timestamp: chrono_now(),
+                    workspace: None,
+                },
+            );
+        }
+        let all = manager.recent_stderr(usize::MAX);
+        assert_eq!(all.len(), STDERR_BACKLOG_LIMIT);
+        assert_eq!(all[0].line, "line 5");
+        let last: Vec<_> = manager
+            .recent_stderr(2)
+            .into_iter()
+            .map(|line| line.line)
+            .collect();
+        let expected = vec![
+            format!("line {}", STDERR_BACKLOG_LIMIT + 3),
+            format!("line {}", STDERR_BACKLOG_LIMIT + 4),
+        ];
+        assert_eq!(last, expected);
+    }
 }
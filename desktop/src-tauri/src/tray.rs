@@ -1,32 +1,28 @@
 use tauri::{
     image::Image,
-    menu::{MenuBuilder, MenuItemBuilder, PredefinedMenuItem},
+    menu::{Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Emitter, Manager,
+    AppHandle, Manager, Wry,
 };
 use tracing::info;
 
-pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    fn t(_app: &AppHandle, en: &str, zh: &str) -> String {
-        let locale = std::env::var("LC_ALL")
-            .or_else(|_| std::env::var("LANG"))
-            .unwrap_or_else(|_| String::from("en"))
-            .to_lowercase();
-
-        if locale.starts_with("zh") {
-            zh.to_string()
-        } else {
-            en.to_string()
-        }
+use crate::command_history::emit_command_executed;
+use crate::locale::{current_language, Language};
+
+const TRAY_ID: &str = "main";
+
+fn t(app: &AppHandle, en: &str, zh: &str) -> String {
+    match current_language(app) {
+        Language::Zh => zh.to_string(),
+        Language::En => en.to_string(),
     }
+}
 
-    let icon = Image::from_path("icons/icon.png")
-        .or_else(|_| Image::from_path("icons/logo.ico"))
-        .unwrap_or_else(|_| {
-            Image::from_bytes(include_bytes!("../icons/icon.png"))
-                .expect("Failed to load embedded tray icon")
-        });
+fn tray_tooltip(app: &AppHandle) -> String {
+    t(app, "CoworkAny - AI Assistant", "CoworkAny - AI 助手")
+}
 
+fn tray_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
     let open_main = MenuItemBuilder::new(t(app, "Open Main Window", "打开主窗口"))
         .id("open_main")
         .build(app)?;
@@ -55,7 +51,7 @@ pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
         .id("quit")
         .build(app)?;
 
-    let menu = MenuBuilder::new(app)
+    MenuBuilder::new(app)
         .item(&open_main)
         .item(&separator1)
         .item(&new_task)
@@ -65,15 +61,35 @@ pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
         .item(&shortcuts)
         .item(&separator2)
         .item(&quit)
-        .build()?;
+        .build()
+}
+
+/// Rebuild the tray menu and tooltip, e.g. after the UI language changed
+pub fn refresh_tray(app: &AppHandle) -> tauri::Result<()> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    tray.set_menu(Some(tray_menu(app)?))?;
+    tray.set_tooltip(Some(tray_tooltip(app)))?;
+    Ok(())
+}
+
+pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let icon = Image::from_path("icons/icon.png")
+        .or_else(|_| Image::from_path("icons/logo.ico"))
+        .unwrap_or_else(|_| {
+            Image::from_bytes(include_bytes!("../icons/icon.png"))
+                .expect("Failed to load embedded tray icon")
+        });
+    let menu = tray_menu(app)?;
 
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(icon)
         .menu(&menu)
-        .tooltip(t(app, "CoworkAny - AI Assistant", "CoworkAny - AI 助手"))
+        .tooltip(tray_tooltip(app))
         .on_menu_event(move |app, event| match event.id().as_ref() {
             "open_main" => {
-                let _ = app.emit("command-executed", serde_json::json!({ "id": "new-task" }));
+                emit_command_executed(app, "new-task");
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
@@ -84,28 +100,28 @@ pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
-                let _ = app.emit("command-executed", serde_json::json!({ "id": "new-task" }));
+                emit_command_executed(app, "new-task");
             }
             "task_list" => {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
-                let _ = app.emit("command-executed", serde_json::json!({ "id": "task-list" }));
+                emit_command_executed(app, "task-list");
             }
             "settings" => {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
-                let _ = app.emit("command-executed", serde_json::json!({ "id": "settings" }));
+                emit_command_executed(app, "settings");
             }
             "shortcuts" => {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
-                let _ = app.emit("command-executed", serde_json::json!({ "id": "shortcuts" }));
+                emit_command_executed(app, "shortcuts");
             }
             "quit" => {
                 app.exit(0);
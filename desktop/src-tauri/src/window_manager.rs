@@ -1,6 +1,342 @@
-use tauri::AppHandle;
+//! CoworkAny Desktop - Window Layout
+//!
+//! Compact mode collapses the app to the launcher bar: the main window shrinks
+//! to `LAUNCHER_SIZE` and every other window is hidden. The layout it replaced
+//! is stashed as JSON in the shared app data directory, so toggling back after
+//! a restart still restores it.
+//!
+//! Sizes are placed per monitor: the launcher bar is scaled by the scale
+//! factor of the monitor the main window is on, so it keeps the same visual
+//! size on mixed-DPI setups.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, State};
+use tracing::warn;
+
+/// The window that becomes the launcher bar in compact mode
+pub const MAIN_WINDOW: &str = "main";
+
+/// Width and height of the main window in compact mode, in logical pixels
+pub const LAUNCHER_SIZE: (u32, u32) = (720, 72);
 
 #[tauri::command]
 pub fn quit_app(app: AppHandle) {
     app.exit(0);
 }
+
+// ============================================================================
+// Monitors
+// ============================================================================
+
+/// One connected monitor; position and size in physical pixels
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+impl MonitorInfo {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && i64::from(x) < i64::from(self.x) + i64::from(self.width)
+            && i64::from(y) < i64::from(self.y) + i64::from(self.height)
+    }
+}
+
+/// The monitor showing the physical point (`x`, `y`); the primary one (or
+/// failing that the first) when the point is off every monitor.
+pub fn monitor_at(monitors: &[MonitorInfo], x: i32, y: i32) -> Option<&MonitorInfo> {
+    monitors
+        .iter()
+        .find(|monitor| monitor.contains(x, y))
+        .or_else(|| monitors.iter().find(|monitor| monitor.primary))
+        .or_else(|| monitors.first())
+}
+
+/// A logical size in physical pixels on a monitor with `scale_factor`
+pub fn to_physical_size(logical: (u32, u32), scale_factor: f64) -> (u32, u32) {
+    let scale = |value: u32| (f64::from(value) * scale_factor).round() as u32;
+    (scale(logical.0), scale(logical.1))
+}
+
+fn monitors(app: &AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let primary = app.primary_monitor().map_err(|e| e.to_string())?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    Ok(monitors
+        .iter()
+        .map(|monitor| MonitorInfo {
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+            primary: primary.as_ref().is_some_and(|primary| {
+                primary.position() == monitor.position() && primary.size() == monitor.size()
+            }),
+        })
+        .collect())
+}
+
+/// Every connected monitor with its scale factor, for placing windows on
+/// mixed-DPI setups
+#[tauri::command]
+pub fn list_monitors(app: AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    monitors(&app)
+}
+
+// ============================================================================
+// Compact Mode
+// ============================================================================
+
+/// Where a window is and whether it shows, in physical pixels
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowLayout {
+    pub label: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub visible: bool,
+}
+
+/// `current` collapsed to the launcher bar: the main window stays where it
+/// is at launcher size, scaled for the monitor it is on, and shows; every
+/// other window is hidden.
+pub fn compact_layout(current: &[WindowLayout], monitors: &[MonitorInfo]) -> Vec<WindowLayout> {
+    current
+        .iter()
+        .map(|window| {
+            if window.label == MAIN_WINDOW {
+                let scale_factor = monitor_at(monitors, window.x, window.y)
+                    .map_or(1.0, |monitor| monitor.scale_factor);
+                let (width, height) = to_physical_size(LAUNCHER_SIZE, scale_factor);
+                WindowLayout {
+                    width,
+                    height,
+                    visible: true,
+                    ..window.clone()
+                }
+            } else {
+                WindowLayout {
+                    visible: false,
+                    ..window.clone()
+                }
+            }
+        })
+        .collect()
+}
+
+/// What to apply to leave compact mode: the stashed layout of each window
+/// that is still open. Windows opened while compact are left alone.
+pub fn restored_layout(stash: &[WindowLayout], current: &[WindowLayout]) -> Vec<WindowLayout> {
+    stash
+        .iter()
+        .filter(|window| current.iter().any(|open| open.label == window.label))
+        .cloned()
+        .collect()
+}
+
+pub struct CompactModeState {
+    /// Where the stashed layout is persisted while compact
+    path: PathBuf,
+    /// The layout compact mode replaced; `Some` while compact
+    stash: Mutex<Option<Vec<WindowLayout>>>,
+}
+
+impl CompactModeState {
+    /// Pick up a layout stashed at `path` by a run that quit while compact.
+    /// An unreadable file is ignored rather than failing startup.
+    pub fn with_path(path: PathBuf) -> Self {
+        let stash = match fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .map_err(|e| warn!("Ignoring unreadable compact layout {:?}: {}", path, e))
+                .ok(),
+            Err(_) => None,
+        };
+        Self {
+            path,
+            stash: Mutex::new(stash),
+        }
+    }
+
+    /// Remember `stash`, or forget it with `None`, on disk and in memory
+    fn set(&self, stash: Option<Vec<WindowLayout>>) -> Result<(), String> {
+        match &stash {
+            Some(layout) => {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let raw = serde_json::to_string_pretty(layout).map_err(|e| e.to_string())?;
+                fs::write(&self.path, raw).map_err(|e| e.to_string())?;
+            }
+            None => {
+                if self.path.exists() {
+                    fs::remove_file(&self.path).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        *self.stash.lock().map_err(|e| e.to_string())? = stash;
+        Ok(())
+    }
+}
+
+fn current_layout(app: &AppHandle) -> Vec<WindowLayout> {
+    let mut layout: Vec<WindowLayout> = app
+        .webview_windows()
+        .into_iter()
+        .filter_map(|(label, window)| {
+            let position = window.outer_position().ok()?;
+            let size = window.outer_size().ok()?;
+            Some(WindowLayout {
+                label,
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                visible: window.is_visible().unwrap_or(false),
+            })
+        })
+        .collect();
+    layout.sort_by(|a, b| a.label.cmp(&b.label));
+    layout
+}
+
+fn apply_layout(app: &AppHandle, layout: &[WindowLayout]) -> Result<(), String> {
+    let windows = app.webview_windows();
+    for target in layout {
+        let Some(window) = windows.get(&target.label) else {
+            continue;
+        };
+        window
+            .set_size(PhysicalSize::new(target.width, target.height))
+            .map_err(|e| e.to_string())?;
+        window
+            .set_position(PhysicalPosition::new(target.x, target.y))
+            .map_err(|e| e.to_string())?;
+        if target.visible {
+            window.show().map_err(|e| e.to_string())?;
+        } else {
+            window.hide().map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Collapse to the launcher bar, or put every window back the way it was
+/// before. Returns whether compact mode is now on.
+#[tauri::command]
+pub async fn toggle_compact_mode(
+    app: AppHandle,
+    state: State<'_, CompactModeState>,
+) -> Result<bool, String> {
+    let current = current_layout(&app);
+    let stash = state.stash.lock().map_err(|e| e.to_string())?.clone();
+    match stash {
+        Some(stash) => {
+            apply_layout(&app, &restored_layout(&stash, &current))?;
+            state.set(None)?;
+            Ok(false)
+        }
+        None => {
+            // Stash first: if applying fails halfway, toggling again restores.
+            state.set(Some(current.clone()))?;
+            let monitors = monitors(&app).unwrap_or_else(|e| {
+                warn!("Placing the launcher bar without monitor info: {}", e);
+                Vec::new()
+            });
+            apply_layout(&app, &compact_layout(&current, &monitors))?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(label: &str, width: u32, height: u32, visible: bool) -> WindowLayout {
+        WindowLayout {
+            label: label.to_string(),
+            x: 40,
+            y: 60,
+            width,
+            height,
+            visible,
+        }
+    }
+
+    #[test]
+    fn compact_layout_hides_secondaries_and_restores_the_stash() {
+        let before = vec![
+            window("dashboard", 900, 700, true),
+            window(MAIN_WINDOW, 1200, 860, true),
+            window("settings", 600, 500, false),
+        ];
+
+        let compact = compact_layout(&before, &[]);
+        assert_eq!(compact[1], window(MAIN_WINDOW, 720, 72, true));
+        assert!(!compact[0].visible && !compact[2].visible);
+        assert_eq!((compact[0].width, compact[0].height), (900, 700));
+
+        let path = std::env::temp_dir().join(format!(
+            "coworkany-compact-layout-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        CompactModeState::with_path(path.clone())
+            .set(Some(before.clone()))
+            .unwrap();
+
+        // As after a restart while compact, with settings closed meanwhile
+        let state = CompactModeState::with_path(path.clone());
+        let stash = state.stash.lock().unwrap().clone().unwrap();
+        let open = &compact[..2];
+        assert_eq!(restored_layout(&stash, open), before[..2]);
+
+        state.set(None).unwrap();
+        assert!(!path.exists());
+        let reloaded = CompactModeState::with_path(path);
+        assert!(reloaded.stash.lock().unwrap().is_none());
+    }
+
+    fn monitor(x: i32, width: u32, scale_factor: f64, primary: bool) -> MonitorInfo {
+        MonitorInfo {
+            name: None,
+            x,
+            y: 0,
+            width,
+            height: 1080,
+            scale_factor,
+            primary,
+        }
+    }
+
+    #[test]
+    fn launcher_bar_is_scaled_for_the_monitor_it_lands_on() {
+        let monitors = [monitor(0, 1920, 1.0, true), monitor(1920, 3840, 2.0, false)];
+        assert_eq!(monitor_at(&monitors, 1919, 10).unwrap().x, 0);
+        assert_eq!(monitor_at(&monitors, 1920, 10).unwrap().x, 1920);
+        // Off every monitor falls back to the primary one
+        assert!(monitor_at(&monitors, -50, 10).unwrap().primary);
+        assert_eq!(to_physical_size((720, 72), 1.25), (900, 90));
+
+        let mut main = window(MAIN_WINDOW, 1200, 860, true);
+        let compact = compact_layout(std::slice::from_ref(&main), &monitors);
+        assert_eq!((compact[0].width, compact[0].height), (720, 72));
+        main.x = 2400;
+        let compact = compact_layout(&[main], &monitors);
+        assert_eq!((compact[0].width, compact[0].height), (1440, 144));
+    }
+}
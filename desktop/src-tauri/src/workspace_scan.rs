@@ -0,0 +1,327 @@
+//! CoworkAny Desktop - Workspace Scanner
+//!
+//! Shared, cancellable directory walker for features that need to visit a
+//! whole workspace (storage usage, auto-stage, git-diff staging). Honors
+//! `.gitignore`/`.ignore` rules via the `ignore` crate and reports each entry
+//! as it goes so the UI can show progress on large repos.
+
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// Directories that are never part of a workspace scan.
+const ALWAYS_SKIPPED_DIRS: &[&str] = &[".git", ".coworkany"];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanWorkspaceOptions {
+    pub root: String,
+    /// Caller-chosen id used to cancel the scan; generated when absent
+    pub scan_id: Option<String>,
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Apply `.gitignore`/`.ignore` rules (default true)
+    pub respect_ignore_files: Option<bool>,
+    pub max_depth: Option<usize>,
+    /// Stop after this many files
+    pub max_entries: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanEntry {
+    /// Path relative to the scan root, `/`-separated
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub scan_id: String,
+    pub root: String,
+    pub files: usize,
+    pub directories: usize,
+    pub total_bytes: u64,
+    /// Entries that could not be read (permission errors, broken links)
+    pub errors: usize,
+    pub cancelled: bool,
+    pub truncated: bool,
+}
+
+/// Cancellation flags for in-flight scans, keyed by scan id.
+#[derive(Default)]
+pub struct WorkspaceScanState(pub Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+// ============================================================================
+// Walker
+// ============================================================================
+
+/// Walk `options.root`, calling `on_entry` for every file and directory
+/// below it. Checks `cancel` between entries and stops early when it is set.
+pub fn walk_workspace(
+    options: &ScanWorkspaceOptions,
+    scan_id: &str,
+    cancel: &AtomicBool,
+    mut on_entry: impl FnMut(&ScanEntry),
+) -> ScanSummary {
+    let root = PathBuf::from(&options.root);
+    let respect_ignore_files = options.respect_ignore_files.unwrap_or(true);
+
+    let mut summary = ScanSummary {
+        scan_id: scan_id.to_string(),
+        root: options.root.clone(),
+        ..Default::default()
+    };
+
+    let walker = WalkBuilder::new(&root)
+        .hidden(!options.include_hidden)
+        .ignore(respect_ignore_files)
+        .git_ignore(respect_ignore_files)
+        .git_exclude(respect_ignore_files)
+        .git_global(respect_ignore_files)
+        .parents(respect_ignore_files)
+        .require_git(false)
+        .max_depth(options.max_depth)
+        .filter_entry(|entry| {
+            !entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| ALWAYS_SKIPPED_DIRS.contains(&name))
+        })
+        .build();
+
+    for result in walker {
+        if cancel.load(Ordering::Relaxed) {
+            summary.cancelled = true;
+            break;
+        }
+
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Workspace scan entry error: {}", e);
+                summary.errors += 1;
+                continue;
+            }
+        };
+
+        // The root itself is not reported.
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        let size = if is_dir {
+            0
+        } else {
+            entry.metadata().map(|m| m.len()).unwrap_or(0)
+        };
+
+        if is_dir {
+            summary.directories += 1;
+        } else {
+            if options.max_entries.is_some_and(|max| summary.files >= max) {
+                summary.truncated = true;
+                break;
+            }
+            summary.files += 1;
+            summary.total_bytes += size;
+        }
+
+        on_entry(&ScanEntry {
+            path: relative_path(&root, entry.path()),
+            is_dir,
+            size,
+        });
+    }
+
+    summary
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Scan a workspace on a background thread, emitting `scan-entry` events,
+/// and return a summary once done or cancelled.
+#[tauri::command]
+pub async fn scan_workspace(
+    options: ScanWorkspaceOptions,
+    app_handle: AppHandle,
+    state: State<'_, WorkspaceScanState>,
+) -> Result<ScanSummary, String> {
+    if !Path::new(&options.root).is_dir() {
+        return Err(format!("Not a directory: {}", options.root));
+    }
+
+    let scan_id = options
+        .scan_id
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancel = Arc::new(AtomicBool::new(false));
+    state
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(scan_id.clone(), cancel.clone());
+
+    let worker_scan_id = scan_id.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        walk_workspace(&options, &worker_scan_id, &cancel, |entry| {
+            let _ = app_handle.emit(
+                "scan-entry",
+                serde_json::json!({
+                    "scanId": worker_scan_id,
+                    "entry": entry,
+                }),
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    if let Ok(mut scans) = state.0.lock() {
+        scans.remove(&scan_id);
+    }
+
+    let summary = result?;
+    info!(
+        "Workspace scan {} finished: {} files, {} dirs, cancelled: {}",
+        summary.scan_id, summary.files, summary.directories, summary.cancelled
+    );
+    Ok(summary)
+}
+
+/// Request cancellation of an in-flight scan. Returns false if no scan with
+/// that id is running.
+#[tauri::command]
+pub fn cancel_workspace_scan(
+    scan_id: String,
+    state: State<'_, WorkspaceScanState>,
+) -> Result<bool, String> {
+    let scans = state.0.lock().map_err(|e| e.to_string())?;
+    match scans.get(&scan_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        std::env::temp_dir().join(format!("coworkany-{name}-{}-{nanos}", std::process::id()))
+    }
+
+    fn options(root: &Path) -> ScanWorkspaceOptions {
+        ScanWorkspaceOptions {
+            root: root.to_string_lossy().to_string(),
+            scan_id: None,
+            include_hidden: false,
+            respect_ignore_files: None,
+            max_depth: None,
+            max_entries: None,
+        }
+    }
+
+    #[test]
+    fn applies_ignore_rules_and_skips_internal_dirs() {
+        let root = unique_temp_dir("scan-ignore");
+        fs::create_dir_all(root.join("src")).expect("create src");
+        fs::create_dir_all(root.join("build")).expect("create build");
+        fs::create_dir_all(root.join(".coworkany/shadow")).expect("create shadow");
+        fs::write(root.join(".gitignore"), "build/\n*.log\n").expect("write gitignore");
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").expect("write main");
+        fs::write(root.join("debug.log"), "noise").expect("write log");
+        fs::write(root.join("build/out.bin"), "bin").expect("write build output");
+        fs::write(root.join(".coworkany/shadow/index.json"), "{}").expect("write index");
+
+        let mut seen = Vec::new();
+        let summary = walk_workspace(&options(&root), "scan-1", &AtomicBool::new(false), |e| {
+            seen.push(e.path.clone())
+        });
+        seen.sort();
+
+        assert_eq!(seen, vec!["src".to_string(), "src/main.rs".to_string()]);
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.directories, 1);
+        assert!(!summary.cancelled);
+
+        let mut unfiltered = options(&root);
+        unfiltered.respect_ignore_files = Some(false);
+        let summary = walk_workspace(&unfiltered, "scan-2", &AtomicBool::new(false), |_| {});
+        assert_eq!(summary.files, 3);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn stops_when_cancelled_mid_walk() {
+        let root = unique_temp_dir("scan-cancel");
+        fs::create_dir_all(&root).expect("create root");
+        for i in 0..50 {
+            fs::write(root.join(format!("file-{i}.txt")), "x").expect("write file");
+        }
+
+        let cancel = AtomicBool::new(false);
+        let mut visited = 0;
+        let summary = walk_workspace(&options(&root), "scan-3", &cancel, |_| {
+            visited += 1;
+            if visited == 5 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+
+        assert!(summary.cancelled);
+        assert_eq!(visited, 5);
+        assert_eq!(summary.files, 5);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn truncates_at_max_entries() {
+        let root = unique_temp_dir("scan-truncate");
+        fs::create_dir_all(&root).expect("create root");
+        for i in 0..10 {
+            fs::write(root.join(format!("file-{i}.txt")), "x").expect("write file");
+        }
+
+        let mut limited = options(&root);
+        limited.max_entries = Some(4);
+        let summary = walk_workspace(&limited, "scan-4", &AtomicBool::new(false), |_| {});
+        assert!(summary.truncated);
+        assert_eq!(summary.files, 4);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}